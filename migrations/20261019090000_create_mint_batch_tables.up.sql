@@ -0,0 +1,21 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS mint_batches (
+    id UUID PRIMARY KEY,
+    mint_pubkey TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS mint_batch_items (
+    batch_id UUID NOT NULL REFERENCES mint_batches (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    receiver_pubkey TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    signature TEXT,
+    recent_blockhash TEXT,
+    error TEXT,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (batch_id, position)
+);
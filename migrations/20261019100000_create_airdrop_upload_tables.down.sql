@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS airdrop_upload_rows;
+DROP TABLE IF EXISTS airdrop_uploads;
@@ -0,0 +1,20 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS airdrop_uploads (
+    id UUID PRIMARY KEY,
+    mint_pubkey TEXT NOT NULL,
+    file_name TEXT,
+    status TEXT NOT NULL,
+    batch_id UUID REFERENCES mint_batches (id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    confirmed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE TABLE IF NOT EXISTS airdrop_upload_rows (
+    upload_id UUID NOT NULL REFERENCES airdrop_uploads (id) ON DELETE CASCADE,
+    row_number INTEGER NOT NULL,
+    recipient TEXT NOT NULL,
+    receiver_pubkey TEXT,
+    amount BIGINT,
+    error TEXT,
+    PRIMARY KEY (upload_id, row_number)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS distribution_leaves;
+DROP TABLE IF EXISTS distributions;
@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS distributions (
+    id UUID PRIMARY KEY,
+    mint_pubkey TEXT NOT NULL,
+    merkle_root TEXT NOT NULL,
+    leaf_count INTEGER NOT NULL,
+    total_amount BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS distribution_leaves (
+    distribution_id UUID NOT NULL REFERENCES distributions (id) ON DELETE CASCADE,
+    leaf_index INTEGER NOT NULL,
+    wallet_pubkey TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    proof TEXT[] NOT NULL,
+    status TEXT NOT NULL,
+    signature TEXT,
+    claimed_at TIMESTAMP WITH TIME ZONE,
+    PRIMARY KEY (distribution_id, leaf_index),
+    UNIQUE (distribution_id, wallet_pubkey)
+);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS wallet_export_audit;
+DROP TABLE IF EXISTS wallet_exports;
+ALTER TABLE users DROP COLUMN IF EXISTS exported_at;
@@ -0,0 +1,25 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS exported_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE IF NOT EXISTS wallet_exports (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    pubkey TEXT NOT NULL,
+    code_hash TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    confirmed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE TABLE IF NOT EXISTS wallet_export_audit (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    export_id UUID REFERENCES wallet_exports (id) ON DELETE SET NULL,
+    pubkey TEXT NOT NULL,
+    event TEXT NOT NULL,
+    detail TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS wallet_export_audit_user_id_idx ON wallet_export_audit (user_id, created_at);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+-- Fails while derived wallets exist, since their keys are not stored anywhere else.
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_custodial_key_check;
+ALTER TABLE users ALTER COLUMN secret_key SET NOT NULL;
+ALTER TABLE users DROP COLUMN IF EXISTS derivation_index;
+DROP SEQUENCE IF EXISTS user_derivation_index_seq;
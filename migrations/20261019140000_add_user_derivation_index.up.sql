@@ -0,0 +1,6 @@
+-- Add up migration script here
+CREATE SEQUENCE IF NOT EXISTS user_derivation_index_seq AS INTEGER MINVALUE 0 START WITH 0;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS derivation_index INTEGER UNIQUE;
+ALTER TABLE users ALTER COLUMN secret_key DROP NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_custodial_key_check CHECK (derivation_index IS NOT NULL OR secret_key IS NOT NULL);
@@ -0,0 +1,28 @@
+-- Add down migration script here
+-- Only the primary wallet of each user fits back into the users table.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS public_key TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS secret_key TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS derivation_index INTEGER UNIQUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS exported_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE users SET
+    public_key = wallets.pubkey,
+    secret_key = wallets.secret_key,
+    derivation_index = wallets.derivation_index,
+    exported_at = wallets.exported_at
+FROM wallets
+WHERE wallets.user_id = users.id AND wallets.is_primary;
+
+ALTER TABLE users ALTER COLUMN public_key SET NOT NULL;
+ALTER TABLE users ADD CONSTRAINT users_custodial_key_check CHECK (derivation_index IS NOT NULL OR secret_key IS NOT NULL);
+
+CREATE TABLE IF NOT EXISTS user_wallets (
+    pubkey TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    linked_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+INSERT INTO user_wallets (pubkey, user_id, linked_at)
+SELECT pubkey, user_id, created_at FROM wallets WHERE kind = 'external';
+
+DROP TABLE IF EXISTS wallets;
@@ -0,0 +1,44 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS wallets (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    pubkey TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL,
+    label TEXT,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    derivation_index INTEGER UNIQUE,
+    secret_key TEXT,
+    exported_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE,
+    CHECK (kind = 'external' OR derivation_index IS NOT NULL OR secret_key IS NOT NULL),
+    CHECK (NOT (is_primary AND archived_at IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS wallets_primary_idx ON wallets (user_id) WHERE is_primary;
+
+INSERT INTO wallets (id, user_id, pubkey, kind, is_primary, derivation_index, secret_key, exported_at, created_at)
+SELECT
+    gen_random_uuid(),
+    id,
+    public_key,
+    CASE WHEN derivation_index IS NULL THEN 'custodial' ELSE 'derived' END,
+    TRUE,
+    derivation_index,
+    secret_key,
+    exported_at,
+    created_at
+FROM users;
+
+INSERT INTO wallets (id, user_id, pubkey, kind, is_primary, created_at)
+SELECT gen_random_uuid(), user_id, pubkey, 'external', FALSE, linked_at
+FROM user_wallets
+ON CONFLICT (pubkey) DO NOTHING;
+
+DROP TABLE IF EXISTS user_wallets;
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_custodial_key_check;
+ALTER TABLE users DROP COLUMN IF EXISTS public_key;
+ALTER TABLE users DROP COLUMN IF EXISTS secret_key;
+ALTER TABLE users DROP COLUMN IF EXISTS derivation_index;
+ALTER TABLE users DROP COLUMN IF EXISTS exported_at;
@@ -0,0 +1,18 @@
+-- Add down migration script here
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS scopes TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE api_keys
+SET scopes = ARRAY(
+    SELECT DISTINCT role_permissions.permission
+    FROM role_assignments
+    JOIN role_permissions ON role_permissions.role_id = role_assignments.role_id
+    WHERE role_assignments.api_key_id = api_keys.id
+      AND role_permissions.permission IN ('users:read', 'users:write', 'tokens:read', 'tokens:mint', 'tokens:sponsor', 'admin')
+);
+
+ALTER TABLE api_keys ALTER COLUMN scopes DROP DEFAULT;
+
+DROP TABLE IF EXISTS role_assignments;
+DROP TABLE IF EXISTS role_permissions;
+DROP TABLE IF EXISTS roles;
+DROP TABLE IF EXISTS permissions;
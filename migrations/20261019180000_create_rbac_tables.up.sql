@@ -0,0 +1,88 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS permissions (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL
+);
+
+INSERT INTO permissions (name, description) VALUES
+    ('users:read', 'Read users and their wallets'),
+    ('users:write', 'Create users and manage their wallets'),
+    ('tokens:read', 'Read mints, mint batches and fee estimates'),
+    ('tokens:mint', 'Mint tokens to wallets and users, one by one or in batches'),
+    ('tokens:sponsor', 'Build and submit transactions whose fees the service pays'),
+    ('tokens:create_mint', 'Create new mints'),
+    ('tokens:freeze', 'Freeze and thaw token accounts'),
+    ('admin', 'Every other permission, plus API key and role management');
+
+CREATE TABLE IF NOT EXISTS roles (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role_id UUID NOT NULL REFERENCES roles (id) ON DELETE CASCADE,
+    permission TEXT NOT NULL REFERENCES permissions (name),
+    PRIMARY KEY (role_id, permission)
+);
+
+-- A principal is either an API key or an operator, a user who logs in with a
+-- magic link and acts on other users' behalf.
+CREATE TABLE IF NOT EXISTS role_assignments (
+    id UUID PRIMARY KEY,
+    role_id UUID NOT NULL REFERENCES roles (id) ON DELETE CASCADE,
+    api_key_id UUID REFERENCES api_keys (id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    CHECK (num_nonnulls(api_key_id, user_id) = 1),
+    UNIQUE (role_id, api_key_id),
+    UNIQUE (role_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS role_assignments_api_key_id_idx ON role_assignments (api_key_id);
+CREATE INDEX IF NOT EXISTS role_assignments_user_id_idx ON role_assignments (user_id);
+
+INSERT INTO roles (id, name, description, created_at) VALUES
+    (gen_random_uuid(), 'end_user', 'Client apps acting for end users', NOW()),
+    (gen_random_uuid(), 'support_agent', 'Look up and fix user accounts and wallets', NOW()),
+    (gen_random_uuid(), 'campaign_manager', 'Mint tokens to users', NOW()),
+    (gen_random_uuid(), 'admin', 'Full access', NOW());
+
+INSERT INTO role_permissions (role_id, permission)
+SELECT roles.id, grants.permission
+FROM (VALUES
+    ('end_user', 'tokens:read'),
+    ('end_user', 'tokens:sponsor'),
+    ('support_agent', 'users:read'),
+    ('support_agent', 'users:write'),
+    ('support_agent', 'tokens:read'),
+    ('campaign_manager', 'users:read'),
+    ('campaign_manager', 'tokens:read'),
+    ('campaign_manager', 'tokens:mint'),
+    ('admin', 'admin')
+) AS grants (role, permission)
+JOIN roles ON roles.name = grants.role;
+
+-- Existing keys keep exactly the access they had: admin keys get the admin
+-- role, every other key gets a role of its own holding its former scopes.
+INSERT INTO roles (id, name, description, created_at)
+SELECT gen_random_uuid(), 'api_key:' || api_keys.id, 'Scopes of API key ' || api_keys.name, NOW()
+FROM api_keys
+WHERE NOT 'admin' = ANY (api_keys.scopes);
+
+INSERT INTO role_permissions (role_id, permission)
+SELECT DISTINCT roles.id, scope
+FROM api_keys
+CROSS JOIN LATERAL unnest(api_keys.scopes) AS scope
+JOIN roles ON roles.name = 'api_key:' || api_keys.id;
+
+INSERT INTO role_assignments (id, role_id, api_key_id, created_at)
+SELECT gen_random_uuid(), roles.id, api_keys.id, NOW()
+FROM api_keys
+JOIN roles ON roles.name = CASE
+    WHEN 'admin' = ANY (api_keys.scopes) THEN 'admin'
+    ELSE 'api_key:' || api_keys.id
+END;
+
+ALTER TABLE api_keys DROP COLUMN scopes;
@@ -0,0 +1,10 @@
+-- Add down migration script here
+DELETE FROM role_permissions WHERE permission IN ('programs:manage', 'programs:earn');
+DELETE FROM roles WHERE name = 'merchant';
+DELETE FROM permissions WHERE name IN ('programs:manage', 'programs:earn');
+
+DROP TABLE IF EXISTS point_earnings;
+DROP TABLE IF EXISTS earning_rules;
+DROP TABLE IF EXISTS programs;
+DROP TABLE IF EXISTS merchants;
+DROP TABLE IF EXISTS mints;
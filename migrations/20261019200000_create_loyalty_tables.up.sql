@@ -0,0 +1,80 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS mints (
+    pubkey TEXT PRIMARY KEY,
+    decimals SMALLINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS merchants (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS programs (
+    id UUID PRIMARY KEY,
+    merchant_id UUID NOT NULL REFERENCES merchants (id),
+    name TEXT NOT NULL,
+    mint_pubkey TEXT NOT NULL REFERENCES mints (pubkey),
+    currency TEXT NOT NULL,
+    max_points_per_purchase BIGINT CHECK (max_points_per_purchase > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS programs_merchant_id_idx ON programs (merchant_id);
+
+CREATE TABLE IF NOT EXISTS earning_rules (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    points_per_unit BIGINT,
+    unit_amount BIGINT,
+    multiplier_bps INTEGER,
+    min_purchase_amount BIGINT NOT NULL DEFAULT 0,
+    max_points BIGINT,
+    starts_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ends_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE,
+    CHECK (kind <> 'base' OR (points_per_unit > 0 AND unit_amount > 0)),
+    CHECK (kind <> 'multiplier' OR multiplier_bps > 0)
+);
+
+CREATE INDEX IF NOT EXISTS earning_rules_program_id_idx ON earning_rules (program_id);
+
+CREATE TABLE IF NOT EXISTS point_earnings (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id),
+    user_id UUID NOT NULL REFERENCES users (id),
+    reference TEXT,
+    purchase_amount BIGINT NOT NULL,
+    points BIGINT NOT NULL,
+    receiver_pubkey TEXT NOT NULL,
+    status TEXT NOT NULL,
+    signature TEXT,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE (program_id, reference)
+);
+
+CREATE INDEX IF NOT EXISTS point_earnings_user_id_idx ON point_earnings (user_id);
+
+INSERT INTO permissions (name, description) VALUES
+    ('programs:manage', 'Manage merchants, loyalty programs and their earning rules'),
+    ('programs:earn', 'Award program points for purchases')
+ON CONFLICT DO NOTHING;
+
+INSERT INTO roles (id, name, description, created_at)
+VALUES (gen_random_uuid(), 'merchant', 'Point of sale integrations awarding points', NOW())
+ON CONFLICT DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission)
+SELECT roles.id, grants.permission
+FROM (VALUES
+    ('merchant', 'programs:earn'),
+    ('campaign_manager', 'programs:manage')
+) AS grants (role, permission)
+JOIN roles ON roles.name = grants.role
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS redemption_order_events;
+DROP TABLE IF EXISTS redemption_orders;
+DROP TABLE IF EXISTS reward_items;
@@ -0,0 +1,47 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS reward_items (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    point_price BIGINT NOT NULL CHECK (point_price > 0),
+    -- Unlimited when NULL.
+    stock INTEGER CHECK (stock >= 0),
+    valid_from TIMESTAMP WITH TIME ZONE NOT NULL,
+    valid_until TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS reward_items_program_id_idx ON reward_items (program_id);
+
+CREATE TABLE IF NOT EXISTS redemption_orders (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id),
+    item_id UUID NOT NULL REFERENCES reward_items (id),
+    user_id UUID NOT NULL REFERENCES users (id),
+    wallet_pubkey TEXT NOT NULL,
+    points BIGINT NOT NULL,
+    status TEXT NOT NULL,
+    code TEXT UNIQUE,
+    burn_signature TEXT,
+    burn_recent_blockhash TEXT,
+    refund_signature TEXT,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- Codes only exist for orders whose burn confirmed.
+    CHECK (code IS NULL OR status IN ('fulfilled', 'refunding', 'refund_failed', 'refunded'))
+);
+
+CREATE INDEX IF NOT EXISTS redemption_orders_user_id_idx ON redemption_orders (user_id);
+
+CREATE TABLE IF NOT EXISTS redemption_order_events (
+    id UUID PRIMARY KEY,
+    order_id UUID NOT NULL REFERENCES redemption_orders (id) ON DELETE CASCADE,
+    step TEXT NOT NULL,
+    detail TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS redemption_order_events_order_id_idx ON redemption_order_events (order_id);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS point_lot_consumptions;
+DROP TABLE IF EXISTS point_expirations;
+DROP TABLE IF EXISTS point_lots;
+ALTER TABLE programs DROP COLUMN IF EXISTS points_ttl_days;
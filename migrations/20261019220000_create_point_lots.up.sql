@@ -0,0 +1,49 @@
+-- Add up migration script here
+-- Points of programs without a TTL never expire.
+ALTER TABLE programs ADD COLUMN IF NOT EXISTS points_ttl_days INTEGER CHECK (points_ttl_days > 0);
+
+CREATE TABLE IF NOT EXISTS point_lots (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users (id),
+    earning_id UUID UNIQUE REFERENCES point_earnings (id),
+    points BIGINT NOT NULL CHECK (points > 0),
+    remaining BIGINT NOT NULL CHECK (remaining >= 0 AND remaining <= points),
+    earned_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS point_lots_user_id_program_id_idx ON point_lots (user_id, program_id) WHERE remaining > 0;
+CREATE INDEX IF NOT EXISTS point_lots_expires_at_idx ON point_lots (expires_at) WHERE remaining > 0;
+
+CREATE TABLE IF NOT EXISTS point_expirations (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users (id),
+    points BIGINT NOT NULL CHECK (points > 0),
+    status TEXT NOT NULL,
+    wallet_pubkey TEXT,
+    signature TEXT,
+    recent_blockhash TEXT,
+    error TEXT,
+    notified_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS point_expirations_status_idx ON point_expirations (status);
+
+-- Points taken from a lot, either by a redemption order or by its expiration.
+CREATE TABLE IF NOT EXISTS point_lot_consumptions (
+    id UUID PRIMARY KEY,
+    lot_id UUID NOT NULL REFERENCES point_lots (id) ON DELETE CASCADE,
+    order_id UUID REFERENCES redemption_orders (id) ON DELETE CASCADE,
+    expiration_id UUID REFERENCES point_expirations (id) ON DELETE CASCADE,
+    points BIGINT NOT NULL CHECK (points > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    CHECK (num_nonnulls(order_id, expiration_id) = 1)
+);
+
+CREATE INDEX IF NOT EXISTS point_lot_consumptions_order_id_idx ON point_lot_consumptions (order_id);
+CREATE INDEX IF NOT EXISTS point_lot_consumptions_expiration_id_idx ON point_lot_consumptions (expiration_id);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS settlement_items;
+DROP TABLE IF EXISTS settlements;
+DROP TABLE IF EXISTS ledger_entries;
+DROP TABLE IF EXISTS ledger_transactions;
+ALTER TABLE programs DROP COLUMN IF EXISTS settlement_mode;
@@ -0,0 +1,63 @@
+-- Add up migration script here
+-- Deferred programs credit points in the ledger and settle them on chain in batches.
+ALTER TABLE programs ADD COLUMN IF NOT EXISTS settlement_mode TEXT NOT NULL DEFAULT 'immediate';
+
+CREATE TABLE IF NOT EXISTS ledger_transactions (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    -- Id of the earning, order or expiration that posted the transaction.
+    reference TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE (kind, reference)
+);
+
+-- Entries of a transaction add up to zero. Only user balances have a user.
+CREATE TABLE IF NOT EXISTS ledger_entries (
+    id UUID PRIMARY KEY,
+    transaction_id UUID NOT NULL REFERENCES ledger_transactions (id) ON DELETE CASCADE,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    account TEXT NOT NULL,
+    user_id UUID REFERENCES users (id),
+    amount BIGINT NOT NULL CHECK (amount <> 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    CHECK ((account = 'user_balance') = (user_id IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS ledger_entries_transaction_id_idx ON ledger_entries (transaction_id);
+CREATE INDEX IF NOT EXISTS ledger_entries_program_id_user_id_idx ON ledger_entries (program_id, user_id);
+
+CREATE TABLE IF NOT EXISTS settlements (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    status TEXT NOT NULL,
+    mint_batch_id UUID REFERENCES mint_batches (id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS settlements_program_id_idx ON settlements (program_id);
+
+-- Net points moved on chain for a user: minted when positive, burned when negative.
+CREATE TABLE IF NOT EXISTS settlement_items (
+    id UUID PRIMARY KEY,
+    settlement_id UUID NOT NULL REFERENCES settlements (id) ON DELETE CASCADE,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users (id),
+    wallet_pubkey TEXT NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount <> 0),
+    status TEXT NOT NULL,
+    -- Position of mints in the settlement's mint batch.
+    mint_batch_position INTEGER,
+    signature TEXT,
+    recent_blockhash TEXT,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS settlement_items_settlement_id_idx ON settlement_items (settlement_id);
+CREATE INDEX IF NOT EXISTS settlement_items_program_id_user_id_idx ON settlement_items (program_id, user_id);
+
+-- A program settles one batch at a time.
+CREATE UNIQUE INDEX IF NOT EXISTS settlements_processing_program_id_idx ON settlements (program_id) WHERE status = 'processing';
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS reconciliation_discrepancies;
+DROP TABLE IF EXISTS reconciliation_mint_snapshots;
+DROP TABLE IF EXISTS reconciliation_reports;
@@ -0,0 +1,35 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS reconciliation_reports (
+    id UUID PRIMARY KEY,
+    status TEXT NOT NULL,
+    discrepancy_count INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    finished_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX IF NOT EXISTS reconciliation_reports_started_at_idx ON reconciliation_reports (started_at);
+
+-- Supply of each mint next to the net supply change the database recorded for it,
+-- both in base units. A later report expects their difference to stay the same.
+CREATE TABLE IF NOT EXISTS reconciliation_mint_snapshots (
+    report_id UUID NOT NULL REFERENCES reconciliation_reports (id) ON DELETE CASCADE,
+    mint_pubkey TEXT NOT NULL REFERENCES mints (pubkey),
+    supply BIGINT NOT NULL,
+    recorded_supply BIGINT NOT NULL,
+    holders INTEGER NOT NULL,
+    PRIMARY KEY (report_id, mint_pubkey)
+);
+
+CREATE TABLE IF NOT EXISTS reconciliation_discrepancies (
+    id UUID PRIMARY KEY,
+    report_id UUID NOT NULL REFERENCES reconciliation_reports (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    mint_pubkey TEXT NOT NULL,
+    -- Signature, owner or user the discrepancy is about, when there is one.
+    subject TEXT,
+    detail TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS reconciliation_discrepancies_report_id_idx ON reconciliation_discrepancies (report_id);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS referral_rewards;
+DROP TABLE IF EXISTS referral_rules;
+DROP TABLE IF EXISTS referrals;
+DROP TABLE IF EXISTS referral_codes;
@@ -0,0 +1,53 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS referral_codes (
+    user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    code TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- Recorded when the referee signs up with the referrer's code. A user is referred at most once.
+CREATE TABLE IF NOT EXISTS referrals (
+    id UUID PRIMARY KEY,
+    referrer_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    referee_id UUID NOT NULL UNIQUE REFERENCES users (id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    CHECK (referrer_id <> referee_id)
+);
+
+CREATE INDEX IF NOT EXISTS referrals_referrer_id_idx ON referrals (referrer_id);
+
+CREATE TABLE IF NOT EXISTS referral_rules (
+    program_id UUID PRIMARY KEY REFERENCES programs (id) ON DELETE CASCADE,
+    referrer_points BIGINT NOT NULL CHECK (referrer_points >= 0),
+    referee_points BIGINT NOT NULL CHECK (referee_points >= 0),
+    -- The referee's first earning at or above this purchase amount qualifies the referral.
+    min_purchase_amount BIGINT NOT NULL DEFAULT 0 CHECK (min_purchase_amount >= 0),
+    max_rewards_per_referrer INTEGER CHECK (max_rewards_per_referrer > 0),
+    max_daily_rewards_per_referrer INTEGER CHECK (max_daily_rewards_per_referrer > 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- One reward per referral and program. Both parties are paid through point earnings.
+CREATE TABLE IF NOT EXISTS referral_rewards (
+    id UUID PRIMARY KEY,
+    referral_id UUID NOT NULL REFERENCES referrals (id) ON DELETE CASCADE,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    referrer_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    referee_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    qualifying_earning_id UUID NOT NULL REFERENCES point_earnings (id),
+    referrer_points BIGINT NOT NULL,
+    referee_points BIGINT NOT NULL,
+    referrer_earning_id UUID REFERENCES point_earnings (id),
+    referee_earning_id UUID REFERENCES point_earnings (id),
+    status TEXT NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE (referral_id, program_id)
+);
+
+CREATE INDEX IF NOT EXISTS referral_rewards_referrer_id_program_id_idx ON referral_rewards (referrer_id, program_id);
+CREATE INDEX IF NOT EXISTS referral_rewards_referee_id_idx ON referral_rewards (referee_id);
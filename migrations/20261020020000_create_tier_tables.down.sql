@@ -0,0 +1,5 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS tier_badges;
+DROP TABLE IF EXISTS user_tiers;
+DROP TABLE IF EXISTS program_tiers;
+ALTER TABLE programs DROP COLUMN IF EXISTS tier_basis;
@@ -0,0 +1,45 @@
+-- Add up migration script here
+-- What tier thresholds are compared with: points earned so far, or points held now.
+ALTER TABLE programs ADD COLUMN IF NOT EXISTS tier_basis TEXT NOT NULL DEFAULT 'lifetime_earned';
+
+CREATE TABLE IF NOT EXISTS program_tiers (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    threshold BIGINT NOT NULL CHECK (threshold >= 0),
+    -- Mint of the badge holders of the tier get, frozen so it cannot be transferred.
+    badge_mint_pubkey TEXT REFERENCES mints (pubkey),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS program_tiers_program_id_name_idx ON program_tiers (program_id, name) WHERE archived_at IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS program_tiers_program_id_threshold_idx ON program_tiers (program_id, threshold) WHERE archived_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS user_tiers (
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    tier_id UUID REFERENCES program_tiers (id),
+    points BIGINT NOT NULL,
+    computed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (user_id, program_id)
+);
+
+CREATE TABLE IF NOT EXISTS tier_badges (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    tier_id UUID NOT NULL REFERENCES program_tiers (id),
+    mint_pubkey TEXT NOT NULL,
+    wallet_pubkey TEXT NOT NULL,
+    status TEXT NOT NULL,
+    mint_signature TEXT,
+    freeze_signature TEXT,
+    revoke_signature TEXT,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS tier_badges_user_id_program_id_idx ON tier_badges (user_id, program_id);
@@ -0,0 +1,9 @@
+-- Add down migration script here
+DELETE FROM point_lot_consumptions WHERE conversion_id IS NOT NULL;
+ALTER TABLE point_lot_consumptions DROP CONSTRAINT IF EXISTS point_lot_consumptions_check;
+ALTER TABLE point_lot_consumptions DROP COLUMN IF EXISTS conversion_id;
+ALTER TABLE point_lot_consumptions ADD CONSTRAINT point_lot_consumptions_check CHECK (num_nonnulls(order_id, expiration_id) = 1);
+ALTER TABLE point_lots DROP COLUMN IF EXISTS conversion_id;
+DROP TABLE IF EXISTS point_conversions;
+DROP TABLE IF EXISTS exchange_rate_snapshots;
+DROP TABLE IF EXISTS exchange_rates;
@@ -0,0 +1,56 @@
+-- Add up migration script here
+-- `source_points` points of the source program convert into `target_points` points of the target program.
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    id UUID PRIMARY KEY,
+    source_program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    target_program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    source_points BIGINT NOT NULL CHECK (source_points > 0),
+    target_points BIGINT NOT NULL CHECK (target_points > 0),
+    min_source_points BIGINT NOT NULL DEFAULT 1 CHECK (min_source_points > 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE (source_program_id, target_program_id),
+    CHECK (source_program_id <> target_program_id)
+);
+
+-- Every version of a rate. Conversions reference the one they were priced with.
+CREATE TABLE IF NOT EXISTS exchange_rate_snapshots (
+    id UUID PRIMARY KEY,
+    rate_id UUID NOT NULL REFERENCES exchange_rates (id) ON DELETE CASCADE,
+    source_points BIGINT NOT NULL,
+    target_points BIGINT NOT NULL,
+    min_source_points BIGINT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS exchange_rate_snapshots_rate_id_idx ON exchange_rate_snapshots (rate_id);
+
+CREATE TABLE IF NOT EXISTS point_conversions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id),
+    source_program_id UUID NOT NULL REFERENCES programs (id),
+    target_program_id UUID NOT NULL REFERENCES programs (id),
+    rate_snapshot_id UUID NOT NULL REFERENCES exchange_rate_snapshots (id),
+    source_points BIGINT NOT NULL CHECK (source_points > 0),
+    target_points BIGINT NOT NULL CHECK (target_points > 0),
+    wallet_pubkey TEXT NOT NULL,
+    status TEXT NOT NULL,
+    signature TEXT,
+    recent_blockhash TEXT,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS point_conversions_user_id_idx ON point_conversions (user_id);
+CREATE INDEX IF NOT EXISTS point_conversions_status_idx ON point_conversions (status);
+
+-- Converted points are taken from the source program's lots and land as a lot of the target program.
+ALTER TABLE point_lots ADD COLUMN IF NOT EXISTS conversion_id UUID UNIQUE REFERENCES point_conversions (id);
+ALTER TABLE point_lot_consumptions ADD COLUMN IF NOT EXISTS conversion_id UUID REFERENCES point_conversions (id) ON DELETE CASCADE;
+ALTER TABLE point_lot_consumptions DROP CONSTRAINT IF EXISTS point_lot_consumptions_check;
+ALTER TABLE point_lot_consumptions ADD CONSTRAINT point_lot_consumptions_check CHECK (num_nonnulls(order_id, expiration_id, conversion_id) = 1);
+
+CREATE INDEX IF NOT EXISTS point_lot_consumptions_conversion_id_idx ON point_lot_consumptions (conversion_id);
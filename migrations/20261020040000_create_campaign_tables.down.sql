@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS campaign_awards;
+DROP TABLE IF EXISTS campaigns;
@@ -0,0 +1,40 @@
+-- Add up migration script here
+-- Time-boxed promotions adding bonus points on top of the program's earning rules.
+CREATE TABLE IF NOT EXISTS campaigns (
+    id UUID PRIMARY KEY,
+    program_id UUID NOT NULL REFERENCES programs (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    multiplier_bps INTEGER,
+    bonus_points BIGINT,
+    category TEXT,
+    min_purchase_amount BIGINT NOT NULL DEFAULT 0 CHECK (min_purchase_amount >= 0),
+    budget_points BIGINT NOT NULL CHECK (budget_points > 0),
+    -- Bonus points reserved by earnings so far. The campaign is exhausted once they reach the budget.
+    issued_points BIGINT NOT NULL DEFAULT 0 CHECK (issued_points >= 0 AND issued_points <= budget_points),
+    status TEXT NOT NULL,
+    starts_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ends_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    exhausted_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    CHECK (ends_at > starts_at),
+    CHECK (kind <> 'multiplier' OR multiplier_bps > 10000),
+    CHECK (kind <> 'first_purchase' OR bonus_points > 0),
+    CHECK (kind <> 'category' OR (multiplier_bps > 10000 AND category IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS campaigns_program_id_idx ON campaigns (program_id);
+
+-- Bonus points a campaign added to one earning.
+CREATE TABLE IF NOT EXISTS campaign_awards (
+    id UUID PRIMARY KEY,
+    campaign_id UUID NOT NULL REFERENCES campaigns (id) ON DELETE CASCADE,
+    earning_id UUID NOT NULL REFERENCES point_earnings (id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users (id),
+    points BIGINT NOT NULL CHECK (points > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE (campaign_id, earning_id)
+);
+
+CREATE INDEX IF NOT EXISTS campaign_awards_earning_id_idx ON campaign_awards (earning_id);
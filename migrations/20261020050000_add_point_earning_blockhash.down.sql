@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE point_earnings DROP COLUMN IF EXISTS recent_blockhash;
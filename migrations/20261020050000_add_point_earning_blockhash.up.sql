@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE point_earnings ADD COLUMN IF NOT EXISTS recent_blockhash TEXT;
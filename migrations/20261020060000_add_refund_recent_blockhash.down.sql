@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE redemption_orders DROP COLUMN IF EXISTS refund_recent_blockhash;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE redemption_orders ADD COLUMN IF NOT EXISTS refund_recent_blockhash TEXT;
@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- Token accounts whose rent the service paid in sponsored transactions, counted per signing owner.
+CREATE TABLE IF NOT EXISTS sponsored_token_accounts (
+    account_pubkey TEXT PRIMARY KEY,
+    owner_pubkey TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS sponsored_token_accounts_owner_pubkey_idx ON sponsored_token_accounts (owner_pubkey);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE distribution_leaves DROP COLUMN IF EXISTS recent_blockhash;
@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Emails are matched regardless of case, so they must also be unique regardless of case.
+-- Both statements fail while two users share an email up to case; merge them by hand first.
+UPDATE users SET email = LOWER(TRIM(email)) WHERE email <> LOWER(TRIM(email));
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_idx ON users (LOWER(email));
@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE point_lots DROP COLUMN IF EXISTS wallet_pubkey;
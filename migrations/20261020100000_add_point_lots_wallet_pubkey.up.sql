@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Wallet the points of a lot were minted to, so they expire from that wallet.
+ALTER TABLE point_lots ADD COLUMN IF NOT EXISTS wallet_pubkey TEXT;
+
+UPDATE point_lots SET wallet_pubkey = point_earnings.receiver_pubkey FROM point_earnings WHERE point_earnings.id = point_lots.earning_id AND point_lots.wallet_pubkey IS NULL;
+UPDATE point_lots SET wallet_pubkey = point_conversions.wallet_pubkey FROM point_conversions WHERE point_conversions.id = point_lots.conversion_id AND point_lots.wallet_pubkey IS NULL;
@@ -0,0 +1,2 @@
+-- Add down migration script here
+UPDATE tier_badges SET status = 'revoked' WHERE status = 'stranded';
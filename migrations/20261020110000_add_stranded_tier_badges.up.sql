@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- Badges left frozen in external wallets were recorded as revoked.
+UPDATE tier_badges SET status = 'stranded' WHERE status = 'revoked' AND error = 'Badge stays frozen in a wallet the service does not hold';
@@ -0,0 +1,104 @@
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport,
+    AsyncTransport,
+    Message,
+    Tokio1Executor
+};
+use thiserror::Error;
+
+/// Connection settings for `MailerClient::smtp`.
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Upgrades the connection with STARTTLS. Local stand-ins like MailHog
+    /// only speak plain SMTP.
+    pub starttls: bool,
+    pub from: String
+}
+
+/// Delivers transactional emails, such as verification codes and magic links.
+#[derive(Clone)]
+pub enum MailerClient {
+    /// Writes emails to the service logs instead of delivering them, secrets
+    /// included. Only used when `MAILER` is explicitly set to `log`, for local
+    /// development.
+    Log,
+    Smtp {
+        transport: Box<AsyncSmtpTransport<Tokio1Executor>>,
+        from: Mailbox
+    }
+}
+
+impl MailerClient {
+    pub fn smtp(config: SmtpConfig) -> Result<Self, MailerError> {
+        let builder = match config.starttls {
+            true => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host).map_err(|e| {
+                println!("Error configuring SMTP transport: {}", e);
+                MailerError::ConfigError
+            })?,
+            false => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        };
+
+        let builder = match (config.username, config.password) {
+            (Some(username), Some(password)) => builder.credentials(Credentials::new(username, password)),
+            _ => builder
+        };
+
+        let from = config.from.parse::<Mailbox>().map_err(|e| {
+            println!("Error parsing sender address: {}", e);
+            MailerError::ConfigError
+        })?;
+
+        Ok(MailerClient::Smtp {
+            transport: Box::new(builder.port(config.port).build()),
+            from
+        })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailerError> {
+        match self {
+            MailerClient::Log => {
+                println!("Email to {}: {}\n{}", to, subject, body);
+                Ok(())
+            },
+            MailerClient::Smtp { transport, from } => {
+                let to = to.parse::<Mailbox>().map_err(|e| {
+                    println!("Error parsing recipient address: {}", e);
+                    MailerError::InvalidAddressError
+                })?;
+
+                let message = Message::builder()
+                    .from(from.clone())
+                    .to(to)
+                    .subject(subject)
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(body.to_string())
+                    .map_err(|e| {
+                        println!("Error building email: {}", e);
+                        MailerError::SendError
+                    })?;
+
+                transport.send(message).await.map_err(|e| {
+                    println!("Error sending email: {}", e);
+                    MailerError::SendError
+                })?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum MailerError {
+    #[error("Mailer is misconfigured")]
+    ConfigError,
+    #[error("Email address could not be parsed")]
+    InvalidAddressError,
+    #[error("Error sending email")]
+    SendError
+}
@@ -1 +1,2 @@
-pub mod solana_rpc_client;
\ No newline at end of file
+pub mod solana_rpc_client;
+pub mod mailer_client;
\ No newline at end of file
@@ -1,9 +1,28 @@
-use serde::Serialize;
-use solana_client::rpc_client::RpcClient;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient, 
+    rpc_config::{
+        RpcAccountInfoConfig, 
+        RpcProgramAccountsConfig, 
+        RpcSimulateTransactionAccountsConfig, 
+        RpcSimulateTransactionConfig, 
+        RpcTransactionConfig
+    }, 
+    rpc_filter::{Memcmp, RpcFilterType}, 
+    rpc_request::{MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS}, 
+    rpc_response::RpcSimulateTransactionResult
+};
 use solana_sdk::{
+    account::Account as SolanaAccount, 
+    borsh1::try_from_slice_unchecked, 
     commitment_config::CommitmentConfig, 
+    compute_budget::ComputeBudgetInstruction, 
+    hash::Hash, 
     instruction::Instruction, 
     message::Message, 
+    packet::PACKET_DATA_SIZE, 
     program_pack::Pack, 
     pubkey::Pubkey, 
     signature::{
@@ -11,27 +30,76 @@ use solana_sdk::{
         Signature
     }, 
     signer::Signer, 
-    system_instruction::create_account, 
+    system_instruction::{create_account, transfer}, 
     transaction::Transaction
 };
 use spl_associated_token_account::{
     get_associated_token_address, 
-    instruction::create_associated_token_account
+    instruction::{
+        create_associated_token_account, 
+        create_associated_token_account_idempotent
+    }
 };
-use spl_token::{state::Mint, instruction::{
+use spl_token::{state::{Account as TokenAccount, AccountState, Mint}, instruction::{
+    burn_checked, 
+    close_account, 
+    freeze_account, 
     initialize_mint, 
-    mint_to
+    mint_to, 
+    thaw_account, 
+    transfer_checked, 
+    TokenInstruction
 }};
 use thiserror::Error;
 use tokio::task;
-use std::sync::Arc;
+use std::{collections::{hash_map::Entry, HashMap, HashSet}, sync::Arc};
 
 use crate::helpers::solana_helper::SolanaHelper;
 
+/// Maximum compute units a single transaction can request.
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+
+/// Compute units the cluster grants each instruction of a transaction that sets
+/// no compute unit limit.
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+
+/// Conservative compute cost of an idempotent ATA creation, used to pack batches.
+const CREATE_ATA_COMPUTE_UNITS: u64 = 40_000;
+
+/// Conservative compute cost of a `mint_to` instruction, used to pack batches.
+const MINT_TO_COMPUTE_UNITS: u64 = 6_000;
+
+/// Decimals of every mint the service creates.
+pub const MINT_DECIMALS: u8 = 6;
+
+/// Controls how compute budget instructions are attached to outgoing transactions.
+#[derive(Clone, Debug)]
+pub struct PriorityFeeConfig {
+    /// Upper bound, in lamports, for the priority part of a transaction fee.
+    pub max_priority_fee_lamports: u64,
+    /// Extra compute units requested on top of the simulated consumption, in percent.
+    pub compute_unit_headroom_percent: u64,
+    /// Percentile of recent prioritization fees used as the compute unit price.
+    pub fee_percentile: u64,
+}
+
+impl Default for PriorityFeeConfig {
+    fn default() -> Self {
+        Self {
+            max_priority_fee_lamports: 100_000,
+            compute_unit_headroom_percent: 10,
+            fee_percentile: 75
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SolanaRpcClient {
     client: Arc<RpcClient>,
     keypair: Arc<Keypair>,
+    priority_fee_config: PriorityFeeConfig,
 }
 
 impl SolanaRpcClient {
@@ -39,20 +107,27 @@ impl SolanaRpcClient {
         rpc_url: &str, 
         commitment: CommitmentConfig,
         keypair_base58_string: &str,
+        priority_fee_config: PriorityFeeConfig,
     ) -> Self {
         let client = RpcClient::new_with_commitment(
             rpc_url, 
             commitment
         );
 
-        let keypair = Keypair::from_base58_string(&keypair_base58_string);
+        let keypair = Keypair::from_base58_string(keypair_base58_string);
         
         Self { 
             client: Arc::new(client),
-            keypair: Arc::new(keypair)
+            keypair: Arc::new(keypair),
+            priority_fee_config
         }
     }
 
+    /// The fee payer of every transaction, and the authority of the mints it creates.
+    pub fn service_pubkey(&self) -> String {
+        self.keypair.pubkey().to_string()
+    }
+
     pub async fn fetch_token_account(
         &self, 
         mint_pubkey_str: &str
@@ -62,15 +137,7 @@ impl SolanaRpcClient {
         let client = Arc::clone(&self.client);
 
         let mint_result = task::spawn_blocking(move || -> Result<Mint, SolanaError> {
-            let account = client.get_account(&mint_pubkey).map_err(|e| {
-                println!("Error getting account: {}", e);
-                SolanaError::AccountFetchError
-            })?;
-
-            Mint::unpack(&account.data).map_err(|e| {
-                println!("Error parsing mint account: {}", e);
-                SolanaError::MintParseError
-            })
+            Self::get_mint(&client, &mint_pubkey)
         }).await;
 
         match mint_result {
@@ -78,7 +145,8 @@ impl SolanaRpcClient {
                 let mint_response = MintResponse {
                     pubkey: mint_pubkey_str.to_string(),
                     supply: mint.supply,
-                    decimals: mint.decimals
+                    decimals: mint.decimals,
+                    mint_authority: Option::<Pubkey>::from(mint.mint_authority).map(|authority| authority.to_string())
                 };
 
                 Ok(mint_response)
@@ -89,75 +157,246 @@ impl SolanaRpcClient {
     
     }
 
+    /// Token balances, in base units, of each owner's ATA for the mint. Owners
+    /// without an ATA hold nothing.
+    pub async fn get_token_balances(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_strs: Vec<String>
+    ) -> Result<Vec<u64>, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let owner_pubkeys = owner_pubkey_strs
+            .iter()
+            .map(|owner_pubkey_str| SolanaHelper::try_to_convert_str_to_pubkey(owner_pubkey_str))
+            .collect::<Result<Vec<Pubkey>, SolanaError>>()?;
+
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<Vec<u64>, SolanaError> {
+            let ata_addresses: Vec<Pubkey> = owner_pubkeys
+                .iter()
+                .map(|owner_pubkey| get_associated_token_address(owner_pubkey, &mint_pubkey))
+                .collect();
+
+            let mut balances = Vec::with_capacity(ata_addresses.len());
+
+            for chunk in ata_addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+                let accounts = client
+                    .get_multiple_accounts(chunk)
+                    .map_err(|e| {
+                        println!("Error getting multiple accounts: {}", e);
+                        SolanaError::AccountFetchError
+                    })?;
+
+                for account in accounts {
+                    let balance = match account {
+                        Some(account) => TokenAccount::unpack(&account.data)
+                            .map_err(|e| {
+                                println!("Error parsing token account: {}", e);
+                                SolanaError::TokenAccountParseError
+                            })?
+                            .amount,
+                        None => 0
+                    };
+
+                    balances.push(balance);
+                }
+            }
+
+            Ok(balances)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Balance and freeze state of the owner's ATA for the mint, `None` when the
+    /// ATA does not exist.
+    pub async fn get_token_account_state(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str
+    ) -> Result<Option<TokenAccountState>, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let owner_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(owner_pubkey_str)?;
+        let token_account_pubkey = get_associated_token_address(&owner_pubkey, &mint_pubkey);
+
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<Option<TokenAccountState>, SolanaError> {
+            let account = client
+                .get_account_with_commitment(&token_account_pubkey, client.commitment())
+                .map_err(|e| {
+                    println!("Error getting account: {}", e);
+                    SolanaError::AccountFetchError
+                })?
+                .value;
+
+            let Some(account) = account else {
+                return Ok(None);
+            };
+
+            let token_account = TokenAccount::unpack(&account.data).map_err(|e| {
+                println!("Error parsing token account: {}", e);
+                SolanaError::TokenAccountParseError
+            })?;
+
+            Ok(Some(TokenAccountState {
+                amount: token_account.amount,
+                frozen: token_account.state == AccountState::Frozen
+            }))
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Owners holding a non-zero balance of the mint, summed across their token
+    /// accounts, ATA or not.
+    pub async fn get_token_holders(&self, mint_pubkey_str: &str) -> Result<Vec<TokenHolder>, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<Vec<TokenHolder>, SolanaError> {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(TokenAccount::LEN as u64),
+                    // The mint is the first field of a token account.
+                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, mint_pubkey.to_bytes().to_vec()))
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+
+            let accounts = client
+                .get_program_accounts_with_config(&spl_token::ID, config)
+                .map_err(|e| {
+                    println!("Error getting token accounts: {}", e);
+                    SolanaError::AccountFetchError
+                })?;
+
+            let mut holders: Vec<TokenHolder> = Vec::new();
+
+            for (_, account) in accounts {
+                let token_account = TokenAccount::unpack(&account.data).map_err(|e| {
+                    println!("Error parsing token account: {}", e);
+                    SolanaError::TokenAccountParseError
+                })?;
+
+                if token_account.amount == 0 {
+                    continue;
+                }
+
+                let owner = token_account.owner.to_string();
+
+                match holders.iter_mut().find(|holder| holder.owner == owner) {
+                    Some(holder) => holder.amount = holder.amount.saturating_add(token_account.amount),
+                    None => holders.push(TokenHolder { owner, amount: token_account.amount })
+                }
+            }
+
+            Ok(holders)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Status of each signature, searching the full ledger history. `None` when the
+    /// cluster does not know the signature.
+    pub async fn get_signature_statuses(
+        &self,
+        signature_strs: Vec<String>
+    ) -> Result<Vec<Option<TransactionStatus>>, SolanaError> {
+        let signatures = signature_strs
+            .iter()
+            .map(|signature_str| signature_str.parse::<Signature>().map_err(|e| {
+                println!("Error parsing signature: {}", e);
+                SolanaError::SignatureParsingError
+            }))
+            .collect::<Result<Vec<Signature>, SolanaError>>()?;
+
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<Vec<Option<TransactionStatus>>, SolanaError> {
+            let mut statuses = Vec::with_capacity(signatures.len());
+
+            for chunk in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+                let chunk_statuses = client
+                    .get_signature_statuses_with_history(chunk)
+                    .map_err(|e| {
+                        println!("Error getting signature statuses: {}", e);
+                        SolanaError::GetSignatureStatusError
+                    })?
+                    .value;
+
+                statuses.extend(chunk_statuses.into_iter().map(|status| status.map(|status| match status.err {
+                    Some(err) => TransactionStatus::Failed(err.to_string()),
+                    None if status.satisfies_commitment(client.commitment()) => TransactionStatus::Confirmed,
+                    None => TransactionStatus::Pending
+                })));
+            }
+
+            Ok(statuses)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
     pub async fn create_token_mint(
-        &self
-    ) -> Result<CreateMintResponse, SolanaError> {
+        &self,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<CreateMintResponse>, SolanaError> {
         let payer = self.keypair.clone();
         let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
         let mint_keypair = Keypair::new();
 
         let mint_pubkey = mint_keypair.pubkey();
         
-        let task_result = task::spawn_blocking(move || -> Result<Signature, SolanaError> {
-            let lamports = client
-                .get_minimum_balance_for_rent_exemption(Mint::LEN)
-                .map_err(|e| {
-                    println!("Error getting minimum balance: {}", e);
-                    SolanaError::GetMinimumBalanceError
-                })?;
+        let task_result = task::spawn_blocking(move || -> Result<Execution, SolanaError> {
+            let lamports = Self::get_rent_exemption(&client, Mint::LEN)?;
     
-            let create_account_instruction = create_account(
+            let instructions = Self::create_mint_instructions(
                 &payer.pubkey(), 
                 &mint_pubkey, 
-                lamports, 
-                Mint::LEN as u64, 
-                &spl_token::ID
-            );
-    
-            let initialize_mint_instruction = initialize_mint(
-                &spl_token::ID,
-                &mint_pubkey, 
-                &payer.pubkey(), 
-                Some(&payer.pubkey()), 
-                6
-            ).map_err(|e| {
-                println!("Error creating initialize_mint instruction: {}", e);
-                SolanaError::CreateInstructionError
-            })?;
-    
-            let message = Message::new(
-                &[create_account_instruction, initialize_mint_instruction], 
-                Some(&payer.pubkey())
-            );
-    
-            let recent_blockhash = client.get_latest_blockhash().map_err(|e| {
-                println!("Error getting latest blockhash: {}", e);
-                SolanaError::GetBlockhashError
-            })?;
-    
-            let transaction = Transaction::new(
-                &[&payer, &mint_keypair], 
-                message, 
-                recent_blockhash
-            );
+                lamports
+            )?;
 
-            client
-                .send_and_confirm_transaction(&transaction)
-                .map_err(|e| {
-                    println!("Error sending transaction: {}", e);
-                    SolanaError::SendTransactionError
-                })
+            Self::execute(
+                &client, 
+                &priority_fee_config, 
+                &payer, 
+                &[&mint_keypair], 
+                &instructions,
+                dry_run
+            )
         }).await;
 
         match task_result {
-            Ok(Ok(signature)) => {
+            Ok(Ok(Execution::Sent(sent))) => {
                 let create_mint_response = CreateMintResponse {
                     pubkey: mint_pubkey.to_string(),
-                    signature: signature.to_string()
+                    signature: sent.signature.to_string(),
+                    fee: sent.fee
                 };
         
-                Ok(create_mint_response)
+                Ok(TransactionOutcome::Sent(create_mint_response))
             },
+            Ok(Ok(Execution::Simulated(simulation))) => Ok(TransactionOutcome::Simulated(simulation)),
             Ok(Err(e)) => Err(e),
             Err(_) => Err(SolanaError::UnkownError)
         }
@@ -167,167 +406,2323 @@ impl SolanaRpcClient {
         &self,
         mint_pubkey_str: &str,
         receiver_pubkey_str: &str,
-        amount: u64
-    ) -> Result<MintToResponse, SolanaError> {
+        amount: u64,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<MintToResponse>, SolanaError> {
         let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
         let receiver_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(receiver_pubkey_str)?;
         let payer = self.keypair.clone();
 
         let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
 
-        let task_result = task::spawn_blocking(move || -> Result<Signature, SolanaError> {
+        let task_result = task::spawn_blocking(move || -> Result<Execution, SolanaError> {
             let ata = Self::get_and_verify_ata(
                 &client, 
                 &receiver_pubkey, 
                 &mint_pubkey
             )?;
     
-            let mut instructions: Vec<Instruction> = Vec::new();
-    
-            if !ata.is_created {
-                let create_ata_instruction = create_associated_token_account(
-                    &payer.pubkey(), 
-                    &receiver_pubkey, 
-                    &mint_pubkey, 
-                    &spl_token::ID
-                );
-    
-                instructions.push(create_ata_instruction);
-            }
-    
-            let mint_to_instruction = mint_to(
-                &spl_token::ID, 
-                &mint_pubkey, 
-                &ata.ata_pubkey, 
+            let instructions = Self::mint_to_instructions(
                 &payer.pubkey(), 
-                &[], 
+                &receiver_pubkey, 
+                &mint_pubkey, 
+                &ata, 
                 amount
-            ).map_err(|e| {
-                println!("Error creating mint_to instruction: {}", e);
-                SolanaError::CreateInstructionError
-            })?;
-    
-            instructions.push(mint_to_instruction);
-    
-            let recent_blockhash = client.get_latest_blockhash().map_err(|e| {
-                println!("Error getting latest blockhash: {}", e);
-                SolanaError::GetBlockhashError
-            })?;
-    
-    
-            let message = Message::new(
-                &instructions, 
-                Some(&payer.pubkey())
-            );
-    
-            let transaction = Transaction::new(
-                &[payer], 
-                message, 
-                recent_blockhash
-            );
+            )?;
     
-            client
-                .send_and_confirm_transaction(&transaction)
-                .map_err(|e| {
-                    println!("Error sending transaction: {}", e);
-                    SolanaError::SendTransactionError
-                })
-
+            Self::execute(
+                &client, 
+                &priority_fee_config, 
+                &payer, 
+                &[], 
+                &instructions,
+                dry_run
+            )
         }).await;
 
         match task_result {
-            Ok(Ok(signature)) => {
+            Ok(Ok(Execution::Sent(sent))) => {
                 let mint_to_response = MintToResponse {
-                    signature: signature.to_string()
+                    signature: sent.signature.to_string(),
+                    fee: sent.fee
                 };
         
-                Ok(mint_to_response)
+                Ok(TransactionOutcome::Sent(mint_to_response))
             },
+            Ok(Ok(Execution::Simulated(simulation))) => Ok(TransactionOutcome::Simulated(simulation)),
             Ok(Err(e)) => Err(e),
             Err(_) => Err(SolanaError::UnkownError)
         }
       
     }
 
-    fn get_and_verify_ata(
-        rpc_client: &RpcClient,
-        wallet_pubkey: &Pubkey,
-        mint_pubkey: &Pubkey
-    ) -> Result<VerifyAndGetAtaResponse, SolanaError> {
-        let ata_address = get_associated_token_address(
-            wallet_pubkey, 
-            mint_pubkey
-        );
+    /// Freezes or thaws the owner's token account of a mint. The service keypair is
+    /// the freeze authority of every mint it creates.
+    pub async fn set_token_account_frozen(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str,
+        frozen: bool,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<FreezeAccountResponse>, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let owner_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(owner_pubkey_str)?;
+        let token_account_pubkey = get_associated_token_address(&owner_pubkey, &mint_pubkey);
+        let payer = self.keypair.clone();
 
-        let result = rpc_client.get_account_with_commitment(&ata_address, rpc_client.commitment())
-            .map_err(|e| {
-                println!("Error in get_account_with_commitment: {}", e);
-                SolanaError::AccountFetchError
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<Execution, SolanaError> {
+            let instruction = match frozen {
+                true => freeze_account(
+                    &spl_token::ID, 
+                    &token_account_pubkey, 
+                    &mint_pubkey, 
+                    &payer.pubkey(), 
+                    &[]
+                ),
+                false => thaw_account(
+                    &spl_token::ID, 
+                    &token_account_pubkey, 
+                    &mint_pubkey, 
+                    &payer.pubkey(), 
+                    &[]
+                )
+            }.map_err(|e| {
+                println!("Error creating freeze instruction: {}", e);
+                SolanaError::CreateInstructionError
             })?;
-        
-        match result.value {
-            Some(account) => {
-                if account.owner == spl_token::ID {
-                    Ok(VerifyAndGetAtaResponse {
-                        ata_pubkey: ata_address,
-                        is_created: true
-                    })
-                } else {
-                    Err(SolanaError::AtaOwnerError)
-                }
+
+            Self::execute(
+                &client, 
+                &priority_fee_config, 
+                &payer, 
+                &[], 
+                &[instruction],
+                dry_run
+            )
+        }).await;
+
+        match task_result {
+            Ok(Ok(Execution::Sent(sent))) => {
+                let freeze_account_response = FreezeAccountResponse {
+                    token_account_pubkey: token_account_pubkey.to_string(),
+                    frozen,
+                    signature: sent.signature.to_string(),
+                    fee: sent.fee
+                };
+
+                Ok(TransactionOutcome::Sent(freeze_account_response))
             },
-            None => {
-                Ok(VerifyAndGetAtaResponse {
-                    ata_pubkey: ata_address,
-                    is_created: false
-                })
+            Ok(Ok(Execution::Simulated(simulation))) => Ok(TransactionOutcome::Simulated(simulation)),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    pub async fn estimate_operation(
+        &self,
+        operation: PlannedOperation
+    ) -> Result<FeeEstimateResponse, SolanaError> {
+        let payer_pubkey = self.keypair.pubkey();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<FeeEstimateResponse, SolanaError> {
+            match operation {
+                PlannedOperation::CreateMint => Self::estimate_create_mint(
+                    &client, 
+                    &priority_fee_config, 
+                    &payer_pubkey
+                ),
+                PlannedOperation::MintTo { mint_pubkey, recipients } => Self::estimate_mint_to(
+                    &client, 
+                    &priority_fee_config, 
+                    &payer_pubkey, 
+                    &mint_pubkey, 
+                    &recipients
+                ),
+                PlannedOperation::Transfer { mint_pubkey, source_pubkey, destination_pubkey, amount } => Self::estimate_transfer(
+                    &client, 
+                    &priority_fee_config, 
+                    &payer_pubkey, 
+                    &mint_pubkey, 
+                    &source_pubkey, 
+                    &destination_pubkey, 
+                    amount
+                )
             }
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
         }
     }
-}
 
-#[derive(Debug)]
-pub struct VerifyAndGetAtaResponse {
-    pub ata_pubkey: Pubkey,
-    pub is_created: bool
-}
+    fn estimate_create_mint(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer_pubkey: &Pubkey
+    ) -> Result<FeeEstimateResponse, SolanaError> {
+        let lamports = Self::get_rent_exemption(rpc_client, Mint::LEN)?;
 
-#[derive(Serialize, Debug)]
-pub struct CreateMintResponse {
-    pub pubkey: String,
-    pub signature: String
-}
+        let instructions = Self::create_mint_instructions(
+            payer_pubkey, 
+            &Keypair::new().pubkey(), 
+            lamports
+        )?;
 
-#[derive(Serialize, Debug)]
-pub struct MintToResponse {
-    pub signature: String
-}
+        let fee = Self::estimate_fee(rpc_client, config, payer_pubkey, &instructions)?;
 
-#[derive(Serialize, Debug)]
-pub struct MintResponse {
-    pub pubkey: String,
-    pub supply: u64,
-    pub decimals: u8
-}
+        let mut estimate = FeeEstimateResponse::default();
+        estimate.add_accounts(1, lamports);
+        estimate.add_transactions(1, &fee);
 
-#[derive(Error, Debug)]
-pub enum SolanaError {
-    #[error("Pubkey could not be parsed")]
-    PubkeyParsingError,
-    #[error("Mint account could not be parsed")]
-    MintParseError,
-    #[error("Unknown error occurred")]
-    UnkownError,
-    #[error("Error fetching account")]
-    AccountFetchError,
-    #[error("Error creating instruction")]
-    CreateInstructionError,
-    #[error("Error getting latest blockhash")]
-    GetBlockhashError,
-    #[error("Error sending transaction")]
-    SendTransactionError,
-    #[error("Error getting minimum balance")]
-    GetMinimumBalanceError,
-    #[error("ATA is not owned by spl program")]
-    AtaOwnerError
-}
\ No newline at end of file
+        Ok(estimate)
+    }
+
+    /// Estimates the transactions a batch mint sends: recipients are packed the
+    /// way `pack_mint_to_batch` packs them and each distinct group shape is
+    /// simulated once. Rent is counted once per missing ATA, however many times
+    /// its receiver appears.
+    fn estimate_mint_to(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer_pubkey: &Pubkey,
+        mint_pubkey_str: &str,
+        recipients: &[MintRecipient]
+    ) -> Result<FeeEstimateResponse, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let receiver_pubkeys = recipients
+            .iter()
+            .map(|recipient| SolanaHelper::try_to_convert_str_to_pubkey(&recipient.receiver_pubkey))
+            .collect::<Result<Vec<Pubkey>, SolanaError>>()?;
+
+        let atas = Self::get_and_verify_atas(rpc_client, &receiver_pubkeys, &mint_pubkey)?;
+
+        let mut estimate = FeeEstimateResponse::default();
+
+        let missing_atas: HashSet<Pubkey> = atas
+            .iter()
+            .filter(|ata| !ata.is_created)
+            .map(|ata| ata.ata_pubkey)
+            .collect();
+        if !missing_atas.is_empty() {
+            let rent = Self::get_rent_exemption(rpc_client, TokenAccount::LEN)?;
+            estimate.add_accounts(missing_atas.len() as u64, rent);
+        }
+
+        let groups = Self::pack_recipients(payer_pubkey, &mint_pubkey, &receiver_pubkeys, &atas, recipients)?;
+
+        // Groups minting to as many recipients and creating as many ATAs cost the same.
+        let mut fees: HashMap<(usize, usize), TransactionFee> = HashMap::new();
+
+        for group in groups {
+            let created_count = group
+                .iter()
+                .filter(|index| !atas[**index].is_created)
+                .map(|index| atas[*index].ata_pubkey)
+                .collect::<HashSet<Pubkey>>()
+                .len();
+
+            let fee = match fees.entry((group.len(), created_count)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let instructions = Self::group_instructions(payer_pubkey, &mint_pubkey, &receiver_pubkeys, &atas, recipients, &group)?;
+
+                    entry.insert(Self::estimate_fee(rpc_client, config, payer_pubkey, &instructions)?)
+                }
+            };
+
+            estimate.add_transactions(1, fee);
+        }
+
+        Ok(estimate)
+    }
+
+    /// Estimates a service-paid transfer of `amount` tokens between two wallets,
+    /// creating the destination ATA when needed.
+    fn estimate_transfer(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer_pubkey: &Pubkey,
+        mint_pubkey_str: &str,
+        source_pubkey_str: &str,
+        destination_pubkey_str: &str,
+        amount: u64
+    ) -> Result<FeeEstimateResponse, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let source_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(source_pubkey_str)?;
+        let destination_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(destination_pubkey_str)?;
+
+        let (instructions, creates_ata) = Self::transfer_instructions(
+            rpc_client, 
+            payer_pubkey, 
+            &mint_pubkey, 
+            &source_pubkey, 
+            &destination_pubkey, 
+            amount
+        )?;
+
+        let mut estimate = FeeEstimateResponse::default();
+
+        if creates_ata {
+            let rent = Self::get_rent_exemption(rpc_client, TokenAccount::LEN)?;
+            estimate.add_accounts(1, rent);
+        }
+
+        let fee = Self::estimate_fee(rpc_client, config, payer_pubkey, &instructions)?;
+        estimate.add_transactions(1, &fee);
+
+        Ok(estimate)
+    }
+
+    /// Builds a `transfer_checked` between the ATAs of two wallets, creating the
+    /// destination ATA at the payer's expense when it does not exist. Also returns
+    /// whether that ATA gets created.
+    fn transfer_instructions(
+        rpc_client: &RpcClient,
+        payer_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        source_pubkey: &Pubkey,
+        destination_pubkey: &Pubkey,
+        amount: u64
+    ) -> Result<(Vec<Instruction>, bool), SolanaError> {
+        let mint = Self::get_mint(rpc_client, mint_pubkey)?;
+        let destination_ata = Self::get_and_verify_ata(rpc_client, destination_pubkey, mint_pubkey)?;
+
+        let mut instructions: Vec<Instruction> = Vec::new();
+
+        if !destination_ata.is_created {
+            instructions.push(create_associated_token_account_idempotent(
+                payer_pubkey, 
+                destination_pubkey, 
+                mint_pubkey, 
+                &spl_token::ID
+            ));
+        }
+
+        let transfer_instruction = transfer_checked(
+            &spl_token::ID, 
+            &get_associated_token_address(source_pubkey, mint_pubkey), 
+            mint_pubkey, 
+            &destination_ata.ata_pubkey, 
+            source_pubkey, 
+            &[], 
+            amount, 
+            mint.decimals
+        ).map_err(|e| {
+            println!("Error creating transfer_checked instruction: {}", e);
+            SolanaError::CreateInstructionError
+        })?;
+
+        instructions.push(transfer_instruction);
+
+        Ok((instructions, !destination_ata.is_created))
+    }
+
+    fn burn_instructions(
+        rpc_client: &RpcClient,
+        mint_pubkey: &Pubkey,
+        owner_pubkey: &Pubkey,
+        amount: u64
+    ) -> Result<Vec<Instruction>, SolanaError> {
+        let mint = Self::get_mint(rpc_client, mint_pubkey)?;
+
+        let burn_instruction = burn_checked(
+            &spl_token::ID, 
+            &get_associated_token_address(owner_pubkey, mint_pubkey), 
+            mint_pubkey, 
+            owner_pubkey, 
+            &[], 
+            amount, 
+            mint.decimals
+        ).map_err(|e| {
+            println!("Error creating burn_checked instruction: {}", e);
+            SolanaError::CreateInstructionError
+        })?;
+
+        Ok(vec![burn_instruction])
+    }
+
+    fn create_mint_instructions(
+        payer_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        lamports: u64
+    ) -> Result<Vec<Instruction>, SolanaError> {
+        let create_account_instruction = create_account(
+            payer_pubkey, 
+            mint_pubkey, 
+            lamports, 
+            Mint::LEN as u64, 
+            &spl_token::ID
+        );
+
+        let initialize_mint_instruction = initialize_mint(
+            &spl_token::ID,
+            mint_pubkey, 
+            payer_pubkey, 
+            Some(payer_pubkey), 
+            MINT_DECIMALS
+        ).map_err(|e| {
+            println!("Error creating initialize_mint instruction: {}", e);
+            SolanaError::CreateInstructionError
+        })?;
+
+        Ok(vec![create_account_instruction, initialize_mint_instruction])
+    }
+
+    fn mint_to_instructions(
+        payer_pubkey: &Pubkey,
+        receiver_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        ata: &VerifyAndGetAtaResponse,
+        amount: u64
+    ) -> Result<Vec<Instruction>, SolanaError> {
+        let mut instructions: Vec<Instruction> = Vec::new();
+
+        if !ata.is_created {
+            let create_ata_instruction = create_associated_token_account(
+                payer_pubkey, 
+                receiver_pubkey, 
+                mint_pubkey, 
+                &spl_token::ID
+            );
+
+            instructions.push(create_ata_instruction);
+        }
+
+        let mint_to_instruction = mint_to(
+            &spl_token::ID, 
+            mint_pubkey, 
+            &ata.ata_pubkey, 
+            payer_pubkey, 
+            &[], 
+            amount
+        ).map_err(|e| {
+            println!("Error creating mint_to instruction: {}", e);
+            SolanaError::CreateInstructionError
+        })?;
+
+        instructions.push(mint_to_instruction);
+
+        Ok(instructions)
+    }
+
+    fn get_rent_exemption(rpc_client: &RpcClient, data_len: usize) -> Result<u64, SolanaError> {
+        rpc_client
+            .get_minimum_balance_for_rent_exemption(data_len)
+            .map_err(|e| {
+                println!("Error getting minimum balance: {}", e);
+                SolanaError::GetMinimumBalanceError
+            })
+    }
+
+    fn get_mint(rpc_client: &RpcClient, mint_pubkey: &Pubkey) -> Result<Mint, SolanaError> {
+        let account = rpc_client.get_account(mint_pubkey).map_err(|e| {
+            println!("Error getting account: {}", e);
+            SolanaError::AccountFetchError
+        })?;
+
+        Mint::unpack(&account.data).map_err(|e| {
+            println!("Error parsing mint account: {}", e);
+            SolanaError::MintParseError
+        })
+    }
+
+    /// Splits `recipients` into groups whose create-ATA and mint_to instructions fit
+    /// in a single transaction, returning the recipient indexes of each group.
+    pub async fn pack_mint_to_batch(
+        &self,
+        mint_pubkey_str: &str,
+        recipients: Vec<MintRecipient>
+    ) -> Result<Vec<Vec<usize>>, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let payer_pubkey = self.keypair.pubkey();
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<Vec<Vec<usize>>, SolanaError> {
+            let receiver_pubkeys = recipients
+                .iter()
+                .map(|recipient| SolanaHelper::try_to_convert_str_to_pubkey(&recipient.receiver_pubkey))
+                .collect::<Result<Vec<Pubkey>, SolanaError>>()?;
+
+            let atas = Self::get_and_verify_atas(&client, &receiver_pubkeys, &mint_pubkey)?;
+
+            Self::pack_recipients(&payer_pubkey, &mint_pubkey, &receiver_pubkeys, &atas, &recipients)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Builds and signs, without sending, one transaction minting to every recipient
+    /// of a group produced by `pack_mint_to_batch`.
+    pub async fn prepare_mint_to_batch(
+        &self,
+        mint_pubkey_str: &str,
+        recipients: Vec<MintRecipient>
+    ) -> Result<PreparedTransaction, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<PreparedTransaction, SolanaError> {
+            let receiver_pubkeys = recipients
+                .iter()
+                .map(|recipient| SolanaHelper::try_to_convert_str_to_pubkey(&recipient.receiver_pubkey))
+                .collect::<Result<Vec<Pubkey>, SolanaError>>()?;
+
+            let atas = Self::get_and_verify_atas(&client, &receiver_pubkeys, &mint_pubkey)?;
+
+            let group: Vec<usize> = (0..recipients.len()).collect();
+            let instructions = Self::group_instructions(
+                &payer.pubkey(), 
+                &mint_pubkey, 
+                &receiver_pubkeys, 
+                &atas, 
+                &recipients, 
+                &group
+            )?;
+
+            Self::prepare_transaction(
+                &client, 
+                &priority_fee_config, 
+                &payer, 
+                &[], 
+                &instructions
+            )
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Simulates every transaction a batch mint would send, packed the way
+    /// `pack_mint_to_batch` packs them.
+    pub async fn simulate_mint_to_batch(
+        &self,
+        mint_pubkey_str: &str,
+        recipients: Vec<MintRecipient>
+    ) -> Result<Vec<PackedSimulation>, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<Vec<PackedSimulation>, SolanaError> {
+            let receiver_pubkeys = recipients
+                .iter()
+                .map(|recipient| SolanaHelper::try_to_convert_str_to_pubkey(&recipient.receiver_pubkey))
+                .collect::<Result<Vec<Pubkey>, SolanaError>>()?;
+
+            let atas = Self::get_and_verify_atas(&client, &receiver_pubkeys, &mint_pubkey)?;
+            let groups = Self::pack_recipients(&payer.pubkey(), &mint_pubkey, &receiver_pubkeys, &atas, &recipients)?;
+
+            groups
+                .into_iter()
+                .map(|group| {
+                    let instructions = Self::group_instructions(
+                        &payer.pubkey(), 
+                        &mint_pubkey, 
+                        &receiver_pubkeys, 
+                        &atas, 
+                        &recipients, 
+                        &group
+                    )?;
+
+                    let simulation = Self::simulate_with_compute_budget(
+                        &client, 
+                        &priority_fee_config, 
+                        &payer, 
+                        &[], 
+                        &instructions
+                    )?;
+
+                    Ok(PackedSimulation { recipient_indexes: group, simulation })
+                })
+                .collect()
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Builds and signs, without sending, a burn from a wallet the service holds
+    /// the keys of. The service pays the fee.
+    pub async fn prepare_burn(
+        &self,
+        owner: Keypair,
+        mint_pubkey_str: &str,
+        amount: u64
+    ) -> Result<PreparedTransaction, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<PreparedTransaction, SolanaError> {
+            let instructions = Self::burn_instructions(
+                &client, 
+                &mint_pubkey, 
+                &owner.pubkey(), 
+                amount
+            )?;
+
+            Self::prepare_transaction(
+                &client, 
+                &priority_fee_config, 
+                &payer, 
+                &[&owner], 
+                &instructions
+            )
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Builds and signs, without sending, one transaction that burns
+    /// `burn_amount` of the source mint from a wallet the service holds the keys
+    /// of and mints `mint_amount` of the target mint to the same wallet. The
+    /// service must be the authority of both mints; it signs as such and pays the
+    /// fee, and the owner signs the burn.
+    pub async fn prepare_conversion(
+        &self,
+        owner: Keypair,
+        source_mint_pubkey_str: &str,
+        burn_amount: u64,
+        target_mint_pubkey_str: &str,
+        mint_amount: u64
+    ) -> Result<PreparedTransaction, SolanaError> {
+        let source_mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(source_mint_pubkey_str)?;
+        let target_mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(target_mint_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<PreparedTransaction, SolanaError> {
+            for mint_pubkey in [&source_mint_pubkey, &target_mint_pubkey] {
+                let mint = Self::get_mint(&client, mint_pubkey)?;
+
+                if Option::<Pubkey>::from(mint.mint_authority) != Some(payer.pubkey()) {
+                    return Err(SolanaError::MintAuthorityError);
+                }
+            }
+
+            let mut instructions = Self::burn_instructions(
+                &client, 
+                &source_mint_pubkey, 
+                &owner.pubkey(), 
+                burn_amount
+            )?;
+
+            let ata = Self::get_and_verify_ata(&client, &owner.pubkey(), &target_mint_pubkey)?;
+
+            instructions.extend(Self::batch_recipient_instructions(
+                &payer.pubkey(), 
+                &owner.pubkey(), 
+                &target_mint_pubkey, 
+                &ata, 
+                !ata.is_created, 
+                mint_amount
+            )?);
+
+            Self::prepare_transaction(
+                &client, 
+                &priority_fee_config, 
+                &payer, 
+                &[&owner], 
+                &instructions
+            )
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    pub async fn send_prepared_transaction(
+        &self,
+        prepared: PreparedTransaction
+    ) -> Result<MintToResponse, SolanaError> {
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<MintToResponse, SolanaError> {
+            let signature = client
+                .send_and_confirm_transaction(&prepared.transaction)
+                .map_err(|e| {
+                    println!("Error sending transaction: {}", e);
+                    SolanaError::SendTransactionError
+                })?;
+
+            Ok(MintToResponse {
+                signature: signature.to_string(),
+                fee: Self::paid_fee(&client, &signature, prepared.fee)
+            })
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Looks up a previously submitted transaction. A transaction that is not found
+    /// once its blockhash has expired can never land and is safe to send again.
+    pub async fn get_transaction_status(
+        &self,
+        signature_str: &str,
+        recent_blockhash_str: &str
+    ) -> Result<TransactionStatus, SolanaError> {
+        let signature = signature_str.parse::<Signature>().map_err(|e| {
+            println!("Error parsing signature: {}", e);
+            SolanaError::SignatureParsingError
+        })?;
+        let recent_blockhash = recent_blockhash_str.parse::<Hash>().map_err(|e| {
+            println!("Error parsing blockhash: {}", e);
+            SolanaError::BlockhashParsingError
+        })?;
+
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<TransactionStatus, SolanaError> {
+            let statuses = client
+                .get_signature_statuses_with_history(&[signature])
+                .map_err(|e| {
+                    println!("Error getting signature statuses: {}", e);
+                    SolanaError::GetSignatureStatusError
+                })?
+                .value;
+
+            if let Some(Some(status)) = statuses.into_iter().next() {
+                return Ok(match status.err {
+                    Some(err) => TransactionStatus::Failed(err.to_string()),
+                    None if status.satisfies_commitment(client.commitment()) => TransactionStatus::Confirmed,
+                    None => TransactionStatus::Pending
+                });
+            }
+
+            let is_valid = client
+                .is_blockhash_valid(&recent_blockhash, client.commitment())
+                .map_err(|e| {
+                    println!("Error checking blockhash validity: {}", e);
+                    SolanaError::GetBlockhashError
+                })?;
+
+            Ok(if is_valid { TransactionStatus::Pending } else { TransactionStatus::Expired })
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Packing shared by `pack_mint_to_batch` and `estimate_mint_to`.
+    fn pack_recipients(
+        payer_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        receiver_pubkeys: &[Pubkey],
+        atas: &[VerifyAndGetAtaResponse],
+        recipients: &[MintRecipient]
+    ) -> Result<Vec<Vec<usize>>, SolanaError> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut group: Vec<usize> = Vec::new();
+        let mut group_instructions: Vec<Instruction> = Vec::new();
+        let mut group_units: u64 = 0;
+        let mut created_atas: HashSet<Pubkey> = HashSet::new();
+
+        for (index, recipient) in recipients.iter().enumerate() {
+            let mut needs_ata = !atas[index].is_created && !created_atas.contains(&atas[index].ata_pubkey);
+            let mut instructions = Self::batch_recipient_instructions(
+                payer_pubkey, 
+                &receiver_pubkeys[index], 
+                mint_pubkey, 
+                &atas[index], 
+                needs_ata, 
+                recipient.amount
+            )?;
+
+            let mut candidate = group_instructions.clone();
+            candidate.extend_from_slice(&instructions);
+
+            let units = Self::batch_recipient_units(needs_ata);
+            let fits = group_units + units <= MAX_COMPUTE_UNIT_LIMIT as u64
+                && Self::transaction_size(payer_pubkey, &candidate) <= PACKET_DATA_SIZE;
+
+            if !fits && !group.is_empty() {
+                groups.push(std::mem::take(&mut group));
+                group_instructions.clear();
+                group_units = 0;
+                created_atas.clear();
+
+                needs_ata = !atas[index].is_created;
+                instructions = Self::batch_recipient_instructions(
+                    payer_pubkey, 
+                    &receiver_pubkeys[index], 
+                    mint_pubkey, 
+                    &atas[index], 
+                    needs_ata, 
+                    recipient.amount
+                )?;
+            }
+
+            if needs_ata {
+                created_atas.insert(atas[index].ata_pubkey);
+            }
+
+            group.push(index);
+            group_units += Self::batch_recipient_units(needs_ata);
+            group_instructions.extend(instructions);
+        }
+
+        if !group.is_empty() {
+            groups.push(group);
+        }
+
+        Ok(groups)
+    }
+
+    /// Instructions of one group, creating each missing ATA once.
+    fn group_instructions(
+        payer_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        receiver_pubkeys: &[Pubkey],
+        atas: &[VerifyAndGetAtaResponse],
+        recipients: &[MintRecipient],
+        group: &[usize]
+    ) -> Result<Vec<Instruction>, SolanaError> {
+        let mut instructions: Vec<Instruction> = Vec::new();
+        let mut created_atas: HashSet<Pubkey> = HashSet::new();
+
+        for &index in group {
+            let needs_ata = !atas[index].is_created && created_atas.insert(atas[index].ata_pubkey);
+
+            instructions.extend(Self::batch_recipient_instructions(
+                payer_pubkey, 
+                &receiver_pubkeys[index], 
+                mint_pubkey, 
+                &atas[index], 
+                needs_ata, 
+                recipients[index].amount
+            )?);
+        }
+
+        Ok(instructions)
+    }
+
+    fn batch_recipient_instructions(
+        payer_pubkey: &Pubkey,
+        receiver_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey,
+        ata: &VerifyAndGetAtaResponse,
+        needs_ata: bool,
+        amount: u64
+    ) -> Result<Vec<Instruction>, SolanaError> {
+        let mut instructions: Vec<Instruction> = Vec::new();
+
+        if needs_ata {
+            instructions.push(create_associated_token_account_idempotent(
+                payer_pubkey, 
+                receiver_pubkey, 
+                mint_pubkey, 
+                &spl_token::ID
+            ));
+        }
+
+        let mint_to_instruction = mint_to(
+            &spl_token::ID, 
+            mint_pubkey, 
+            &ata.ata_pubkey, 
+            payer_pubkey, 
+            &[], 
+            amount
+        ).map_err(|e| {
+            println!("Error creating mint_to instruction: {}", e);
+            SolanaError::CreateInstructionError
+        })?;
+
+        instructions.push(mint_to_instruction);
+
+        Ok(instructions)
+    }
+
+    fn batch_recipient_units(needs_ata: bool) -> u64 {
+        if needs_ata {
+            CREATE_ATA_COMPUTE_UNITS + MINT_TO_COMPUTE_UNITS
+        } else {
+            MINT_TO_COMPUTE_UNITS
+        }
+    }
+
+    /// Serialized size of a transaction signed only by `payer_pubkey`, including the
+    /// compute budget instructions added by `prepare_transaction`.
+    fn transaction_size(payer_pubkey: &Pubkey, instructions: &[Instruction]) -> usize {
+        let message = Message::new(
+            &Self::with_compute_budget(MAX_COMPUTE_UNIT_LIMIT, 0, instructions), 
+            Some(payer_pubkey)
+        );
+        let signature_count = message.header.num_required_signatures as usize;
+
+        1 + signature_count * 64 + message.serialize().len()
+    }
+
+    /// Builds a token transfer paid for by the service and signed only by it. The
+    /// owner's wallet has to add its signature before calling `submit_sponsored_transaction`.
+    pub async fn build_sponsored_transfer(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str,
+        destination_pubkey_str: &str,
+        amount: u64
+    ) -> Result<SponsoredTransactionResponse, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let owner_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(owner_pubkey_str)?;
+        let destination_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(destination_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<SponsoredTransactionResponse, SolanaError> {
+            let (instructions, _) = Self::transfer_instructions(
+                &client, 
+                &payer.pubkey(), 
+                &mint_pubkey, 
+                &owner_pubkey, 
+                &destination_pubkey, 
+                amount
+            )?;
+
+            Self::sponsor_transaction(&client, &priority_fee_config, &payer, &owner_pubkey, &instructions)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Same as `build_sponsored_transfer`, burning tokens from the owner's ATA.
+    pub async fn build_sponsored_burn(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str,
+        amount: u64
+    ) -> Result<SponsoredTransactionResponse, SolanaError> {
+        let mint_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)?;
+        let owner_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(owner_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<SponsoredTransactionResponse, SolanaError> {
+            let instructions = Self::burn_instructions(
+                &client, 
+                &mint_pubkey, 
+                &owner_pubkey, 
+                amount
+            )?;
+
+            Self::sponsor_transaction(&client, &priority_fee_config, &payer, &owner_pubkey, &instructions)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Decodes and verifies a sponsored transaction once its owner signed it. The
+    /// transaction must be fully signed, use the service as fee payer, and only
+    /// contain instructions a sponsored transaction is allowed to carry.
+    pub async fn decode_sponsored_transaction(
+        &self,
+        transaction_base64: &str
+    ) -> Result<SponsoredSubmission, SolanaError> {
+        let bytes = BASE64.decode(transaction_base64).map_err(|e| {
+            println!("Error decoding transaction: {}", e);
+            SolanaError::TransactionDecodeError
+        })?;
+
+        let transaction: Transaction = bincode::deserialize(&bytes).map_err(|e| {
+            println!("Error deserializing transaction: {}", e);
+            SolanaError::TransactionDecodeError
+        })?;
+
+        let created_accounts = Self::verify_sponsored_transaction(&transaction, &self.keypair.pubkey())?;
+        let owner_pubkey = Self::sponsored_owner(&transaction)?;
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<SponsoredSubmission, SolanaError> {
+            let accounts = match created_accounts.is_empty() {
+                true => Vec::new(),
+                false => client
+                    .get_multiple_accounts(&created_accounts)
+                    .map_err(|e| {
+                        println!("Error getting multiple accounts: {}", e);
+                        SolanaError::AccountFetchError
+                    })?
+            };
+
+            // Idempotent creation of an existing account costs the service no rent.
+            let new_token_accounts = created_accounts
+                .iter()
+                .zip(accounts)
+                .filter(|(_, account)| account.is_none())
+                .map(|(pubkey, _)| pubkey.to_string())
+                .collect();
+
+            Ok(SponsoredSubmission {
+                transaction,
+                owner_pubkey: owner_pubkey.to_string(),
+                new_token_accounts
+            })
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Broadcasts a sponsored transaction verified by `decode_sponsored_transaction`.
+    pub async fn submit_sponsored_transaction(
+        &self,
+        submission: SponsoredSubmission
+    ) -> Result<SubmitSponsoredResponse, SolanaError> {
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<Signature, SolanaError> {
+            client
+                .send_and_confirm_transaction(&submission.transaction)
+                .map_err(|e| {
+                    println!("Error sending transaction: {}", e);
+                    SolanaError::SendTransactionError
+                })
+        }).await;
+
+        match task_result {
+            Ok(Ok(signature)) => Ok(SubmitSponsoredResponse {
+                signature: signature.to_string()
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Simulates a verified sponsored transaction as signed, without sending it.
+    pub async fn simulate_sponsored_transaction(
+        &self,
+        submission: SponsoredSubmission
+    ) -> Result<SimulationResponse, SolanaError> {
+        let client = Arc::clone(&self.client);
+
+        let task_result = task::spawn_blocking(move || -> Result<SimulationResponse, SolanaError> {
+            let message = &submission.transaction.message;
+            let touched_accounts: Vec<Pubkey> = message.account_keys
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| message.is_maybe_writable(*index, None))
+                .map(|(_, pubkey)| *pubkey)
+                .collect();
+
+            let accounts_before = client
+                .get_multiple_accounts(&touched_accounts)
+                .map_err(|e| {
+                    println!("Error getting multiple accounts: {}", e);
+                    SolanaError::AccountFetchError
+                })?;
+
+            let total_lamports = client
+                .get_fee_for_message(message)
+                .map_err(|e| {
+                    println!("Error getting fee for message: {}", e);
+                    SolanaError::GetFeeError
+                })?;
+            let (compute_unit_limit, compute_unit_price) = Self::message_compute_budget(message);
+
+            let result = Self::simulate(
+                &client, 
+                &submission.transaction, 
+                &touched_accounts
+            )?;
+
+            Ok(Self::simulation_response(
+                result, 
+                &touched_accounts, 
+                accounts_before, 
+                Some(TransactionFee { total_lamports, compute_unit_limit, compute_unit_price, is_estimate: true })
+            ))
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// The compute unit limit and price a message sets, or the cluster defaults.
+    fn message_compute_budget(message: &Message) -> (u32, u64) {
+        let mut compute_unit_limit = None;
+        let mut compute_unit_price = 0;
+        let mut instruction_count: u32 = 0;
+
+        for instruction in &message.instructions {
+            if message.account_keys.get(instruction.program_id_index as usize) != Some(&solana_sdk::compute_budget::ID) {
+                instruction_count += 1;
+                continue;
+            }
+
+            match try_from_slice_unchecked::<ComputeBudgetInstruction>(&instruction.data) {
+                Ok(ComputeBudgetInstruction::SetComputeUnitLimit(units)) => compute_unit_limit = Some(units),
+                Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => compute_unit_price = price,
+                _ => {}
+            }
+        }
+
+        let compute_unit_limit = compute_unit_limit
+            .unwrap_or(instruction_count.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT))
+            .min(MAX_COMPUTE_UNIT_LIMIT);
+
+        (compute_unit_limit, compute_unit_price)
+    }
+
+    /// The signer of a sponsored transaction other than the service.
+    fn sponsored_owner(transaction: &Transaction) -> Result<Pubkey, SolanaError> {
+        let message = &transaction.message;
+
+        message.account_keys
+            .iter()
+            .take(message.header.num_required_signatures as usize)
+            .nth(1)
+            .copied()
+            .ok_or(SolanaError::TransactionNotAllowed("missing owner signature".to_string()))
+    }
+
+    fn sponsor_transaction(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer: &Keypair,
+        owner_pubkey: &Pubkey,
+        instructions: &[Instruction]
+    ) -> Result<SponsoredTransactionResponse, SolanaError> {
+        let (message, fee) = Self::budget_message(
+            rpc_client, 
+            config, 
+            &payer.pubkey(), 
+            instructions
+        )?;
+
+        let recent_blockhash = message.recent_blockhash;
+        let mut transaction = Transaction::new_unsigned(message);
+
+        transaction.try_partial_sign(&[payer], recent_blockhash).map_err(|e| {
+            println!("Error signing transaction: {}", e);
+            SolanaError::SignTransactionError
+        })?;
+
+        let bytes = bincode::serialize(&transaction).map_err(|e| {
+            println!("Error serializing transaction: {}", e);
+            SolanaError::TransactionDecodeError
+        })?;
+
+        Ok(SponsoredTransactionResponse {
+            transaction: BASE64.encode(bytes),
+            required_signer: owner_pubkey.to_string(),
+            fee
+        })
+    }
+
+    /// Returns the token accounts the transaction creates for the service's rent.
+    /// Transfers and burns must move tokens, and accounts may only be created as the
+    /// destination of a transfer in the same transaction.
+    fn verify_sponsored_transaction(
+        transaction: &Transaction,
+        payer_pubkey: &Pubkey
+    ) -> Result<Vec<Pubkey>, SolanaError> {
+        let message = &transaction.message;
+
+        if message.account_keys.first() != Some(payer_pubkey) {
+            return Err(SolanaError::TransactionNotAllowed("fee payer is not the service".to_string()));
+        }
+
+        transaction.verify().map_err(|e| {
+            println!("Error verifying transaction signatures: {}", e);
+            SolanaError::InvalidSignatureError
+        })?;
+
+        if !transaction.is_signed() {
+            return Err(SolanaError::InvalidSignatureError);
+        }
+
+        let mut transfer_destinations = Vec::new();
+        let mut created_accounts = Vec::new();
+
+        for instruction in &message.instructions {
+            let program_id = message.account_keys
+                .get(instruction.program_id_index as usize)
+                .ok_or(SolanaError::TransactionNotAllowed("unknown program".to_string()))?;
+
+            let accounts: Vec<&Pubkey> = instruction.accounts
+                .iter()
+                .filter_map(|index| message.account_keys.get(*index as usize))
+                .collect();
+
+            let is_allowed = if *program_id == solana_sdk::compute_budget::ID {
+                true
+            } else if *program_id == spl_token::ID {
+                // The service is a mint authority, so it must never act inside token instructions.
+                !accounts.contains(&payer_pubkey) && match TokenInstruction::unpack(&instruction.data) {
+                    Ok(TokenInstruction::TransferChecked { amount, .. }) => {
+                        transfer_destinations.extend(accounts.get(2).copied());
+                        amount > 0
+                    },
+                    Ok(TokenInstruction::BurnChecked { amount, .. }) => amount > 0,
+                    _ => false
+                }
+            } else if *program_id == spl_associated_token_account::ID {
+                // Only idempotent ATA creation, with the service as funding account.
+                created_accounts.extend(accounts.get(1).copied());
+                instruction.data == [1] && accounts.first() == Some(&payer_pubkey)
+            } else {
+                false
+            };
+
+            if !is_allowed {
+                return Err(SolanaError::TransactionNotAllowed(format!("instruction for program {}", program_id)));
+            }
+        }
+
+        if let Some(account) = created_accounts.iter().find(|account| !transfer_destinations.contains(*account)) {
+            return Err(SolanaError::TransactionNotAllowed(format!("creation of account {} not receiving a transfer", account)));
+        }
+
+        Ok(created_accounts.into_iter().copied().collect())
+    }
+
+    /// Moves every token balance and all lamports held by `owner` to `destination`,
+    /// one transaction per token account. Emptied token accounts are closed so their
+    /// rent goes back to the service, which funded them. Frozen accounts are skipped.
+    pub async fn sweep_wallet(
+        &self,
+        owner: Keypair,
+        destination_pubkey_str: &str
+    ) -> Result<Vec<SweptBalance>, SolanaError> {
+        let destination_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(destination_pubkey_str)?;
+        let payer = self.keypair.clone();
+        let client = Arc::clone(&self.client);
+        let priority_fee_config = self.priority_fee_config.clone();
+
+        let task_result = task::spawn_blocking(move || -> Result<Vec<SweptBalance>, SolanaError> {
+            let owner_pubkey = owner.pubkey();
+            let mut swept = Vec::new();
+
+            for (account_pubkey, account) in Self::get_token_accounts(&client, &owner_pubkey)? {
+                if account.state != AccountState::Initialized {
+                    continue;
+                }
+
+                let mut instructions: Vec<Instruction> = Vec::new();
+
+                if account.amount > 0 {
+                    let mint = Self::get_mint(&client, &account.mint)?;
+
+                    instructions.push(create_associated_token_account_idempotent(
+                        &payer.pubkey(), 
+                        &destination_pubkey, 
+                        &account.mint, 
+                        &spl_token::ID
+                    ));
+
+                    let transfer_instruction = transfer_checked(
+                        &spl_token::ID, 
+                        &account_pubkey, 
+                        &account.mint, 
+                        &get_associated_token_address(&destination_pubkey, &account.mint), 
+                        &owner_pubkey, 
+                        &[], 
+                        account.amount, 
+                        mint.decimals
+                    ).map_err(|e| {
+                        println!("Error creating transfer_checked instruction: {}", e);
+                        SolanaError::CreateInstructionError
+                    })?;
+
+                    instructions.push(transfer_instruction);
+                }
+
+                let close_instruction = close_account(
+                    &spl_token::ID, 
+                    &account_pubkey, 
+                    &payer.pubkey(), 
+                    &owner_pubkey, 
+                    &[]
+                ).map_err(|e| {
+                    println!("Error creating close_account instruction: {}", e);
+                    SolanaError::CreateInstructionError
+                })?;
+
+                instructions.push(close_instruction);
+
+                let sent = Self::send_with_compute_budget(
+                    &client, 
+                    &priority_fee_config, 
+                    &payer, 
+                    &[&owner], 
+                    &instructions
+                )?;
+
+                swept.push(SweptBalance {
+                    mint_pubkey: Some(account.mint.to_string()),
+                    amount: account.amount,
+                    signature: sent.signature.to_string()
+                });
+            }
+
+            let lamports = client.get_balance(&owner_pubkey).map_err(|e| {
+                println!("Error getting balance: {}", e);
+                SolanaError::AccountFetchError
+            })?;
+
+            if lamports > 0 {
+                let sent = Self::send_with_compute_budget(
+                    &client, 
+                    &priority_fee_config, 
+                    &payer, 
+                    &[&owner], 
+                    &[transfer(&owner_pubkey, &destination_pubkey, lamports)]
+                )?;
+
+                swept.push(SweptBalance {
+                    mint_pubkey: None,
+                    amount: lamports,
+                    signature: sent.signature.to_string()
+                });
+            }
+
+            Ok(swept)
+        }).await;
+
+        match task_result {
+            Ok(result) => result,
+            Err(_) => Err(SolanaError::UnkownError)
+        }
+    }
+
+    /// Every SPL token account owned by `owner_pubkey`, ATA or not.
+    fn get_token_accounts(
+        rpc_client: &RpcClient,
+        owner_pubkey: &Pubkey
+    ) -> Result<Vec<(Pubkey, TokenAccount)>, SolanaError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(TokenAccount::LEN as u64),
+                // The owner field follows the 32-byte mint.
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, owner_pubkey.to_bytes().to_vec()))
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        let accounts = rpc_client
+            .get_program_accounts_with_config(&spl_token::ID, config)
+            .map_err(|e| {
+                println!("Error getting token accounts: {}", e);
+                SolanaError::AccountFetchError
+            })?;
+
+        accounts
+            .into_iter()
+            .map(|(pubkey, account)| {
+                TokenAccount::unpack(&account.data)
+                    .map(|token_account| (pubkey, token_account))
+                    .map_err(|e| {
+                        println!("Error parsing token account: {}", e);
+                        SolanaError::TokenAccountParseError
+                    })
+            })
+            .collect()
+    }
+
+    /// Sends `instructions` as a budgeted transaction, or only simulates it when
+    /// `dry_run` is set.
+    fn execute(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer: &Keypair,
+        extra_signers: &[&Keypair],
+        instructions: &[Instruction],
+        dry_run: bool
+    ) -> Result<Execution, SolanaError> {
+        if dry_run {
+            Self::simulate_with_compute_budget(
+                rpc_client, 
+                config, 
+                payer, 
+                extra_signers, 
+                instructions
+            ).map(Execution::Simulated)
+        } else {
+            Self::send_with_compute_budget(
+                rpc_client, 
+                config, 
+                payer, 
+                extra_signers, 
+                instructions
+            ).map(Execution::Sent)
+        }
+    }
+
+    /// Builds the same transaction `send_with_compute_budget` would send and simulates
+    /// it, reporting logs, consumed compute units, account changes and the fee.
+    fn simulate_with_compute_budget(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer: &Keypair,
+        extra_signers: &[&Keypair],
+        instructions: &[Instruction]
+    ) -> Result<SimulationResponse, SolanaError> {
+        let mut touched_accounts = vec![payer.pubkey()];
+        for pubkey in Self::writable_accounts(instructions) {
+            if !touched_accounts.contains(&pubkey) {
+                touched_accounts.push(pubkey);
+            }
+        }
+
+        let accounts_before = rpc_client
+            .get_multiple_accounts(&touched_accounts)
+            .map_err(|e| {
+                println!("Error getting multiple accounts: {}", e);
+                SolanaError::AccountFetchError
+            })?;
+
+        let estimated_price = Self::estimate_compute_unit_price(
+            rpc_client, 
+            config, 
+            instructions
+        )?;
+
+        let message = Message::new(
+            &Self::with_compute_budget(MAX_COMPUTE_UNIT_LIMIT, estimated_price, instructions), 
+            Some(&payer.pubkey())
+        );
+
+        let probe = Self::simulate(
+            rpc_client, 
+            &Transaction::new_unsigned(message), 
+            &touched_accounts
+        )?;
+
+        // A failing transaction cannot be budgeted, so report the probe as is.
+        if probe.err.is_some() {
+            return Ok(Self::simulation_response(
+                probe, 
+                &touched_accounts, 
+                accounts_before, 
+                None
+            ));
+        }
+
+        let prepared = Self::budget_transaction(
+            rpc_client, 
+            config, 
+            payer, 
+            extra_signers, 
+            instructions, 
+            estimated_price, 
+            probe.units_consumed.unwrap_or(MAX_COMPUTE_UNIT_LIMIT as u64)
+        )?;
+
+        let result = Self::simulate(
+            rpc_client, 
+            &prepared.transaction, 
+            &touched_accounts
+        )?;
+
+        Ok(Self::simulation_response(
+            result, 
+            &touched_accounts, 
+            accounts_before, 
+            Some(prepared.fee)
+        ))
+    }
+
+    fn simulate(
+        rpc_client: &RpcClient,
+        transaction: &Transaction,
+        addresses: &[Pubkey]
+    ) -> Result<RpcSimulateTransactionResult, SolanaError> {
+        let is_signed = transaction.is_signed();
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: is_signed,
+            replace_recent_blockhash: !is_signed,
+            commitment: Some(rpc_client.commitment()),
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: addresses.iter().map(|pubkey| pubkey.to_string()).collect()
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        };
+
+        rpc_client
+            .simulate_transaction_with_config(transaction, config)
+            .map(|response| response.value)
+            .map_err(|e| {
+                println!("Error simulating transaction: {}", e);
+                SolanaError::SimulateTransactionError
+            })
+    }
+
+    fn simulation_response(
+        result: RpcSimulateTransactionResult,
+        addresses: &[Pubkey],
+        accounts_before: Vec<Option<SolanaAccount>>,
+        fee: Option<TransactionFee>
+    ) -> SimulationResponse {
+        let accounts_after: Vec<Option<SolanaAccount>> = result.accounts
+            .unwrap_or_default()
+            .into_iter()
+            .map(|account| account.and_then(|account| account.decode::<SolanaAccount>()))
+            .collect();
+
+        let account_changes = addresses
+            .iter()
+            .enumerate()
+            .map(|(index, pubkey)| {
+                let before = accounts_before.get(index).cloned().flatten();
+                let after = accounts_after.get(index).cloned().flatten();
+
+                AccountChange {
+                    pubkey: pubkey.to_string(),
+                    existed_before: before.is_some(),
+                    lamports_before: before.as_ref().map_or(0, |account| account.lamports),
+                    lamports_after: after.as_ref().map_or(0, |account| account.lamports),
+                    token_amount_before: before.as_ref().and_then(Self::token_amount),
+                    token_amount_after: after.as_ref().and_then(Self::token_amount)
+                }
+            })
+            .collect();
+
+        SimulationResponse {
+            success: result.err.is_none(),
+            error: result.err.map(|err| err.to_string()),
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed.unwrap_or_default(),
+            account_changes,
+            fee
+        }
+    }
+
+    fn token_amount(account: &SolanaAccount) -> Option<u64> {
+        if account.owner != spl_token::ID {
+            return None;
+        }
+
+        match account.data.len() {
+            TokenAccount::LEN => TokenAccount::unpack(&account.data).ok().map(|token| token.amount),
+            Mint::LEN => Mint::unpack(&account.data).ok().map(|mint| mint.supply),
+            _ => None
+        }
+    }
+
+    /// Prices, sizes and signs `instructions` with a compute budget, then sends the
+    /// transaction and waits for confirmation.
+    fn send_with_compute_budget(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer: &Keypair,
+        extra_signers: &[&Keypair],
+        instructions: &[Instruction]
+    ) -> Result<SentTransaction, SolanaError> {
+        let prepared = Self::prepare_transaction(
+            rpc_client, 
+            config, 
+            payer, 
+            extra_signers, 
+            instructions
+        )?;
+
+        let signature = rpc_client
+            .send_and_confirm_transaction(&prepared.transaction)
+            .map_err(|e| {
+                println!("Error sending transaction: {}", e);
+                SolanaError::SendTransactionError
+            })?;
+
+        Ok(SentTransaction {
+            signature,
+            fee: Self::paid_fee(rpc_client, &signature, prepared.fee)
+        })
+    }
+
+    /// `estimate` with the fee the cluster charged for the confirmed transaction.
+    /// The estimate is kept, flagged as such, when the transaction cannot be
+    /// fetched, as it landed already and failing here would invite sending it again.
+    fn paid_fee(
+        rpc_client: &RpcClient,
+        signature: &Signature,
+        estimate: TransactionFee
+    ) -> TransactionFee {
+        let config = RpcTransactionConfig {
+            commitment: Some(rpc_client.commitment()),
+            max_supported_transaction_version: Some(0),
+            ..RpcTransactionConfig::default()
+        };
+
+        match rpc_client.get_transaction_with_config(signature, config) {
+            Ok(confirmed) => match confirmed.transaction.meta {
+                Some(meta) => TransactionFee { total_lamports: meta.fee, is_estimate: false, ..estimate },
+                None => estimate
+            },
+            Err(e) => {
+                println!("Error getting transaction fee: {}", e);
+                estimate
+            }
+        }
+    }
+
+    /// Builds the signed transaction for `instructions`, prefixed with a compute unit
+    /// limit taken from simulation and a compute unit price taken from recent fees.
+    fn prepare_transaction(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer: &Keypair,
+        extra_signers: &[&Keypair],
+        instructions: &[Instruction]
+    ) -> Result<PreparedTransaction, SolanaError> {
+        let estimated_price = Self::estimate_compute_unit_price(
+            rpc_client, 
+            config, 
+            instructions
+        )?;
+
+        let consumed_units = Self::simulate_compute_units(
+            rpc_client, 
+            &payer.pubkey(), 
+            estimated_price, 
+            instructions
+        )?;
+
+        Self::budget_transaction(
+            rpc_client, 
+            config, 
+            payer, 
+            extra_signers, 
+            instructions, 
+            estimated_price, 
+            consumed_units
+        )
+    }
+
+    /// Signs `instructions` behind a compute unit limit sized from `consumed_units`
+    /// and a compute unit price capped by the configured maximum priority fee.
+    fn budget_transaction(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer: &Keypair,
+        extra_signers: &[&Keypair],
+        instructions: &[Instruction],
+        estimated_price: u64,
+        consumed_units: u64
+    ) -> Result<PreparedTransaction, SolanaError> {
+        let (compute_unit_limit, compute_unit_price) = Self::compute_budget(
+            config, 
+            estimated_price, 
+            consumed_units
+        );
+
+        let message = Message::new(
+            &Self::with_compute_budget(compute_unit_limit, compute_unit_price, instructions), 
+            Some(&payer.pubkey())
+        );
+
+        let recent_blockhash = rpc_client.get_latest_blockhash().map_err(|e| {
+            println!("Error getting latest blockhash: {}", e);
+            SolanaError::GetBlockhashError
+        })?;
+
+        let mut signers: Vec<&Keypair> = vec![payer];
+        signers.extend_from_slice(extra_signers);
+
+        let transaction = Transaction::new(
+            &signers, 
+            message, 
+            recent_blockhash
+        );
+
+        let total_lamports = rpc_client
+            .get_fee_for_message(&transaction.message)
+            .map_err(|e| {
+                println!("Error getting fee for message: {}", e);
+                SolanaError::GetFeeError
+            })?;
+
+        Ok(PreparedTransaction {
+            transaction,
+            fee: TransactionFee {
+                total_lamports,
+                compute_unit_limit,
+                compute_unit_price,
+                is_estimate: true
+            }
+        })
+    }
+
+    /// Same pricing as `prepare_transaction`, for instructions the service cannot sign.
+    fn estimate_fee(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer_pubkey: &Pubkey,
+        instructions: &[Instruction]
+    ) -> Result<TransactionFee, SolanaError> {
+        Self::budget_message(rpc_client, config, payer_pubkey, instructions)
+            .map(|(_, fee)| fee)
+    }
+
+    /// Builds the budgeted, unsigned message `prepare_transaction` would sign.
+    fn budget_message(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        payer_pubkey: &Pubkey,
+        instructions: &[Instruction]
+    ) -> Result<(Message, TransactionFee), SolanaError> {
+        let estimated_price = Self::estimate_compute_unit_price(
+            rpc_client, 
+            config, 
+            instructions
+        )?;
+
+        let consumed_units = Self::simulate_compute_units(
+            rpc_client, 
+            payer_pubkey, 
+            estimated_price, 
+            instructions
+        )?;
+
+        let (compute_unit_limit, compute_unit_price) = Self::compute_budget(
+            config, 
+            estimated_price, 
+            consumed_units
+        );
+
+        let recent_blockhash = rpc_client.get_latest_blockhash().map_err(|e| {
+            println!("Error getting latest blockhash: {}", e);
+            SolanaError::GetBlockhashError
+        })?;
+
+        let message = Message::new_with_blockhash(
+            &Self::with_compute_budget(compute_unit_limit, compute_unit_price, instructions), 
+            Some(payer_pubkey), 
+            &recent_blockhash
+        );
+
+        let total_lamports = rpc_client
+            .get_fee_for_message(&message)
+            .map_err(|e| {
+                println!("Error getting fee for message: {}", e);
+                SolanaError::GetFeeError
+            })?;
+
+        let fee = TransactionFee {
+            total_lamports,
+            compute_unit_limit,
+            compute_unit_price,
+            is_estimate: true
+        };
+
+        Ok((message, fee))
+    }
+
+    /// Returns the compute unit limit and price for a transaction that consumed
+    /// `consumed_units` in simulation.
+    fn compute_budget(
+        config: &PriorityFeeConfig,
+        estimated_price: u64,
+        consumed_units: u64
+    ) -> (u32, u64) {
+        let compute_unit_limit = consumed_units
+            .saturating_mul(100 + config.compute_unit_headroom_percent)
+            .div_ceil(100)
+            .min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32;
+
+        let max_price = config.max_priority_fee_lamports
+            .saturating_mul(MICRO_LAMPORTS_PER_LAMPORT) / compute_unit_limit.max(1) as u64;
+
+        (compute_unit_limit, estimated_price.min(max_price))
+    }
+
+    /// Picks a compute unit price, in micro-lamports, from the recent prioritization
+    /// fees paid by transactions that wrote to the same accounts.
+    fn estimate_compute_unit_price(
+        rpc_client: &RpcClient,
+        config: &PriorityFeeConfig,
+        instructions: &[Instruction]
+    ) -> Result<u64, SolanaError> {
+        let writable_accounts = Self::writable_accounts(instructions);
+
+        let recent_fees = rpc_client
+            .get_recent_prioritization_fees(&writable_accounts)
+            .map_err(|e| {
+                println!("Error getting recent prioritization fees: {}", e);
+                SolanaError::GetPrioritizationFeesError
+            })?;
+
+        let mut fees: Vec<u64> = recent_fees
+            .iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+
+        if fees.is_empty() {
+            return Ok(0);
+        }
+
+        fees.sort_unstable();
+        let index = (fees.len() - 1) * config.fee_percentile.min(100) as usize / 100;
+
+        Ok(fees[index])
+    }
+
+    fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+        let mut writable_accounts: Vec<Pubkey> = instructions
+            .iter()
+            .flat_map(|instruction| instruction.accounts.iter())
+            .filter(|account| account.is_writable)
+            .map(|account| account.pubkey)
+            .collect();
+        writable_accounts.sort();
+        writable_accounts.dedup();
+
+        writable_accounts
+    }
+
+    /// Simulates `instructions` with the maximum compute unit limit and returns the
+    /// units actually consumed.
+    fn simulate_compute_units(
+        rpc_client: &RpcClient,
+        payer_pubkey: &Pubkey,
+        compute_unit_price: u64,
+        instructions: &[Instruction]
+    ) -> Result<u64, SolanaError> {
+        let message = Message::new(
+            &Self::with_compute_budget(MAX_COMPUTE_UNIT_LIMIT, compute_unit_price, instructions), 
+            Some(payer_pubkey)
+        );
+
+        let transaction = Transaction::new_unsigned(message);
+
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(rpc_client.commitment()),
+            ..RpcSimulateTransactionConfig::default()
+        };
+
+        let result = rpc_client
+            .simulate_transaction_with_config(&transaction, config)
+            .map_err(|e| {
+                println!("Error simulating transaction: {}", e);
+                SolanaError::SimulateTransactionError
+            })?
+            .value;
+
+        if let Some(err) = result.err {
+            println!("Transaction simulation failed: {}", err);
+            if let Some(logs) = result.logs {
+                println!("Simulation logs: {:?}", logs);
+            }
+            return Err(SolanaError::TransactionRejected(err.to_string()));
+        }
+
+        Ok(result.units_consumed.unwrap_or(MAX_COMPUTE_UNIT_LIMIT as u64))
+    }
+
+    fn with_compute_budget(
+        compute_unit_limit: u32,
+        compute_unit_price: u64,
+        instructions: &[Instruction]
+    ) -> Vec<Instruction> {
+        let mut budgeted = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+            ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price)
+        ];
+        budgeted.extend_from_slice(instructions);
+
+        budgeted
+    }
+
+    fn get_and_verify_ata(
+        rpc_client: &RpcClient,
+        wallet_pubkey: &Pubkey,
+        mint_pubkey: &Pubkey
+    ) -> Result<VerifyAndGetAtaResponse, SolanaError> {
+        let ata_address = get_associated_token_address(
+            wallet_pubkey, 
+            mint_pubkey
+        );
+
+        let result = rpc_client.get_account_with_commitment(&ata_address, rpc_client.commitment())
+            .map_err(|e| {
+                println!("Error in get_account_with_commitment: {}", e);
+                SolanaError::AccountFetchError
+            })?;
+        
+        match result.value {
+            Some(account) => {
+                if account.owner == spl_token::ID {
+                    Ok(VerifyAndGetAtaResponse {
+                        ata_pubkey: ata_address,
+                        is_created: true
+                    })
+                } else {
+                    Err(SolanaError::AtaOwnerError)
+                }
+            },
+            None => {
+                Ok(VerifyAndGetAtaResponse {
+                    ata_pubkey: ata_address,
+                    is_created: false
+                })
+            }
+        }
+    }
+
+    fn get_and_verify_atas(
+        rpc_client: &RpcClient,
+        wallet_pubkeys: &[Pubkey],
+        mint_pubkey: &Pubkey
+    ) -> Result<Vec<VerifyAndGetAtaResponse>, SolanaError> {
+        let ata_addresses: Vec<Pubkey> = wallet_pubkeys
+            .iter()
+            .map(|wallet_pubkey| get_associated_token_address(wallet_pubkey, mint_pubkey))
+            .collect();
+
+        let mut atas = Vec::with_capacity(ata_addresses.len());
+
+        for chunk in ata_addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = rpc_client
+                .get_multiple_accounts(chunk)
+                .map_err(|e| {
+                    println!("Error getting multiple accounts: {}", e);
+                    SolanaError::AccountFetchError
+                })?;
+
+            for (ata_address, account) in chunk.iter().zip(accounts) {
+                if let Some(account) = &account {
+                    if account.owner != spl_token::ID {
+                        return Err(SolanaError::AtaOwnerError);
+                    }
+                }
+
+                atas.push(VerifyAndGetAtaResponse {
+                    ata_pubkey: *ata_address,
+                    is_created: account.is_some()
+                });
+            }
+        }
+
+        Ok(atas)
+    }
+}
+
+/// A signed transaction that has not been sent yet.
+pub struct PreparedTransaction {
+    transaction: Transaction,
+    fee: TransactionFee
+}
+
+impl PreparedTransaction {
+    pub fn signature(&self) -> String {
+        self.transaction.signatures[0].to_string()
+    }
+
+    pub fn recent_blockhash(&self) -> String {
+        self.transaction.message.recent_blockhash.to_string()
+    }
+}
+
+struct SentTransaction {
+    signature: Signature,
+    fee: TransactionFee
+}
+
+enum Execution {
+    Sent(SentTransaction),
+    Simulated(SimulationResponse)
+}
+
+/// Result of a mutating call: the confirmed transaction, or its simulation on dry runs.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum TransactionOutcome<T, S = SimulationResponse> {
+    Sent(T),
+    Simulated(S)
+}
+
+#[derive(Serialize, Debug)]
+pub struct SimulationResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: u64,
+    pub account_changes: Vec<AccountChange>,
+    /// Missing when the simulation failed and the transaction could not be budgeted.
+    pub fee: Option<TransactionFee>
+}
+
+/// The simulation of one transaction of a batch mint, minting to the recipients
+/// at `recipient_indexes`.
+#[derive(Serialize, Debug)]
+pub struct PackedSimulation {
+    pub recipient_indexes: Vec<usize>,
+    pub simulation: SimulationResponse
+}
+
+#[derive(Serialize, Debug)]
+pub struct AccountChange {
+    pub pubkey: String,
+    pub existed_before: bool,
+    pub lamports_before: u64,
+    pub lamports_after: u64,
+    /// Token balance for token accounts, total supply for mints.
+    pub token_amount_before: Option<u64>,
+    pub token_amount_after: Option<u64>
+}
+
+#[derive(Debug)]
+pub struct VerifyAndGetAtaResponse {
+    pub ata_pubkey: Pubkey,
+    pub is_created: bool
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TransactionFee {
+    /// Estimated before sending, and the fee actually charged once confirmed.
+    pub total_lamports: u64,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+    /// Whether `total_lamports` is still the estimate, also after sending when
+    /// the charged fee could not be read back.
+    pub is_estimate: bool
+}
+
+impl TransactionFee {
+    pub fn priority_lamports(&self) -> u64 {
+        (self.compute_unit_limit as u64)
+            .saturating_mul(self.compute_unit_price)
+            .div_ceil(MICRO_LAMPORTS_PER_LAMPORT)
+    }
+}
+
+/// An operation whose cost is estimated without sending anything.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum PlannedOperation {
+    CreateMint,
+    MintTo {
+        mint_pubkey: String,
+        recipients: Vec<MintRecipient>
+    },
+    Transfer {
+        mint_pubkey: String,
+        source_pubkey: String,
+        destination_pubkey: String,
+        amount: u64
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MintRecipient {
+    pub receiver_pubkey: String,
+    pub amount: u64
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct FeeEstimateResponse {
+    pub transaction_count: u64,
+    pub accounts_to_create: u64,
+    pub rent_lamports: u64,
+    pub signature_fee_lamports: u64,
+    pub priority_fee_lamports: u64,
+    pub total_lamports: u64
+}
+
+impl FeeEstimateResponse {
+    fn add_accounts(&mut self, count: u64, rent_per_account: u64) {
+        self.accounts_to_create += count;
+        self.rent_lamports += count * rent_per_account;
+        self.total_lamports += count * rent_per_account;
+    }
+
+    fn add_transactions(&mut self, count: u64, fee: &TransactionFee) {
+        let priority_lamports = fee.priority_lamports();
+
+        self.transaction_count += count;
+        self.signature_fee_lamports += count * fee.total_lamports.saturating_sub(priority_lamports);
+        self.priority_fee_lamports += count * priority_lamports;
+        self.total_lamports += count * fee.total_lamports;
+    }
+}
+
+#[derive(Debug)]
+pub enum TransactionStatus {
+    Confirmed,
+    Failed(String),
+    /// Not confirmed yet, but may still land.
+    Pending,
+    /// Never landed and can no longer land.
+    Expired
+}
+
+/// A transaction paid and partially signed by the service, base64-encoded.
+#[derive(Serialize, Debug)]
+pub struct SponsoredTransactionResponse {
+    pub transaction: String,
+    pub required_signer: String,
+    pub fee: TransactionFee
+}
+
+/// A verified sponsored transaction, with the token accounts it creates that do
+/// not exist yet.
+pub struct SponsoredSubmission {
+    transaction: Transaction,
+    pub owner_pubkey: String,
+    pub new_token_accounts: Vec<String>
+}
+
+#[derive(Serialize, Debug)]
+pub struct SubmitSponsoredResponse {
+    pub signature: String
+}
+
+/// A balance moved by `sweep_wallet`. `mint_pubkey` is missing for lamports.
+#[derive(Serialize, Debug)]
+pub struct SweptBalance {
+    pub mint_pubkey: Option<String>,
+    pub amount: u64,
+    pub signature: String
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreateMintResponse {
+    pub pubkey: String,
+    pub signature: String,
+    pub fee: TransactionFee
+}
+
+#[derive(Serialize, Debug)]
+pub struct MintToResponse {
+    pub signature: String,
+    pub fee: TransactionFee
+}
+
+#[derive(Serialize, Debug)]
+pub struct FreezeAccountResponse {
+    pub token_account_pubkey: String,
+    pub frozen: bool,
+    pub signature: String,
+    pub fee: TransactionFee
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct TokenAccountState {
+    pub amount: u64,
+    pub frozen: bool
+}
+
+/// An owner's balance of a mint, in base units.
+#[derive(Serialize, Debug)]
+pub struct TokenHolder {
+    pub owner: String,
+    pub amount: u64
+}
+
+#[derive(Serialize, Debug)]
+pub struct MintResponse {
+    pub pubkey: String,
+    pub supply: u64,
+    pub decimals: u8,
+    pub mint_authority: Option<String>
+}
+
+#[derive(Error, Debug)]
+pub enum SolanaError {
+    #[error("Pubkey could not be parsed")]
+    PubkeyParsingError,
+    #[error("Mint account could not be parsed")]
+    MintParseError,
+    #[error("Unknown error occurred")]
+    UnkownError,
+    #[error("Error fetching account")]
+    AccountFetchError,
+    #[error("Error creating instruction")]
+    CreateInstructionError,
+    #[error("Error getting latest blockhash")]
+    GetBlockhashError,
+    #[error("Error sending transaction")]
+    SendTransactionError,
+    #[error("Error getting minimum balance")]
+    GetMinimumBalanceError,
+    #[error("ATA is not owned by spl program")]
+    AtaOwnerError,
+    #[error("Error getting recent prioritization fees")]
+    GetPrioritizationFeesError,
+    #[error("Error simulating transaction")]
+    SimulateTransactionError,
+    #[error("Transaction would fail: {0}")]
+    TransactionRejected(String),
+    #[error("Error getting fee for message")]
+    GetFeeError,
+    #[error("Signature could not be parsed")]
+    SignatureParsingError,
+    #[error("Blockhash could not be parsed")]
+    BlockhashParsingError,
+    #[error("Error getting signature status")]
+    GetSignatureStatusError,
+    #[error("Transaction could not be decoded")]
+    TransactionDecodeError,
+    #[error("Error signing transaction")]
+    SignTransactionError,
+    #[error("Transaction signatures are missing or invalid")]
+    InvalidSignatureError,
+    #[error("Transaction is not allowed: {0}")]
+    TransactionNotAllowed(String),
+    #[error("Keypair could not be parsed")]
+    KeypairParsingError,
+    #[error("Error deriving keypair")]
+    KeyDerivationError,
+    #[error("Keypair does not match the wallet")]
+    WalletKeyMismatchError,
+    #[error("Token account could not be parsed")]
+    TokenAccountParseError,
+    #[error("Service is not the mint authority")]
+    MintAuthorityError
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sponsored(payer: &Keypair, owner: &Keypair, instructions: &[Instruction]) -> Transaction {
+        let message = Message::new(instructions, Some(&payer.pubkey()));
+
+        Transaction::new(&[payer, owner], message, Hash::default())
+    }
+
+    fn transfer_instructions(payer: &Keypair, owner: &Keypair, destination: &Pubkey, amount: u64) -> (Pubkey, Vec<Instruction>) {
+        let mint = Pubkey::new_unique();
+        let source = get_associated_token_address(&owner.pubkey(), &mint);
+        let destination_ata = get_associated_token_address(destination, &mint);
+
+        let instructions = vec![
+            create_associated_token_account_idempotent(&payer.pubkey(), destination, &mint, &spl_token::ID),
+            transfer_checked(&spl_token::ID, &source, &mint, &destination_ata, &owner.pubkey(), &[], amount, MINT_DECIMALS).unwrap()
+        ];
+
+        (destination_ata, instructions)
+    }
+
+    #[test]
+    fn verify_sponsored_transaction_returns_created_accounts() {
+        let (payer, owner) = (Keypair::new(), Keypair::new());
+        let (destination_ata, instructions) = transfer_instructions(&payer, &owner, &Pubkey::new_unique(), 1);
+        let transaction = sponsored(&payer, &owner, &instructions);
+
+        let created = SolanaRpcClient::verify_sponsored_transaction(&transaction, &payer.pubkey()).unwrap();
+
+        assert_eq!(created, vec![destination_ata]);
+        assert_eq!(SolanaRpcClient::sponsored_owner(&transaction).unwrap(), owner.pubkey());
+    }
+
+    #[test]
+    fn verify_sponsored_transaction_rejects_zero_amounts() {
+        let (payer, owner) = (Keypair::new(), Keypair::new());
+        let (_, instructions) = transfer_instructions(&payer, &owner, &Pubkey::new_unique(), 0);
+        let transaction = sponsored(&payer, &owner, &instructions);
+
+        assert!(matches!(
+            SolanaRpcClient::verify_sponsored_transaction(&transaction, &payer.pubkey()),
+            Err(SolanaError::TransactionNotAllowed(_))
+        ));
+
+        let mint = Pubkey::new_unique();
+        let source = get_associated_token_address(&owner.pubkey(), &mint);
+        let burn = burn_checked(&spl_token::ID, &source, &mint, &owner.pubkey(), &[], 0, MINT_DECIMALS).unwrap();
+        let transaction = sponsored(&payer, &owner, &[burn]);
+
+        assert!(matches!(
+            SolanaRpcClient::verify_sponsored_transaction(&transaction, &payer.pubkey()),
+            Err(SolanaError::TransactionNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn verify_sponsored_transaction_rejects_accounts_not_receiving_a_transfer() {
+        let (payer, owner) = (Keypair::new(), Keypair::new());
+        let (_, mut instructions) = transfer_instructions(&payer, &owner, &Pubkey::new_unique(), 1);
+        instructions.push(create_associated_token_account_idempotent(
+            &payer.pubkey(), 
+            &Pubkey::new_unique(), 
+            &Pubkey::new_unique(), 
+            &spl_token::ID
+        ));
+        let transaction = sponsored(&payer, &owner, &instructions);
+
+        assert!(matches!(
+            SolanaRpcClient::verify_sponsored_transaction(&transaction, &payer.pubkey()),
+            Err(SolanaError::TransactionNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn pack_recipients_creates_each_missing_ata_once_per_group() {
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let receiver_pubkeys = vec![Pubkey::new_unique(); 3];
+        let atas: Vec<VerifyAndGetAtaResponse> = receiver_pubkeys
+            .iter()
+            .map(|receiver| VerifyAndGetAtaResponse { ata_pubkey: get_associated_token_address(receiver, &mint), is_created: false })
+            .collect();
+        let recipients: Vec<MintRecipient> = receiver_pubkeys
+            .iter()
+            .map(|receiver| MintRecipient { receiver_pubkey: receiver.to_string(), amount: 1 })
+            .collect();
+
+        let groups = SolanaRpcClient::pack_recipients(&payer, &mint, &receiver_pubkeys, &atas, &recipients).unwrap();
+        assert_eq!(groups, vec![vec![0, 1, 2]]);
+
+        let instructions = SolanaRpcClient::group_instructions(&payer, &mint, &receiver_pubkeys, &atas, &recipients, &groups[0]).unwrap();
+        let created = instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == spl_associated_token_account::ID)
+            .count();
+        assert_eq!((instructions.len(), created), (4, 1));
+    }
+
+    #[test]
+    fn message_compute_budget_reads_the_budget_instructions() {
+        let (payer, owner) = (Keypair::new(), Keypair::new());
+        let (_, instructions) = transfer_instructions(&payer, &owner, &Pubkey::new_unique(), 1);
+
+        let message = Message::new(&instructions, Some(&payer.pubkey()));
+        assert_eq!(SolanaRpcClient::message_compute_budget(&message), (400_000, 0));
+
+        let message = Message::new(&SolanaRpcClient::with_compute_budget(30_000, 5, &instructions), Some(&payer.pubkey()));
+        assert_eq!(SolanaRpcClient::message_compute_budget(&message), (30_000, 5));
+    }
+}
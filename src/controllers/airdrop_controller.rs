@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use csv::{ReaderBuilder, Trim};
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{MintRecipient, PlannedOperation},
+    helpers::solana_helper::SolanaHelper,
+    models::{
+        airdrop_model::{AirdropPreview, AirdropUpload, AirdropUploadRow},
+        mint_batch_model::MintBatchReport,
+        user_model::User
+    },
+    repositories::{
+        airdrop_repository::{AirdropRepository, AirdropRepositoryError},
+        user_repository::UserRepository
+    }
+};
+
+use super::{token_controller::{TokenController, MAX_BATCH_RECIPIENTS}, ApiError};
+
+#[derive(Clone)]
+pub struct AirdropController {
+    airdrop_repository: AirdropRepository,
+    user_repository: UserRepository,
+    token_controller: TokenController
+}
+
+impl AirdropController {
+    pub fn new(
+        airdrop_repository: AirdropRepository,
+        user_repository: UserRepository,
+        token_controller: TokenController
+    ) -> Self {
+        Self { airdrop_repository, user_repository, token_controller }
+    }
+
+    /// Validates every row of a `recipient,amount` CSV, where recipient is a user
+    /// email or a wallet pubkey, and stores it for preview. Nothing is minted yet.
+    pub async fn create_upload(
+        &self,
+        mint_pubkey_str: &str,
+        file_name: Option<&str>,
+        csv_bytes: &[u8]
+    ) -> Result<AirdropPreview, ApiError> {
+        SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        let parsed_rows = Self::parse_csv(csv_bytes)?;
+
+        if parsed_rows.is_empty() || parsed_rows.len() > MAX_BATCH_RECIPIENTS {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("A CSV needs between 1 and {} rows", MAX_BATCH_RECIPIENTS)
+            ));
+        }
+
+        let emails: Vec<String> = parsed_rows
+            .iter()
+            .filter(|(_, recipient, _)| recipient.contains('@'))
+            .map(|(_, recipient, _)| User::normalize_email(recipient))
+            .collect();
+
+        let users_by_email: HashMap<String, String> = self.user_repository
+            .fetch_users_by_emails(&emails)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching users!".to_string()))?
+            .into_iter()
+            .map(|user| (user.email, user.public_key))
+            .collect();
+
+        let id = Uuid::new_v4();
+        let mut seen: HashMap<String, i32> = HashMap::new();
+
+        let rows: Vec<AirdropUploadRow> = parsed_rows
+            .into_iter()
+            .map(|(row_number, recipient, amount)| {
+                let resolved = Self::resolve_recipient(&recipient, &users_by_email)
+                    .and_then(|pubkey| match seen.get(&pubkey) {
+                        Some(first_row) => Err(format!("Duplicate of row {}", first_row)),
+                        None => {
+                            seen.insert(pubkey.clone(), row_number);
+                            Ok(pubkey)
+                        }
+                    });
+                let amount = Self::parse_amount(&amount);
+
+                let error = match (&resolved, &amount) {
+                    (Err(e), _) | (_, Err(e)) => Some(e.clone()),
+                    _ => None
+                };
+
+                AirdropUploadRow {
+                    upload_id: id,
+                    row_number,
+                    recipient,
+                    receiver_pubkey: resolved.ok(),
+                    amount: amount.ok(),
+                    error
+                }
+            })
+            .collect();
+
+        Self::total_amount(&rows)?;
+
+        let upload = self.airdrop_repository
+            .create_upload(
+                &id,
+                &Utc::now(),
+                mint_pubkey_str,
+                file_name,
+                &rows
+            )
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error saving airdrop upload!".to_string()))?;
+
+        self.build_preview(upload, rows).await
+    }
+
+    pub async fn fetch_preview(&self, id: Uuid) -> Result<AirdropPreview, ApiError> {
+        let upload = self.fetch_upload(id).await?;
+        let rows = self.fetch_rows(id).await?;
+
+        self.build_preview(upload, rows).await
+    }
+
+    /// Mints to every row of a previewed upload through a mint batch. Uploads with
+    /// invalid rows have to be fixed and uploaded again.
+    pub async fn confirm_upload(&self, id: Uuid) -> Result<MintBatchReport, ApiError> {
+        self.fetch_upload(id).await?;
+        let rows = self.fetch_rows(id).await?;
+
+        if rows.iter().any(|row| row.error.is_some()) {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Airdrop upload has invalid rows".to_string()
+            ));
+        }
+
+        let upload = self.airdrop_repository
+            .confirm_upload(&id, &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error confirming airdrop upload!".to_string()))?
+            .ok_or((StatusCode::CONFLICT, "Airdrop upload was already confirmed".to_string()))?;
+
+        let batch = match self.token_controller
+            .register_mint_batch(&upload.mint_pubkey, Self::recipients(&rows))
+            .await
+        {
+            Ok(batch) => batch,
+            Err(e) => {
+                let _ = self.airdrop_repository.revert_confirmation(&id).await;
+                return Err(e);
+            }
+        };
+
+        self.airdrop_repository
+            .set_upload_batch(&id, &batch.id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error confirming airdrop upload!".to_string()))?;
+
+        self.token_controller.run_mint_batch(batch.id, batch.mint_pubkey).await
+    }
+
+    async fn build_preview(
+        &self,
+        upload: AirdropUpload,
+        rows: Vec<AirdropUploadRow>
+    ) -> Result<AirdropPreview, ApiError> {
+        let recipients = Self::recipients(&rows);
+
+        let estimated_cost = if recipients.is_empty() {
+            None
+        } else {
+            let operation = PlannedOperation::MintTo {
+                mint_pubkey: upload.mint_pubkey.clone(),
+                recipients
+            };
+
+            Some(self.token_controller.estimate(operation).await?)
+        };
+
+        let valid_rows = rows.iter().filter(|row| row.error.is_none()).count();
+        let total_amount = Self::total_amount(&rows)?;
+
+        Ok(AirdropPreview {
+            upload,
+            total_rows: rows.len(),
+            valid_rows,
+            invalid_rows: rows.len() - valid_rows,
+            total_amount,
+            estimated_cost,
+            rows
+        })
+    }
+
+    /// Returns `(line number, recipient, amount)` for every record of the CSV.
+    fn parse_csv(csv_bytes: &[u8]) -> Result<Vec<(i32, String, String)>, ApiError> {
+        let mut reader = ReaderBuilder::new()
+            .trim(Trim::All)
+            .from_reader(csv_bytes);
+
+        let headers = reader
+            .headers()
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid CSV header: {}", e)))?
+            .clone();
+
+        let column = |name: &str| headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+            .ok_or((StatusCode::BAD_REQUEST, format!("CSV is missing the `{}` column", name)));
+
+        let recipient_column = column("recipient")?;
+        let amount_column = column("amount")?;
+
+        reader
+            .records()
+            .map(|record| {
+                let record = record.map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid CSV: {}", e)))?;
+                let line = record.position().map_or(0, |position| position.line() as i32);
+
+                Ok((
+                    line,
+                    record.get(recipient_column).unwrap_or_default().to_string(),
+                    record.get(amount_column).unwrap_or_default().to_string()
+                ))
+            })
+            .collect()
+    }
+
+    fn resolve_recipient(
+        recipient: &str,
+        users_by_email: &HashMap<String, String>
+    ) -> Result<String, String> {
+        if recipient.contains('@') {
+            return users_by_email
+                .get(&User::normalize_email(recipient))
+                .cloned()
+                .ok_or(format!("No user with email {}", recipient));
+        }
+
+        SolanaHelper::try_to_convert_str_to_pubkey(recipient)
+            .map(|pubkey| pubkey.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    fn total_amount(rows: &[AirdropUploadRow]) -> Result<i64, ApiError> {
+        rows
+            .iter()
+            .filter_map(|row| row.amount)
+            .try_fold(0i64, |total, amount| total.checked_add(amount))
+            .ok_or((StatusCode::BAD_REQUEST, "Total amount of the airdrop is too large".to_string()))
+    }
+
+    fn parse_amount(amount: &str) -> Result<i64, String> {
+        amount
+            .parse::<i64>()
+            .ok()
+            .filter(|amount| *amount > 0)
+            .ok_or(format!("Invalid amount {}", amount))
+    }
+
+    fn recipients(rows: &[AirdropUploadRow]) -> Vec<MintRecipient> {
+        rows
+            .iter()
+            .filter(|row| row.error.is_none())
+            .filter_map(|row| Some(MintRecipient {
+                receiver_pubkey: row.receiver_pubkey.clone()?,
+                amount: row.amount? as u64
+            }))
+            .collect()
+    }
+
+    async fn fetch_upload(&self, id: Uuid) -> Result<AirdropUpload, ApiError> {
+        self.airdrop_repository
+            .fetch_upload(&id)
+            .await
+            .map_err(|e| match e {
+                AirdropRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Airdrop upload not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching airdrop upload!".to_string())
+            })
+    }
+
+    async fn fetch_rows(&self, id: Uuid) -> Result<Vec<AirdropUploadRow>, ApiError> {
+        self.airdrop_repository
+            .fetch_upload_rows(&id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching airdrop upload!".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(amount: Option<i64>) -> AirdropUploadRow {
+        AirdropUploadRow {
+            upload_id: Uuid::nil(),
+            row_number: 1,
+            recipient: "user@example.com".to_string(),
+            receiver_pubkey: None,
+            amount,
+            error: None
+        }
+    }
+
+    #[test]
+    fn total_amount_skips_invalid_rows() {
+        let rows = vec![row(Some(5)), row(None), row(Some(7))];
+
+        assert_eq!(AirdropController::total_amount(&rows).unwrap(), 12);
+        assert_eq!(AirdropController::total_amount(&[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn total_amount_rejects_overflow() {
+        let rows = vec![row(Some(i64::MAX)), row(Some(1))];
+
+        let (status, _) = AirdropController::total_amount(&rows).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn resolve_recipient_ignores_email_case() {
+        let users_by_email = HashMap::from([("user@example.com".to_string(), "pubkey".to_string())]);
+
+        assert_eq!(AirdropController::resolve_recipient("User@Example.COM", &users_by_email).unwrap(), "pubkey");
+        assert!(AirdropController::resolve_recipient("other@example.com", &users_by_email).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use solana_sdk::hash::hash;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        api_key_model::{ApiKey, CreateApiKeyRequest, CreatedApiKeyResponse},
+        role_model::Role
+    },
+    repositories::{
+        api_key_repository::{ApiKeyRepository, ApiKeyRepositoryError},
+        role_repository::RoleRepository
+    }
+};
+
+use super::ApiError;
+
+const API_KEY_PREFIX: &str = "px_";
+
+/// Characters of the key kept in `ApiKey::prefix`, including `API_KEY_PREFIX`.
+const DISPLAY_PREFIX_LEN: usize = 11;
+
+const BOOTSTRAP_KEY_NAME: &str = "bootstrap";
+
+const ADMIN_ROLE_NAME: &str = "admin";
+
+#[derive(Clone)]
+pub struct ApiKeyController {
+    api_key_repository: ApiKeyRepository,
+    role_repository: RoleRepository
+}
+
+impl ApiKeyController {
+    pub fn new(api_key_repository: ApiKeyRepository, role_repository: RoleRepository) -> Self {
+        Self { api_key_repository, role_repository }
+    }
+
+    pub async fn create_api_key(&self, request: CreateApiKeyRequest) -> Result<CreatedApiKeyResponse, ApiError> {
+        if request.name.trim().is_empty() || request.roles.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "A name and at least one role are required".to_string()));
+        }
+
+        let roles = self.fetch_roles_by_name(&request.roles).await?;
+
+        let key = format!("{}{}{}", API_KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let api_key = Self::new_api_key(request.name.trim(), &key);
+        let role_ids: Vec<Uuid> = roles.iter().map(|role| role.id).collect();
+
+        match self.api_key_repository
+            .create_api_key(&api_key, &role_ids)
+            .await
+        {
+            Ok(Some(api_key)) => Ok(CreatedApiKeyResponse { key, api_key, roles }),
+            _ => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error creating API key!".to_string()))
+        }
+    }
+
+    pub async fn fetch_api_keys(&self) -> Result<Vec<ApiKey>, ApiError> {
+        match self.api_key_repository
+            .fetch_api_keys()
+            .await
+        {
+            Ok(api_keys) => Ok(api_keys),
+            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching API keys!".to_string()))
+        }
+    }
+
+    pub async fn revoke_api_key(&self, id: Uuid) -> Result<ApiKey, ApiError> {
+        self.api_key_repository
+            .revoke_api_key(&id, &Utc::now())
+            .await
+            .map_err(|e| match e {
+                ApiKeyRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error revoking API key!".to_string())
+            })
+    }
+
+    /// Resolves a plain key to its active `ApiKey` and records the use.
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey, ApiError> {
+        let api_key = self.api_key_repository
+            .fetch_active_api_key(&Self::hash_key(key))
+            .await
+            .map_err(|e| match e {
+                ApiKeyRepositoryError::RowNotFound => (StatusCode::UNAUTHORIZED, "Invalid API key".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error authenticating!".to_string())
+            })?;
+
+        if let Err(e) = self.api_key_repository.touch_api_key(&api_key.id, &Utc::now()).await {
+            println!("Error updating API key last use: {}", e);
+        }
+
+        Ok(api_key)
+    }
+
+    /// Registers an admin key configured in secrets, so a fresh deployment has a
+    /// way to create the rest of its keys.
+    pub async fn bootstrap_admin_key(&self, key: &str) -> Result<(), ApiError> {
+        let roles = self.fetch_roles_by_name(&[ADMIN_ROLE_NAME.to_string()]).await?;
+        let api_key = Self::new_api_key(BOOTSTRAP_KEY_NAME, key);
+        let role_ids: Vec<Uuid> = roles.iter().map(|role| role.id).collect();
+
+        self.api_key_repository
+            .create_api_key(&api_key, &role_ids)
+            .await
+            .map(|_| ())
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating API key!".to_string()))
+    }
+
+    /// Fails with 400 when any of the names does not match a role.
+    async fn fetch_roles_by_name(&self, names: &[String]) -> Result<Vec<Role>, ApiError> {
+        let roles = self.role_repository
+            .fetch_roles_by_name(names)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching roles!".to_string()))?;
+
+        if let Some(unknown) = names.iter().find(|name| !roles.iter().any(|role| &role.name == *name)) {
+            return Err((StatusCode::BAD_REQUEST, format!("Role {} does not exist", unknown)));
+        }
+
+        Ok(roles)
+    }
+
+    fn new_api_key(name: &str, key: &str) -> ApiKey {
+        ApiKey {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            prefix: key.chars().take(DISPLAY_PREFIX_LEN).collect(),
+            key_hash: Self::hash_key(key),
+            created_at: Utc::now(),
+            last_used_at: None,
+            revoked_at: None
+        }
+    }
+
+    /// Keys are random and long, so an unsalted SHA-256 is enough to look them up
+    /// without storing them.
+    fn hash_key(key: &str) -> String {
+        hash(key.as_bytes()).to_string()
+    }
+}
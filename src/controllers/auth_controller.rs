@@ -0,0 +1,166 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use solana_sdk::hash::hash;
+use uuid::Uuid;
+
+use crate::{
+    clients::mailer_client::MailerClient,
+    helpers::jwt_helper::JwtHelper,
+    models::auth_model::{AuthenticatedUser, MagicLink, RefreshToken, SessionResponse},
+    repositories::{
+        auth_repository::{AuthRepository, AuthRepositoryError},
+        user_repository::{UserRepository, UserRepositoryError}
+    }
+};
+
+use super::ApiError;
+
+const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+#[derive(Clone)]
+pub struct AuthController {
+    auth_repository: AuthRepository,
+    user_repository: UserRepository,
+    mailer_client: MailerClient,
+    jwt_helper: JwtHelper,
+    magic_link_url: String
+}
+
+impl AuthController {
+    pub fn new(
+        auth_repository: AuthRepository,
+        user_repository: UserRepository,
+        mailer_client: MailerClient,
+        jwt_helper: JwtHelper,
+        magic_link_url: String
+    ) -> Self {
+        Self { auth_repository, user_repository, mailer_client, jwt_helper, magic_link_url }
+    }
+
+    /// Emails a one-time login link. Unknown emails succeed silently, so the
+    /// endpoint cannot be used to find out who has an account.
+    pub async fn request_magic_link(&self, email: &str) -> Result<(), ApiError> {
+        let user = match self.user_repository.fetch_user_by_email(email.trim()).await {
+            Ok(user) => user,
+            Err(UserRepositoryError::RowNotFound) => return Ok(()),
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string()))
+        };
+
+        let token = Self::generate_token();
+        let now = Utc::now();
+
+        let magic_link = MagicLink {
+            id: Uuid::new_v4(),
+            user_id: user.id,
+            token_hash: Self::hash_token(&token),
+            created_at: now,
+            expires_at: now + Duration::minutes(MAGIC_LINK_TTL_MINUTES),
+            used_at: None
+        };
+
+        self.auth_repository
+            .create_magic_link(&magic_link)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating magic link!".to_string()))?;
+
+        self.mailer_client.send(
+            &user.email,
+            "Your login link",
+            &format!(
+                "Log in with this link: {}?token={}\n\nIt expires in {} minutes and works once. If you did not request it, ignore this email.",
+                self.magic_link_url,
+                token,
+                MAGIC_LINK_TTL_MINUTES
+            )
+        ).await.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error sending magic link!".to_string()))
+    }
+
+    pub async fn verify_magic_link(&self, token: &str) -> Result<SessionResponse, ApiError> {
+        let magic_link = self.auth_repository
+            .consume_magic_link(&Self::hash_token(token), &Utc::now())
+            .await
+            .map_err(|e| match e {
+                AuthRepositoryError::TokenUnavailable => (StatusCode::UNAUTHORIZED, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error verifying magic link!".to_string())
+            })?;
+
+        let refresh_token = Self::generate_token();
+        let now = Utc::now();
+
+        let stored = RefreshToken {
+            id: Uuid::new_v4(),
+            user_id: magic_link.user_id,
+            token_hash: Self::hash_token(&refresh_token),
+            created_at: now,
+            expires_at: now + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+            revoked_at: None
+        };
+
+        self.auth_repository
+            .create_refresh_token(&stored)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating session!".to_string()))?;
+
+        self.session_response(stored, refresh_token)
+    }
+
+    /// Trades a refresh token for a new access token and a new refresh token.
+    pub async fn refresh_session(&self, refresh_token: &str) -> Result<SessionResponse, ApiError> {
+        let replacement = Self::generate_token();
+        let now = Utc::now();
+
+        let stored = self.auth_repository
+            .rotate_refresh_token(
+                &Self::hash_token(refresh_token),
+                &Self::hash_token(&replacement),
+                &(now + Duration::days(REFRESH_TOKEN_TTL_DAYS)),
+                &now
+            )
+            .await
+            .map_err(|e| match e {
+                AuthRepositoryError::TokenUnavailable => (StatusCode::UNAUTHORIZED, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error refreshing session!".to_string())
+            })?;
+
+        self.session_response(stored, replacement)
+    }
+
+    pub async fn logout(&self, refresh_token: &str) -> Result<(), ApiError> {
+        self.auth_repository
+            .revoke_refresh_token(&Self::hash_token(refresh_token), &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error logging out!".to_string()))
+    }
+
+    pub fn authenticate_session(&self, access_token: &str) -> Result<AuthenticatedUser, ApiError> {
+        self.jwt_helper
+            .verify_access_token(access_token)
+            .map(|user_id| AuthenticatedUser { user_id })
+            .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))
+    }
+
+    fn session_response(&self, stored: RefreshToken, refresh_token: String) -> Result<SessionResponse, ApiError> {
+        let (access_token, expires_at) = self.jwt_helper
+            .issue_access_token(stored.user_id)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(SessionResponse {
+            user_id: stored.user_id,
+            token_type: "Bearer".to_string(),
+            access_token,
+            expires_at,
+            refresh_token,
+            refresh_expires_at: stored.expires_at
+        })
+    }
+
+    fn generate_token() -> String {
+        format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+    }
+
+    fn hash_token(token: &str) -> String {
+        hash(token.as_bytes()).to_string()
+    }
+}
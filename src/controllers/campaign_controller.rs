@@ -0,0 +1,150 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        campaign_model::{Campaign, CampaignKind, CampaignReport, CampaignStatus, CreateCampaignRequest},
+        program_model::BASIS_POINTS
+    },
+    repositories::{
+        campaign_repository::{CampaignRepository, CampaignRepositoryError},
+        program_repository::{ProgramRepository, ProgramRepositoryError}
+    }
+};
+
+use super::ApiError;
+
+/// Manages bonus point campaigns. They are applied by the earn endpoint of
+/// `ProgramController`.
+#[derive(Clone)]
+pub struct CampaignController {
+    campaign_repository: CampaignRepository,
+    program_repository: ProgramRepository
+}
+
+impl CampaignController {
+    pub fn new(campaign_repository: CampaignRepository, program_repository: ProgramRepository) -> Self {
+        Self { campaign_repository, program_repository }
+    }
+
+    pub async fn create_campaign(&self, program_id: Uuid, request: CreateCampaignRequest) -> Result<Campaign, ApiError> {
+        self.fetch_program(&program_id).await?;
+
+        let category = request.category
+            .map(|category| category.trim().to_string())
+            .filter(|category| !category.is_empty());
+
+        let valid = match request.kind {
+            CampaignKind::Multiplier => request.multiplier_bps.is_some_and(|bps| bps as i64 > BASIS_POINTS)
+                && request.bonus_points.is_none()
+                && category.is_none(),
+            CampaignKind::FirstPurchase => request.bonus_points.is_some_and(|points| points > 0)
+                && request.multiplier_bps.is_none()
+                && category.is_none(),
+            CampaignKind::Category => request.multiplier_bps.is_some_and(|bps| bps as i64 > BASIS_POINTS)
+                && request.bonus_points.is_none()
+                && category.is_some()
+        };
+
+        if !valid || request.name.trim().is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Campaigns need a name; multiplier campaigns a multiplier_bps above 10000, first purchase campaigns positive bonus_points and category campaigns a category and a multiplier_bps above 10000".to_string()
+            ));
+        }
+
+        if request.budget_points <= 0 || request.min_purchase_amount.is_some_and(|amount| amount < 0) {
+            return Err((StatusCode::BAD_REQUEST, "budget_points must be positive and min_purchase_amount not negative".to_string()));
+        }
+
+        let now = Utc::now();
+        let starts_at = request.starts_at.unwrap_or(now);
+
+        if request.ends_at <= starts_at || request.ends_at <= now {
+            return Err((StatusCode::BAD_REQUEST, "ends_at must be after starts_at and in the future".to_string()));
+        }
+
+        let campaign = Campaign {
+            id: Uuid::new_v4(),
+            program_id,
+            name: request.name.trim().to_string(),
+            kind: request.kind,
+            multiplier_bps: request.multiplier_bps,
+            bonus_points: request.bonus_points,
+            category,
+            min_purchase_amount: request.min_purchase_amount.unwrap_or(0),
+            budget_points: request.budget_points,
+            issued_points: 0,
+            status: CampaignStatus::Active,
+            starts_at,
+            ends_at: request.ends_at,
+            exhausted_at: None,
+            created_at: now,
+            updated_at: now
+        };
+
+        self.campaign_repository
+            .create_campaign(&campaign)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating campaign!".to_string()))
+    }
+
+    pub async fn fetch_campaigns(&self, program_id: Uuid) -> Result<Vec<Campaign>, ApiError> {
+        self.fetch_program(&program_id).await?;
+
+        self.campaign_repository
+            .fetch_campaigns(&program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching campaigns!".to_string()))
+    }
+
+    pub async fn pause_campaign(&self, id: Uuid) -> Result<Campaign, ApiError> {
+        self.set_status(&id, CampaignStatus::Active, CampaignStatus::Paused).await
+    }
+
+    /// Exhausted campaigns cannot be resumed.
+    pub async fn resume_campaign(&self, id: Uuid) -> Result<Campaign, ApiError> {
+        self.set_status(&id, CampaignStatus::Paused, CampaignStatus::Active).await
+    }
+
+    pub async fn fetch_reports(&self, program_id: Uuid) -> Result<Vec<CampaignReport>, ApiError> {
+        self.fetch_program(&program_id).await?;
+
+        self.campaign_repository
+            .fetch_reports(&program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching campaign reports!".to_string()))
+    }
+
+    async fn set_status(&self, id: &Uuid, from: CampaignStatus, to: CampaignStatus) -> Result<Campaign, ApiError> {
+        self.campaign_repository
+            .fetch_campaign(id)
+            .await
+            .map_err(|e| Self::campaign_error(e, "Error fetching campaign!"))?;
+
+        self.campaign_repository
+            .set_status(id, from, to, &Utc::now())
+            .await
+            .map_err(|e| Self::campaign_error(e, "Error updating campaign!"))
+    }
+
+    async fn fetch_program(&self, id: &Uuid) -> Result<(), ApiError> {
+        self.program_repository
+            .fetch_program(id)
+            .await
+            .map(|_| ())
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Program was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string())
+            })
+    }
+
+    fn campaign_error(e: CampaignRepositoryError, message: &str) -> ApiError {
+        match e {
+            CampaignRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Campaign was not found".to_string()),
+            CampaignRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+}
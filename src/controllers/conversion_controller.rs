@@ -0,0 +1,358 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{SolanaRpcClient, TransactionStatus},
+    helpers::hd_wallet_helper::HdWalletHelper,
+    models::{
+        conversion_model::{
+            ConvertPointsRequest,
+            ExchangeRate,
+            ExchangeRateSnapshot,
+            PointConversion,
+            PointConversionResponse,
+            PointConversionStatus,
+            UpsertExchangeRateRequest
+        },
+        program_model::{Program, SettlementMode},
+        wallet_model::WalletKind
+    },
+    repositories::{
+        conversion_repository::{ConversionRepository, ConversionRepositoryError},
+        program_repository::{ProgramRepository, ProgramRepositoryError},
+        wallet_repository::WalletRepository
+    }
+};
+
+use super::{token_controller::TokenController, ApiError};
+
+/// Reserved conversions whose transaction was never recorded after this long are released.
+const RESERVATION_TIMEOUT_MINUTES: i64 = 5;
+
+/// Converts points between partner programs at the rate set for the pair.
+#[derive(Clone)]
+pub struct ConversionController {
+    conversion_repository: ConversionRepository,
+    program_repository: ProgramRepository,
+    wallet_repository: WalletRepository,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient,
+    hd_wallet_helper: HdWalletHelper
+}
+
+impl ConversionController {
+    pub fn new(
+        conversion_repository: ConversionRepository,
+        program_repository: ProgramRepository,
+        wallet_repository: WalletRepository,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self {
+            conversion_repository,
+            program_repository,
+            wallet_repository,
+            token_controller,
+            solana_rpc_client,
+            hd_wallet_helper
+        }
+    }
+
+    /// Sets the rate points of the source program convert into the target
+    /// program at. Every change is kept as a snapshot.
+    pub async fn upsert_rate(
+        &self,
+        source_program_id: Uuid,
+        target_program_id: Uuid,
+        request: UpsertExchangeRateRequest
+    ) -> Result<ExchangeRate, ApiError> {
+        if source_program_id == target_program_id {
+            return Err((StatusCode::BAD_REQUEST, "Points can only be converted into another program".to_string()));
+        }
+
+        if request.source_points <= 0 || request.target_points <= 0 || request.min_source_points.is_some_and(|points| points <= 0) {
+            return Err((StatusCode::BAD_REQUEST, "Rate points must be positive".to_string()));
+        }
+
+        for program_id in [&source_program_id, &target_program_id] {
+            let program = self.fetch_program(program_id).await?;
+            Self::check_settlement_mode(&program)?;
+        }
+
+        let now = Utc::now();
+
+        let rate = ExchangeRate {
+            id: Uuid::new_v4(),
+            source_program_id,
+            target_program_id,
+            source_points: request.source_points,
+            target_points: request.target_points,
+            min_source_points: request.min_source_points.unwrap_or(1),
+            enabled: request.enabled.unwrap_or(true),
+            created_at: now,
+            updated_at: now
+        };
+
+        let (rate, _) = self.conversion_repository
+            .upsert_rate(&rate)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error saving exchange rate!".to_string()))?;
+
+        Ok(rate)
+    }
+
+    pub async fn fetch_rates(&self, source_program_id: Uuid) -> Result<Vec<ExchangeRate>, ApiError> {
+        self.fetch_program(&source_program_id).await?;
+
+        self.conversion_repository
+            .fetch_rates(&source_program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching exchange rates!".to_string()))
+    }
+
+    pub async fn fetch_rate_snapshots(&self, rate_id: Uuid) -> Result<Vec<ExchangeRateSnapshot>, ApiError> {
+        self.conversion_repository
+            .fetch_snapshots(&rate_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching exchange rate snapshots!".to_string()))
+    }
+
+    /// Prices the conversion at the current rate, consumes the source lots, then
+    /// burns the source points and mints the target points in one transaction,
+    /// so either both happen or neither does.
+    pub async fn convert(&self, user_id: Uuid, request: ConvertPointsRequest) -> Result<PointConversion, ApiError> {
+        let rate = self.conversion_repository
+            .fetch_current_snapshot(&request.source_program_id, &request.target_program_id)
+            .await
+            .map_err(|e| match e {
+                ConversionRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "No exchange rate between these programs".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching exchange rate!".to_string())
+            })?;
+
+        if !rate.enabled {
+            return Err((StatusCode::CONFLICT, "Conversions between these programs are disabled".to_string()));
+        }
+
+        if request.source_points < rate.min_source_points {
+            return Err((StatusCode::BAD_REQUEST, format!("At least {} points must be converted", rate.min_source_points)));
+        }
+
+        let target_points = rate.convert(request.source_points)
+            .filter(|points| *points > 0)
+            .ok_or((StatusCode::BAD_REQUEST, "Points convert into no target points".to_string()))?;
+
+        let source_program = self.fetch_program(&request.source_program_id).await?;
+        let target_program = self.fetch_program(&request.target_program_id).await?;
+        Self::check_settlement_mode(&source_program)?;
+        Self::check_settlement_mode(&target_program)?;
+
+        let burn_amount = self.base_units(&source_program, request.source_points).await?;
+        let mint_amount = self.base_units(&target_program, target_points).await?;
+
+        let wallet = self.wallet_repository
+            .fetch_wallets(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching wallets!".to_string()))?
+            .into_iter()
+            .find(|wallet| wallet.is_primary)
+            .ok_or((StatusCode::NOT_FOUND, "User has no primary wallet".to_string()))?;
+
+        if wallet.kind == WalletKind::External {
+            return Err((StatusCode::BAD_REQUEST, "Points can only be converted from a custodial wallet".to_string()));
+        }
+
+        let balance = self.solana_rpc_client
+            .get_token_balances(&source_program.mint_pubkey, vec![wallet.pubkey.clone()])
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        if balance < burn_amount {
+            return Err((StatusCode::CONFLICT, "Not enough points".to_string()));
+        }
+
+        let now = Utc::now();
+
+        let conversion = PointConversion {
+            id: Uuid::new_v4(),
+            user_id,
+            source_program_id: source_program.id,
+            target_program_id: target_program.id,
+            rate_snapshot_id: rate.id,
+            source_points: request.source_points,
+            target_points,
+            wallet_pubkey: wallet.pubkey.clone(),
+            status: PointConversionStatus::Reserved,
+            signature: None,
+            recent_blockhash: None,
+            error: None,
+            created_at: now,
+            updated_at: now
+        };
+
+        let conversion = self.conversion_repository
+            .reserve_conversion(&conversion)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error reserving conversion!".to_string()))?;
+
+        let prepared = match self.hd_wallet_helper.wallet_keypair(&wallet) {
+            Ok(owner) => self.solana_rpc_client
+                .prepare_conversion(owner, &source_program.mint_pubkey, burn_amount, &target_program.mint_pubkey, mint_amount)
+                .await,
+            Err(e) => Err(e)
+        };
+
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                let conversion = self.fail_conversion(&conversion, &e.to_string()).await?;
+                return Err(Self::conversion_error(&conversion));
+            }
+        };
+
+        let conversion = self.conversion_repository
+            .submit_conversion(&conversion.id, &prepared.signature(), &prepared.recent_blockhash(), &Utc::now())
+            .await
+            .map_err(|e| Self::status_error(e, "Error recording conversion!"))?;
+
+        let conversion = match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.complete_conversion(&conversion, &target_program).await?,
+            // The transaction may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_conversion(conversion).await?
+        };
+
+        match conversion.status {
+            PointConversionStatus::Failed => Err(Self::conversion_error(&conversion)),
+            _ => Ok(conversion)
+        }
+    }
+
+    /// Settles a conversion left in flight: its transaction is looked up on
+    /// chain, and reservations that never recorded one are released once they
+    /// time out.
+    pub async fn resume_conversion(&self, id: Uuid) -> Result<PointConversion, ApiError> {
+        let conversion = self.fetch_conversion_record(&id).await?;
+
+        match conversion.status {
+            PointConversionStatus::Submitted => self.settle_conversion(conversion).await,
+            PointConversionStatus::Reserved if conversion.created_at + Duration::minutes(RESERVATION_TIMEOUT_MINUTES) < Utc::now() => {
+                self.fail_conversion(&conversion, "Reservation timed out before the transaction was sent").await
+            },
+            _ => Ok(conversion)
+        }
+    }
+
+    pub async fn fetch_conversion(&self, id: Uuid) -> Result<PointConversionResponse, ApiError> {
+        let conversion = self.fetch_conversion_record(&id).await?;
+
+        let rate = self.conversion_repository
+            .fetch_snapshot(&conversion.rate_snapshot_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching exchange rate snapshot!".to_string()))?;
+
+        Ok(PointConversionResponse { conversion, rate })
+    }
+
+    pub async fn fetch_user_conversions(&self, user_id: Uuid) -> Result<Vec<PointConversion>, ApiError> {
+        self.conversion_repository
+            .fetch_user_conversions(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching conversions!".to_string()))
+    }
+
+    /// Decides a `Submitted` conversion from the status of its transaction.
+    /// Pending transactions leave the conversion as it is.
+    async fn settle_conversion(&self, conversion: PointConversion) -> Result<PointConversion, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&conversion.signature, &conversion.recent_blockhash) else {
+            return self.fail_conversion(&conversion, "Transaction was not recorded").await;
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => {
+                let target_program = self.fetch_program(&conversion.target_program_id).await?;
+                self.complete_conversion(&conversion, &target_program).await
+            },
+            TransactionStatus::Failed(error) => self.fail_conversion(&conversion, &error).await,
+            TransactionStatus::Expired => self.fail_conversion(&conversion, "Transaction expired before landing").await,
+            TransactionStatus::Pending => Ok(conversion)
+        }
+    }
+
+    async fn complete_conversion(&self, conversion: &PointConversion, target_program: &Program) -> Result<PointConversion, ApiError> {
+        let now = Utc::now();
+
+        self.conversion_repository
+            .complete_conversion(&conversion.id, target_program.points_expire_at(now), &now)
+            .await
+            .map_err(|e| Self::status_error(e, "Error completing conversion!"))
+    }
+
+    async fn fail_conversion(&self, conversion: &PointConversion, error: &str) -> Result<PointConversion, ApiError> {
+        self.conversion_repository
+            .fail_conversion(&conversion.id, conversion.status, error, &Utc::now())
+            .await
+            .map_err(|e| Self::status_error(e, "Error failing conversion!"))
+    }
+
+    async fn base_units(&self, program: &Program, points: i64) -> Result<u64, ApiError> {
+        let mint = self.token_controller.fetch_mint(&program.mint_pubkey).await?;
+
+        mint.base_units(points)
+            .ok_or((StatusCode::BAD_REQUEST, "Points exceed the mint supply range".to_string()))
+    }
+
+    async fn fetch_conversion_record(&self, id: &Uuid) -> Result<PointConversion, ApiError> {
+        self.conversion_repository
+            .fetch_conversion(id)
+            .await
+            .map_err(|e| match e {
+                ConversionRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Conversion was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching conversion!".to_string())
+            })
+    }
+
+    async fn fetch_program(&self, id: &Uuid) -> Result<Program, ApiError> {
+        self.program_repository
+            .fetch_program(id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Program was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string())
+            })
+    }
+
+    /// Points of deferred programs live in the ledger until settlement, so
+    /// they cannot move in a single on-chain transaction.
+    fn check_settlement_mode(program: &Program) -> Result<(), ApiError> {
+        match program.settlement_mode {
+            SettlementMode::Immediate => Ok(()),
+            SettlementMode::Deferred => Err((
+                StatusCode::BAD_REQUEST,
+                "Points can only be converted between programs settled immediately".to_string()
+            ))
+        }
+    }
+
+    fn status_error(e: ConversionRepositoryError, message: &str) -> ApiError {
+        match e {
+            ConversionRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+
+    fn conversion_error(conversion: &PointConversion) -> ApiError {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("Error converting points: {}", conversion.error.clone().unwrap_or_default())
+        )
+    }
+}
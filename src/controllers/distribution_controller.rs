@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use solana_sdk::hash::Hash;
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{MintRecipient, SolanaRpcClient, TransactionStatus},
+    helpers::{merkle_helper::MerkleHelper, solana_helper::SolanaHelper},
+    models::distribution_model::{
+        ClaimRequest,
+        Distribution,
+        DistributionLeaf,
+        DistributionLeafStatus
+    },
+    repositories::distribution_repository::{DistributionRepository, DistributionRepositoryError}
+};
+
+use super::ApiError;
+
+const MAX_DISTRIBUTION_LEAVES: usize = 100_000;
+
+/// How long a leaf may stay reserved without a recorded mint before settling
+/// releases it. Covers claims interrupted between reserving and recording.
+const STALE_CLAIM_MINUTES: i64 = 10;
+
+#[derive(Clone)]
+pub struct DistributionController {
+    distribution_repository: DistributionRepository,
+    solana_rpc_client: SolanaRpcClient
+}
+
+impl DistributionController {
+    pub fn new(
+        distribution_repository: DistributionRepository,
+        solana_rpc_client: SolanaRpcClient
+    ) -> Self {
+        Self { distribution_repository, solana_rpc_client }
+    }
+
+    /// Builds the Merkle tree over `(wallet, amount)` and stores every proof. No
+    /// account is created until a wallet claims.
+    pub async fn create_distribution(
+        &self,
+        mint_pubkey_str: &str,
+        recipients: Vec<MintRecipient>
+    ) -> Result<Distribution, ApiError> {
+        SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        if recipients.is_empty() || recipients.len() > MAX_DISTRIBUTION_LEAVES {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("A distribution needs between 1 and {} recipients", MAX_DISTRIBUTION_LEAVES)
+            ));
+        }
+
+        let mut wallets = HashSet::new();
+        let mut leaf_hashes = Vec::with_capacity(recipients.len());
+        let mut total_amount: i64 = 0;
+
+        for (index, recipient) in recipients.iter().enumerate() {
+            let wallet_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(&recipient.receiver_pubkey)
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Recipient {}: {}", index, e)))?;
+
+            if !wallets.insert(wallet_pubkey) {
+                return Err((StatusCode::BAD_REQUEST, format!("Recipient {}: duplicate wallet", index)));
+            }
+
+            total_amount = i64::try_from(recipient.amount)
+                .ok()
+                .filter(|amount| *amount > 0)
+                .and_then(|amount| total_amount.checked_add(amount))
+                .ok_or((StatusCode::BAD_REQUEST, format!("Recipient {}: invalid amount", index)))?;
+
+            leaf_hashes.push(MerkleHelper::leaf_hash(&wallet_pubkey, recipient.amount));
+        }
+
+        let (root, proofs) = MerkleHelper::build_tree(&leaf_hashes);
+
+        let distribution = Distribution {
+            id: Uuid::new_v4(),
+            mint_pubkey: mint_pubkey_str.to_string(),
+            merkle_root: root.to_string(),
+            leaf_count: recipients.len() as i32,
+            total_amount,
+            created_at: Utc::now()
+        };
+
+        let leaves: Vec<DistributionLeaf> = recipients
+            .into_iter()
+            .zip(proofs)
+            .enumerate()
+            .map(|(index, (recipient, proof))| DistributionLeaf {
+                distribution_id: distribution.id,
+                leaf_index: index as i32,
+                wallet_pubkey: recipient.receiver_pubkey,
+                amount: recipient.amount as i64,
+                proof: proof.iter().map(|hash| hash.to_string()).collect(),
+                status: DistributionLeafStatus::Unclaimed,
+                signature: None,
+                recent_blockhash: None,
+                claimed_at: None
+            })
+            .collect();
+
+        self.distribution_repository
+            .create_distribution(&distribution, &leaves)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating distribution!".to_string()))
+    }
+
+    pub async fn fetch_distribution(&self, id: Uuid) -> Result<Distribution, ApiError> {
+        self.distribution_repository
+            .fetch_distribution(&id)
+            .await
+            .map_err(|e| match e {
+                DistributionRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Distribution not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching distribution!".to_string())
+            })
+    }
+
+    pub async fn fetch_leaf(&self, id: Uuid, wallet_pubkey: &str) -> Result<DistributionLeaf, ApiError> {
+        self.distribution_repository
+            .fetch_leaf(&id, wallet_pubkey)
+            .await
+            .map_err(|e| match e {
+                DistributionRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Wallet is not part of this distribution".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching distribution!".to_string())
+            })
+    }
+
+    /// Mints the claimed amount once the proof checks out against the stored root.
+    /// Tokens only ever go to the wallet in the leaf, so the proof alone is enough.
+    pub async fn claim(&self, id: Uuid, request: ClaimRequest) -> Result<DistributionLeaf, ApiError> {
+        let distribution = self.fetch_distribution(id).await?;
+
+        let wallet_pubkey = SolanaHelper::try_to_convert_str_to_pubkey(&request.wallet_pubkey)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        let proof = request.proof
+            .iter()
+            .map(|hash| hash.parse::<Hash>())
+            .collect::<Result<Vec<Hash>, _>>()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "Proof could not be parsed".to_string()))?;
+
+        let root = distribution.merkle_root
+            .parse::<Hash>()
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Stored Merkle root is invalid".to_string()))?;
+
+        let leaf = MerkleHelper::leaf_hash(&wallet_pubkey, request.amount);
+
+        if !MerkleHelper::verify_proof(&root, leaf, &proof) {
+            return Err((StatusCode::FORBIDDEN, "Invalid proof".to_string()));
+        }
+
+        let wallet_pubkey_str = wallet_pubkey.to_string();
+
+        // A previous claim whose mint failed or expired gives the leaf back first.
+        let leaf = self.fetch_leaf(id, &wallet_pubkey_str).await?;
+
+        if leaf.status == DistributionLeafStatus::Claiming {
+            self.settle_leaf(leaf).await?;
+        }
+
+        let claimed = self.distribution_repository
+            .start_claim(&id, &wallet_pubkey_str, request.amount as i64, &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error claiming distribution!".to_string()))?;
+
+        if claimed.is_none() {
+            return Err((StatusCode::CONFLICT, "Already claimed".to_string()));
+        }
+
+        let recipient = MintRecipient {
+            receiver_pubkey: wallet_pubkey_str.clone(),
+            amount: request.amount
+        };
+
+        let prepared = match self.solana_rpc_client.prepare_mint_to_batch(&distribution.mint_pubkey, vec![recipient]).await {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                let _ = self.distribution_repository.release_claim(&id, &wallet_pubkey_str, None).await;
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()));
+            }
+        };
+
+        let leaf = self.distribution_repository
+            .submit_claim(&id, &wallet_pubkey_str, &prepared.signature(), &prepared.recent_blockhash())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording mint!".to_string()))?
+            .ok_or((StatusCode::CONFLICT, "Claim is already being minted".to_string()))?;
+
+        let leaf = match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.finish_claim(&leaf).await?,
+            // The mint may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_leaf(leaf).await?
+        };
+
+        match leaf.status {
+            DistributionLeafStatus::Unclaimed => Err((StatusCode::BAD_GATEWAY, "Mint did not land, the leaf can be claimed again".to_string())),
+            _ => Ok(leaf)
+        }
+    }
+
+    /// Settles a leaf left `Claiming` by a claim whose send failed or was cut short.
+    pub async fn settle_claim(&self, id: Uuid, wallet_pubkey: &str) -> Result<DistributionLeaf, ApiError> {
+        let leaf = self.fetch_leaf(id, wallet_pubkey).await?;
+
+        match leaf.status {
+            DistributionLeafStatus::Claiming => self.settle_leaf(leaf).await,
+            _ => Ok(leaf)
+        }
+    }
+
+    /// Decides a `Claiming` leaf from the status of its recorded mint: `Claimed` once
+    /// it landed, `Unclaimed` once it can no longer land. Pending mints, and fresh
+    /// claims that did not record a mint yet, leave the leaf reserved.
+    async fn settle_leaf(&self, leaf: DistributionLeaf) -> Result<DistributionLeaf, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&leaf.signature, &leaf.recent_blockhash) else {
+            let is_stale = leaf.claimed_at
+                .is_none_or(|claimed_at| Utc::now() - claimed_at > Duration::minutes(STALE_CLAIM_MINUTES));
+
+            return match is_stale {
+                true => self.release_claim(&leaf).await,
+                false => Ok(leaf)
+            };
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => self.finish_claim(&leaf).await,
+            TransactionStatus::Failed(_) | TransactionStatus::Expired => self.release_claim(&leaf).await,
+            TransactionStatus::Pending => Ok(leaf)
+        }
+    }
+
+    async fn finish_claim(&self, leaf: &DistributionLeaf) -> Result<DistributionLeaf, ApiError> {
+        let finished = self.distribution_repository
+            .finish_claim(&leaf.distribution_id, &leaf.wallet_pubkey)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error claiming distribution!".to_string()))?;
+
+        match finished {
+            Some(leaf) => Ok(leaf),
+            None => self.fetch_leaf(leaf.distribution_id, &leaf.wallet_pubkey).await
+        }
+    }
+
+    async fn release_claim(&self, leaf: &DistributionLeaf) -> Result<DistributionLeaf, ApiError> {
+        let released = self.distribution_repository
+            .release_claim(&leaf.distribution_id, &leaf.wallet_pubkey, leaf.signature.as_deref())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error releasing claim!".to_string()))?;
+
+        match released {
+            Some(leaf) => Ok(leaf),
+            None => self.fetch_leaf(leaf.distribution_id, &leaf.wallet_pubkey).await
+        }
+    }
+}
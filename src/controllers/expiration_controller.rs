@@ -0,0 +1,312 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    clients::{
+        mailer_client::MailerClient,
+        solana_rpc_client::{SolanaRpcClient, TransactionStatus}
+    },
+    helpers::hd_wallet_helper::HdWalletHelper,
+    models::{
+        expiration_model::{ExpiringPoints, PointExpiration, PointExpirationStatus},
+        ledger_model::{LedgerAccount, LedgerTransactionKind},
+        program_model::SettlementMode,
+        wallet_model::WalletKind
+    },
+    repositories::{
+        expiration_repository::{ExpirationRepository, ExpirationRepositoryError},
+        ledger_repository::LedgerRepository,
+        program_repository::ProgramRepository,
+        user_repository::UserRepository,
+        wallet_repository::WalletRepository
+    }
+};
+
+use super::{token_controller::TokenController, ApiError};
+
+/// Default window of the expiring points preview.
+pub const DEFAULT_PREVIEW_DAYS: i64 = 30;
+
+const MAX_PREVIEW_DAYS: i64 = 365;
+
+#[derive(Clone)]
+pub struct ExpirationController {
+    expiration_repository: ExpirationRepository,
+    program_repository: ProgramRepository,
+    wallet_repository: WalletRepository,
+    user_repository: UserRepository,
+    ledger_repository: LedgerRepository,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient,
+    mailer_client: MailerClient,
+    hd_wallet_helper: HdWalletHelper
+}
+
+impl ExpirationController {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        expiration_repository: ExpirationRepository,
+        program_repository: ProgramRepository,
+        wallet_repository: WalletRepository,
+        user_repository: UserRepository,
+        ledger_repository: LedgerRepository,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient,
+        mailer_client: MailerClient,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self {
+            expiration_repository,
+            program_repository,
+            wallet_repository,
+            user_repository,
+            ledger_repository,
+            token_controller,
+            solana_rpc_client,
+            mailer_client,
+            hd_wallet_helper
+        }
+    }
+
+    /// Expires every lot past its expiry and burns the points of each pending or
+    /// in-flight expiration. One failing expiration does not stop the others.
+    pub async fn run_expirations(&self) -> Result<Vec<PointExpiration>, ApiError> {
+        self.expiration_repository
+            .claim_expired_lots(&Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error expiring point lots!".to_string()))?;
+
+        let expirations = self.expiration_repository
+            .fetch_unsettled_expirations()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching expirations!".to_string()))?;
+
+        let mut processed = Vec::with_capacity(expirations.len());
+
+        for expiration in expirations {
+            match self.process_expiration(expiration).await {
+                Ok(expiration) => processed.push(expiration),
+                Err((_, message)) => println!("Error processing point expiration: {}", message)
+            }
+        }
+
+        Ok(processed)
+    }
+
+    pub async fn retry_expiration(&self, id: Uuid) -> Result<PointExpiration, ApiError> {
+        let expiration = self.expiration_repository
+            .retry_expiration(&id, &Utc::now())
+            .await
+            .map_err(|e| Self::expiration_error(e, "Error retrying expiration!"))?;
+
+        self.process_expiration(expiration).await
+    }
+
+    pub async fn fetch_expirations(&self, status: Option<PointExpirationStatus>) -> Result<Vec<PointExpiration>, ApiError> {
+        self.expiration_repository
+            .fetch_expirations(status)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching expirations!".to_string()))
+    }
+
+    pub async fn fetch_expiration(&self, id: Uuid) -> Result<PointExpiration, ApiError> {
+        self.expiration_repository
+            .fetch_expiration(&id)
+            .await
+            .map_err(|e| Self::expiration_error(e, "Error fetching expiration!"))
+    }
+
+    pub async fn fetch_expiring_points(&self, user_id: Uuid, days: Option<i64>) -> Result<Vec<ExpiringPoints>, ApiError> {
+        let days = days.unwrap_or(DEFAULT_PREVIEW_DAYS);
+
+        if !(1..=MAX_PREVIEW_DAYS).contains(&days) {
+            return Err((StatusCode::BAD_REQUEST, format!("days must be between 1 and {}", MAX_PREVIEW_DAYS)));
+        }
+
+        let now = Utc::now();
+
+        self.expiration_repository
+            .fetch_expiring_points(&user_id, &now, &(now + Duration::days(days)))
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching expiring points!".to_string()))
+    }
+
+    async fn process_expiration(&self, expiration: PointExpiration) -> Result<PointExpiration, ApiError> {
+        let expiration = match expiration.status {
+            PointExpirationStatus::Pending => self.burn_expiration(expiration).await?,
+            PointExpirationStatus::Burning => self.settle_burn(expiration).await?,
+            _ => return Ok(expiration)
+        };
+
+        if matches!(expiration.status, PointExpirationStatus::Completed | PointExpirationStatus::Skipped)
+            && expiration.notified_at.is_none()
+        {
+            self.notify_user(&expiration).await;
+        }
+
+        Ok(expiration)
+    }
+
+    /// Burns the expired points from the wallet their lots were minted to, or the
+    /// user's primary wallet for lots without one, or debits them in the ledger
+    /// of deferred programs. Points held in a wallet the service cannot sign for
+    /// are only expired off chain.
+    async fn burn_expiration(&self, expiration: PointExpiration) -> Result<PointExpiration, ApiError> {
+        let program = self.program_repository
+            .fetch_program(&expiration.program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string()))?;
+
+        if program.settlement_mode == SettlementMode::Deferred {
+            let debited = self.ledger_repository
+                .post_user_transaction(
+                    &program.id,
+                    &expiration.user_id,
+                    LedgerTransactionKind::Expiration,
+                    LedgerAccount::Expired,
+                    -expiration.points,
+                    &expiration.id.to_string(),
+                    &Utc::now()
+                )
+                .await;
+
+            return match debited {
+                Ok(_) => self.finish_expiration(&expiration.id, PointExpirationStatus::Completed, None).await,
+                Err(e) => self.finish_expiration(&expiration.id, PointExpirationStatus::Failed, Some(&e.to_string())).await
+            };
+        }
+
+        let mint = self.token_controller.fetch_mint(&program.mint_pubkey).await?;
+
+        let wallet = self.wallet_repository
+            .fetch_wallets(&expiration.user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching wallets!".to_string()))?
+            .into_iter()
+            .find(|wallet| match &expiration.wallet_pubkey {
+                Some(wallet_pubkey) => wallet.pubkey == *wallet_pubkey,
+                None => wallet.is_primary
+            });
+
+        let wallet = match wallet {
+            Some(wallet) if wallet.kind != WalletKind::External => wallet,
+            _ => return self.finish_expiration(
+                &expiration.id,
+                PointExpirationStatus::Skipped,
+                Some("Wallet is not held by the service")
+            ).await
+        };
+
+        let Some(amount) = mint.base_units(expiration.points) else {
+            return self.finish_expiration(
+                &expiration.id,
+                PointExpirationStatus::Failed,
+                Some("Points exceed the mint supply range")
+            ).await;
+        };
+
+        let prepared = match self.hd_wallet_helper.wallet_keypair(&wallet) {
+            Ok(owner) => self.solana_rpc_client.prepare_burn(owner, &program.mint_pubkey, amount).await,
+            Err(e) => Err(e)
+        };
+
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => return self.finish_expiration(&expiration.id, PointExpirationStatus::Failed, Some(&e.to_string())).await
+        };
+
+        let expiration = self.expiration_repository
+            .submit_burn(&expiration.id, &wallet.pubkey, &prepared.signature(), &prepared.recent_blockhash(), &Utc::now())
+            .await
+            .map_err(|e| Self::expiration_error(e, "Error recording burn!"))?;
+
+        match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.finish_expiration(&expiration.id, PointExpirationStatus::Completed, None).await,
+            // The burn may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_burn(expiration).await
+        }
+    }
+
+    /// Decides a `Burning` expiration from the status of its burn. Pending burns
+    /// are left for the next run.
+    async fn settle_burn(&self, expiration: PointExpiration) -> Result<PointExpiration, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&expiration.signature, &expiration.recent_blockhash) else {
+            return self.finish_expiration(&expiration.id, PointExpirationStatus::Failed, Some("Burn was not recorded")).await;
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => self.finish_expiration(&expiration.id, PointExpirationStatus::Completed, None).await,
+            TransactionStatus::Failed(error) => self.finish_expiration(&expiration.id, PointExpirationStatus::Failed, Some(&error)).await,
+            TransactionStatus::Expired => self.finish_expiration(
+                &expiration.id,
+                PointExpirationStatus::Failed,
+                Some("Burn expired before landing")
+            ).await,
+            TransactionStatus::Pending => Ok(expiration)
+        }
+    }
+
+    async fn finish_expiration(
+        &self,
+        id: &Uuid,
+        status: PointExpirationStatus,
+        error: Option<&str>
+    ) -> Result<PointExpiration, ApiError> {
+        self.expiration_repository
+            .finish_expiration(id, status, error, &Utc::now())
+            .await
+            .map_err(|e| Self::expiration_error(e, "Error updating expiration!"))
+    }
+
+    /// Notifications are best effort: a failed email is logged and retried on
+    /// the next run.
+    async fn notify_user(&self, expiration: &PointExpiration) {
+        let user = match self.user_repository.fetch_user(&expiration.user_id).await {
+            Ok(user) => user,
+            Err(e) => {
+                println!("Error fetching user to notify of expiration: {}", e);
+                return;
+            }
+        };
+
+        let program_name = self.program_repository
+            .fetch_program(&expiration.program_id)
+            .await
+            .map(|program| program.name)
+            .unwrap_or_else(|_| "your loyalty program".to_string());
+
+        let sent = self.mailer_client.send(
+            &user.email,
+            "Some of your points expired",
+            &format!(
+                "{} points of {} expired on {}.",
+                expiration.points,
+                program_name,
+                expiration.created_at.format("%Y-%m-%d")
+            )
+        ).await;
+
+        if let Err(e) = sent {
+            println!("Error notifying user of expiration: {}", e);
+            return;
+        }
+
+        if let Err(e) = self.expiration_repository.mark_notified(&expiration.id, &Utc::now()).await {
+            println!("Error marking expiration as notified: {}", e);
+        }
+    }
+
+    fn expiration_error(e: ExpirationRepositoryError, message: &str) -> ApiError {
+        match e {
+            ExpirationRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Expiration was not found".to_string()),
+            ExpirationRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{MintRecipient, SolanaRpcClient, TransactionStatus},
+    helpers::hd_wallet_helper::HdWalletHelper,
+    models::{
+        ledger_model::{
+            BalanceDivergence,
+            LedgerBalance,
+            LedgerEntry,
+            ReconciliationReport,
+            Settlement,
+            SettlementItem,
+            SettlementItemStatus,
+            SettlementReport
+        },
+        mint_batch_model::{MintBatchItemStatus, MintBatchStatus},
+        mint_model::Mint,
+        program_model::{Program, SettlementMode},
+        wallet_model::WalletKind
+    },
+    repositories::{
+        ledger_repository::{LedgerRepository, LedgerRepositoryError},
+        program_repository::{ProgramRepository, ProgramRepositoryError},
+        wallet_repository::{WalletRepository, WalletRepositoryError}
+    }
+};
+
+use super::{token_controller::TokenController, ApiError};
+
+#[derive(Clone)]
+pub struct LedgerController {
+    ledger_repository: LedgerRepository,
+    program_repository: ProgramRepository,
+    wallet_repository: WalletRepository,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient,
+    hd_wallet_helper: HdWalletHelper
+}
+
+impl LedgerController {
+    pub fn new(
+        ledger_repository: LedgerRepository,
+        program_repository: ProgramRepository,
+        wallet_repository: WalletRepository,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self {
+            ledger_repository,
+            program_repository,
+            wallet_repository,
+            token_controller,
+            solana_rpc_client,
+            hd_wallet_helper
+        }
+    }
+
+    pub async fn fetch_user_balances(&self, user_id: Uuid) -> Result<Vec<LedgerBalance>, ApiError> {
+        self.ledger_repository
+            .fetch_user_balances(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching balances!".to_string()))
+    }
+
+    pub async fn fetch_user_entries(&self, user_id: Uuid) -> Result<Vec<LedgerEntry>, ApiError> {
+        self.ledger_repository
+            .fetch_user_entries(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching ledger entries!".to_string()))
+    }
+
+    /// Settles every deferred program. One failing program does not stop the others.
+    pub async fn run_settlements(&self) -> Result<Vec<SettlementReport>, ApiError> {
+        let programs = self.program_repository
+            .fetch_programs()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching programs!".to_string()))?;
+
+        let mut reports = Vec::new();
+
+        for program in programs.into_iter().filter(|program| program.settlement_mode == SettlementMode::Deferred) {
+            match self.settle_program(program.id).await {
+                Ok(Some(report)) => reports.push(report),
+                Ok(None) => {},
+                Err((_, message)) => println!("Error settling program {}: {}", program.id, message)
+            }
+        }
+
+        Ok(reports)
+    }
+
+    /// Moves the processing settlement of the program forward, or starts a new one
+    /// netting every user's ledger balance against the points already committed on
+    /// chain. Returns `None` when there is nothing to settle.
+    pub async fn settle_program(&self, program_id: Uuid) -> Result<Option<SettlementReport>, ApiError> {
+        let program = self.fetch_deferred_program(&program_id).await?;
+        let mint = self.token_controller.fetch_mint(&program.mint_pubkey).await?;
+
+        let processing = self.ledger_repository
+            .fetch_processing_settlement(&program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching settlement!".to_string()))?;
+
+        let settlement = match processing {
+            Some(settlement) => settlement,
+            None => {
+                let positions = self.ledger_repository
+                    .fetch_settlement_positions(&program_id)
+                    .await
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching settlement positions!".to_string()))?;
+
+                let created = self.ledger_repository
+                    .create_settlement(&program_id, &positions, &Utc::now())
+                    .await
+                    .map_err(|e| Self::ledger_error(e, "Error creating settlement!"))?;
+
+                match created {
+                    Some(settlement) => settlement,
+                    None => return Ok(None)
+                }
+            }
+        };
+
+        let settlement = self.process_settlement(&program, &mint, settlement).await?;
+
+        self.settlement_report(settlement).await.map(Some)
+    }
+
+    pub async fn fetch_settlements(&self, program_id: Uuid) -> Result<Vec<Settlement>, ApiError> {
+        self.ledger_repository
+            .fetch_settlements(&program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching settlements!".to_string()))
+    }
+
+    pub async fn fetch_settlement(&self, id: Uuid) -> Result<SettlementReport, ApiError> {
+        let settlement = self.ledger_repository
+            .fetch_settlement(&id)
+            .await
+            .map_err(|e| Self::ledger_error(e, "Error fetching settlement!"))?;
+
+        self.settlement_report(settlement).await
+    }
+
+    /// Compares each user's on-chain balance with the points settlements confirmed
+    /// for them. Assumes the program's mint is not shared with other programs.
+    pub async fn reconcile(&self, program_id: Uuid) -> Result<ReconciliationReport, ApiError> {
+        let program = self.fetch_deferred_program(&program_id).await?;
+        let mint = self.token_controller.fetch_mint(&program.mint_pubkey).await?;
+
+        let positions = self.ledger_repository
+            .fetch_settlement_positions(&program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching settlement positions!".to_string()))?;
+
+        let wallet_pubkeys: Vec<String> = positions
+            .iter()
+            .filter_map(|position| position.wallet_pubkey.clone())
+            .collect();
+
+        let balances = self.solana_rpc_client
+            .get_token_balances(&program.mint_pubkey, wallet_pubkeys)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+        let mut balances = balances.into_iter();
+
+        let mut divergences = Vec::new();
+        let mut unsettled_points = 0;
+
+        for position in &positions {
+            unsettled_points += position.ledger_points - position.settled_points;
+
+            let on_chain_amount = match position.wallet_pubkey {
+                Some(_) => balances.next().unwrap_or_default(),
+                None => 0
+            };
+            let expected_amount = mint.base_units(position.settled_points).unwrap_or_default();
+
+            if on_chain_amount != expected_amount {
+                divergences.push(BalanceDivergence {
+                    user_id: position.user_id,
+                    wallet_pubkey: position.wallet_pubkey.clone(),
+                    ledger_points: position.ledger_points,
+                    settled_points: position.settled_points,
+                    expected_amount,
+                    on_chain_amount
+                });
+            }
+        }
+
+        Ok(ReconciliationReport {
+            program_id,
+            checked_at: Utc::now(),
+            checked: positions.len(),
+            unsettled_points,
+            divergences
+        })
+    }
+
+    /// Sends what the settlement has not sent yet and settles what is in flight.
+    /// The settlement completes once every item is final.
+    async fn process_settlement(&self, program: &Program, mint: &Mint, settlement: Settlement) -> Result<Settlement, ApiError> {
+        let items = self.fetch_items(&settlement.id).await?;
+
+        self.process_mint_items(program, mint, &settlement, &items).await?;
+
+        for item in items.into_iter().filter(|item| item.amount < 0) {
+            let result = match item.status {
+                SettlementItemStatus::Pending => self.burn_item(program, mint, item).await,
+                SettlementItemStatus::Burning => self.settle_burn_item(item).await,
+                _ => continue
+            };
+
+            if let Err((_, message)) = result {
+                println!("Error settling burn of settlement {}: {}", settlement.id, message);
+            }
+        }
+
+        self.ledger_repository
+            .complete_settlement(&settlement.id, &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error completing settlement!".to_string()))
+    }
+
+    /// Mints the positive items through one mint batch, which sends them in as few
+    /// transactions as fit and never mints twice when resumed. Items stay batched
+    /// until the batch confirms them, and fail once the batch is closed.
+    async fn process_mint_items(
+        &self,
+        program: &Program,
+        mint: &Mint,
+        settlement: &Settlement,
+        items: &[SettlementItem]
+    ) -> Result<(), ApiError> {
+        let report = match settlement.mint_batch_id {
+            Some(mint_batch_id) => {
+                if !items.iter().any(|item| item.status == SettlementItemStatus::Batched) {
+                    return Ok(());
+                }
+
+                let report = self.token_controller.fetch_mint_batch(mint_batch_id).await?;
+
+                match report.status {
+                    MintBatchStatus::Closed => report,
+                    _ => match self.token_controller.resume_mint_batch(mint_batch_id).await {
+                        Ok(report) => report,
+                        // Another run is processing the batch.
+                        Err((StatusCode::CONFLICT, _)) => return Ok(()),
+                        Err(e) => return Err(e)
+                    }
+                }
+            },
+            None => {
+                let mut positions = Vec::new();
+                let mut recipients = Vec::new();
+
+                for item in items.iter().filter(|item| item.amount > 0 && item.status == SettlementItemStatus::Pending) {
+                    match mint.base_units(item.amount) {
+                        Some(amount) => {
+                            positions.push((item.id, recipients.len() as i32));
+                            recipients.push(MintRecipient { receiver_pubkey: item.wallet_pubkey.clone(), amount });
+                        },
+                        None => {
+                            self.ledger_repository
+                                .finish_item(&item.id, SettlementItemStatus::Failed, Some("Points exceed the mint supply range"), &Utc::now())
+                                .await
+                                .map_err(|e| Self::ledger_error(e, "Error updating settlement item!"))?;
+                        }
+                    }
+                }
+
+                if recipients.is_empty() {
+                    return Ok(());
+                }
+
+                let batch = self.token_controller.register_mint_batch(&program.mint_pubkey, recipients).await?;
+
+                let attached = self.ledger_repository
+                    .attach_mint_batch(&settlement.id, &batch.id, &positions, &Utc::now())
+                    .await
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording mint batch!".to_string()))?;
+
+                // Another run attached its own batch first, so this one is never sent.
+                if !attached {
+                    return Ok(());
+                }
+
+                self.token_controller.run_mint_batch(batch.id, batch.mint_pubkey).await?
+            }
+        };
+
+        let confirmed: Vec<i32> = report.items
+            .iter()
+            .filter(|item| item.status == MintBatchItemStatus::Confirmed)
+            .map(|item| item.position)
+            .collect();
+
+        self.ledger_repository
+            .confirm_mint_items(&settlement.id, &confirmed, &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error updating settlement items!".to_string()))?;
+
+        // A batch that failed for good is closed instead of resumed forever, and its
+        // unconfirmed items fail so the next settlement nets them again.
+        let is_closed = match report.status {
+            MintBatchStatus::Closed => true,
+            MintBatchStatus::PartiallyFailed => self.token_controller.close_mint_batch(report.id).await?,
+            MintBatchStatus::Processing | MintBatchStatus::Completed => false
+        };
+
+        if !is_closed {
+            return Ok(());
+        }
+
+        self.ledger_repository
+            .fail_batched_items(&settlement.id, "Mint batch failed", &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error updating settlement items!".to_string()))
+    }
+
+    async fn burn_item(&self, program: &Program, mint: &Mint, item: SettlementItem) -> Result<SettlementItem, ApiError> {
+        let wallet = match self.wallet_repository.fetch_wallet(&item.user_id, &item.wallet_pubkey).await {
+            Ok(wallet) => wallet,
+            Err(WalletRepositoryError::WalletNotFound) => {
+                return self.finish_item(&item.id, SettlementItemStatus::Failed, Some("Wallet was not found")).await;
+            },
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching wallet!".to_string()))
+        };
+
+        if wallet.kind == WalletKind::External {
+            return self.finish_item(&item.id, SettlementItemStatus::Skipped, Some("Wallet is not held by the service")).await;
+        }
+
+        let Some(amount) = mint.base_units(-item.amount) else {
+            return self.finish_item(&item.id, SettlementItemStatus::Failed, Some("Points exceed the mint supply range")).await;
+        };
+
+        let prepared = match self.hd_wallet_helper.wallet_keypair(&wallet) {
+            Ok(owner) => self.solana_rpc_client.prepare_burn(owner, &program.mint_pubkey, amount).await,
+            Err(e) => Err(e)
+        };
+
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => return self.finish_item(&item.id, SettlementItemStatus::Failed, Some(&e.to_string())).await
+        };
+
+        let item = self.ledger_repository
+            .submit_burn_item(&item.id, &prepared.signature(), &prepared.recent_blockhash(), &Utc::now())
+            .await
+            .map_err(|e| Self::ledger_error(e, "Error recording burn!"))?;
+
+        match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.finish_item(&item.id, SettlementItemStatus::Completed, None).await,
+            // The burn may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_burn_item(item).await
+        }
+    }
+
+    async fn settle_burn_item(&self, item: SettlementItem) -> Result<SettlementItem, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&item.signature, &item.recent_blockhash) else {
+            return self.finish_item(&item.id, SettlementItemStatus::Failed, Some("Burn was not recorded")).await;
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => self.finish_item(&item.id, SettlementItemStatus::Completed, None).await,
+            TransactionStatus::Failed(error) => self.finish_item(&item.id, SettlementItemStatus::Failed, Some(&error)).await,
+            TransactionStatus::Expired => self.finish_item(&item.id, SettlementItemStatus::Failed, Some("Burn expired before landing")).await,
+            TransactionStatus::Pending => Ok(item)
+        }
+    }
+
+    async fn finish_item(&self, id: &Uuid, status: SettlementItemStatus, error: Option<&str>) -> Result<SettlementItem, ApiError> {
+        self.ledger_repository
+            .finish_item(id, status, error, &Utc::now())
+            .await
+            .map_err(|e| Self::ledger_error(e, "Error updating settlement item!"))
+    }
+
+    async fn settlement_report(&self, settlement: Settlement) -> Result<SettlementReport, ApiError> {
+        let items = self.fetch_items(&settlement.id).await?;
+
+        Ok(SettlementReport { settlement, items })
+    }
+
+    async fn fetch_items(&self, settlement_id: &Uuid) -> Result<Vec<SettlementItem>, ApiError> {
+        self.ledger_repository
+            .fetch_settlement_items(settlement_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching settlement items!".to_string()))
+    }
+
+    async fn fetch_deferred_program(&self, id: &Uuid) -> Result<Program, ApiError> {
+        let program = self.program_repository
+            .fetch_program(id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Program was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string())
+            })?;
+
+        if program.settlement_mode != SettlementMode::Deferred {
+            return Err((StatusCode::BAD_REQUEST, "Program settles its points immediately".to_string()));
+        }
+
+        Ok(program)
+    }
+
+    fn ledger_error(e: LedgerRepositoryError, message: &str) -> ApiError {
+        match e {
+            LedgerRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Settlement was not found".to_string()),
+            LedgerRepositoryError::SettlementInProgress
+            | LedgerRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+}
@@ -2,5 +2,20 @@ use axum::http::StatusCode;
 
 pub mod user_controller;
 pub mod token_controller;
+pub mod airdrop_controller;
+pub mod distribution_controller;
+pub mod wallet_controller;
+pub mod api_key_controller;
+pub mod auth_controller;
+pub mod role_controller;
+pub mod program_controller;
+pub mod reward_controller;
+pub mod expiration_controller;
+pub mod ledger_controller;
+pub mod reconciliation_controller;
+pub mod referral_controller;
+pub mod tier_controller;
+pub mod conversion_controller;
+pub mod campaign_controller;
 
 pub type ApiError = (StatusCode, String);
\ No newline at end of file
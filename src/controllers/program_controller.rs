@@ -0,0 +1,736 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{MintRecipient, SolanaRpcClient, TransactionStatus},
+    models::{
+        campaign_model::CampaignAward,
+        ledger_model::{LedgerAccount, LedgerTransactionKind},
+        program_model::{
+            CreateEarningRuleRequest,
+            CreateMerchantRequest,
+            CreateProgramRequest,
+            EarnPointsRequest,
+            EarningRule,
+            EarningRuleKind,
+            Merchant,
+            PointEarning,
+            PointEarningStatus,
+            Program,
+            ProgramResponse,
+            SettlementMode,
+            BASIS_POINTS
+        },
+        referral_model::{ReferralReward, ReferralRewardStatus},
+        tier_model::TierBasis
+    },
+    repositories::{
+        campaign_repository::CampaignRepository,
+        ledger_repository::LedgerRepository,
+        program_repository::{ProgramRepository, ProgramRepositoryError},
+        referral_repository::{ReferralRepository, ReferralRepositoryError},
+        user_repository::{UserRepository, UserRepositoryError}
+    }
+};
+
+use super::{token_controller::TokenController, ApiError};
+
+/// Pending earnings whose mint was never recorded, and pending referral rewards,
+/// can be retried after this long.
+const PENDING_TIMEOUT_MINUTES: i64 = 5;
+
+#[derive(Clone)]
+pub struct ProgramController {
+    program_repository: ProgramRepository,
+    user_repository: UserRepository,
+    ledger_repository: LedgerRepository,
+    referral_repository: ReferralRepository,
+    campaign_repository: CampaignRepository,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient
+}
+
+impl ProgramController {
+    pub fn new(
+        program_repository: ProgramRepository,
+        user_repository: UserRepository,
+        ledger_repository: LedgerRepository,
+        referral_repository: ReferralRepository,
+        campaign_repository: CampaignRepository,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient
+    ) -> Self {
+        Self {
+            program_repository,
+            user_repository,
+            ledger_repository,
+            referral_repository,
+            campaign_repository,
+            token_controller,
+            solana_rpc_client
+        }
+    }
+
+    pub async fn create_merchant(&self, request: CreateMerchantRequest) -> Result<Merchant, ApiError> {
+        if request.name.trim().is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "A name is required".to_string()));
+        }
+
+        let merchant = Merchant {
+            id: Uuid::new_v4(),
+            name: request.name.trim().to_string(),
+            created_at: Utc::now()
+        };
+
+        self.program_repository
+            .create_merchant(&merchant)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating merchant!".to_string()))
+    }
+
+    pub async fn fetch_merchants(&self) -> Result<Vec<Merchant>, ApiError> {
+        self.program_repository
+            .fetch_merchants()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching merchants!".to_string()))
+    }
+
+    pub async fn create_program(&self, request: CreateProgramRequest) -> Result<Program, ApiError> {
+        if request.name.trim().is_empty() || request.currency.trim().is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "A name and a currency are required".to_string()));
+        }
+
+        if request.max_points_per_purchase.is_some_and(|max_points| max_points <= 0)
+            || request.points_ttl_days.is_some_and(|days| days <= 0)
+        {
+            return Err((StatusCode::BAD_REQUEST, "max_points_per_purchase and points_ttl_days must be positive".to_string()));
+        }
+
+        self.program_repository
+            .fetch_merchant(&request.merchant_id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Merchant was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching merchant!".to_string())
+            })?;
+
+        let mint = self.token_controller
+            .fetch_mint(&request.mint_pubkey)
+            .await
+            .map_err(|(status, message)| match status {
+                StatusCode::NOT_FOUND => (StatusCode::BAD_REQUEST, message),
+                _ => (status, message)
+            })?;
+
+        // Badge token accounts get frozen, which would lock the points held there.
+        let is_badge_mint = self.program_repository
+            .is_badge_mint(&mint.pubkey)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error checking mint!".to_string()))?;
+
+        if is_badge_mint {
+            return Err((StatusCode::BAD_REQUEST, "Mint is used for tier badges".to_string()));
+        }
+
+        let program = Program {
+            id: Uuid::new_v4(),
+            merchant_id: request.merchant_id,
+            name: request.name.trim().to_string(),
+            mint_pubkey: mint.pubkey,
+            currency: request.currency.trim().to_uppercase(),
+            max_points_per_purchase: request.max_points_per_purchase,
+            points_ttl_days: request.points_ttl_days,
+            settlement_mode: request.settlement_mode,
+            tier_basis: TierBasis::default(),
+            created_at: Utc::now()
+        };
+
+        self.program_repository
+            .create_program(&program)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating program!".to_string()))
+    }
+
+    pub async fn fetch_programs(&self) -> Result<Vec<Program>, ApiError> {
+        self.program_repository
+            .fetch_programs()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching programs!".to_string()))
+    }
+
+    pub async fn fetch_program(&self, id: Uuid) -> Result<ProgramResponse, ApiError> {
+        let program = self.fetch_program_record(&id).await?;
+        let rules = self.fetch_earning_rules(&id).await?;
+
+        Ok(ProgramResponse { program, rules })
+    }
+
+    pub async fn create_earning_rule(&self, program_id: Uuid, request: CreateEarningRuleRequest) -> Result<EarningRule, ApiError> {
+        self.fetch_program_record(&program_id).await?;
+
+        let valid = match request.kind {
+            EarningRuleKind::Base => request.points_per_unit.is_some_and(|points| points > 0)
+                && request.unit_amount.is_some_and(|amount| amount > 0)
+                && request.multiplier_bps.is_none(),
+            EarningRuleKind::Multiplier => request.multiplier_bps.is_some_and(|bps| bps as i64 > BASIS_POINTS)
+                && request.points_per_unit.is_none()
+                && request.unit_amount.is_none()
+                && request.max_points.is_none()
+        };
+
+        if !valid || request.name.trim().is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Base rules need a name, points_per_unit and unit_amount; multiplier rules need a name and a multiplier_bps above 10000".to_string()
+            ));
+        }
+
+        if request.max_points.is_some_and(|max_points| max_points <= 0)
+            || request.min_purchase_amount.is_some_and(|amount| amount < 0)
+        {
+            return Err((StatusCode::BAD_REQUEST, "max_points must be positive and min_purchase_amount not negative".to_string()));
+        }
+
+        let now = Utc::now();
+        let starts_at = request.starts_at.unwrap_or(now);
+
+        if request.ends_at.is_some_and(|ends_at| ends_at <= starts_at) {
+            return Err((StatusCode::BAD_REQUEST, "ends_at must be after starts_at".to_string()));
+        }
+
+        let rule = EarningRule {
+            id: Uuid::new_v4(),
+            program_id,
+            name: request.name.trim().to_string(),
+            kind: request.kind,
+            points_per_unit: request.points_per_unit,
+            unit_amount: request.unit_amount,
+            multiplier_bps: request.multiplier_bps,
+            min_purchase_amount: request.min_purchase_amount.unwrap_or(0),
+            max_points: request.max_points,
+            starts_at,
+            ends_at: request.ends_at,
+            created_at: now,
+            archived_at: None
+        };
+
+        self.program_repository
+            .create_earning_rule(&rule)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating earning rule!".to_string()))
+    }
+
+    pub async fn archive_earning_rule(&self, program_id: Uuid, rule_id: Uuid) -> Result<EarningRule, ApiError> {
+        self.program_repository
+            .archive_earning_rule(&program_id, &rule_id, &Utc::now())
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Earning rule was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error archiving earning rule!".to_string())
+            })
+    }
+
+    /// Computes the points a purchase earns under the program's active rules and
+    /// mints them to the user's primary wallet, or credits them in the ledger for
+    /// deferred programs. Sending a reference again returns the recorded earning,
+    /// settling a mint that was sent and retrying it once it failed or was left
+    /// pending. The earning may also qualify the user's referral, whose reward is
+    /// issued right after.
+    pub async fn earn(&self, program_id: Uuid, request: EarnPointsRequest) -> Result<PointEarning, ApiError> {
+        if request.purchase_amount <= 0 {
+            return Err((StatusCode::BAD_REQUEST, "purchase_amount must be positive".to_string()));
+        }
+
+        let program = self.fetch_program_record(&program_id).await?;
+
+        let user = match (request.user_id, request.email) {
+            (Some(user_id), None) => self.user_repository.fetch_user(&user_id).await,
+            (None, Some(email)) => self.user_repository.fetch_user_by_email(&email).await,
+            _ => return Err((StatusCode::BAD_REQUEST, "Exactly one of user_id or email is required".to_string()))
+        }.map_err(|e| match e {
+            UserRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string())
+        })?;
+
+        let rules = self.fetch_earning_rules(&program_id).await?;
+        let now = Utc::now();
+        let points = program.compute_points(&rules, request.purchase_amount, now);
+        let earning_id = Uuid::new_v4();
+
+        let awards = self.campaign_awards(
+            &program,
+            &user.id,
+            &earning_id,
+            points,
+            request.purchase_amount,
+            request.category.as_deref()
+        ).await?;
+        let bonus_points: i64 = awards.iter().map(|award| award.points).sum();
+
+        let earning = PointEarning {
+            id: earning_id,
+            program_id,
+            user_id: user.id,
+            reference: request.reference,
+            purchase_amount: request.purchase_amount,
+            points,
+            receiver_pubkey: user.public_key,
+            // Nothing to mint for purchases below every rule and campaign.
+            status: if points > 0 || bonus_points > 0 { PointEarningStatus::Pending } else { PointEarningStatus::Completed },
+            signature: None,
+            recent_blockhash: None,
+            error: None,
+            created_at: now,
+            updated_at: now
+        };
+
+        let earning = self.issue_earning(&program, earning, &awards).await?;
+        self.reward_referral(&program, &earning).await;
+
+        Ok(earning)
+    }
+
+    /// Settles a sent mint and retries an earning that failed or was left
+    /// pending, as sending its reference again would. Earnings without a
+    /// reference can only be retried this way.
+    pub async fn resume_earning(&self, id: Uuid) -> Result<PointEarning, ApiError> {
+        let earning = self.program_repository
+            .fetch_earning(&id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Earning was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching earning!".to_string())
+            })?;
+
+        let program = self.fetch_program_record(&earning.program_id).await?;
+
+        let earning = self.retry_earning(&program, earning).await?;
+        self.reward_referral(&program, &earning).await;
+
+        Ok(earning)
+    }
+
+    /// Issues a failed or stale pending referral reward again. Points already
+    /// issued to one of the parties are not issued twice.
+    pub async fn retry_referral_reward(&self, id: Uuid) -> Result<ReferralReward, ApiError> {
+        let reward = self.referral_repository
+            .fetch_reward(&id)
+            .await
+            .map_err(|e| Self::referral_error(e, "Error fetching referral reward!"))?;
+
+        let program = self.fetch_program_record(&reward.program_id).await?;
+        let now = Utc::now();
+
+        let reward = self.referral_repository
+            .retry_reward(&reward.id, &(now - Duration::minutes(PENDING_TIMEOUT_MINUTES)), &now)
+            .await
+            .map_err(|e| Self::referral_error(e, "Error retrying referral reward!"))?;
+
+        self.issue_referral_reward(&program, reward).await
+    }
+
+    /// Records the earning with its campaign bonuses and mints or credits its
+    /// points. An earning whose reference was already recorded is returned as is,
+    /// or retried when it failed or was left pending.
+    async fn issue_earning(&self, program: &Program, earning: PointEarning, awards: &[CampaignAward]) -> Result<PointEarning, ApiError> {
+        let created = self.program_repository
+            .create_earning(&earning, awards, program.max_points_per_purchase)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording earning!".to_string()))?;
+
+        let earning = match (created, &earning.reference) {
+            (Some(created), _) => created,
+            (None, Some(reference)) => {
+                let existing = self.program_repository
+                    .fetch_earning_by_reference(&program.id, reference)
+                    .await
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching earning!".to_string()))?;
+
+                return self.retry_earning(program, existing).await;
+            },
+            (None, None) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Error recording earning!".to_string()))
+        };
+
+        self.process_earning(program, earning).await
+    }
+
+    /// Retries an earning that failed or was left pending past
+    /// `PENDING_TIMEOUT_MINUTES`, and returns any other as is.
+    async fn retry_earning(&self, program: &Program, earning: PointEarning) -> Result<PointEarning, ApiError> {
+        // A mint that was sent is settled first; it is only retried once it can no
+        // longer land.
+        let earning = match earning.status {
+            PointEarningStatus::Submitted => self.settle_earning(program, earning).await?,
+            _ => earning
+        };
+
+        let now = Utc::now();
+
+        let claimed = self.program_repository
+            .claim_earning(&earning.id, &(now - Duration::minutes(PENDING_TIMEOUT_MINUTES)), &now)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error retrying earning!".to_string()))?;
+
+        match claimed {
+            Some(claimed) => self.process_earning(program, claimed).await,
+            None => Ok(earning)
+        }
+    }
+
+    async fn process_earning(&self, program: &Program, earning: PointEarning) -> Result<PointEarning, ApiError> {
+        if earning.status != PointEarningStatus::Pending {
+            return Ok(earning);
+        }
+
+        match program.settlement_mode {
+            SettlementMode::Immediate => self.mint_earning(program, earning).await,
+            SettlementMode::Deferred => self.credit_earning(program, earning).await
+        }
+    }
+
+    /// Bonuses of the program's running campaigns the purchase qualifies for,
+    /// before their budgets and the program cap are applied.
+    async fn campaign_awards(
+        &self,
+        program: &Program,
+        user_id: &Uuid,
+        earning_id: &Uuid,
+        base_points: i64,
+        purchase_amount: i64,
+        category: Option<&str>
+    ) -> Result<Vec<CampaignAward>, ApiError> {
+        let now = Utc::now();
+
+        let campaigns = self.campaign_repository
+            .fetch_running_campaigns(&program.id, &now)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching campaigns!".to_string()))?;
+
+        if campaigns.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Whether this is the user's first purchase is only decided when the earning
+        // is recorded, so first purchase bonuses are always candidates here.
+        Ok(campaigns
+            .iter()
+            .filter(|campaign| campaign.applies_to(purchase_amount, category, true, now))
+            .map(|campaign| CampaignAward {
+                id: Uuid::new_v4(),
+                campaign_id: campaign.id,
+                earning_id: *earning_id,
+                user_id: *user_id,
+                points: campaign.bonus_points(base_points),
+                created_at: now
+            })
+            .filter(|award| award.points > 0)
+            .collect())
+    }
+
+    /// Rewards the referral of the earning's user once a purchase earning is
+    /// issued. Errors are logged, since the earning itself went through.
+    async fn reward_referral(&self, program: &Program, earning: &PointEarning) {
+        if earning.purchase_amount == 0 || !matches!(earning.status, PointEarningStatus::Completed | PointEarningStatus::Credited) {
+            return;
+        }
+
+        if let Err((_, message)) = self.reward_purchase_referral(program, earning).await {
+            println!("Error rewarding referral of earning {}: {}", earning.id, message);
+        }
+    }
+
+    /// Rewards the referral of the earning's user when the program has an enabled
+    /// referral rule the earning meets and the referral was not rewarded yet.
+    async fn reward_purchase_referral(&self, program: &Program, earning: &PointEarning) -> Result<(), ApiError> {
+        let rule = self.referral_repository
+            .fetch_rule(&program.id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referral rule!".to_string()))?;
+
+        let Some(rule) = rule.filter(|rule| rule.enabled && earning.purchase_amount >= rule.min_purchase_amount) else {
+            return Ok(());
+        };
+
+        if rule.referrer_points == 0 && rule.referee_points == 0 {
+            return Ok(());
+        }
+
+        let referral = self.referral_repository
+            .fetch_referral_by_referee(&earning.user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referral!".to_string()))?;
+
+        let Some(referral) = referral else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+
+        let reward = ReferralReward {
+            id: Uuid::new_v4(),
+            referral_id: referral.id,
+            program_id: program.id,
+            referrer_id: referral.referrer_id,
+            referee_id: referral.referee_id,
+            qualifying_earning_id: earning.id,
+            referrer_points: rule.referrer_points,
+            referee_points: rule.referee_points,
+            referrer_earning_id: None,
+            referee_earning_id: None,
+            status: ReferralRewardStatus::Pending,
+            error: None,
+            created_at: now,
+            updated_at: now
+        };
+
+        let claimed = self.referral_repository
+            .claim_reward(&reward, &rule)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording referral reward!".to_string()))?;
+
+        match claimed {
+            Some(reward) if reward.status == ReferralRewardStatus::Pending => {
+                self.issue_referral_reward(program, reward).await?;
+                Ok(())
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// Issues both parties' points as earnings referencing the reward, so issuing
+    /// again only retries what failed.
+    async fn issue_referral_reward(&self, program: &Program, reward: ReferralReward) -> Result<ReferralReward, ApiError> {
+        let referrer_earning = self
+            .issue_referral_earning(program, &reward, &reward.referrer_id, reward.referrer_points, "referrer")
+            .await;
+        let referee_earning = self
+            .issue_referral_earning(program, &reward, &reward.referee_id, reward.referee_points, "referee")
+            .await;
+
+        let error = match (&referrer_earning, &referee_earning) {
+            (Err((_, message)), _) | (_, Err((_, message))) => Some(message.clone()),
+            _ => None
+        };
+        let status = match error {
+            Some(_) => ReferralRewardStatus::Failed,
+            None => ReferralRewardStatus::Completed
+        };
+
+        let reward = self.referral_repository
+            .finish_reward(
+                &reward.id,
+                status,
+                referrer_earning.ok().flatten().map(|earning| earning.id).as_ref(),
+                referee_earning.ok().flatten().map(|earning| earning.id).as_ref(),
+                error.as_deref(),
+                &Utc::now()
+            )
+            .await
+            .map_err(|e| Self::referral_error(e, "Error updating referral reward!"))?;
+
+        match reward.status {
+            ReferralRewardStatus::Failed => Err((
+                StatusCode::BAD_GATEWAY,
+                format!("Error issuing referral reward: {}", reward.error.clone().unwrap_or_default())
+            )),
+            _ => Ok(reward)
+        }
+    }
+
+    async fn issue_referral_earning(
+        &self,
+        program: &Program,
+        reward: &ReferralReward,
+        user_id: &Uuid,
+        points: i64,
+        party: &str
+    ) -> Result<Option<PointEarning>, ApiError> {
+        if points == 0 {
+            return Ok(None);
+        }
+
+        let user = self.user_repository
+            .fetch_user(user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string()))?;
+
+        let now = Utc::now();
+
+        let earning = PointEarning {
+            id: Uuid::new_v4(),
+            program_id: program.id,
+            user_id: user.id,
+            reference: Some(format!("referral:{}:{}", reward.id, party)),
+            purchase_amount: 0,
+            points,
+            receiver_pubkey: user.public_key,
+            status: PointEarningStatus::Pending,
+            signature: None,
+            recent_blockhash: None,
+            error: None,
+            created_at: now,
+            updated_at: now
+        };
+
+        let earning = self.issue_earning(program, earning, &[]).await?;
+
+        match earning.status {
+            PointEarningStatus::Completed | PointEarningStatus::Credited => Ok(Some(earning)),
+            _ => Err((StatusCode::CONFLICT, format!("Earning {} of the referral reward is still in progress", earning.id)))
+        }
+    }
+
+    /// Records the signed mint on the earning before sending it, so that a send
+    /// that errors but lands is never minted again.
+    async fn mint_earning(&self, program: &Program, earning: PointEarning) -> Result<PointEarning, ApiError> {
+        let prepared = match self.token_controller.fetch_mint(&program.mint_pubkey).await {
+            Ok(mint) => match mint.base_units(earning.points) {
+                Some(amount) => self.solana_rpc_client
+                    .prepare_mint_to_batch(&program.mint_pubkey, vec![MintRecipient {
+                        receiver_pubkey: earning.receiver_pubkey.clone(),
+                        amount
+                    }])
+                    .await
+                    .map_err(|e| e.to_string()),
+                None => Err("Points exceed the mint supply range".to_string())
+            },
+            Err((_, message)) => Err(message)
+        };
+
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(message) => {
+                let earning = self.finish_earning(program, &earning, PointEarningStatus::Failed, Some(&message)).await?;
+                return Err(Self::mint_error(&earning));
+            }
+        };
+
+        let earning = self.program_repository
+            .submit_earning(&earning.id, &prepared.signature(), &prepared.recent_blockhash(), &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording mint!".to_string()))?
+            .ok_or((StatusCode::CONFLICT, format!("Earning {} is already being minted", earning.id)))?;
+
+        let earning = match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.finish_earning(program, &earning, PointEarningStatus::Completed, None).await?,
+            // The mint may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_earning(program, earning).await?
+        };
+
+        match earning.status {
+            PointEarningStatus::Failed => Err(Self::mint_error(&earning)),
+            _ => Ok(earning)
+        }
+    }
+
+    /// Decides a `Submitted` earning from the status of its mint. Pending mints
+    /// leave the earning as it is.
+    async fn settle_earning(&self, program: &Program, earning: PointEarning) -> Result<PointEarning, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&earning.signature, &earning.recent_blockhash) else {
+            return self.finish_earning(program, &earning, PointEarningStatus::Failed, Some("Mint was not recorded")).await;
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => self.finish_earning(program, &earning, PointEarningStatus::Completed, None).await,
+            TransactionStatus::Failed(error) => self.finish_earning(program, &earning, PointEarningStatus::Failed, Some(&error)).await,
+            TransactionStatus::Expired => self.finish_earning(program, &earning, PointEarningStatus::Failed, Some("Mint expired before landing")).await,
+            TransactionStatus::Pending => Ok(earning)
+        }
+    }
+
+    async fn finish_earning(
+        &self,
+        program: &Program,
+        earning: &PointEarning,
+        status: PointEarningStatus,
+        error: Option<&str>
+    ) -> Result<PointEarning, ApiError> {
+        let now = Utc::now();
+
+        self.program_repository
+            .finish_earning(&earning.id, status, None, error, program.points_expire_at(now), &now)
+            .await
+            .map_err(|e| Self::finish_earning_error(e, earning))
+    }
+
+    fn finish_earning_error(e: ProgramRepositoryError, earning: &PointEarning) -> ApiError {
+        match e {
+            ProgramRepositoryError::InvalidStatus => (StatusCode::CONFLICT, format!("Earning {} was already settled", earning.id)),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error updating earning!".to_string())
+        }
+    }
+
+    fn mint_error(earning: &PointEarning) -> ApiError {
+        (StatusCode::BAD_GATEWAY, format!("Error minting points: {}", earning.error.clone().unwrap_or_default()))
+    }
+
+    /// Credits the points in the ledger; the next settlement mints them.
+    async fn credit_earning(&self, program: &Program, earning: PointEarning) -> Result<PointEarning, ApiError> {
+        let now = Utc::now();
+
+        let credited = self.ledger_repository
+            .post_user_transaction(
+                &program.id,
+                &earning.user_id,
+                LedgerTransactionKind::Earning,
+                LedgerAccount::Issued,
+                earning.points,
+                &earning.id.to_string(),
+                &now
+            )
+            .await;
+
+        let (status, error) = match credited {
+            Ok(_) => (PointEarningStatus::Credited, None),
+            Err(e) => (PointEarningStatus::Failed, Some(e.to_string()))
+        };
+
+        let earning = self.program_repository
+            .finish_earning(
+                &earning.id,
+                status,
+                None,
+                error.as_deref(),
+                program.points_expire_at(now),
+                &now
+            )
+            .await
+            .map_err(|e| Self::finish_earning_error(e, &earning))?;
+
+        match earning.status {
+            PointEarningStatus::Failed => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error crediting points!".to_string())),
+            _ => Ok(earning)
+        }
+    }
+
+    async fn fetch_program_record(&self, id: &Uuid) -> Result<Program, ApiError> {
+        self.program_repository
+            .fetch_program(id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Program was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string())
+            })
+    }
+
+    fn referral_error(e: ReferralRepositoryError, message: &str) -> ApiError {
+        match e {
+            ReferralRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Referral reward was not found".to_string()),
+            ReferralRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+
+    async fn fetch_earning_rules(&self, program_id: &Uuid) -> Result<Vec<EarningRule>, ApiError> {
+        self.program_repository
+            .fetch_earning_rules(program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching earning rules!".to_string()))
+    }
+}
@@ -0,0 +1,361 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{SolanaRpcClient, TransactionStatus},
+    models::{
+        mint_model::Mint,
+        program_model::SettlementMode,
+        reconciliation_model::{
+            ChainReconciliation,
+            ChainReconciliationResponse,
+            Discrepancy,
+            DiscrepancyKind,
+            MintSnapshot,
+            ReconciliationStatus
+        }
+    },
+    repositories::{
+        program_repository::ProgramRepository,
+        reconciliation_repository::{ReconciliationRepository, ReconciliationRepositoryError}
+    }
+};
+
+use super::{ledger_controller::LedgerController, token_controller::TokenController, ApiError};
+
+/// Compares what the database believes about every registered mint with the chain
+/// and stores the discrepancies it finds as a report.
+#[derive(Clone)]
+pub struct ReconciliationController {
+    reconciliation_repository: ReconciliationRepository,
+    program_repository: ProgramRepository,
+    ledger_controller: LedgerController,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient
+}
+
+impl ReconciliationController {
+    pub fn new(
+        reconciliation_repository: ReconciliationRepository,
+        program_repository: ProgramRepository,
+        ledger_controller: LedgerController,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient
+    ) -> Self {
+        Self {
+            reconciliation_repository,
+            program_repository,
+            ledger_controller,
+            token_controller,
+            solana_rpc_client
+        }
+    }
+
+    /// Runs a reconciliation and stores its report. A run that cannot finish, e.g.
+    /// because the cluster is unreachable, is stored as failed.
+    pub async fn run_reconciliation(&self) -> Result<ChainReconciliationResponse, ApiError> {
+        let report = ChainReconciliation {
+            id: Uuid::new_v4(),
+            status: ReconciliationStatus::Running,
+            discrepancy_count: 0,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None
+        };
+
+        let report = self.reconciliation_repository
+            .create_report(&report)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating reconciliation report!".to_string()))?;
+
+        let (snapshots, discrepancies) = match self.reconcile(&report).await {
+            Ok(found) => found,
+            Err((status, message)) => {
+                if let Err(e) = self.reconciliation_repository.fail_report(&report.id, &message, &Utc::now()).await {
+                    println!("Error failing reconciliation report {}: {}", report.id, e);
+                }
+
+                return Err((status, message));
+            }
+        };
+
+        let report = self.reconciliation_repository
+            .finish_report(&report.id, &snapshots, &discrepancies, &Utc::now())
+            .await
+            .map_err(|e| Self::reconciliation_error(e, "Error storing reconciliation report!"))?;
+
+        Ok(ChainReconciliationResponse { report, snapshots, discrepancies })
+    }
+
+    pub async fn fetch_reports(&self) -> Result<Vec<ChainReconciliation>, ApiError> {
+        self.reconciliation_repository
+            .fetch_reports()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching reconciliation reports!".to_string()))
+    }
+
+    pub async fn fetch_report(&self, id: Uuid) -> Result<ChainReconciliationResponse, ApiError> {
+        let report = self.reconciliation_repository
+            .fetch_report(&id)
+            .await
+            .map_err(|e| Self::reconciliation_error(e, "Error fetching reconciliation report!"))?;
+
+        self.report_response(report).await
+    }
+
+    /// The most recent completed report.
+    pub async fn fetch_latest_report(&self) -> Result<ChainReconciliationResponse, ApiError> {
+        let report = self.reconciliation_repository
+            .fetch_latest_report()
+            .await
+            .map_err(|e| Self::reconciliation_error(e, "Error fetching reconciliation report!"))?;
+
+        self.report_response(report).await
+    }
+
+    async fn reconcile(&self, report: &ChainReconciliation) -> Result<(Vec<MintSnapshot>, Vec<Discrepancy>), ApiError> {
+        let mints = self.token_controller.fetch_mints().await?;
+
+        // Signatures checked by the previous report are not checked again.
+        let since = match self.reconciliation_repository.fetch_latest_report().await {
+            Ok(previous) => Some(previous.started_at),
+            Err(ReconciliationRepositoryError::RowNotFound) => None,
+            Err(_) => return Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching reconciliation report!".to_string()))
+        };
+
+        let mut discrepancies = self.check_transactions(report, since).await?;
+        let mut snapshots = Vec::with_capacity(mints.len());
+
+        for mint in &mints {
+            if let Some(snapshot) = self.check_mint(report, mint, &mut discrepancies).await? {
+                snapshots.push(snapshot);
+            }
+        }
+
+        discrepancies.extend(self.check_ledgers(report).await?);
+
+        Ok((snapshots, discrepancies))
+    }
+
+    /// Flags recorded signatures the cluster does not know or that failed.
+    async fn check_transactions(
+        &self,
+        report: &ChainReconciliation,
+        since: Option<DateTime<Utc>>
+    ) -> Result<Vec<Discrepancy>, ApiError> {
+        let transactions = self.reconciliation_repository
+            .fetch_recorded_transactions(since.as_ref())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching recorded transactions!".to_string()))?;
+
+        let statuses = self.solana_rpc_client
+            .get_signature_statuses(transactions.iter().map(|transaction| transaction.signature.clone()).collect())
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        let discrepancies = transactions
+            .into_iter()
+            .zip(statuses)
+            .filter_map(|(transaction, status)| {
+                let detail = match status {
+                    None => format!("{} {} is not known to the cluster", transaction.source, transaction.reference),
+                    Some(TransactionStatus::Failed(error)) => format!("{} {} failed on chain: {}", transaction.source, transaction.reference, error),
+                    Some(_) => return None
+                };
+
+                Some(Self::discrepancy(
+                    report,
+                    DiscrepancyKind::MissingTransaction,
+                    &transaction.mint_pubkey,
+                    Some(transaction.signature),
+                    detail
+                ))
+            })
+            .collect();
+
+        Ok(discrepancies)
+    }
+
+    /// Checks the mint's authority, supply and holders, returning its snapshot
+    /// unless the mint cannot be read.
+    async fn check_mint(
+        &self,
+        report: &ChainReconciliation,
+        mint: &Mint,
+        discrepancies: &mut Vec<Discrepancy>
+    ) -> Result<Option<MintSnapshot>, ApiError> {
+        let chain_mint = match self.solana_rpc_client.fetch_token_account(&mint.pubkey).await {
+            Ok(chain_mint) => chain_mint,
+            Err(e) => {
+                discrepancies.push(Self::discrepancy(report, DiscrepancyKind::MintMissing, &mint.pubkey, None, e.to_string()));
+                return Ok(None);
+            }
+        };
+
+        let service_pubkey = self.solana_rpc_client.service_pubkey();
+
+        if chain_mint.mint_authority.as_deref() != Some(service_pubkey.as_str()) {
+            let detail = match &chain_mint.mint_authority {
+                Some(authority) => format!("Mint authority is {}", authority),
+                None => "Mint authority was revoked".to_string()
+            };
+
+            discrepancies.push(Self::discrepancy(report, DiscrepancyKind::MintAuthorityChanged, &mint.pubkey, None, detail));
+        }
+
+        let supply = i64::try_from(chain_mint.supply)
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Supply exceeds the supported range".to_string()))?;
+
+        let recorded_supply = self.reconciliation_repository
+            .fetch_recorded_supply(&mint.pubkey, mint.decimals)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching recorded supply!".to_string()))?;
+
+        let previous = self.reconciliation_repository
+            .fetch_latest_snapshot(&mint.pubkey)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mint snapshot!".to_string()))?;
+
+        // The first snapshot of a mint is the baseline. Transactions in flight while
+        // a report runs can be flagged once and are absorbed by the next report.
+        if let Some(previous) = previous {
+            let recorded_change = recorded_supply - previous.recorded_supply;
+            let expected_supply = previous.supply + recorded_change;
+
+            if supply != expected_supply {
+                discrepancies.push(Self::discrepancy(
+                    report,
+                    DiscrepancyKind::UnexpectedSupplyChange,
+                    &mint.pubkey,
+                    None,
+                    format!(
+                        "Supply moved from {} to {}, but only {} was recorded since the last report",
+                        previous.supply,
+                        supply,
+                        recorded_change
+                    )
+                ));
+            }
+        }
+
+        let holders = self.solana_rpc_client
+            .get_token_holders(&mint.pubkey)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        let owners: Vec<String> = holders
+            .iter()
+            .filter(|holder| holder.owner != service_pubkey)
+            .map(|holder| holder.owner.clone())
+            .collect();
+
+        let unknown_owners = self.reconciliation_repository
+            .fetch_unknown_owners(&mint.pubkey, &owners)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error checking token holders!".to_string()))?;
+
+        for holder in holders.iter().filter(|holder| unknown_owners.contains(&holder.owner)) {
+            discrepancies.push(Self::discrepancy(
+                report,
+                DiscrepancyKind::UnknownHolder,
+                &mint.pubkey,
+                Some(holder.owner.clone()),
+                format!("Holds {} base units", holder.amount)
+            ));
+        }
+
+        Ok(Some(MintSnapshot {
+            report_id: report.id,
+            mint_pubkey: mint.pubkey.clone(),
+            supply,
+            recorded_supply,
+            holders: i32::try_from(holders.len()).unwrap_or(i32::MAX)
+        }))
+    }
+
+    /// Flags users of deferred programs whose token balance differs from what
+    /// settlements confirmed for them, and programs that could not be checked.
+    async fn check_ledgers(&self, report: &ChainReconciliation) -> Result<Vec<Discrepancy>, ApiError> {
+        let programs = self.program_repository
+            .fetch_programs()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching programs!".to_string()))?;
+
+        let mut discrepancies = Vec::new();
+
+        for program in programs.iter().filter(|program| program.settlement_mode == SettlementMode::Deferred) {
+            let ledger_report = match self.ledger_controller.reconcile(program.id).await {
+                Ok(ledger_report) => ledger_report,
+                Err((_, message)) => {
+                    discrepancies.push(Self::discrepancy(
+                        report,
+                        DiscrepancyKind::LedgerUnchecked,
+                        &program.mint_pubkey,
+                        None,
+                        format!("Program {} could not be reconciled: {}", program.id, message)
+                    ));
+                    continue;
+                }
+            };
+
+            for divergence in ledger_report.divergences {
+                discrepancies.push(Self::discrepancy(
+                    report,
+                    DiscrepancyKind::LedgerDivergence,
+                    &program.mint_pubkey,
+                    Some(divergence.user_id.to_string()),
+                    format!(
+                        "Program {} settled {} base units to {}, but it holds {}",
+                        program.id,
+                        divergence.expected_amount,
+                        divergence.wallet_pubkey.as_deref().unwrap_or("no wallet"),
+                        divergence.on_chain_amount
+                    )
+                ));
+            }
+        }
+
+        Ok(discrepancies)
+    }
+
+    async fn report_response(&self, report: ChainReconciliation) -> Result<ChainReconciliationResponse, ApiError> {
+        let snapshots = self.reconciliation_repository
+            .fetch_snapshots(&report.id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mint snapshots!".to_string()))?;
+
+        let discrepancies = self.reconciliation_repository
+            .fetch_discrepancies(&report.id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching discrepancies!".to_string()))?;
+
+        Ok(ChainReconciliationResponse { report, snapshots, discrepancies })
+    }
+
+    fn discrepancy(
+        report: &ChainReconciliation,
+        kind: DiscrepancyKind,
+        mint_pubkey: &str,
+        subject: Option<String>,
+        detail: String
+    ) -> Discrepancy {
+        Discrepancy {
+            id: Uuid::new_v4(),
+            report_id: report.id,
+            kind,
+            mint_pubkey: mint_pubkey.to_string(),
+            subject,
+            detail,
+            created_at: Utc::now()
+        }
+    }
+
+    fn reconciliation_error(e: ReconciliationRepositoryError, message: &str) -> ApiError {
+        match e {
+            ReconciliationRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Reconciliation report was not found".to_string()),
+            ReconciliationRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+}
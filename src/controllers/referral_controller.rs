@@ -0,0 +1,139 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    models::referral_model::{
+        Referral,
+        ReferralCode,
+        ReferralReward,
+        ReferralRewardStatus,
+        ReferralRule,
+        UpsertReferralRuleRequest
+    },
+    repositories::{
+        referral_repository::ReferralRepository,
+        user_repository::{UserRepository, UserRepositoryError}
+    }
+};
+
+use super::{program_controller::ProgramController, ApiError};
+
+/// Referral codes, the referrals they produced and the rules programs reward
+/// them with. Rewards themselves are issued by `ProgramController` when the
+/// referee earns.
+#[derive(Clone)]
+pub struct ReferralController {
+    referral_repository: ReferralRepository,
+    user_repository: UserRepository,
+    program_controller: ProgramController
+}
+
+impl ReferralController {
+    pub fn new(
+        referral_repository: ReferralRepository,
+        user_repository: UserRepository,
+        program_controller: ProgramController
+    ) -> Self {
+        Self { referral_repository, user_repository, program_controller }
+    }
+
+    /// The user's referral code, created the first time it is asked for.
+    pub async fn fetch_referral_code(&self, user_id: Uuid) -> Result<ReferralCode, ApiError> {
+        self.user_repository
+            .fetch_user(&user_id)
+            .await
+            .map_err(|e| match e {
+                UserRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string())
+            })?;
+
+        let code = ReferralCode {
+            user_id,
+            code: Self::referral_code(),
+            created_at: Utc::now()
+        };
+
+        self.referral_repository
+            .create_code(&code)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating referral code!".to_string()))
+    }
+
+    pub async fn fetch_referrals(&self, user_id: Uuid) -> Result<Vec<Referral>, ApiError> {
+        self.referral_repository
+            .fetch_referrals(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referrals!".to_string()))
+    }
+
+    pub async fn fetch_user_rewards(&self, user_id: Uuid) -> Result<Vec<ReferralReward>, ApiError> {
+        self.referral_repository
+            .fetch_user_rewards(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referral rewards!".to_string()))
+    }
+
+    pub async fn upsert_rule(&self, program_id: Uuid, request: UpsertReferralRuleRequest) -> Result<ReferralRule, ApiError> {
+        if request.referrer_points < 0
+            || request.referee_points < 0
+            || request.min_purchase_amount.is_some_and(|amount| amount < 0)
+        {
+            return Err((StatusCode::BAD_REQUEST, "Points and min_purchase_amount must not be negative".to_string()));
+        }
+
+        if request.max_rewards_per_referrer.is_some_and(|max| max <= 0)
+            || request.max_daily_rewards_per_referrer.is_some_and(|max| max <= 0)
+        {
+            return Err((StatusCode::BAD_REQUEST, "Referral limits must be positive".to_string()));
+        }
+
+        self.program_controller.fetch_program(program_id).await?;
+
+        let now = Utc::now();
+
+        let rule = ReferralRule {
+            program_id,
+            referrer_points: request.referrer_points,
+            referee_points: request.referee_points,
+            min_purchase_amount: request.min_purchase_amount.unwrap_or(0),
+            max_rewards_per_referrer: request.max_rewards_per_referrer,
+            max_daily_rewards_per_referrer: request.max_daily_rewards_per_referrer,
+            enabled: request.enabled.unwrap_or(true),
+            created_at: now,
+            updated_at: now
+        };
+
+        self.referral_repository
+            .upsert_rule(&rule)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error saving referral rule!".to_string()))
+    }
+
+    pub async fn fetch_rule(&self, program_id: Uuid) -> Result<ReferralRule, ApiError> {
+        self.referral_repository
+            .fetch_rule(&program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referral rule!".to_string()))?
+            .ok_or((StatusCode::NOT_FOUND, "Program has no referral rule".to_string()))
+    }
+
+    pub async fn fetch_program_rewards(
+        &self,
+        program_id: Uuid,
+        status: Option<ReferralRewardStatus>
+    ) -> Result<Vec<ReferralReward>, ApiError> {
+        self.referral_repository
+            .fetch_program_rewards(&program_id, status)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referral rewards!".to_string()))
+    }
+
+    pub async fn retry_reward(&self, id: Uuid) -> Result<ReferralReward, ApiError> {
+        self.program_controller.retry_referral_reward(id).await
+    }
+
+    fn referral_code() -> String {
+        Uuid::new_v4().simple().to_string()[..10].to_uppercase()
+    }
+}
@@ -0,0 +1,515 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{MintRecipient, SolanaRpcClient, TransactionStatus},
+    helpers::hd_wallet_helper::HdWalletHelper,
+    models::{
+        ledger_model::{LedgerAccount, LedgerTransactionKind},
+        program_model::{Program, SettlementMode},
+        reward_model::{
+            CreateRewardItemRequest,
+            RedemptionOrder,
+            RedemptionOrderResponse,
+            RedemptionOrderStatus,
+            RewardItem
+        },
+        wallet_model::WalletKind
+    },
+    repositories::{
+        ledger_repository::{LedgerRepository, LedgerRepositoryError},
+        program_repository::{ProgramRepository, ProgramRepositoryError},
+        reward_repository::{RewardRepository, RewardRepositoryError},
+        wallet_repository::WalletRepository
+    }
+};
+
+use super::{token_controller::TokenController, ApiError};
+
+/// Reserved orders whose burn was never recorded after this long are released,
+/// and refunds whose mint was never recorded can be claimed again.
+const RESERVATION_TIMEOUT_MINUTES: i64 = 5;
+
+#[derive(Clone)]
+pub struct RewardController {
+    reward_repository: RewardRepository,
+    program_repository: ProgramRepository,
+    wallet_repository: WalletRepository,
+    ledger_repository: LedgerRepository,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient,
+    hd_wallet_helper: HdWalletHelper
+}
+
+impl RewardController {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        reward_repository: RewardRepository,
+        program_repository: ProgramRepository,
+        wallet_repository: WalletRepository,
+        ledger_repository: LedgerRepository,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self {
+            reward_repository,
+            program_repository,
+            wallet_repository,
+            ledger_repository,
+            token_controller,
+            solana_rpc_client,
+            hd_wallet_helper
+        }
+    }
+
+    pub async fn create_reward_item(&self, program_id: Uuid, request: CreateRewardItemRequest) -> Result<RewardItem, ApiError> {
+        self.fetch_program(&program_id).await?;
+
+        if request.name.trim().is_empty() || request.point_price <= 0 {
+            return Err((StatusCode::BAD_REQUEST, "A name and a positive point_price are required".to_string()));
+        }
+
+        if request.stock.is_some_and(|stock| stock < 0) {
+            return Err((StatusCode::BAD_REQUEST, "stock must not be negative".to_string()));
+        }
+
+        let now = Utc::now();
+        let valid_from = request.valid_from.unwrap_or(now);
+
+        if request.valid_until.is_some_and(|valid_until| valid_until <= valid_from) {
+            return Err((StatusCode::BAD_REQUEST, "valid_until must be after valid_from".to_string()));
+        }
+
+        let item = RewardItem {
+            id: Uuid::new_v4(),
+            program_id,
+            name: request.name.trim().to_string(),
+            description: request.description,
+            point_price: request.point_price,
+            stock: request.stock,
+            valid_from,
+            valid_until: request.valid_until,
+            created_at: now,
+            archived_at: None
+        };
+
+        self.reward_repository
+            .create_item(&item)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating reward!".to_string()))
+    }
+
+    pub async fn fetch_available_items(&self, program_id: Uuid) -> Result<Vec<RewardItem>, ApiError> {
+        self.fetch_program(&program_id).await?;
+
+        self.reward_repository
+            .fetch_available_items(&program_id, &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching rewards!".to_string()))
+    }
+
+    pub async fn archive_reward_item(&self, program_id: Uuid, item_id: Uuid) -> Result<RewardItem, ApiError> {
+        self.reward_repository
+            .archive_item(&program_id, &item_id, &Utc::now())
+            .await
+            .map_err(|e| match e {
+                RewardRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Reward was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error archiving reward!".to_string())
+            })
+    }
+
+    /// Reserves a unit of the item, burns its price from the user's primary wallet
+    /// and issues a redemption code once the burn is confirmed. Deferred programs
+    /// debit the ledger instead of burning. Every step is recorded, so an order is
+    /// never fulfilled without its points being taken.
+    pub async fn place_order(&self, user_id: Uuid, item_id: Uuid) -> Result<RedemptionOrder, ApiError> {
+        let item = self.reward_repository
+            .fetch_item(&item_id)
+            .await
+            .map_err(|e| match e {
+                RewardRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Reward was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching reward!".to_string())
+            })?;
+
+        let program = self.fetch_program(&item.program_id).await?;
+        let mint = self.token_controller.fetch_mint(&program.mint_pubkey).await?;
+
+        let amount = mint.base_units(item.point_price)
+            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Points exceed the mint supply range".to_string()))?;
+
+        let wallet = self.wallet_repository
+            .fetch_wallets(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching wallets!".to_string()))?
+            .into_iter()
+            .find(|wallet| wallet.is_primary)
+            .ok_or((StatusCode::NOT_FOUND, "User has no primary wallet".to_string()))?;
+
+        if program.settlement_mode == SettlementMode::Immediate && wallet.kind == WalletKind::External {
+            return Err((StatusCode::BAD_REQUEST, "Points can only be redeemed from a custodial wallet".to_string()));
+        }
+
+        let now = Utc::now();
+
+        let order = RedemptionOrder {
+            id: Uuid::new_v4(),
+            program_id: program.id,
+            item_id: item.id,
+            user_id,
+            wallet_pubkey: wallet.pubkey.clone(),
+            points: item.point_price,
+            status: RedemptionOrderStatus::Reserved,
+            code: None,
+            burn_signature: None,
+            burn_recent_blockhash: None,
+            refund_signature: None,
+            refund_recent_blockhash: None,
+            error: None,
+            created_at: now,
+            updated_at: now
+        };
+
+        let order = self.reward_repository
+            .reserve_order(&order)
+            .await
+            .map_err(|e| match e {
+                RewardRepositoryError::ItemUnavailable => (StatusCode::CONFLICT, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error reserving reward!".to_string())
+            })?;
+
+        if program.settlement_mode == SettlementMode::Deferred {
+            return self.debit_order(order).await;
+        }
+
+        let prepared = match self.hd_wallet_helper.wallet_keypair(&wallet) {
+            Ok(owner) => self.solana_rpc_client.prepare_burn(owner, &program.mint_pubkey, amount).await,
+            Err(e) => Err(e)
+        };
+
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                let order = self.fail_order(&order.id, &e.to_string()).await?;
+                return Err(Self::burn_error(&order));
+            }
+        };
+
+        let order = self.reward_repository
+            .submit_burn(&order.id, &prepared.signature(), &prepared.recent_blockhash(), &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording burn!".to_string()))?;
+
+        let order = match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.fulfill_order(&order.id).await?,
+            // The burn may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_burn(order).await?
+        };
+
+        match order.status {
+            RedemptionOrderStatus::Failed => Err(Self::burn_error(&order)),
+            _ => Ok(order)
+        }
+    }
+
+    /// Settles orders left in flight: burns and refund mints are looked up on chain,
+    /// and reservations and refunds that never recorded their transaction time out.
+    /// Reserved orders of deferred programs retry their debit, which posts at most
+    /// once.
+    pub async fn resume_order(&self, id: Uuid) -> Result<RedemptionOrder, ApiError> {
+        let order = self.fetch_order_record(&id).await?;
+
+        match order.status {
+            RedemptionOrderStatus::Burning => self.settle_burn(order).await,
+            RedemptionOrderStatus::Refunding if order.refund_signature.is_some() => self.settle_refund(order).await,
+            RedemptionOrderStatus::Refunding => self.time_out_refund(order).await,
+            RedemptionOrderStatus::Reserved => {
+                let program = self.fetch_program(&order.program_id).await?;
+
+                if program.settlement_mode == SettlementMode::Deferred {
+                    return self.debit_order(order).await;
+                }
+
+                if order.created_at + Duration::minutes(RESERVATION_TIMEOUT_MINUTES) < Utc::now() {
+                    return self.fail_order(&order.id, "Reservation timed out before the burn was sent").await;
+                }
+
+                Ok(order)
+            },
+            _ => Ok(order)
+        }
+    }
+
+    /// Mints the points of a fulfilled order back to the wallet they were burned
+    /// from, or credits them back in the ledger of deferred programs. Failed
+    /// refunds can be retried; a refund mint that was sent is settled first, and a
+    /// new one is only built once it can no longer land or was never recorded
+    /// within the timeout.
+    pub async fn refund_order(&self, id: Uuid) -> Result<RedemptionOrder, ApiError> {
+        let order = self.fetch_order_record(&id).await?;
+
+        if order.status == RedemptionOrderStatus::Refunding {
+            let order = match order.refund_signature {
+                Some(_) => self.settle_refund(order).await?,
+                None => self.time_out_refund(order).await?
+            };
+
+            if order.status != RedemptionOrderStatus::RefundFailed {
+                return Ok(order);
+            }
+        }
+
+        let order = self.reward_repository
+            .claim_refund(&id, &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error refunding order!"))?;
+
+        let program = self.fetch_program(&order.program_id).await?;
+
+        if program.settlement_mode == SettlementMode::Deferred {
+            return self.credit_refund(order).await;
+        }
+
+        let prepared = match self.token_controller.fetch_mint(&program.mint_pubkey).await {
+            Ok(mint) => match mint.base_units(order.points) {
+                Some(amount) => self.solana_rpc_client
+                    .prepare_mint_to_batch(&program.mint_pubkey, vec![MintRecipient {
+                        receiver_pubkey: order.wallet_pubkey.clone(),
+                        amount
+                    }])
+                    .await
+                    .map_err(|e| e.to_string()),
+                None => Err("Points exceed the mint supply range".to_string())
+            },
+            Err((_, message)) => Err(message)
+        };
+
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(message) => {
+                let order = self.fail_refund(&order.id, &message).await?;
+                return Err(Self::refund_error(&order));
+            }
+        };
+
+        let order = self.reward_repository
+            .submit_refund(&order.id, &prepared.signature(), &prepared.recent_blockhash(), &order.updated_at, &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error recording refund!"))?;
+
+        let order = match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(sent) => self.complete_refund(&order.id, &sent.signature).await?,
+            // The refund may still have landed, so its outcome is looked up instead.
+            Err(_) => self.settle_refund(order).await?
+        };
+
+        match order.status {
+            RedemptionOrderStatus::RefundFailed => Err(Self::refund_error(&order)),
+            _ => Ok(order)
+        }
+    }
+
+    pub async fn fetch_order(&self, id: Uuid) -> Result<RedemptionOrderResponse, ApiError> {
+        let order = self.fetch_order_record(&id).await?;
+
+        let events = self.reward_repository
+            .fetch_order_events(&id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching order events!".to_string()))?;
+
+        Ok(RedemptionOrderResponse { order, events })
+    }
+
+    pub async fn fetch_user_orders(&self, user_id: Uuid) -> Result<Vec<RedemptionOrder>, ApiError> {
+        self.reward_repository
+            .fetch_user_orders(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching orders!".to_string()))
+    }
+
+    /// Takes the points of an order of a deferred program from its ledger balance.
+    async fn debit_order(&self, order: RedemptionOrder) -> Result<RedemptionOrder, ApiError> {
+        let debited = self.ledger_repository
+            .post_user_transaction(
+                &order.program_id,
+                &order.user_id,
+                LedgerTransactionKind::Redemption,
+                LedgerAccount::Redeemed,
+                -order.points,
+                &order.id.to_string(),
+                &Utc::now()
+            )
+            .await;
+
+        match debited {
+            Ok(transaction) => {
+                let code = Self::redemption_code();
+
+                self.reward_repository
+                    .fulfill_debited_order(&order.id, &code, &transaction.id, &Utc::now())
+                    .await
+                    .map_err(|e| Self::order_error(e, "Error fulfilling order!"))
+            },
+            Err(LedgerRepositoryError::InsufficientBalance) => {
+                self.fail_order(&order.id, "Not enough points").await?;
+                Err((StatusCode::CONFLICT, "Not enough points".to_string()))
+            },
+            Err(e) => {
+                let order = self.fail_order(&order.id, &e.to_string()).await?;
+                Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Error debiting points: {}", order.error.unwrap_or_default())))
+            }
+        }
+    }
+
+    async fn credit_refund(&self, order: RedemptionOrder) -> Result<RedemptionOrder, ApiError> {
+        let now = Utc::now();
+
+        let credited = self.ledger_repository
+            .post_user_transaction(
+                &order.program_id,
+                &order.user_id,
+                LedgerTransactionKind::Refund,
+                LedgerAccount::Redeemed,
+                order.points,
+                &order.id.to_string(),
+                &now
+            )
+            .await;
+
+        let refunded = match credited {
+            Ok(_) => self.reward_repository.complete_refund(&order.id, None, &now).await,
+            Err(e) => self.reward_repository.fail_refund(&order.id, &e.to_string(), &now).await
+        }.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error updating order!".to_string()))?;
+
+        match refunded.status {
+            RedemptionOrderStatus::RefundFailed => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error crediting points back!".to_string())),
+            _ => Ok(refunded)
+        }
+    }
+
+    /// Decides a `Burning` order from the status of its burn. Pending burns leave
+    /// the order as it is.
+    async fn settle_burn(&self, order: RedemptionOrder) -> Result<RedemptionOrder, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&order.burn_signature, &order.burn_recent_blockhash) else {
+            return self.fail_order(&order.id, "Burn was not recorded").await;
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => self.fulfill_order(&order.id).await,
+            TransactionStatus::Failed(error) => self.fail_order(&order.id, &error).await,
+            TransactionStatus::Expired => self.fail_order(&order.id, "Burn expired before landing").await,
+            TransactionStatus::Pending => Ok(order)
+        }
+    }
+
+    /// Decides a `Refunding` order from the status of its refund mint. Pending
+    /// mints leave the order as it is.
+    async fn settle_refund(&self, order: RedemptionOrder) -> Result<RedemptionOrder, ApiError> {
+        let (Some(signature), Some(recent_blockhash)) = (&order.refund_signature, &order.refund_recent_blockhash) else {
+            return self.fail_refund(&order.id, "Refund mint was not recorded").await;
+        };
+
+        let status = self.solana_rpc_client
+            .get_transaction_status(signature, recent_blockhash)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        match status {
+            TransactionStatus::Confirmed => self.complete_refund(&order.id, signature).await,
+            TransactionStatus::Failed(error) => self.fail_refund(&order.id, &error).await,
+            TransactionStatus::Expired => self.fail_refund(&order.id, "Refund mint expired before landing").await,
+            TransactionStatus::Pending => Ok(order)
+        }
+    }
+
+    /// Fails a refund whose mint was never recorded once it timed out. Refunds
+    /// still within the timeout are left as they are.
+    async fn time_out_refund(&self, order: RedemptionOrder) -> Result<RedemptionOrder, ApiError> {
+        if order.updated_at + Duration::minutes(RESERVATION_TIMEOUT_MINUTES) > Utc::now() {
+            return Ok(order);
+        }
+
+        self.reward_repository
+            .time_out_refund(&order.id, &order.updated_at, &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error failing refund!"))
+    }
+
+    async fn complete_refund(&self, id: &Uuid, signature: &str) -> Result<RedemptionOrder, ApiError> {
+        self.reward_repository
+            .complete_refund(id, Some(signature), &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error completing refund!"))
+    }
+
+    async fn fail_refund(&self, id: &Uuid, error: &str) -> Result<RedemptionOrder, ApiError> {
+        self.reward_repository
+            .fail_refund(id, error, &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error failing refund!"))
+    }
+
+    fn refund_error(order: &RedemptionOrder) -> ApiError {
+        (StatusCode::BAD_GATEWAY, format!("Error minting points back: {}", order.error.clone().unwrap_or_default()))
+    }
+
+    async fn fulfill_order(&self, id: &Uuid) -> Result<RedemptionOrder, ApiError> {
+        let code = Self::redemption_code();
+
+        self.reward_repository
+            .fulfill_order(id, &code, &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error fulfilling order!"))
+    }
+
+    async fn fail_order(&self, id: &Uuid, error: &str) -> Result<RedemptionOrder, ApiError> {
+        self.reward_repository
+            .fail_order(id, error, &Utc::now())
+            .await
+            .map_err(|e| Self::order_error(e, "Error failing order!"))
+    }
+
+    async fn fetch_order_record(&self, id: &Uuid) -> Result<RedemptionOrder, ApiError> {
+        self.reward_repository
+            .fetch_order(id)
+            .await
+            .map_err(|e| match e {
+                RewardRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Order was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching order!".to_string())
+            })
+    }
+
+    async fn fetch_program(&self, id: &Uuid) -> Result<Program, ApiError> {
+        self.program_repository
+            .fetch_program(id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Program was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string())
+            })
+    }
+
+    fn redemption_code() -> String {
+        Uuid::new_v4().simple().to_string()[..12].to_uppercase()
+    }
+
+    fn order_error(e: RewardRepositoryError, message: &str) -> ApiError {
+        match e {
+            RewardRepositoryError::InvalidStatus => (StatusCode::CONFLICT, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+        }
+    }
+
+    fn burn_error(order: &RedemptionOrder) -> ApiError {
+        (
+            StatusCode::BAD_GATEWAY,
+            format!("Error burning points: {}", order.error.clone().unwrap_or_default())
+        )
+    }
+}
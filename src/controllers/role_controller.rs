@@ -0,0 +1,131 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    models::role_model::{
+        AssignRoleRequest,
+        CreateRoleRequest,
+        Permission,
+        Principal,
+        Role,
+        RoleAssignment
+    },
+    repositories::role_repository::{RoleRepository, RoleRepositoryError}
+};
+
+use super::{api_key_controller::ApiKeyController, auth_controller::AuthController, ApiError};
+
+#[derive(Clone)]
+pub struct RoleController {
+    role_repository: RoleRepository,
+    api_key_controller: ApiKeyController,
+    auth_controller: AuthController
+}
+
+impl RoleController {
+    pub fn new(
+        role_repository: RoleRepository,
+        api_key_controller: ApiKeyController,
+        auth_controller: AuthController
+    ) -> Self {
+        Self { role_repository, api_key_controller, auth_controller }
+    }
+
+    pub async fn fetch_roles(&self) -> Result<Vec<Role>, ApiError> {
+        self.role_repository
+            .fetch_roles()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching roles!".to_string()))
+    }
+
+    pub async fn create_role(&self, request: CreateRoleRequest) -> Result<Role, ApiError> {
+        if request.name.trim().is_empty() || request.permissions.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "A name and at least one permission are required".to_string()));
+        }
+
+        let role = Role {
+            id: Uuid::new_v4(),
+            name: request.name.trim().to_string(),
+            description: request.description,
+            permissions: request.permissions,
+            created_at: Utc::now()
+        };
+
+        self.role_repository
+            .create_role(&role)
+            .await
+            .map_err(|e| match e {
+                RoleRepositoryError::RoleExists => (StatusCode::CONFLICT, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error creating role!".to_string())
+            })?;
+
+        Ok(role)
+    }
+
+    pub async fn fetch_assignments(&self) -> Result<Vec<RoleAssignment>, ApiError> {
+        self.role_repository
+            .fetch_assignments()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching role assignments!".to_string()))
+    }
+
+    pub async fn assign_role(&self, role_id: Uuid, request: AssignRoleRequest) -> Result<RoleAssignment, ApiError> {
+        if request.api_key_id.is_some() == request.user_id.is_some() {
+            return Err((StatusCode::BAD_REQUEST, "Exactly one of api_key_id or user_id is required".to_string()));
+        }
+
+        let assignment = RoleAssignment {
+            id: Uuid::new_v4(),
+            role_id,
+            api_key_id: request.api_key_id,
+            user_id: request.user_id,
+            created_at: Utc::now()
+        };
+
+        self.role_repository
+            .assign_role(&assignment)
+            .await
+            .map_err(|e| match e {
+                RoleRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                RoleRepositoryError::AlreadyAssigned => (StatusCode::CONFLICT, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error assigning role!".to_string())
+            })
+    }
+
+    pub async fn revoke_assignment(&self, id: Uuid) -> Result<RoleAssignment, ApiError> {
+        self.role_repository
+            .revoke_assignment(&id)
+            .await
+            .map_err(|e| match e {
+                RoleRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error revoking role assignment!".to_string())
+            })
+    }
+
+    /// Resolves the credentials of a request. API keys win over access tokens.
+    pub async fn authenticate(&self, api_key: Option<&str>, access_token: Option<&str>) -> Result<Principal, ApiError> {
+        match (api_key, access_token) {
+            (Some(api_key), _) => self.api_key_controller
+                .authenticate(api_key)
+                .await
+                .map(Principal::ApiKey),
+            (None, Some(access_token)) => self.auth_controller
+                .authenticate_session(access_token)
+                .map(Principal::User),
+            (None, None) => Err((StatusCode::UNAUTHORIZED, "Missing API key or access token".to_string()))
+        }
+    }
+
+    pub async fn authorize(&self, principal: &Principal, permission: Permission) -> Result<(), ApiError> {
+        let permissions = match principal {
+            Principal::ApiKey(api_key) => self.role_repository.fetch_api_key_permissions(&api_key.id).await,
+            Principal::User(user) => self.role_repository.fetch_user_permissions(&user.user_id).await
+        }.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching permissions!".to_string()))?;
+
+        match Role::grants(&permissions, permission) {
+            true => Ok(()),
+            false => Err((StatusCode::FORBIDDEN, "Missing the required permission".to_string()))
+        }
+    }
+}
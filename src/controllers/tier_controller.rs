@@ -0,0 +1,477 @@
+use std::collections::HashSet;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::{SolanaRpcClient, TransactionOutcome},
+    helpers::hd_wallet_helper::HdWalletHelper,
+    models::{
+        program_model::{Program, SettlementMode},
+        tier_model::{
+            ProgramTier,
+            SetTiersRequest,
+            TierBadge,
+            TierBadgeStatus,
+            TierBasis,
+            TierMember,
+            TiersResponse,
+            UserTier,
+            UserTierSummary
+        },
+        wallet_model::WalletKind
+    },
+    repositories::{
+        program_repository::{ProgramRepository, ProgramRepositoryError},
+        tier_repository::TierRepository,
+        wallet_repository::WalletRepository
+    }
+};
+
+use super::{token_controller::TokenController, ApiError};
+
+/// Computes the tier of each user in programs with tiers and keeps the badge
+/// token of their tier in their wallet.
+#[derive(Clone)]
+pub struct TierController {
+    tier_repository: TierRepository,
+    program_repository: ProgramRepository,
+    wallet_repository: WalletRepository,
+    token_controller: TokenController,
+    solana_rpc_client: SolanaRpcClient,
+    hd_wallet_helper: HdWalletHelper
+}
+
+impl TierController {
+    pub fn new(
+        tier_repository: TierRepository,
+        program_repository: ProgramRepository,
+        wallet_repository: WalletRepository,
+        token_controller: TokenController,
+        solana_rpc_client: SolanaRpcClient,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self {
+            tier_repository,
+            program_repository,
+            wallet_repository,
+            token_controller,
+            solana_rpc_client,
+            hd_wallet_helper
+        }
+    }
+
+    /// Replaces the program's tiers. Users move to their new tier at the next refresh.
+    pub async fn set_tiers(&self, program_id: Uuid, request: SetTiersRequest) -> Result<TiersResponse, ApiError> {
+        self.fetch_program(&program_id).await?;
+
+        let mut names = HashSet::new();
+        let mut thresholds = HashSet::new();
+
+        for definition in &request.tiers {
+            let name = definition.name.trim();
+
+            if name.is_empty() || definition.threshold < 0 {
+                return Err((StatusCode::BAD_REQUEST, "Tiers need a name and a threshold that is not negative".to_string()));
+            }
+
+            if !names.insert(name.to_lowercase()) || !thresholds.insert(definition.threshold) {
+                return Err((StatusCode::BAD_REQUEST, "Tier names and thresholds must be unique".to_string()));
+            }
+
+            if let Some(badge_mint_pubkey) = &definition.badge_mint_pubkey {
+                self.token_controller
+                    .fetch_mint(badge_mint_pubkey)
+                    .await
+                    .map_err(|(status, message)| match status {
+                        StatusCode::NOT_FOUND => (StatusCode::BAD_REQUEST, message),
+                        _ => (status, message)
+                    })?;
+
+                if self.is_program_mint(badge_mint_pubkey).await? {
+                    return Err((StatusCode::BAD_REQUEST, "Badge mints cannot hold the points of a program".to_string()));
+                }
+            }
+        }
+
+        let now = Utc::now();
+
+        let tiers: Vec<ProgramTier> = request.tiers
+            .into_iter()
+            .map(|definition| ProgramTier {
+                id: Uuid::new_v4(),
+                program_id,
+                name: definition.name.trim().to_string(),
+                threshold: definition.threshold,
+                badge_mint_pubkey: definition.badge_mint_pubkey,
+                created_at: now,
+                archived_at: None
+            })
+            .collect();
+
+        let mut tiers = self.tier_repository
+            .replace_tiers(&program_id, request.basis, &tiers, &now)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error saving tiers!".to_string()))?;
+
+        tiers.sort_by_key(|tier| tier.threshold);
+
+        Ok(TiersResponse { program_id, basis: request.basis, tiers })
+    }
+
+    pub async fn fetch_tiers(&self, program_id: Uuid) -> Result<TiersResponse, ApiError> {
+        let program = self.fetch_program(&program_id).await?;
+        let tiers = self.fetch_program_tiers(&program_id).await?;
+
+        Ok(TiersResponse { program_id, basis: program.tier_basis, tiers })
+    }
+
+    /// Recomputes the tier of every program with tiers. One failing program does
+    /// not stop the others.
+    pub async fn run_tier_refresh(&self) -> Result<Vec<UserTier>, ApiError> {
+        let program_ids = self.tier_repository
+            .fetch_tiered_program_ids()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching tiered programs!".to_string()))?;
+
+        let mut user_tiers = Vec::new();
+
+        for program_id in program_ids {
+            match self.refresh_program(program_id).await {
+                Ok(refreshed) => user_tiers.extend(refreshed),
+                Err((_, message)) => println!("Error refreshing tiers of program {}: {}", program_id, message)
+            }
+        }
+
+        Ok(user_tiers)
+    }
+
+    pub async fn refresh_program(&self, program_id: Uuid) -> Result<Vec<UserTier>, ApiError> {
+        let program = self.fetch_program(&program_id).await?;
+
+        self.refresh(&program, None).await
+    }
+
+    /// Recomputes the user's tier in every program with tiers they earned in.
+    pub async fn refresh_user(&self, user_id: Uuid) -> Result<Vec<UserTier>, ApiError> {
+        let program_ids = self.tier_repository
+            .fetch_tiered_program_ids()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching tiered programs!".to_string()))?;
+
+        let mut user_tiers = Vec::new();
+
+        for program_id in program_ids {
+            let program = self.fetch_program(&program_id).await?;
+            user_tiers.extend(self.refresh(&program, Some(&user_id)).await?);
+        }
+
+        Ok(user_tiers)
+    }
+
+    pub async fn fetch_user_tiers(&self, user_id: Uuid) -> Result<Vec<UserTierSummary>, ApiError> {
+        self.tier_repository
+            .fetch_tier_summaries(&[user_id])
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching tiers!".to_string()))
+    }
+
+    pub async fn fetch_user_badges(&self, user_id: Uuid) -> Result<Vec<TierBadge>, ApiError> {
+        self.tier_repository
+            .fetch_user_badges(&user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching badges!".to_string()))
+    }
+
+    async fn refresh(&self, program: &Program, user_id: Option<&Uuid>) -> Result<Vec<UserTier>, ApiError> {
+        let tiers = self.fetch_program_tiers(&program.id).await?;
+
+        let members = self.tier_repository
+            .fetch_members(&program.id, user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program members!".to_string()))?;
+
+        let points = self.member_points(program, &members).await?;
+        let now = Utc::now();
+        let mut user_tiers = Vec::with_capacity(members.len());
+
+        for (member, points) in members.iter().zip(points) {
+            let tier = tiers
+                .iter()
+                .filter(|tier| tier.threshold <= points)
+                .max_by_key(|tier| tier.threshold);
+
+            let user_tier = self.tier_repository
+                .save_user_tier(&UserTier {
+                    user_id: member.user_id,
+                    program_id: program.id,
+                    tier_id: tier.map(|tier| tier.id),
+                    points,
+                    computed_at: now,
+                    changed_at: now
+                })
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error saving tier!".to_string()))?;
+
+            if let Err((_, message)) = self.sync_badges(program, member, tier).await {
+                println!("Error updating badges of user {} in program {}: {}", member.user_id, program.id, message);
+            }
+
+            user_tiers.push(user_tier);
+        }
+
+        Ok(user_tiers)
+    }
+
+    /// Points each member's tier is computed from, in whole points.
+    async fn member_points(&self, program: &Program, members: &[TierMember]) -> Result<Vec<i64>, ApiError> {
+        match (program.tier_basis, program.settlement_mode) {
+            (TierBasis::LifetimeEarned, _) => Ok(members.iter().map(|member| member.earned_points).collect()),
+            (TierBasis::CurrentBalance, SettlementMode::Deferred) => Ok(members.iter().map(|member| member.ledger_points).collect()),
+            (TierBasis::CurrentBalance, SettlementMode::Immediate) => {
+                let mint = self.token_controller.fetch_mint(&program.mint_pubkey).await?;
+                let unit = mint.base_units(1).unwrap_or(1).max(1);
+
+                let wallet_pubkeys: Vec<String> = members
+                    .iter()
+                    .filter_map(|member| member.wallet_pubkey.clone())
+                    .collect();
+
+                let balances = self.solana_rpc_client
+                    .get_token_balances(&program.mint_pubkey, wallet_pubkeys)
+                    .await
+                    .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+                let mut balances = balances.into_iter();
+
+                Ok(members
+                    .iter()
+                    .map(|member| match member.wallet_pubkey {
+                        Some(_) => i64::try_from(balances.next().unwrap_or_default() / unit).unwrap_or(i64::MAX),
+                        None => 0
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Revokes badges of other tiers and issues, or resumes issuing, the badge of
+    /// the member's tier.
+    async fn sync_badges(&self, program: &Program, member: &TierMember, tier: Option<&ProgramTier>) -> Result<(), ApiError> {
+        let badges = self.tier_repository
+            .fetch_active_badges(&member.user_id, &program.id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching badges!".to_string()))?;
+
+        let wanted = tier.and_then(|tier| tier.badge_mint_pubkey.as_ref().map(|mint_pubkey| (tier.id, mint_pubkey)));
+        let mut current = None;
+
+        for badge in badges {
+            if wanted.is_some_and(|(tier_id, mint_pubkey)| badge.tier_id == tier_id && &badge.mint_pubkey == mint_pubkey) {
+                current = Some(badge);
+            } else if badge.status != TierBadgeStatus::Stranded {
+                // Stranded badges cannot be burned, so they are not retried.
+                self.revoke_badge(badge).await?;
+            }
+        }
+
+        let Some((tier_id, mint_pubkey)) = wanted else {
+            return Ok(());
+        };
+
+        let badge = match current {
+            Some(badge) if badge.status == TierBadgeStatus::Issued => return Ok(()),
+            Some(badge) => badge,
+            None => {
+                let Some(wallet_pubkey) = &member.wallet_pubkey else {
+                    return Ok(());
+                };
+
+                let now = Utc::now();
+
+                self.tier_repository
+                    .create_badge(&TierBadge {
+                        id: Uuid::new_v4(),
+                        user_id: member.user_id,
+                        program_id: program.id,
+                        tier_id,
+                        mint_pubkey: mint_pubkey.clone(),
+                        wallet_pubkey: wallet_pubkey.clone(),
+                        status: TierBadgeStatus::Issuing,
+                        mint_signature: None,
+                        freeze_signature: None,
+                        revoke_signature: None,
+                        error: None,
+                        created_at: now,
+                        updated_at: now
+                    })
+                    .await
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error recording badge!".to_string()))?
+            }
+        };
+
+        self.issue_badge(badge).await.map(|_| ())
+    }
+
+    /// Mints one badge token to the wallet unless it already holds it, then
+    /// freezes the token account so the badge cannot be transferred.
+    async fn issue_badge(&self, badge: TierBadge) -> Result<TierBadge, ApiError> {
+        let issued = self.mint_and_freeze(&badge).await;
+
+        let (status, mint_signature, freeze_signature, error) = match issued {
+            Ok((mint_signature, freeze_signature)) => (TierBadgeStatus::Issued, mint_signature, freeze_signature, None),
+            Err(message) => (TierBadgeStatus::Failed, None, None, Some(message))
+        };
+
+        self.update_badge(&badge, status, mint_signature.as_deref(), freeze_signature.as_deref(), None, error.as_deref()).await
+    }
+
+    async fn mint_and_freeze(&self, badge: &TierBadge) -> Result<(Option<String>, Option<String>), String> {
+        if self.is_program_mint(&badge.mint_pubkey).await.map_err(|(_, message)| message)? {
+            return Err("Badge mint holds the points of a program".to_string());
+        }
+
+        let mint = self.token_controller
+            .fetch_mint(&badge.mint_pubkey)
+            .await
+            .map_err(|(_, message)| message)?;
+        let amount = mint.base_units(1).ok_or("Badge exceeds the mint supply range".to_string())?;
+
+        let state = self.solana_rpc_client
+            .get_token_account_state(&badge.mint_pubkey, &badge.wallet_pubkey)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mint_signature = match state {
+            Some(state) if state.amount >= amount => None,
+            _ => match self.token_controller.mint_to(&badge.mint_pubkey, &badge.wallet_pubkey, amount, false).await {
+                Ok(TransactionOutcome::Sent(sent)) => Some(sent.signature),
+                Ok(TransactionOutcome::Simulated(_)) => return Err("Mint was only simulated".to_string()),
+                Err((_, message)) => return Err(message)
+            }
+        };
+
+        if state.is_some_and(|state| state.frozen) {
+            return Ok((mint_signature, None));
+        }
+
+        match self.token_controller.set_account_frozen(&badge.mint_pubkey, &badge.wallet_pubkey, true, false).await {
+            Ok(TransactionOutcome::Sent(sent)) => Ok((mint_signature, Some(sent.signature))),
+            Ok(TransactionOutcome::Simulated(_)) => Err("Freeze was only simulated".to_string()),
+            Err((_, message)) => Err(message)
+        }
+    }
+
+    /// Thaws and burns the badge when the service holds the wallet. Badges in
+    /// other wallets cannot be burned and are left frozen there as stranded.
+    async fn revoke_badge(&self, badge: TierBadge) -> Result<TierBadge, ApiError> {
+        let state = self.solana_rpc_client
+            .get_token_account_state(&badge.mint_pubkey, &badge.wallet_pubkey)
+            .await
+            .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+        let Some(state) = state.filter(|state| state.amount > 0) else {
+            return self.update_badge(&badge, TierBadgeStatus::Revoked, None, None, None, None).await;
+        };
+
+        // The balance is points, not a badge: it is released but never burned.
+        if self.is_program_mint(&badge.mint_pubkey).await? {
+            if state.frozen {
+                self.token_controller
+                    .set_account_frozen(&badge.mint_pubkey, &badge.wallet_pubkey, false, false)
+                    .await?;
+            }
+
+            return self.update_badge(
+                &badge,
+                TierBadgeStatus::Revoked,
+                None,
+                None,
+                None,
+                Some("Badge mint holds the points of a program, so the account was thawed and nothing burned")
+            ).await;
+        }
+
+        let wallet = self.wallet_repository
+            .fetch_wallet(&badge.user_id, &badge.wallet_pubkey)
+            .await
+            .ok()
+            .filter(|wallet| wallet.kind != WalletKind::External);
+
+        let Some(wallet) = wallet else {
+            return self.update_badge(
+                &badge,
+                TierBadgeStatus::Stranded,
+                None,
+                None,
+                None,
+                Some("Badge stays frozen in a wallet the service does not hold")
+            ).await;
+        };
+
+        if state.frozen {
+            self.token_controller
+                .set_account_frozen(&badge.mint_pubkey, &badge.wallet_pubkey, false, false)
+                .await?;
+        }
+
+        let burned = match self.hd_wallet_helper.wallet_keypair(&wallet) {
+            Ok(owner) => match self.solana_rpc_client.prepare_burn(owner, &badge.mint_pubkey, state.amount).await {
+                Ok(prepared) => self.solana_rpc_client.send_prepared_transaction(prepared).await,
+                Err(e) => Err(e)
+            },
+            Err(e) => Err(e)
+        };
+
+        match burned {
+            Ok(sent) => self.update_badge(&badge, TierBadgeStatus::Revoked, None, None, Some(&sent.signature), None).await,
+            Err(e) => {
+                // Keeps the badge non-transferable until the next refresh retries.
+                if let Err((_, message)) = self.token_controller.set_account_frozen(&badge.mint_pubkey, &badge.wallet_pubkey, true, false).await {
+                    println!("Error freezing badge {} again: {}", badge.id, message);
+                }
+
+                self.update_badge(&badge, badge.status, None, None, None, Some(&e.to_string())).await
+            }
+        }
+    }
+
+    async fn is_program_mint(&self, mint_pubkey: &str) -> Result<bool, ApiError> {
+        self.program_repository
+            .is_program_mint(mint_pubkey)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error checking mint!".to_string()))
+    }
+
+    async fn update_badge(
+        &self,
+        badge: &TierBadge,
+        status: TierBadgeStatus,
+        mint_signature: Option<&str>,
+        freeze_signature: Option<&str>,
+        revoke_signature: Option<&str>,
+        error: Option<&str>
+    ) -> Result<TierBadge, ApiError> {
+        self.tier_repository
+            .update_badge(&badge.id, status, mint_signature, freeze_signature, revoke_signature, error, &Utc::now())
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error updating badge!".to_string()))
+    }
+
+    async fn fetch_program(&self, id: &Uuid) -> Result<Program, ApiError> {
+        self.program_repository
+            .fetch_program(id)
+            .await
+            .map_err(|e| match e {
+                ProgramRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Program was not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching program!".to_string())
+            })
+    }
+
+    async fn fetch_program_tiers(&self, program_id: &Uuid) -> Result<Vec<ProgramTier>, ApiError> {
+        self.tier_repository
+            .fetch_tiers(program_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching tiers!".to_string()))
+    }
+}
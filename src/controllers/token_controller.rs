@@ -1,15 +1,75 @@
+use std::sync::Arc;
+
 use axum::http::StatusCode;
-use crate::clients::solana_rpc_client::{CreateMintResponse, MintResponse, MintToResponse, SolanaRpcClient};
+use chrono::Utc;
+use tokio::{sync::Semaphore, task::JoinSet};
+use uuid::Uuid;
+use crate::{
+    clients::solana_rpc_client::{
+        CreateMintResponse, 
+        FeeEstimateResponse, 
+        FreezeAccountResponse, 
+        MintRecipient, 
+        MINT_DECIMALS, 
+        MintResponse, 
+        MintToResponse, 
+        PackedSimulation, 
+        PlannedOperation, 
+        SolanaError, 
+        SolanaRpcClient, 
+        SponsoredTransactionResponse, 
+        SubmitSponsoredResponse, 
+        TransactionOutcome, 
+        TransactionStatus
+    }, 
+    helpers::solana_helper::SolanaHelper, 
+    models::{
+        mint_batch_model::{
+            MintBatch, 
+            MintBatchItem, 
+            MintBatchItemStatus, 
+            MintBatchReport, 
+            MintBatchStatus
+        }, 
+        mint_model::Mint
+    }, 
+    repositories::{
+        solana_repository::{SolanaRepository, SolanaRepositoryError}, 
+        user_repository::{UserRepository, UserRepositoryError}
+    }
+};
 use super::ApiError;
 
+pub const MAX_BATCH_RECIPIENTS: usize = 10_000;
+
+/// Number of batch transactions in flight at the same time.
+const MINT_BATCH_CONCURRENCY: usize = 4;
+
+/// Most token accounts the service pays rent for in sponsored transactions signed
+/// by one owner.
+const MAX_SPONSORED_TOKEN_ACCOUNTS: i64 = 10;
+
+/// Who receives minted tokens. Users receive them in their primary wallet.
+pub enum MintReceiver {
+    Pubkey(String),
+    UserId(Uuid),
+    Email(String)
+}
+
 #[derive(Clone)]
 pub struct TokenController {
-    solana_rpc_client: SolanaRpcClient
+    solana_rpc_client: SolanaRpcClient,
+    solana_repository: SolanaRepository,
+    user_repository: UserRepository
 }
 
 impl TokenController {
-    pub fn new(solana_rpc_client: SolanaRpcClient) -> Self {
-        Self { solana_rpc_client }
+    pub fn new(
+        solana_rpc_client: SolanaRpcClient,
+        solana_repository: SolanaRepository,
+        user_repository: UserRepository
+    ) -> Self {
+        Self { solana_rpc_client, solana_repository, user_repository }
     }
 
     pub async fn get_token_account(
@@ -26,30 +86,605 @@ impl TokenController {
 
     pub async fn create_mint(
         &self,
-    ) -> Result<CreateMintResponse, ApiError> {
+        dry_run: bool
+    ) -> Result<TransactionOutcome<CreateMintResponse>, ApiError> {
         let mint = self.solana_rpc_client
-            .create_token_mint()
+            .create_token_mint(dry_run)
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+        if let TransactionOutcome::Sent(created) = &mint {
+            let registered = Mint {
+                pubkey: created.pubkey.clone(),
+                decimals: MINT_DECIMALS as i16,
+                created_at: Utc::now()
+            };
+
+            // The mint exists on chain either way; it can be registered again later.
+            if let Err(e) = self.solana_repository.create_mint(&registered).await {
+                println!("Error registering mint {}: {}", created.pubkey, e);
+            }
+        }
+
         Ok(mint)
     }
 
+    /// Registers a mint created outside the service, so programs can use it. The
+    /// service keypair must be its mint authority.
+    pub async fn register_mint(&self, mint_pubkey: &str) -> Result<Mint, ApiError> {
+        let mint_account = self.get_token_account(mint_pubkey).await?;
+
+        if mint_account.mint_authority != Some(self.solana_rpc_client.service_pubkey()) {
+            return Err((StatusCode::BAD_REQUEST, "The service is not the mint authority of this mint".to_string()));
+        }
+
+        let mint = Mint {
+            pubkey: mint_account.pubkey,
+            decimals: mint_account.decimals as i16,
+            created_at: Utc::now()
+        };
+
+        self.solana_repository
+            .create_mint(&mint)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error registering mint!".to_string()))?;
+
+        self.fetch_mint(&mint.pubkey).await
+    }
+
+    pub async fn fetch_mints(&self) -> Result<Vec<Mint>, ApiError> {
+        self.solana_repository
+            .fetch_mints()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mints!".to_string()))
+    }
+
+    pub async fn fetch_mint(&self, mint_pubkey: &str) -> Result<Mint, ApiError> {
+        self.solana_repository
+            .fetch_mint(mint_pubkey)
+            .await
+            .map_err(|e| match e {
+                SolanaRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Mint is not registered".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mint!".to_string())
+            })
+    }
+
     pub async fn mint_to(
         &self,
         mint_pubkey_str: &str,
         receiver_pubkey_str: &str,
-        amount: u64
-    ) -> Result<MintToResponse, ApiError> {
+        amount: u64,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<MintToResponse>, ApiError> {
         let signature = self.solana_rpc_client
             .mint_token_to(
                 mint_pubkey_str, 
                 receiver_pubkey_str, 
-                amount
+                amount,
+                dry_run
             )
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         Ok(signature)
     }
+
+    /// Same as `mint_to`, resolving users to their primary wallet first.
+    pub async fn mint_to_receiver(
+        &self,
+        mint_pubkey_str: &str,
+        receiver: MintReceiver,
+        amount: u64,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<MintToResponse>, ApiError> {
+        let user = match receiver {
+            MintReceiver::Pubkey(receiver_pubkey_str) => {
+                return self.mint_to(mint_pubkey_str, &receiver_pubkey_str, amount, dry_run).await;
+            },
+            MintReceiver::UserId(user_id) => self.user_repository.fetch_user(&user_id).await,
+            MintReceiver::Email(email) => self.user_repository.fetch_user_by_email(&email).await
+        }.map_err(|e| match e {
+            UserRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string())
+        })?;
+
+        self.mint_to(mint_pubkey_str, &user.public_key, amount, dry_run).await
+    }
+
+    pub async fn set_account_frozen(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str,
+        frozen: bool,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<FreezeAccountResponse>, ApiError> {
+        self.solana_rpc_client
+            .set_token_account_frozen(
+                mint_pubkey_str, 
+                owner_pubkey_str, 
+                frozen, 
+                dry_run
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    }
+
+    pub async fn estimate(
+        &self,
+        operation: PlannedOperation
+    ) -> Result<FeeEstimateResponse, ApiError> {
+        if let PlannedOperation::MintTo { recipients, .. } = &operation {
+            if recipients.is_empty() {
+                return Err((StatusCode::BAD_REQUEST, "At least one recipient is required".to_string()));
+            }
+        }
+
+        let estimate = self.solana_rpc_client
+            .estimate_operation(operation)
+            .await
+            .map_err(|e| match e {
+                SolanaError::PubkeyParsingError => (StatusCode::BAD_REQUEST, e.to_string()),
+                // The operation would fail as planned, e.g. on an insufficient balance.
+                SolanaError::TransactionRejected(_) => (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()),
+                e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            })?;
+
+        Ok(estimate)
+    }
+
+    pub async fn build_sponsored_transfer(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str,
+        destination_pubkey_str: &str,
+        amount: u64
+    ) -> Result<SponsoredTransactionResponse, ApiError> {
+        if amount == 0 {
+            return Err((StatusCode::BAD_REQUEST, "Amount must be greater than zero!".to_string()));
+        }
+
+        let transaction = self.solana_rpc_client
+            .build_sponsored_transfer(
+                mint_pubkey_str, 
+                owner_pubkey_str, 
+                destination_pubkey_str, 
+                amount
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(transaction)
+    }
+
+    pub async fn build_sponsored_burn(
+        &self,
+        mint_pubkey_str: &str,
+        owner_pubkey_str: &str,
+        amount: u64
+    ) -> Result<SponsoredTransactionResponse, ApiError> {
+        if amount == 0 {
+            return Err((StatusCode::BAD_REQUEST, "Amount must be greater than zero!".to_string()));
+        }
+
+        let transaction = self.solana_rpc_client
+            .build_sponsored_burn(
+                mint_pubkey_str, 
+                owner_pubkey_str, 
+                amount
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(transaction)
+    }
+
+    /// Token accounts the service pays rent for are recorded against the signing
+    /// owner before sending, up to `MAX_SPONSORED_TOKEN_ACCOUNTS` per owner. Dry
+    /// runs only check the limit and simulate the transaction as signed.
+    pub async fn submit_sponsored_transaction(
+        &self,
+        transaction_base64: &str,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<SubmitSponsoredResponse>, ApiError> {
+        let submission = self.solana_rpc_client
+            .decode_sponsored_transaction(transaction_base64)
+            .await
+            .map_err(|e| match e {
+                SolanaError::TransactionDecodeError => (StatusCode::BAD_REQUEST, e.to_string()),
+                SolanaError::InvalidSignatureError 
+                | SolanaError::TransactionNotAllowed(_) => (StatusCode::FORBIDDEN, e.to_string()),
+                e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            })?;
+
+        if !submission.new_token_accounts.is_empty() {
+            let recorded = match dry_run {
+                true => self.solana_repository
+                    .check_sponsored_token_accounts(
+                        &submission.owner_pubkey, 
+                        &submission.new_token_accounts, 
+                        MAX_SPONSORED_TOKEN_ACCOUNTS
+                    )
+                    .await,
+                false => self.solana_repository
+                    .record_sponsored_token_accounts(
+                        &submission.owner_pubkey, 
+                        &submission.new_token_accounts, 
+                        MAX_SPONSORED_TOKEN_ACCOUNTS, 
+                        &Utc::now()
+                    )
+                    .await
+            };
+
+            recorded.map_err(|e| match e {
+                SolanaRepositoryError::SponsoredTokenAccountLimit => (StatusCode::FORBIDDEN, "Sponsored token account limit reached!".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error recording sponsored token accounts!".to_string())
+            })?;
+        }
+
+        if dry_run {
+            let simulation = self.solana_rpc_client
+                .simulate_sponsored_transaction(submission)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            return Ok(TransactionOutcome::Simulated(simulation));
+        }
+
+        let signature = self.solana_rpc_client
+            .submit_sponsored_transaction(submission)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(TransactionOutcome::Sent(signature))
+    }
+
+    /// Dry runs validate the batch and simulate each transaction it would send,
+    /// without storing anything.
+    pub async fn create_mint_batch(
+        &self,
+        mint_pubkey_str: &str,
+        recipients: Vec<MintRecipient>,
+        dry_run: bool
+    ) -> Result<TransactionOutcome<MintBatchReport, Vec<PackedSimulation>>, ApiError> {
+        if dry_run {
+            Self::validate_batch(mint_pubkey_str, &recipients)?;
+
+            let simulations = self.solana_rpc_client
+                .simulate_mint_to_batch(mint_pubkey_str, recipients)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            return Ok(TransactionOutcome::Simulated(simulations));
+        }
+
+        let batch = self.register_mint_batch(mint_pubkey_str, recipients).await?;
+
+        self.run_mint_batch(batch.id, batch.mint_pubkey).await.map(TransactionOutcome::Sent)
+    }
+
+    /// Validates and stores a batch without sending anything.
+    pub async fn register_mint_batch(
+        &self,
+        mint_pubkey_str: &str,
+        recipients: Vec<MintRecipient>
+    ) -> Result<MintBatch, ApiError> {
+        let rows = Self::validate_batch(mint_pubkey_str, &recipients)?;
+
+        let batch = self.solana_repository
+            .create_mint_batch(
+                &Uuid::new_v4(), 
+                &Utc::now(), 
+                mint_pubkey_str, 
+                &rows
+            )
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating mint batch!".to_string()))?;
+
+        Ok(batch)
+    }
+
+    /// The receiver and amount of every recipient, once all of them are valid.
+    fn validate_batch(mint_pubkey_str: &str, recipients: &[MintRecipient]) -> Result<Vec<(String, i64)>, ApiError> {
+        SolanaHelper::try_to_convert_str_to_pubkey(mint_pubkey_str)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        if recipients.is_empty() || recipients.len() > MAX_BATCH_RECIPIENTS {
+            return Err((
+                StatusCode::BAD_REQUEST, 
+                format!("A batch needs between 1 and {} recipients", MAX_BATCH_RECIPIENTS)
+            ));
+        }
+
+        let mut rows: Vec<(String, i64)> = Vec::with_capacity(recipients.len());
+
+        for (index, recipient) in recipients.iter().enumerate() {
+            SolanaHelper::try_to_convert_str_to_pubkey(&recipient.receiver_pubkey)
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Recipient {}: {}", index, e)))?;
+
+            let amount = i64::try_from(recipient.amount)
+                .ok()
+                .filter(|amount| *amount > 0)
+                .ok_or((StatusCode::BAD_REQUEST, format!("Recipient {}: invalid amount", index)))?;
+
+            rows.push((recipient.receiver_pubkey.clone(), amount));
+        }
+
+        Ok(rows)
+    }
+
+    /// Resends every item of a batch that has not been confirmed yet.
+    pub async fn resume_mint_batch(
+        &self,
+        id: Uuid
+    ) -> Result<MintBatchReport, ApiError> {
+        self.fetch_mint_batch(id).await?;
+
+        let batch = self.solana_repository
+            .claim_mint_batch(&id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error resuming mint batch!".to_string()))?
+            .ok_or((StatusCode::CONFLICT, "Mint batch is already being processed or closed".to_string()))?;
+
+        self.run_mint_batch(batch.id, batch.mint_pubkey).await
+    }
+
+    /// Stops a partially failed batch from ever being resumed, once none of its
+    /// items may still land. Returns whether the batch is closed.
+    pub async fn close_mint_batch(&self, id: Uuid) -> Result<bool, ApiError> {
+        let closed = self.solana_repository
+            .close_mint_batch(&id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error closing mint batch!".to_string()))?;
+
+        Ok(closed.is_some())
+    }
+
+    pub async fn fetch_mint_batch(
+        &self,
+        id: Uuid
+    ) -> Result<MintBatchReport, ApiError> {
+        let batch = self.solana_repository
+            .fetch_mint_batch(&id)
+            .await
+            .map_err(|e| match e {
+                SolanaRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, "Mint batch not found".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mint batch!".to_string())
+            })?;
+
+        let items = self.solana_repository
+            .fetch_mint_batch_items(&id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mint batch!".to_string()))?;
+
+        Ok(MintBatchReport::new(batch, items))
+    }
+
+    /// Processes the batch on its own task so a dropped request does not stop it
+    /// halfway; the batch is left resumable if processing fails.
+    pub async fn run_mint_batch(
+        &self,
+        id: Uuid,
+        mint_pubkey: String
+    ) -> Result<MintBatchReport, ApiError> {
+        let controller = self.clone();
+
+        let result = tokio::spawn(async move {
+            let result = controller.process_mint_batch(id, &mint_pubkey).await;
+
+            if result.is_err() {
+                let _ = controller.solana_repository
+                    .update_mint_batch_status(&id, MintBatchStatus::PartiallyFailed)
+                    .await;
+            }
+
+            result
+        }).await;
+
+        match result {
+            Ok(Ok(())) => self.fetch_mint_batch(id).await,
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Mint batch processing panicked!".to_string()))
+        }
+    }
+
+    async fn process_mint_batch(
+        &self,
+        id: Uuid,
+        mint_pubkey: &str
+    ) -> Result<(), ApiError> {
+        self.reconcile_mint_batch(id).await?;
+
+        let items: Vec<MintBatchItem> = self.fetch_batch_items(id)
+            .await?
+            .into_iter()
+            .filter(|item| matches!(item.status, MintBatchItemStatus::Pending | MintBatchItemStatus::Failed))
+            .collect();
+
+        let recipients: Vec<MintRecipient> = items
+            .iter()
+            .map(|item| MintRecipient {
+                receiver_pubkey: item.receiver_pubkey.clone(),
+                amount: item.amount as u64
+            })
+            .collect();
+
+        if !recipients.is_empty() {
+            let groups = self.solana_rpc_client
+                .pack_mint_to_batch(mint_pubkey, recipients.clone())
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            let semaphore = Arc::new(Semaphore::new(MINT_BATCH_CONCURRENCY));
+            let mut tasks = JoinSet::new();
+
+            for group in groups {
+                let controller = self.clone();
+                let semaphore = Arc::clone(&semaphore);
+                let mint_pubkey = mint_pubkey.to_string();
+                let positions: Vec<i32> = group.iter().map(|index| items[*index].position).collect();
+                let group_recipients: Vec<MintRecipient> = group.iter().map(|index| recipients[*index].clone()).collect();
+
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+
+                    controller.send_mint_batch_group(
+                        id, 
+                        &mint_pubkey, 
+                        &positions, 
+                        group_recipients
+                    ).await
+                });
+            }
+
+            while let Some(result) = tasks.join_next().await {
+                result.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Mint batch task panicked!".to_string()))??;
+            }
+        }
+
+        let all_confirmed = self.fetch_batch_items(id)
+            .await?
+            .iter()
+            .all(|item| item.status == MintBatchItemStatus::Confirmed);
+
+        let status = if all_confirmed {
+            MintBatchStatus::Completed
+        } else {
+            MintBatchStatus::PartiallyFailed
+        };
+
+        self.solana_repository
+            .update_mint_batch_status(&id, status)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error updating mint batch!".to_string()))?;
+
+        Ok(())
+    }
+
+    /// Checks the on-chain outcome of items that were sent before, so that a resumed
+    /// batch never mints twice to the same recipient.
+    async fn reconcile_mint_batch(&self, id: Uuid) -> Result<(), ApiError> {
+        let items = self.fetch_batch_items(id).await?;
+
+        let mut checked: Vec<String> = Vec::new();
+
+        for item in &items {
+            if !matches!(item.status, MintBatchItemStatus::Submitted | MintBatchItemStatus::Failed) {
+                continue;
+            }
+
+            let (Some(signature), Some(recent_blockhash)) = (&item.signature, &item.recent_blockhash) else {
+                continue;
+            };
+
+            if checked.contains(signature) {
+                continue;
+            }
+            checked.push(signature.clone());
+
+            let status = self.solana_rpc_client
+                .get_transaction_status(signature, recent_blockhash)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+            let positions: Vec<i32> = items
+                .iter()
+                .filter(|other| other.signature.as_ref() == Some(signature))
+                .map(|other| other.position)
+                .collect();
+
+            let (status, error) = match status {
+                TransactionStatus::Confirmed => (MintBatchItemStatus::Confirmed, None),
+                TransactionStatus::Failed(error) => (MintBatchItemStatus::Failed, Some(error)),
+                TransactionStatus::Expired => (MintBatchItemStatus::Pending, None),
+                TransactionStatus::Pending => (MintBatchItemStatus::Submitted, None)
+            };
+
+            self.update_batch_items(id, &positions, status, None, None, error.as_deref()).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_mint_batch_group(
+        &self,
+        id: Uuid,
+        mint_pubkey: &str,
+        positions: &[i32],
+        recipients: Vec<MintRecipient>
+    ) -> Result<(), ApiError> {
+        let prepared = match self.solana_rpc_client
+            .prepare_mint_to_batch(mint_pubkey, recipients)
+            .await
+        {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                return self.update_batch_items(
+                    id, 
+                    positions, 
+                    MintBatchItemStatus::Failed, 
+                    None, 
+                    None, 
+                    Some(&e.to_string())
+                ).await;
+            }
+        };
+
+        // Recorded before sending so a crash mid-flight can be reconciled on resume.
+        self.update_batch_items(
+            id, 
+            positions, 
+            MintBatchItemStatus::Submitted, 
+            Some(&prepared.signature()), 
+            Some(&prepared.recent_blockhash()), 
+            None
+        ).await?;
+
+        match self.solana_rpc_client.send_prepared_transaction(prepared).await {
+            Ok(_) => self.update_batch_items(
+                id, 
+                positions, 
+                MintBatchItemStatus::Confirmed, 
+                None, 
+                None, 
+                None
+            ).await,
+            Err(e) => self.update_batch_items(
+                id, 
+                positions, 
+                MintBatchItemStatus::Failed, 
+                None, 
+                None, 
+                Some(&e.to_string())
+            ).await
+        }
+    }
+
+    async fn fetch_batch_items(&self, id: Uuid) -> Result<Vec<MintBatchItem>, ApiError> {
+        self.solana_repository
+            .fetch_mint_batch_items(&id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching mint batch items!".to_string()))
+    }
+
+    async fn update_batch_items(
+        &self,
+        id: Uuid,
+        positions: &[i32],
+        status: MintBatchItemStatus,
+        signature: Option<&str>,
+        recent_blockhash: Option<&str>,
+        error: Option<&str>
+    ) -> Result<(), ApiError> {
+        self.solana_repository
+            .update_mint_batch_items(
+                &id, 
+                positions, 
+                status, 
+                signature, 
+                recent_blockhash, 
+                error
+            )
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error updating mint batch items!".to_string()))
+    }
 }
\ No newline at end of file
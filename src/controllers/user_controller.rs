@@ -1,26 +1,47 @@
 use axum::http::StatusCode;
 use chrono::Utc;
+use solana_sdk::signer::Signer;
 use uuid::Uuid;
 
 use crate::{
-    helpers::solana_helper::SolanaHelper, 
-    models::user_model::{
-        CreateUserRequest, 
-        UserForResponse
+    helpers::hd_wallet_helper::HdWalletHelper, 
+    models::{
+        user_model::{
+            CreateUserRequest, 
+            User, 
+            UserForResponse
+        }, 
+        referral_model::Referral, 
+        wallet_model::Wallet
     }, 
-    repositories::user_repository::UserRepository
+    repositories::{
+        referral_repository::{ReferralRepository, ReferralRepositoryError}, 
+        tier_repository::TierRepository, 
+        user_repository::{UserRepository, UserRepositoryError}, 
+        wallet_repository::WalletRepository
+    }
 };
 
 use super::ApiError;
 
 #[derive(Clone)]
 pub struct UserController {
-    user_repository: UserRepository
+    user_repository: UserRepository,
+    wallet_repository: WalletRepository,
+    referral_repository: ReferralRepository,
+    tier_repository: TierRepository,
+    hd_wallet_helper: HdWalletHelper
 }
 
 impl UserController {
-    pub fn new(user_repository: UserRepository) -> Self {
-        Self { user_repository }
+    pub fn new(
+        user_repository: UserRepository,
+        wallet_repository: WalletRepository,
+        referral_repository: ReferralRepository,
+        tier_repository: TierRepository,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self { user_repository, wallet_repository, referral_repository, tier_repository, hd_wallet_helper }
     }
 
     pub async fn create_user(
@@ -28,22 +49,61 @@ impl UserController {
         body: CreateUserRequest
     ) -> Result<UserForResponse, ApiError> {
 
-        let email = body.email;
+        let email = User::normalize_email(&body.email);
+
+        if email.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "Email is required".to_string()));
+        }
+
         let id = Uuid::new_v4();
         let now = Utc::now();
-        let (public_key, secret_key) = SolanaHelper::get_keypair();
+
+        let referral = match body.referral_code {
+            Some(code) => {
+                let referral_code = self.referral_repository
+                    .fetch_code(code.trim())
+                    .await
+                    .map_err(|e| match e {
+                        ReferralRepositoryError::RowNotFound => (StatusCode::BAD_REQUEST, "Referral code is not valid".to_string()),
+                        _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching referral code!".to_string())
+                    })?;
+
+                Some(Referral {
+                    id: Uuid::new_v4(),
+                    referrer_id: referral_code.user_id,
+                    referee_id: id,
+                    code: referral_code.code,
+                    created_at: now
+                })
+            },
+            None => None
+        };
+
+        let derivation_index = self.wallet_repository
+            .next_derivation_index()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating user!".to_string()))?;
+
+        let public_key = self.hd_wallet_helper
+            .derive_keypair(derivation_index as u32)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .pubkey()
+            .to_string();
+
+        let wallet = Wallet::derived(id, public_key, derivation_index, None, true);
 
         match self.user_repository
             .create_user(
                 &id,
                 &now,
                 &email,
-                &public_key,
-                &secret_key
+                &wallet,
+                referral.as_ref()
             )
             .await 
         {
             Ok(user) => Ok(user.into()),
+            Err(UserRepositoryError::EmailTaken) => Err((StatusCode::CONFLICT, "Email is already registered".to_string())),
             Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error creating user!".to_string()))
         }
     }
@@ -57,7 +117,12 @@ impl UserController {
             .fetch_user(&id)
             .await 
         {
-            Ok(user) => Ok(user.into()),
+            Ok(user) => {
+                let mut users = vec![user.into()];
+                self.attach_tiers(&mut users).await?;
+
+                Ok(users.remove(0))
+            },
             Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string()))
         }
     }
@@ -67,8 +132,30 @@ impl UserController {
             .fetch_all_users()
             .await 
         {
-            Ok(users) => Ok(users.into_iter().map(|user| user.into()).collect()),
+            Ok(users) => {
+                let mut users: Vec<UserForResponse> = users.into_iter().map(|user| user.into()).collect();
+                self.attach_tiers(&mut users).await?;
+
+                Ok(users)
+            },
             Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching users!".to_string()))
         }
     }
-}
+
+    async fn attach_tiers(&self, users: &mut [UserForResponse]) -> Result<(), ApiError> {
+        let user_ids: Vec<Uuid> = users.iter().map(|user| user.id).collect();
+
+        let summaries = self.tier_repository
+            .fetch_tier_summaries(&user_ids)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching tiers!".to_string()))?;
+
+        for summary in summaries {
+            if let Some(user) = users.iter_mut().find(|user| user.id == summary.user_id) {
+                user.tiers.push(summary);
+            }
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,433 @@
+use axum::http::StatusCode;
+use chrono::{Duration, Utc};
+use solana_sdk::{hash::hashv, signature::Keypair, signer::Signer};
+use uuid::Uuid;
+
+use crate::{
+    clients::{mailer_client::MailerClient, solana_rpc_client::SolanaRpcClient},
+    helpers::{hd_wallet_helper::HdWalletHelper, solana_helper::SolanaHelper},
+    models::{
+        user_model::User,
+        wallet_model::{
+            ConfirmWalletExportRequest,
+            ExportedWalletResponse,
+            Wallet,
+            WalletChallenge,
+            WalletChallengeResponse,
+            WalletExport,
+            WalletExportAudit,
+            WalletExportEvent,
+            WalletExportFormat,
+            WalletExportResponse,
+            WalletKind,
+            WalletSweepResponse
+        }
+    },
+    repositories::{
+        user_repository::{UserRepository, UserRepositoryError},
+        wallet_repository::{WalletRepository, WalletRepositoryError}
+    }
+};
+
+use super::ApiError;
+
+const CHALLENGE_DOMAIN: &str = "puntoxero";
+
+const CHALLENGE_TTL_MINUTES: i64 = 10;
+
+const EXPORT_TTL_MINUTES: i64 = 10;
+
+const MAX_EXPORT_ATTEMPTS: i32 = 5;
+
+#[derive(Clone)]
+pub struct WalletController {
+    wallet_repository: WalletRepository,
+    user_repository: UserRepository,
+    solana_rpc_client: SolanaRpcClient,
+    mailer_client: MailerClient,
+    hd_wallet_helper: HdWalletHelper
+}
+
+impl WalletController {
+    pub fn new(
+        wallet_repository: WalletRepository,
+        user_repository: UserRepository,
+        solana_rpc_client: SolanaRpcClient,
+        mailer_client: MailerClient,
+        hd_wallet_helper: HdWalletHelper
+    ) -> Self {
+        Self { wallet_repository, user_repository, solana_rpc_client, mailer_client, hd_wallet_helper }
+    }
+
+    /// Issues a Sign-In With Solana message the external wallet has to sign.
+    pub async fn create_challenge(
+        &self,
+        user_id: Uuid,
+        pubkey_str: &str
+    ) -> Result<WalletChallengeResponse, ApiError> {
+        let user = self.fetch_user(user_id).await?;
+        let pubkey = SolanaHelper::try_to_convert_str_to_pubkey(pubkey_str)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        let wallets = self.fetch_wallets(user_id).await?;
+
+        if wallets.iter().any(|wallet| wallet.pubkey == pubkey.to_string()) {
+            return Err((StatusCode::CONFLICT, WalletRepositoryError::AlreadyLinked.to_string()));
+        }
+
+        let id = Uuid::new_v4();
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(CHALLENGE_TTL_MINUTES);
+
+        let message = format!(
+            "{} wants you to sign in with your Solana account:\n{}\n\nLink this wallet to {}.\n\nNonce: {}\nIssued At: {}\nExpiration Time: {}",
+            CHALLENGE_DOMAIN,
+            pubkey,
+            user.email,
+            id.simple(),
+            now.to_rfc3339(),
+            expires_at.to_rfc3339()
+        );
+
+        let challenge = WalletChallenge {
+            id,
+            user_id,
+            pubkey: pubkey.to_string(),
+            message,
+            created_at: now,
+            expires_at,
+            used_at: None
+        };
+
+        match self.wallet_repository
+            .create_challenge(&challenge)
+            .await
+        {
+            Ok(challenge) => Ok(challenge.into()),
+            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error creating challenge!".to_string()))
+        }
+    }
+
+    /// Links the challenged wallet to the user once its signature over the
+    /// challenge message checks out.
+    pub async fn verify_challenge(
+        &self,
+        user_id: Uuid,
+        challenge_id: Uuid,
+        signature: &str,
+        label: Option<&str>
+    ) -> Result<Wallet, ApiError> {
+        let challenge = self.wallet_repository
+            .fetch_challenge(&challenge_id, &user_id)
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching challenge!".to_string())
+            })?;
+
+        let pubkey = SolanaHelper::try_to_convert_str_to_pubkey(&challenge.pubkey)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        let is_valid = SolanaHelper::verify_message_signature(
+            &pubkey,
+            challenge.message.as_bytes(),
+            signature
+        ).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        if !is_valid {
+            return Err((StatusCode::UNAUTHORIZED, "Invalid signature".to_string()));
+        }
+
+        self.wallet_repository
+            .link_wallet(&challenge, label, &Utc::now())
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::ChallengeUnavailable => (StatusCode::GONE, e.to_string()),
+                WalletRepositoryError::AlreadyLinked => (StatusCode::CONFLICT, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error linking wallet!".to_string())
+            })
+    }
+
+    pub async fn fetch_wallets(&self, user_id: Uuid) -> Result<Vec<Wallet>, ApiError> {
+        self.fetch_user(user_id).await?;
+
+        match self.wallet_repository
+            .fetch_wallets(&user_id)
+            .await
+        {
+            Ok(wallets) => Ok(wallets),
+            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching wallets!".to_string()))
+        }
+    }
+
+    /// Adds a wallet derived from the master seed. External wallets are added
+    /// through the challenge flow instead.
+    pub async fn create_wallet(&self, user_id: Uuid, label: Option<String>) -> Result<Wallet, ApiError> {
+        self.fetch_user(user_id).await?;
+
+        let wallet = self.derive_wallet(user_id, label).await?;
+
+        self.wallet_repository
+            .create_wallet(&wallet)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating wallet!".to_string()))
+    }
+
+    pub async fn set_primary_wallet(&self, user_id: Uuid, pubkey: &str) -> Result<Wallet, ApiError> {
+        self.fetch_wallet(user_id, pubkey).await?;
+
+        self.wallet_repository
+            .set_primary_wallet(&user_id, pubkey)
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::WalletUnavailable => (StatusCode::CONFLICT, "Archived wallets cannot be primary".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error updating wallet!".to_string())
+            })
+    }
+
+    pub async fn archive_wallet(&self, user_id: Uuid, pubkey: &str) -> Result<Wallet, ApiError> {
+        self.fetch_wallet(user_id, pubkey).await?;
+
+        self.wallet_repository
+            .archive_wallet(&user_id, pubkey, &Utc::now())
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::WalletUnavailable => (StatusCode::CONFLICT, "Wallet is primary or already archived".to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error archiving wallet!".to_string())
+            })
+    }
+
+    /// Starts a self-custody export of a wallet held by the service by emailing a
+    /// one-time code that `confirm_export` requires. Only the code's hash is stored.
+    pub async fn request_export(&self, user_id: Uuid, pubkey: &str) -> Result<WalletExportResponse, ApiError> {
+        let user = self.fetch_user(user_id).await?;
+        let wallet = self.fetch_wallet(user_id, pubkey).await?;
+
+        if wallet.kind == WalletKind::External {
+            return Err((StatusCode::BAD_REQUEST, "External wallets are already self-custodied".to_string()));
+        }
+
+        if wallet.archived_at.is_some() {
+            return Err((StatusCode::CONFLICT, "Archived wallets cannot be exported".to_string()));
+        }
+
+        let id = Uuid::new_v4();
+        let code = format!("{:06}", Uuid::new_v4().as_u128() % 1_000_000);
+        let now = Utc::now();
+
+        let export = WalletExport {
+            id,
+            user_id,
+            pubkey: wallet.pubkey,
+            code_hash: Self::hash_export_code(&id, &code),
+            attempts: 0,
+            created_at: now,
+            expires_at: now + Duration::minutes(EXPORT_TTL_MINUTES),
+            confirmed_at: None
+        };
+
+        let export = self.wallet_repository
+            .create_export(&export)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating export!".to_string()))?;
+
+        self.mailer_client.send(
+            &user.email,
+            "Confirm your wallet export",
+            &format!(
+                "Your code to export wallet {} is {}. It expires in {} minutes.\n\nWhoever holds the exported key controls the wallet. If you did not request this, ignore this email.",
+                export.pubkey,
+                code,
+                EXPORT_TTL_MINUTES
+            )
+        ).await.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error sending export code!".to_string()))?;
+
+        Ok(export.into())
+    }
+
+    /// Hands out the wallet's secret key once the emailed code checks out. With
+    /// `sweep` set, every balance then moves to a new derived wallet, so the service
+    /// stops relying on a key it no longer holds alone.
+    pub async fn confirm_export(
+        &self,
+        user_id: Uuid,
+        export_id: Uuid,
+        request: ConfirmWalletExportRequest
+    ) -> Result<ExportedWalletResponse, ApiError> {
+        let export = self.wallet_repository
+            .fetch_export(&export_id, &user_id)
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::ExportNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching export!".to_string())
+            })?;
+
+        let now = Utc::now();
+
+        // The attempt is counted before the code is compared, so parallel guesses
+        // cannot get past the limit.
+        self.wallet_repository
+            .claim_export_attempt(&export.id, MAX_EXPORT_ATTEMPTS, &now)
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::ExportUnavailable => (StatusCode::GONE, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error verifying export!".to_string())
+            })?;
+
+        if Self::hash_export_code(&export.id, request.code.trim()) != export.code_hash {
+            self.wallet_repository
+                .record_failed_export_attempt(&export, &now)
+                .await
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error verifying export!".to_string()))?;
+
+            return Err((StatusCode::UNAUTHORIZED, "Invalid code".to_string()));
+        }
+
+        let wallet = self.fetch_wallet(user_id, &export.pubkey).await?;
+
+        let keypair = self.hd_wallet_helper
+            .wallet_keypair(&wallet)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        let secret_key = match request.format {
+            WalletExportFormat::SolanaCliJson => serde_json::to_string(&keypair.to_bytes().to_vec())
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error encoding keypair!".to_string()))?,
+            WalletExportFormat::Base58 => keypair.to_base58_string()
+        };
+
+        self.wallet_repository
+            .complete_export(&export, MAX_EXPORT_ATTEMPTS, &now)
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::ExportUnavailable => (StatusCode::GONE, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error exporting wallet!".to_string())
+            })?;
+
+        let sweep = match request.sweep {
+            true => Some(self.sweep_exported_wallet(&export, &wallet, keypair).await),
+            false => None
+        };
+
+        Ok(ExportedWalletResponse {
+            pubkey: export.pubkey,
+            secret_key,
+            exported_at: now,
+            sweep
+        })
+    }
+
+    pub async fn fetch_export_audit(&self, user_id: Uuid) -> Result<Vec<WalletExportAudit>, ApiError> {
+        self.fetch_user(user_id).await?;
+
+        match self.wallet_repository
+            .fetch_export_audit(&user_id)
+            .await
+        {
+            Ok(entries) => Ok(entries),
+            Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "Error fetching export audit!".to_string()))
+        }
+    }
+
+    /// The export is already done at this point, so failures are audited and
+    /// reported alongside the secret key instead of failing the request.
+    async fn sweep_exported_wallet(
+        &self,
+        export: &WalletExport,
+        wallet: &Wallet,
+        owner: Keypair
+    ) -> WalletSweepResponse {
+        let replaced = match self.derive_wallet(wallet.user_id, wallet.label.clone()).await {
+            Ok(new_wallet) => self.wallet_repository
+                .replace_wallet(wallet, &new_wallet, &Utc::now())
+                .await
+                .map_err(|_| "Error creating wallet!".to_string()),
+            Err((_, error)) => Err(error)
+        };
+
+        let new_pubkey = match replaced {
+            Ok(new_wallet) => new_wallet.pubkey,
+            Err(error) => {
+                self.audit_export(export, WalletExportEvent::SweepFailed, &error).await;
+
+                return WalletSweepResponse {
+                    new_pubkey: None,
+                    balances: Vec::new(),
+                    error: Some(error)
+                };
+            }
+        };
+
+        match self.solana_rpc_client
+            .sweep_wallet(owner, &new_pubkey)
+            .await
+        {
+            Ok(balances) => {
+                let detail = format!("{} balances moved to {}", balances.len(), new_pubkey);
+                self.audit_export(export, WalletExportEvent::Swept, &detail).await;
+
+                WalletSweepResponse {
+                    new_pubkey: Some(new_pubkey),
+                    balances,
+                    error: None
+                }
+            },
+            Err(e) => {
+                let detail = format!("sweep to {} stopped: {}", new_pubkey, e);
+                self.audit_export(export, WalletExportEvent::SweepFailed, &detail).await;
+
+                WalletSweepResponse {
+                    new_pubkey: Some(new_pubkey),
+                    balances: Vec::new(),
+                    error: Some(e.to_string())
+                }
+            }
+        }
+    }
+
+    /// Builds, without storing it, a wallet at the next index under the master seed.
+    async fn derive_wallet(&self, user_id: Uuid, label: Option<String>) -> Result<Wallet, ApiError> {
+        let derivation_index = self.wallet_repository
+            .next_derivation_index()
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Error creating wallet!".to_string()))?;
+
+        let pubkey = self.hd_wallet_helper
+            .derive_keypair(derivation_index as u32)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .pubkey()
+            .to_string();
+
+        Ok(Wallet::derived(user_id, pubkey, derivation_index, label, false))
+    }
+
+    async fn audit_export(&self, export: &WalletExport, event: WalletExportEvent, detail: &str) {
+        if let Err(e) = self.wallet_repository.audit_export(export, event, Some(detail)).await {
+            println!("Error auditing wallet export {}: {}", export.id, e);
+        }
+    }
+
+    /// Salted with the export id, so equal codes never share a hash.
+    fn hash_export_code(export_id: &Uuid, code: &str) -> String {
+        hashv(&[export_id.as_bytes(), code.as_bytes()]).to_string()
+    }
+
+    async fn fetch_wallet(&self, user_id: Uuid, pubkey: &str) -> Result<Wallet, ApiError> {
+        self.wallet_repository
+            .fetch_wallet(&user_id, pubkey)
+            .await
+            .map_err(|e| match e {
+                WalletRepositoryError::WalletNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching wallet!".to_string())
+            })
+    }
+
+    async fn fetch_user(&self, user_id: Uuid) -> Result<User, ApiError> {
+        self.user_repository
+            .fetch_user(&user_id)
+            .await
+            .map_err(|e| match e {
+                UserRepositoryError::RowNotFound => (StatusCode::NOT_FOUND, e.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "Error fetching user!".to_string())
+            })
+    }
+}
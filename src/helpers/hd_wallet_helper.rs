@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use bip39::{Language, Mnemonic};
+use solana_sdk::{
+    derivation_path::DerivationPath,
+    signature::Keypair,
+    signer::{keypair::keypair_from_seed_and_derivation_path, Signer}
+};
+
+use crate::{
+    clients::solana_rpc_client::SolanaError,
+    helpers::solana_helper::SolanaHelper,
+    models::wallet_model::Wallet
+};
+
+/// Derives custodial wallets from one master seed along SLIP-0010 ed25519 paths
+/// `m/44'/501'/index'`. That is the path `solana-keygen recover 'prompt://?key=<index>/'`
+/// uses, so every wallet can be rebuilt from the seed phrase alone.
+#[derive(Clone)]
+pub struct HdWalletHelper {
+    seed: Arc<Vec<u8>>
+}
+
+impl HdWalletHelper {
+    /// Only accepts English BIP39 phrases with a valid checksum, so a mistyped
+    /// phrase fails instead of deriving other wallets.
+    pub fn from_seed_phrase(seed_phrase: &str) -> Result<Self, SolanaError> {
+        let mnemonic = Mnemonic::parse_in(Language::English, seed_phrase).map_err(|e| {
+            println!("Error parsing seed phrase: {}", e);
+            SolanaError::KeyDerivationError
+        })?;
+
+        Ok(Self { seed: Arc::new(mnemonic.to_seed("").to_vec()) })
+    }
+
+    pub fn derive_keypair(&self, index: u32) -> Result<Keypair, SolanaError> {
+        keypair_from_seed_and_derivation_path(
+            &self.seed,
+            Some(DerivationPath::new_bip44(Some(index), None))
+        ).map_err(|e| {
+            println!("Error deriving keypair {}: {}", index, e);
+            SolanaError::KeyDerivationError
+        })
+    }
+
+    /// The keypair of a wallet held by the service, derived when it has a derivation
+    /// index and read from the stored secret key for wallets created before derivation.
+    /// Fails rather than return a key that does not own the wallet.
+    pub fn wallet_keypair(&self, wallet: &Wallet) -> Result<Keypair, SolanaError> {
+        let keypair = match (wallet.derivation_index, &wallet.secret_key) {
+            (Some(index), _) => {
+                let index = u32::try_from(index).map_err(|_| SolanaError::KeyDerivationError)?;
+                self.derive_keypair(index)?
+            },
+            (None, Some(secret_key)) => SolanaHelper::try_to_convert_str_to_keypair(secret_key)?,
+            (None, None) => return Err(SolanaError::KeypairParsingError)
+        };
+
+        if keypair.pubkey().to_string() != wallet.pubkey {
+            println!("Keypair of wallet {} derives {}", wallet.pubkey, keypair.pubkey());
+            return Err(SolanaError::WalletKeyMismatchError);
+        }
+
+        Ok(keypair)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use solana_sdk::signer::keypair::generate_seed_from_seed_phrase_and_passphrase;
+    use uuid::Uuid;
+
+    use crate::models::wallet_model::WalletKind;
+
+    use super::*;
+
+    const SEED_PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn wallet(pubkey: String, derivation_index: Option<i32>, secret_key: Option<String>) -> Wallet {
+        Wallet {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            pubkey,
+            kind: if derivation_index.is_some() { WalletKind::Derived } else { WalletKind::Custodial },
+            label: None,
+            is_primary: true,
+            derivation_index,
+            secret_key,
+            exported_at: None,
+            created_at: Utc::now(),
+            archived_at: None
+        }
+    }
+
+    #[test]
+    fn from_seed_phrase_derives_the_solana_keygen_seed() {
+        let helper = HdWalletHelper::from_seed_phrase(&format!("  {}\n", SEED_PHRASE.replace(' ', "  "))).unwrap();
+
+        assert_eq!(*helper.seed, generate_seed_from_seed_phrase_and_passphrase(SEED_PHRASE, ""));
+    }
+
+    #[test]
+    fn from_seed_phrase_rejects_bad_checksum() {
+        let seed_phrase = SEED_PHRASE.replace("about", "abandon");
+
+        assert!(HdWalletHelper::from_seed_phrase(&seed_phrase).is_err());
+    }
+
+    #[test]
+    fn from_seed_phrase_rejects_unknown_words_and_counts() {
+        assert!(HdWalletHelper::from_seed_phrase(&SEED_PHRASE.replace("about", "abuot")).is_err());
+        assert!(HdWalletHelper::from_seed_phrase("abandon abandon abandon").is_err());
+        assert!(HdWalletHelper::from_seed_phrase("").is_err());
+    }
+
+    #[test]
+    fn wallet_keypair_checks_the_derived_key() {
+        let helper = HdWalletHelper::from_seed_phrase(SEED_PHRASE).unwrap();
+        let pubkey = helper.derive_keypair(3).unwrap().pubkey().to_string();
+
+        let keypair = helper.wallet_keypair(&wallet(pubkey.clone(), Some(3), None)).unwrap();
+        assert_eq!(keypair.pubkey().to_string(), pubkey);
+
+        assert!(matches!(
+            helper.wallet_keypair(&wallet(pubkey, Some(4), None)),
+            Err(SolanaError::WalletKeyMismatchError)
+        ));
+    }
+
+    #[test]
+    fn wallet_keypair_checks_the_stored_key() {
+        let helper = HdWalletHelper::from_seed_phrase(SEED_PHRASE).unwrap();
+        let keypair = Keypair::new();
+        let secret_key = keypair.to_base58_string();
+
+        assert!(helper.wallet_keypair(&wallet(keypair.pubkey().to_string(), None, Some(secret_key.clone()))).is_ok());
+        assert!(matches!(
+            helper.wallet_keypair(&wallet(Keypair::new().pubkey().to_string(), None, Some(secret_key))),
+            Err(SolanaError::WalletKeyMismatchError)
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::models::auth_model::AccessClaims;
+
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// Issues and verifies the HS256 access tokens of user sessions.
+#[derive(Clone)]
+pub struct JwtHelper {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey
+}
+
+impl JwtHelper {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.as_bytes())
+        }
+    }
+
+    pub fn issue_access_token(&self, user_id: Uuid) -> Result<(String, DateTime<Utc>), JwtError> {
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
+
+        let claims = AccessClaims {
+            sub: user_id,
+            iat: now.timestamp(),
+            exp: expires_at.timestamp()
+        };
+
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key).map_err(|e| {
+            println!("Error encoding access token: {}", e);
+            JwtError::EncodeError
+        })?;
+
+        Ok((token, expires_at))
+    }
+
+    /// Checks the signature and expiry of an access token and returns its user.
+    pub fn verify_access_token(&self, token: &str) -> Result<Uuid, JwtError> {
+        decode::<AccessClaims>(token, &self.decoding_key, &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims.sub)
+            .map_err(|_| JwtError::InvalidToken)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum JwtError {
+    #[error("Error issuing access token")]
+    EncodeError,
+    #[error("Access token is invalid or expired")]
+    InvalidToken
+}
@@ -0,0 +1,116 @@
+use solana_sdk::{hash::{hashv, Hash}, pubkey::Pubkey};
+
+/// Domain separators so a leaf can never be passed off as an internal node.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub struct MerkleHelper;
+
+impl MerkleHelper {
+    pub fn leaf_hash(wallet_pubkey: &Pubkey, amount: u64) -> Hash {
+        hashv(&[LEAF_PREFIX, wallet_pubkey.as_ref(), &amount.to_le_bytes()])
+    }
+
+    /// Returns the root of the tree over `leaves` and, for every leaf, the sibling
+    /// hashes on its path to the root. Pairs are hashed in sorted order, so proofs
+    /// do not need to carry left/right positions.
+    pub fn build_tree(leaves: &[Hash]) -> (Hash, Vec<Vec<Hash>>) {
+        let mut proofs: Vec<Vec<Hash>> = vec![Vec::new(); leaves.len()];
+        let mut positions: Vec<usize> = (0..leaves.len()).collect();
+        let mut level: Vec<Hash> = leaves.to_vec();
+
+        while level.len() > 1 {
+            for (leaf_index, position) in positions.iter_mut().enumerate() {
+                let sibling = *position ^ 1;
+
+                if sibling < level.len() {
+                    proofs[leaf_index].push(level[sibling]);
+                }
+
+                *position /= 2;
+            }
+
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => Self::node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!()
+                })
+                .collect();
+        }
+
+        (level.first().copied().unwrap_or_default(), proofs)
+    }
+
+    pub fn verify_proof(root: &Hash, leaf: Hash, proof: &[Hash]) -> bool {
+        let computed = proof
+            .iter()
+            .fold(leaf, |node, sibling| Self::node_hash(&node, sibling));
+
+        computed == *root
+    }
+
+    fn node_hash(a: &Hash, b: &Hash) -> Hash {
+        let (left, right) = if a.as_ref() <= b.as_ref() { (a, b) } else { (b, a) };
+
+        hashv(&[NODE_PREFIX, left.as_ref(), right.as_ref()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u64) -> Vec<Hash> {
+        (1..=count).map(|amount| MerkleHelper::leaf_hash(&Pubkey::new_unique(), amount)).collect()
+    }
+
+    #[test]
+    fn build_tree_proves_every_leaf_for_odd_and_even_counts() {
+        for count in [1, 2, 3, 5, 7, 8, 13] {
+            let leaves = leaves(count);
+            let (root, proofs) = MerkleHelper::build_tree(&leaves);
+
+            assert_eq!(proofs.len(), leaves.len());
+
+            for (leaf, proof) in leaves.iter().zip(&proofs) {
+                assert!(MerkleHelper::verify_proof(&root, *leaf, proof), "leaf of a {} leaf tree", count);
+            }
+        }
+    }
+
+    #[test]
+    fn build_tree_of_a_single_leaf_is_the_leaf() {
+        let leaves = leaves(1);
+        let (root, proofs) = MerkleHelper::build_tree(&leaves);
+
+        assert_eq!(root, leaves[0]);
+        assert!(proofs[0].is_empty());
+    }
+
+    #[test]
+    fn build_tree_of_no_leaves_has_default_root() {
+        let (root, proofs) = MerkleHelper::build_tree(&[]);
+
+        assert_eq!(root, Hash::default());
+        assert!(proofs.is_empty());
+    }
+
+    #[test]
+    fn verify_proof_rejects_other_amounts_and_wallets() {
+        let wallet = Pubkey::new_unique();
+        let leaves = vec![
+            MerkleHelper::leaf_hash(&wallet, 10),
+            MerkleHelper::leaf_hash(&Pubkey::new_unique(), 20),
+            MerkleHelper::leaf_hash(&Pubkey::new_unique(), 30)
+        ];
+        let (root, proofs) = MerkleHelper::build_tree(&leaves);
+
+        assert!(MerkleHelper::verify_proof(&root, MerkleHelper::leaf_hash(&wallet, 10), &proofs[0]));
+        assert!(!MerkleHelper::verify_proof(&root, MerkleHelper::leaf_hash(&wallet, 0), &proofs[0]));
+        assert!(!MerkleHelper::verify_proof(&root, MerkleHelper::leaf_hash(&wallet, u64::MAX), &proofs[0]));
+        assert!(!MerkleHelper::verify_proof(&root, MerkleHelper::leaf_hash(&Pubkey::new_unique(), 10), &proofs[0]));
+        assert!(!MerkleHelper::verify_proof(&root, leaves[0], &proofs[1]));
+    }
+}
\ No newline at end of file
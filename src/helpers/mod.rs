@@ -1 +1,4 @@
-pub mod solana_helper;
\ No newline at end of file
+pub mod solana_helper;
+pub mod merkle_helper;
+pub mod hd_wallet_helper;
+pub mod jwt_helper;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use solana_sdk::{self, pubkey::Pubkey, signature::Keypair, signer::Signer};
+use solana_sdk::{self, bs58, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer};
 
 use crate::clients::solana_rpc_client::SolanaError;
 
@@ -23,4 +23,33 @@ impl SolanaHelper {
             }
         }
     }
+
+    /// Parses a base58 secret key, as stored for custodial wallets. Unlike
+    /// `Keypair::from_base58_string`, it does not panic on malformed input.
+    pub fn try_to_convert_str_to_keypair(secret_key_str: &str) -> Result<Keypair, SolanaError> {
+        let bytes = bs58::decode(secret_key_str).into_vec().map_err(|e| {
+            println!("Error decoding secret key: {}", e);
+            SolanaError::KeypairParsingError
+        })?;
+
+        Keypair::from_bytes(&bytes).map_err(|e| {
+            println!("Error parsing into Keypair: {}", e);
+            SolanaError::KeypairParsingError
+        })
+    }
+
+    /// Checks an ed25519 signature over `message`, as produced by wallet `signMessage`.
+    pub fn verify_message_signature(
+        pubkey: &Pubkey,
+        message: &[u8],
+        signature_str: &str
+    ) -> Result<bool, SolanaError> {
+        match signature_str.parse::<Signature>() {
+            Ok(signature) => Ok(signature.verify(pubkey.as_ref(), message)),
+            Err(e) => {
+                println!("Error parsing into Signature: {}", e);
+                Err(SolanaError::SignatureParsingError)
+            }
+        }
+    }
 }
\ No newline at end of file
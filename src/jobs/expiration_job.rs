@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+use crate::controllers::expiration_controller::ExpirationController;
+
+/// Runs the point expirations every `period`, starting right away.
+pub fn spawn_expiration_job(expiration_controller: ExpirationController, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            match expiration_controller.run_expirations().await {
+                Ok(expirations) if !expirations.is_empty() => println!("Processed {} point expirations", expirations.len()),
+                Ok(_) => {},
+                Err((_, message)) => println!("Error running point expirations: {}", message)
+            }
+        }
+    });
+}
@@ -0,0 +1,5 @@
+pub mod expiration_job;
+pub mod settlement_job;
+pub mod reconciliation_job;
+pub mod rate_limit_job;
+pub mod tier_job;
\ No newline at end of file
@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+use crate::middlewares::rate_limit_middleware::RateLimitStore;
+
+/// Drops idle rate limit buckets every `period`, so the store does not grow
+/// with every key it has ever seen.
+pub fn spawn_rate_limit_job(rate_limit_store: RateLimitStore, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = rate_limit_store.prune().await {
+                println!("Error pruning rate limit buckets: {}", e);
+            }
+        }
+    });
+}
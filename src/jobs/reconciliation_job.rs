@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use crate::controllers::reconciliation_controller::ReconciliationController;
+
+/// Reconciles the database with the chain every `period` and logs the reports
+/// that found discrepancies.
+pub fn spawn_reconciliation_job(reconciliation_controller: ReconciliationController, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            match reconciliation_controller.run_reconciliation().await {
+                Ok(response) if !response.discrepancies.is_empty() => println!(
+                    "Reconciliation report {} found {} discrepancies",
+                    response.report.id,
+                    response.discrepancies.len()
+                ),
+                Ok(_) => {},
+                Err((_, message)) => println!("Error running reconciliation: {}", message)
+            }
+        }
+    });
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use crate::controllers::ledger_controller::LedgerController;
+
+/// Settles the deferred programs every `period`, then reconciles each settled
+/// program with the chain and logs the users whose balances diverge.
+pub fn spawn_settlement_job(ledger_controller: LedgerController, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            let reports = match ledger_controller.run_settlements().await {
+                Ok(reports) => reports,
+                Err((_, message)) => {
+                    println!("Error running settlements: {}", message);
+                    continue;
+                }
+            };
+
+            for report in reports {
+                let program_id = report.settlement.program_id;
+
+                match ledger_controller.reconcile(program_id).await {
+                    Ok(reconciliation) if !reconciliation.divergences.is_empty() => println!(
+                        "Program {} has {} balances diverging from the ledger",
+                        program_id,
+                        reconciliation.divergences.len()
+                    ),
+                    Ok(_) => {},
+                    Err((_, message)) => println!("Error reconciling program {}: {}", program_id, message)
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+use crate::controllers::tier_controller::TierController;
+
+/// Recomputes every user's tier, and updates their badges, every `period`.
+pub fn spawn_tier_job(tier_controller: TierController, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            if let Err((_, message)) = tier_controller.run_tier_refresh().await {
+                println!("Error refreshing tiers: {}", message);
+            }
+        }
+    });
+}
@@ -1,8 +1,78 @@
-use axum::{routing::get, Router};
-use clients::solana_rpc_client::SolanaRpcClient;
-use controllers::{token_controller::TokenController, user_controller::UserController};
-use repositories::user_repository::UserRepository;
-use routes::{token_routes::token_routes, user_routes::user_routes};
+use std::time::Duration;
+
+use axum::{middleware, routing::get, Router};
+use clients::{
+    mailer_client::{MailerClient, SmtpConfig}, 
+    solana_rpc_client::{PriorityFeeConfig, SolanaRpcClient}
+};
+use controllers::{
+    airdrop_controller::AirdropController, 
+    api_key_controller::ApiKeyController, 
+    auth_controller::AuthController, 
+    campaign_controller::CampaignController, 
+    conversion_controller::ConversionController, 
+    distribution_controller::DistributionController, 
+    expiration_controller::ExpirationController, 
+    ledger_controller::LedgerController, 
+    program_controller::ProgramController, 
+    reconciliation_controller::ReconciliationController, 
+    referral_controller::ReferralController, 
+    reward_controller::RewardController, 
+    role_controller::RoleController, 
+    tier_controller::TierController, 
+    token_controller::TokenController, 
+    user_controller::UserController, 
+    wallet_controller::WalletController
+};
+use helpers::{hd_wallet_helper::HdWalletHelper, jwt_helper::JwtHelper};
+use jobs::{
+    expiration_job::spawn_expiration_job, 
+    rate_limit_job::spawn_rate_limit_job, 
+    reconciliation_job::spawn_reconciliation_job, 
+    settlement_job::spawn_settlement_job, 
+    tier_job::spawn_tier_job
+};
+use middlewares::rate_limit_middleware::{rate_limit_middleware, RateLimit, RateLimitStore};
+use models::rate_limit_model::RateLimitPolicy;
+use repositories::{
+    airdrop_repository::AirdropRepository, 
+    api_key_repository::ApiKeyRepository, 
+    auth_repository::AuthRepository, 
+    campaign_repository::CampaignRepository, 
+    conversion_repository::ConversionRepository, 
+    distribution_repository::DistributionRepository, 
+    expiration_repository::ExpirationRepository, 
+    ledger_repository::LedgerRepository, 
+    program_repository::ProgramRepository, 
+    rate_limit_repository::RateLimitRepository, 
+    reconciliation_repository::ReconciliationRepository, 
+    referral_repository::ReferralRepository, 
+    reward_repository::RewardRepository, 
+    role_repository::RoleRepository, 
+    solana_repository::SolanaRepository, 
+    tier_repository::TierRepository, 
+    user_repository::UserRepository, 
+    wallet_repository::WalletRepository
+};
+use routes::{
+    airdrop_routes::airdrop_routes, 
+    api_key_routes::api_key_routes, 
+    auth_routes::auth_routes, 
+    campaign_routes::campaign_routes, 
+    conversion_routes::conversion_routes, 
+    distribution_routes::distribution_routes, 
+    expiration_routes::expiration_routes, 
+    ledger_routes::ledger_routes, 
+    program_routes::program_routes, 
+    reconciliation_routes::reconciliation_routes, 
+    referral_routes::referral_routes, 
+    reward_routes::reward_routes, 
+    role_routes::role_routes, 
+    tier_routes::tier_routes, 
+    token_routes::token_routes, 
+    user_routes::user_routes, 
+    wallet_routes::wallet_routes
+};
 use solana_sdk::commitment_config::CommitmentConfig;
 use sqlx::PgPool;
 use shuttle_runtime::SecretStore;
@@ -13,6 +83,8 @@ pub mod clients;
 pub mod routes;
 pub mod helpers;
 pub mod controllers;
+pub mod middlewares;
+pub mod jobs;
 
 async fn hello_world() -> &'static str {
     "Hello, world!"
@@ -31,23 +103,344 @@ async fn main(
 
     let helius_rpc_url = secrets.get("HELIUS_RPC_URL").expect("helius rpc url not found in secrets");
     let keypair_base58_string = secrets.get("KEYPAIR_BASE58_STRING").expect("keypair not found in secrets");
+    let master_seed_phrase = secrets.get("MASTER_SEED_PHRASE").expect("master seed phrase not found in secrets");
+    let hd_wallet_helper = HdWalletHelper::from_seed_phrase(&master_seed_phrase).expect("master seed phrase is invalid");
+    let jwt_secret = secrets.get("JWT_SECRET").expect("jwt secret not found in secrets");
+    let magic_link_url = secrets
+        .get("MAGIC_LINK_URL")
+        .unwrap_or_else(|| "http://localhost:3000/login".to_string());
+
+    let mailer_client = match secrets.get("MAILER").as_deref() {
+        Some("smtp") => MailerClient::smtp(SmtpConfig {
+            host: secrets.get("SMTP_HOST").expect("smtp host not found in secrets"),
+            port: secrets
+                .get("SMTP_PORT")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1025),
+            username: secrets.get("SMTP_USERNAME"),
+            password: secrets.get("SMTP_PASSWORD"),
+            starttls: secrets
+                .get("SMTP_STARTTLS")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(false),
+            from: secrets.get("MAIL_FROM").expect("mail from address not found in secrets"),
+        }).expect("Failed to configure SMTP mailer"),
+        // Export codes and magic links end up in the logs, so this has to be asked for.
+        Some("log") => MailerClient::Log,
+        _ => panic!("MAILER must be set to smtp, or to log for local development")
+    };
+
+    let default_fee_config = PriorityFeeConfig::default();
+    let priority_fee_config = PriorityFeeConfig {
+        max_priority_fee_lamports: secrets
+            .get("MAX_PRIORITY_FEE_LAMPORTS")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default_fee_config.max_priority_fee_lamports),
+        compute_unit_headroom_percent: secrets
+            .get("COMPUTE_UNIT_HEADROOM_PERCENT")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default_fee_config.compute_unit_headroom_percent),
+        fee_percentile: secrets
+            .get("PRIORITY_FEE_PERCENTILE")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default_fee_config.fee_percentile),
+    };
+
+    let rate_limit_store = match secrets.get("RATE_LIMIT_STORE").as_deref() {
+        Some("postgres") => RateLimitStore::Postgres(RateLimitRepository::new(pool.clone())),
+        _ => RateLimitStore::memory()
+    };
+    spawn_rate_limit_job(
+        rate_limit_store.clone(), 
+        Duration::from_secs(
+            60 * secrets
+                .get("RATE_LIMIT_PRUNE_INTERVAL_MINUTES")
+                .and_then(|value| value.parse().ok())
+                .filter(|minutes: &u64| *minutes > 0)
+                .unwrap_or(60)
+        )
+    );
+    let trusted_proxy_hops = secrets
+        .get("TRUSTED_PROXY_HOPS")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+    let ip_rate_limit = RateLimit::new(
+        &rate_limit_store, 
+        "ip", 
+        RateLimitPolicy::per_minute(
+            secrets
+                .get("RATE_LIMIT_PER_MINUTE")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(120)
+        ), 
+        trusted_proxy_hops
+    );
+    let mint_rate_limit = RateLimit::new(
+        &rate_limit_store, 
+        "mint", 
+        RateLimitPolicy::per_minute(
+            secrets
+                .get("MINT_RATE_LIMIT_PER_MINUTE")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(10)
+        ), 
+        trusted_proxy_hops
+    );
 
     let solana_rpc_client = SolanaRpcClient::new(
         &helius_rpc_url, 
         CommitmentConfig::confirmed(),
-        &keypair_base58_string
+        &keypair_base58_string,
+        priority_fee_config
+    );
+    let api_key_repository = ApiKeyRepository::new(pool.clone());
+    let role_repository = RoleRepository::new(pool.clone());
+    let api_key_controller = ApiKeyController::new(api_key_repository, role_repository.clone());
+
+    if let Some(admin_api_key) = secrets.get("ADMIN_API_KEY") {
+        api_key_controller
+            .bootstrap_admin_key(&admin_api_key)
+            .await
+            .expect("Failed to register admin API key");
+    }
+
+    let solana_repository = SolanaRepository::new(pool.clone());
+    let user_repository = UserRepository::new(pool.clone());
+
+    let auth_repository = AuthRepository::new(pool.clone());
+    let auth_controller = AuthController::new(
+        auth_repository, 
+        user_repository.clone(), 
+        mailer_client.clone(), 
+        JwtHelper::new(&jwt_secret), 
+        magic_link_url
+    );
+    let role_controller = RoleController::new(
+        role_repository, 
+        api_key_controller.clone(), 
+        auth_controller.clone()
+    );
+    let token_controller = TokenController::new(
+        solana_rpc_client.clone(), 
+        solana_repository, 
+        user_repository.clone()
+    );
+    let token_routes = token_routes(
+        token_controller.clone(), 
+        role_controller.clone(), 
+        mint_rate_limit.clone()
+    );
+
+    let wallet_repository = WalletRepository::new(pool.clone());
+    let referral_repository = ReferralRepository::new(pool.clone());
+    let tier_repository = TierRepository::new(pool.clone());
+    let user_controller = UserController::new(
+        user_repository.clone(), 
+        wallet_repository.clone(), 
+        referral_repository.clone(), 
+        tier_repository.clone(), 
+        hd_wallet_helper.clone()
+    );
+    let user_routes = user_routes(user_controller, role_controller.clone());
+
+    let airdrop_repository = AirdropRepository::new(pool.clone());
+    let airdrop_controller = AirdropController::new(
+        airdrop_repository, 
+        user_repository.clone(), 
+        token_controller.clone()
+    );
+    let airdrop_routes = airdrop_routes(airdrop_controller, role_controller.clone());
+
+    let program_repository = ProgramRepository::new(pool.clone());
+    let ledger_repository = LedgerRepository::new(pool.clone());
+    let campaign_repository = CampaignRepository::new(pool.clone());
+    let program_controller = ProgramController::new(
+        program_repository.clone(), 
+        user_repository.clone(), 
+        ledger_repository.clone(), 
+        referral_repository.clone(), 
+        campaign_repository.clone(), 
+        token_controller.clone(), 
+        solana_rpc_client.clone()
+    );
+    let program_routes = program_routes(
+        program_controller.clone(), 
+        role_controller.clone(), 
+        mint_rate_limit.clone()
+    );
+
+    let campaign_controller = CampaignController::new(campaign_repository, program_repository.clone());
+    let campaign_routes = campaign_routes(campaign_controller, role_controller.clone());
+
+    let referral_controller = ReferralController::new(
+        referral_repository, 
+        user_repository.clone(), 
+        program_controller
+    );
+    let referral_routes = referral_routes(referral_controller, role_controller.clone());
+
+    let reward_repository = RewardRepository::new(pool.clone());
+    let reward_controller = RewardController::new(
+        reward_repository, 
+        program_repository.clone(), 
+        wallet_repository.clone(), 
+        ledger_repository.clone(), 
+        token_controller.clone(), 
+        solana_rpc_client.clone(), 
+        hd_wallet_helper.clone()
+    );
+    let reward_routes = reward_routes(
+        reward_controller, 
+        role_controller.clone(), 
+        mint_rate_limit.clone()
+    );
+
+    let expiration_repository = ExpirationRepository::new(pool.clone());
+    let expiration_controller = ExpirationController::new(
+        expiration_repository, 
+        program_repository.clone(), 
+        wallet_repository.clone(), 
+        user_repository.clone(), 
+        ledger_repository.clone(), 
+        token_controller.clone(), 
+        solana_rpc_client.clone(), 
+        mailer_client.clone(), 
+        hd_wallet_helper.clone()
+    );
+    spawn_expiration_job(
+        expiration_controller.clone(), 
+        Duration::from_secs(
+            60 * secrets
+                .get("EXPIRATION_JOB_INTERVAL_MINUTES")
+                .and_then(|value| value.parse().ok())
+                .filter(|minutes: &u64| *minutes > 0)
+                .unwrap_or(60)
+        )
+    );
+    let expiration_routes = expiration_routes(expiration_controller, role_controller.clone());
+
+    let ledger_controller = LedgerController::new(
+        ledger_repository, 
+        program_repository.clone(), 
+        wallet_repository.clone(), 
+        token_controller.clone(), 
+        solana_rpc_client.clone(), 
+        hd_wallet_helper.clone()
+    );
+    spawn_settlement_job(
+        ledger_controller.clone(), 
+        Duration::from_secs(
+            60 * secrets
+                .get("SETTLEMENT_JOB_INTERVAL_MINUTES")
+                .and_then(|value| value.parse().ok())
+                .filter(|minutes: &u64| *minutes > 0)
+                .unwrap_or(60)
+        )
+    );
+    let ledger_routes = ledger_routes(ledger_controller.clone(), role_controller.clone());
+
+    let conversion_repository = ConversionRepository::new(pool.clone());
+    let conversion_controller = ConversionController::new(
+        conversion_repository, 
+        program_repository.clone(), 
+        wallet_repository.clone(), 
+        token_controller.clone(), 
+        solana_rpc_client.clone(), 
+        hd_wallet_helper.clone()
+    );
+    let conversion_routes = conversion_routes(
+        conversion_controller, 
+        role_controller.clone(), 
+        mint_rate_limit
+    );
+
+    let tier_controller = TierController::new(
+        tier_repository, 
+        program_repository.clone(), 
+        wallet_repository.clone(), 
+        token_controller.clone(), 
+        solana_rpc_client.clone(), 
+        hd_wallet_helper.clone()
+    );
+    spawn_tier_job(
+        tier_controller.clone(), 
+        Duration::from_secs(
+            60 * secrets
+                .get("TIER_JOB_INTERVAL_MINUTES")
+                .and_then(|value| value.parse().ok())
+                .filter(|minutes: &u64| *minutes > 0)
+                .unwrap_or(60)
+        )
+    );
+    let tier_routes = tier_routes(tier_controller, role_controller.clone());
+
+    let reconciliation_repository = ReconciliationRepository::new(pool.clone());
+    let reconciliation_controller = ReconciliationController::new(
+        reconciliation_repository, 
+        program_repository, 
+        ledger_controller, 
+        token_controller, 
+        solana_rpc_client.clone()
+    );
+    spawn_reconciliation_job(
+        reconciliation_controller.clone(), 
+        Duration::from_secs(
+            60 * secrets
+                .get("RECONCILIATION_JOB_INTERVAL_MINUTES")
+                .and_then(|value| value.parse().ok())
+                .filter(|minutes: &u64| *minutes > 0)
+                .unwrap_or(360)
+        )
+    );
+    let reconciliation_routes = reconciliation_routes(reconciliation_controller, role_controller.clone());
+
+    let distribution_repository = DistributionRepository::new(pool.clone());
+    let distribution_controller = DistributionController::new(
+        distribution_repository, 
+        solana_rpc_client.clone()
+    );
+    let distribution_routes = distribution_routes(distribution_controller, role_controller.clone());
+
+    let wallet_controller = WalletController::new(
+        wallet_repository, 
+        user_repository, 
+        solana_rpc_client, 
+        mailer_client, 
+        hd_wallet_helper
     );
-    let token_controller = TokenController::new(solana_rpc_client);
-    let token_routes = token_routes(token_controller);
+    let wallet_routes = wallet_routes(wallet_controller, role_controller.clone());
 
-    let user_repository = UserRepository::new(pool);
-    let user_controller = UserController::new(user_repository);
-    let user_routes = user_routes(user_controller);
+    let api_key_routes = api_key_routes(api_key_controller, role_controller.clone());
+    let role_routes = role_routes(role_controller);
+    let auth_routes = auth_routes(auth_controller);
 
     let router = Router::new()
         .route("/hello-world", get(hello_world))
-        .nest("/api", user_routes)
-        .nest("/solana", token_routes);
+        .nest(
+            "/api", 
+            user_routes
+                .merge(wallet_routes)
+                .merge(api_key_routes)
+                .merge(role_routes)
+                .merge(auth_routes)
+                .merge(program_routes)
+                .merge(campaign_routes)
+                .merge(referral_routes)
+                .merge(reward_routes)
+                .merge(expiration_routes)
+                .merge(ledger_routes)
+                .merge(reconciliation_routes)
+                .merge(tier_routes)
+                .merge(conversion_routes)
+                .route_layer(middleware::from_fn_with_state(ip_rate_limit.clone(), rate_limit_middleware))
+        )
+        .nest(
+            "/solana", 
+            token_routes
+                .merge(airdrop_routes)
+                .merge(distribution_routes)
+                .route_layer(middleware::from_fn_with_state(ip_rate_limit, rate_limit_middleware))
+        );
 
     Ok(router.into())
 }
@@ -0,0 +1,2 @@
+pub mod permission_middleware;
+pub mod rate_limit_middleware;
\ No newline at end of file
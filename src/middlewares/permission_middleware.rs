@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap},
+    middleware::Next,
+    response::Response
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{role_controller::RoleController, ApiError},
+    models::role_model::{Permission, Principal}
+};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// State of the middlewares below: the permission every route behind them requires.
+#[derive(Clone)]
+pub struct RequiredPermission {
+    role_controller: RoleController,
+    permission: Permission
+}
+
+impl RequiredPermission {
+    pub fn new(role_controller: &RoleController, permission: Permission) -> Self {
+        Self { role_controller: role_controller.clone(), permission }
+    }
+}
+
+/// Rejects requests whose API key or operator access token lacks the required
+/// permission. The authenticated `Principal` is added to the request extensions.
+pub async fn permission_middleware(
+    State(required): State<RequiredPermission>,
+    mut request: Request,
+    next: Next
+) -> Result<Response, ApiError> {
+    let principal = authenticate(&required, request.headers()).await?;
+
+    required.role_controller.authorize(&principal, required.permission).await?;
+
+    request.extensions_mut().insert(principal);
+
+    Ok(next.run(request).await)
+}
+
+/// Guards routes under `/users/:id`. A user's own access token opens that user's
+/// routes; any other principal needs the required permission.
+pub async fn own_user_middleware(
+    State(required): State<RequiredPermission>,
+    Path(params): Path<HashMap<String, String>>,
+    mut request: Request,
+    next: Next
+) -> Result<Response, ApiError> {
+    let principal = authenticate(&required, request.headers()).await?;
+
+    let path_user_id = params
+        .get("id")
+        .and_then(|id| id.parse::<Uuid>().ok());
+
+    let is_own_user = match &principal {
+        Principal::User(user) => path_user_id == Some(user.user_id),
+        Principal::ApiKey(_) => false
+    };
+
+    if !is_own_user {
+        required.role_controller.authorize(&principal, required.permission).await?;
+    }
+
+    request.extensions_mut().insert(principal);
+
+    Ok(next.run(request).await)
+}
+
+async fn authenticate(required: &RequiredPermission, headers: &HeaderMap) -> Result<Principal, ApiError> {
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    let access_token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    required.role_controller.authenticate(api_key, access_token).await
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex}
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header::RETRY_AFTER, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response}
+};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{
+    models::{
+        rate_limit_model::{RateLimitDecision, RateLimitPolicy, TokenBucket},
+        role_model::Principal
+    },
+    repositories::rate_limit_repository::{RateLimitRepository, RateLimitRepositoryError}
+};
+
+const RATE_LIMIT_LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+
+const RATE_LIMIT_REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+/// Most buckets kept by the in-memory store.
+const MAX_MEMORY_BUCKETS: usize = 10_000;
+
+/// Buckets dropped at once when the in-memory store is full, so the eviction
+/// scan runs once every this many new keys instead of on every request.
+const EVICTED_MEMORY_BUCKETS: usize = 1_000;
+
+/// Policies refill per minute, so buckets idle this long are full again and
+/// dropping them changes nothing.
+const IDLE_BUCKET_MINUTES: i64 = 60;
+
+/// Where token buckets are kept.
+#[derive(Clone)]
+pub enum RateLimitStore {
+    /// Buckets of this instance only. Enough for a single instance.
+    Memory(Arc<Mutex<HashMap<String, TokenBucket>>>),
+    Postgres(RateLimitRepository)
+}
+
+impl RateLimitStore {
+    pub fn memory() -> Self {
+        RateLimitStore::Memory(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    /// Fails open: requests go through when the store cannot be reached.
+    async fn take_token(&self, key: &str, policy: &RateLimitPolicy) -> RateLimitDecision {
+        let now = Utc::now();
+
+        match self {
+            RateLimitStore::Memory(buckets) => {
+                let mut buckets = buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+                if buckets.len() >= MAX_MEMORY_BUCKETS && !buckets.contains_key(key) {
+                    evict_buckets(&mut buckets, now);
+                }
+
+                let (bucket, decision) = policy.take(buckets.get(key).copied(), now);
+                buckets.insert(key.to_string(), bucket);
+
+                decision
+            },
+            RateLimitStore::Postgres(rate_limit_repository) => rate_limit_repository
+                .take_token(key, policy, &now)
+                .await
+                .unwrap_or_else(|e| {
+                    println!("Error taking rate limit token: {}", e);
+                    RateLimitDecision {
+                        allowed: true,
+                        limit: policy.capacity,
+                        remaining: policy.capacity,
+                        retry_after_seconds: None
+                    }
+                })
+        }
+    }
+
+    /// Drops buckets idle for `IDLE_BUCKET_MINUTES`, which are full again anyway.
+    pub async fn prune(&self) -> Result<u64, RateLimitRepositoryError> {
+        let idle_before = Utc::now() - Duration::minutes(IDLE_BUCKET_MINUTES);
+
+        match self {
+            RateLimitStore::Memory(buckets) => {
+                let mut buckets = buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let count = buckets.len();
+                buckets.retain(|_, bucket| bucket.updated_at > idle_before);
+
+                Ok((count - buckets.len()) as u64)
+            },
+            RateLimitStore::Postgres(rate_limit_repository) => rate_limit_repository.delete_idle_buckets(&idle_before).await
+        }
+    }
+}
+
+/// Drops idle buckets and, when that is not enough, the least recently used
+/// ones until `EVICTED_MEMORY_BUCKETS` slots are free.
+fn evict_buckets(buckets: &mut HashMap<String, TokenBucket>, now: DateTime<Utc>) {
+    buckets.retain(|_, bucket| now - bucket.updated_at < Duration::minutes(IDLE_BUCKET_MINUTES));
+
+    let target = MAX_MEMORY_BUCKETS - EVICTED_MEMORY_BUCKETS;
+
+    if buckets.len() <= target {
+        return;
+    }
+
+    let mut dates = buckets.values().map(|bucket| bucket.updated_at).collect::<Vec<_>>();
+    let excess = buckets.len() - target;
+    let (_, cutoff, _) = dates.select_nth_unstable(excess - 1);
+    let cutoff = *cutoff;
+
+    buckets.retain(|_, bucket| bucket.updated_at > cutoff);
+}
+
+/// State of `rate_limit_middleware`. Each named limit has its own buckets.
+#[derive(Clone)]
+pub struct RateLimit {
+    store: RateLimitStore,
+    name: &'static str,
+    policy: RateLimitPolicy,
+    trusted_proxy_hops: usize
+}
+
+impl RateLimit {
+    /// `trusted_proxy_hops` is the number of proxies in front of the service that
+    /// append the address they received the request from to `X-Forwarded-For`.
+    pub fn new(store: &RateLimitStore, name: &'static str, policy: RateLimitPolicy, trusted_proxy_hops: usize) -> Self {
+        Self { store: store.clone(), name, policy, trusted_proxy_hops }
+    }
+}
+
+/// Limits requests per API key or user when a permission middleware ran before,
+/// and per client IP otherwise. Responses carry the remaining quota of the most
+/// restrictive limit they went through; rejected requests get a 429 with
+/// `Retry-After`.
+pub async fn rate_limit_middleware(
+    State(rate_limit): State<RateLimit>,
+    request: Request,
+    next: Next
+) -> Response {
+    let subject = match request.extensions().get::<Principal>() {
+        Some(Principal::ApiKey(api_key)) => format!("api_key:{}", api_key.id),
+        Some(Principal::User(user)) => format!("user:{}", user.user_id),
+        None => format!("ip:{}", client_ip(&request, rate_limit.trusted_proxy_hops))
+    };
+
+    let key = format!("{}:{}", rate_limit.name, subject);
+    let decision = rate_limit.store.take_token(&key, &rate_limit.policy).await;
+
+    let mut response = match decision.allowed {
+        true => next.run(request).await,
+        false => {
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()).into_response();
+
+            if let Some(retry_after_seconds) = decision.retry_after_seconds {
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after_seconds));
+            }
+
+            response
+        }
+    };
+
+    let headers = response.headers_mut();
+    let inner_remaining = headers
+        .get(&RATE_LIMIT_REMAINING_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u32>().ok());
+
+    if inner_remaining.is_none_or(|remaining| decision.remaining < remaining) {
+        headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(decision.limit));
+        headers.insert(RATE_LIMIT_REMAINING_HEADER, HeaderValue::from(decision.remaining));
+    }
+
+    response
+}
+
+/// Every proxy appends the address it received the request from to
+/// `X-Forwarded-For`, so the client is the entry added by the outermost trusted
+/// proxy. Entries left of it are set by the client and ignored. Without proxies
+/// the client is the peer address, when the server provides it.
+fn client_ip(request: &Request, trusted_proxy_hops: usize) -> String {
+    if trusted_proxy_hops == 0 {
+        return request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+    }
+
+    let forwarded = forwarded_for(request.headers());
+
+    forwarded
+        .len()
+        .checked_sub(trusted_proxy_hops)
+        .and_then(|index| forwarded.get(index))
+        .or_else(|| forwarded.first())
+        .cloned()
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    fn request(forwarded: &[&str]) -> Request {
+        let mut builder = Request::builder();
+
+        for value in forwarded {
+            builder = builder.header("x-forwarded-for", *value);
+        }
+
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn client_ip_ignores_entries_set_by_the_client() {
+        let request = request(&["1.1.1.1, 2.2.2.2", "3.3.3.3"]);
+
+        assert_eq!(client_ip(&request, 1), "3.3.3.3");
+        assert_eq!(client_ip(&request, 2), "2.2.2.2");
+        assert_eq!(client_ip(&request, 5), "1.1.1.1");
+    }
+
+    #[test]
+    fn client_ip_uses_peer_address_without_proxies() {
+        let mut request = request(&["1.1.1.1"]);
+        assert_eq!(client_ip(&request, 0), "unknown");
+
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))));
+        assert_eq!(client_ip(&request, 0), "10.0.0.1");
+    }
+
+    #[test]
+    fn evict_buckets_frees_slots_dropping_least_recently_used() {
+        let now = Utc::now();
+        let mut buckets = (0..MAX_MEMORY_BUCKETS)
+            .map(|i| (i.to_string(), TokenBucket { tokens: 1.0, updated_at: now - Duration::milliseconds(i as i64) }))
+            .collect::<HashMap<_, _>>();
+
+        evict_buckets(&mut buckets, now);
+
+        assert_eq!(buckets.len(), MAX_MEMORY_BUCKETS - EVICTED_MEMORY_BUCKETS);
+        assert!(buckets.contains_key("0"));
+        assert!(!buckets.contains_key(&(MAX_MEMORY_BUCKETS - 1).to_string()));
+    }
+
+    #[test]
+    fn evict_buckets_drops_idle_buckets_first() {
+        let now = Utc::now();
+        let mut buckets = (0..MAX_MEMORY_BUCKETS)
+            .map(|i| {
+                let idle = if i % 2 == 0 { IDLE_BUCKET_MINUTES } else { 0 };
+                (i.to_string(), TokenBucket { tokens: 1.0, updated_at: now - Duration::minutes(idle) })
+            })
+            .collect::<HashMap<_, _>>();
+
+        evict_buckets(&mut buckets, now);
+
+        assert_eq!(buckets.len(), MAX_MEMORY_BUCKETS / 2);
+    }
+}
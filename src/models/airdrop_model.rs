@@ -0,0 +1,48 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+use crate::clients::solana_rpc_client::FeeEstimateResponse;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum AirdropUploadStatus {
+    Previewed,
+    Confirmed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct AirdropUpload {
+    pub id: uuid::Uuid,
+    pub mint_pubkey: String,
+    pub file_name: Option<String>,
+    pub status: AirdropUploadStatus,
+    pub batch_id: Option<uuid::Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+/// One CSV line. `receiver_pubkey` and `amount` are set once the row validated.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct AirdropUploadRow {
+    pub upload_id: uuid::Uuid,
+    pub row_number: i32,
+    pub recipient: String,
+    pub receiver_pubkey: Option<String>,
+    pub amount: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AirdropPreview {
+    pub upload: AirdropUpload,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub invalid_rows: usize,
+    pub total_amount: i64,
+    /// Cost of minting to the valid rows; missing when there are none.
+    pub estimated_cost: Option<FeeEstimateResponse>,
+    pub rows: Vec<AirdropUploadRow>,
+}
@@ -0,0 +1,35 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+use super::role_model::Role;
+
+/// What a key may do is given by the roles assigned to it.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ApiKey {
+    pub id: uuid::Uuid,
+    pub name: String,
+    /// First characters of the key, to tell keys apart without storing them.
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Names of the roles assigned to the new key.
+    pub roles: Vec<String>,
+}
+
+/// The only response that carries the plain key; it cannot be retrieved again.
+#[derive(Serialize, Debug)]
+pub struct CreatedApiKeyResponse {
+    pub key: String,
+    pub api_key: ApiKey,
+    pub roles: Vec<Role>,
+}
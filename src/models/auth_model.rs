@@ -0,0 +1,66 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+/// A one-time login token emailed to a user. Only its hash is stored.
+#[derive(FromRow, Debug)]
+pub struct MagicLink {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+/// A long-lived token that trades for new access tokens. Each one is single-use:
+/// refreshing revokes it and issues a new one.
+#[derive(FromRow, Debug)]
+pub struct RefreshToken {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Claims of the JWT access tokens handed to users.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccessClaims {
+    pub sub: uuid::Uuid,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SessionResponse {
+    pub user_id: uuid::Uuid,
+    pub token_type: String,
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+/// Set on requests authenticated with a user's access token.
+#[derive(Clone, Copy, Debug)]
+pub struct AuthenticatedUser {
+    pub user_id: uuid::Uuid,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyMagicLinkRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
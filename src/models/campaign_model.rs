@@ -0,0 +1,199 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+use super::program_model::BASIS_POINTS;
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum CampaignKind {
+    /// Scales the points of every purchase by `multiplier_bps`, e.g. 20000 for a
+    /// double points weekend.
+    Multiplier,
+    /// Adds `bonus_points` to the user's first purchase in the program.
+    FirstPurchase,
+    /// Scales the points of purchases in `category` by `multiplier_bps`.
+    Category,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum CampaignStatus {
+    Active,
+    Paused,
+    /// Its budget was spent. It stops for good.
+    Exhausted,
+}
+
+/// A promotion adding bonus points on top of the program's earning rules while
+/// it runs. Bonuses of campaigns running at once add up, within the program's
+/// `max_points_per_purchase`.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Campaign {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub name: String,
+    pub kind: CampaignKind,
+    pub multiplier_bps: Option<i32>,
+    pub bonus_points: Option<i64>,
+    pub category: Option<String>,
+    /// Purchases below this amount get no bonus.
+    pub min_purchase_amount: i64,
+    /// Bonus points the campaign may issue in total.
+    pub budget_points: i64,
+    pub issued_points: i64,
+    pub status: CampaignStatus,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub exhausted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Campaign {
+    pub fn applies_to(&self, purchase_amount: i64, category: Option<&str>, first_purchase: bool, date: DateTime<Utc>) -> bool {
+        let matches_kind = match self.kind {
+            CampaignKind::Multiplier => true,
+            CampaignKind::FirstPurchase => first_purchase,
+            CampaignKind::Category => category.is_some_and(|category| {
+                self.category.as_deref().is_some_and(|campaign_category| campaign_category.eq_ignore_ascii_case(category))
+            })
+        };
+
+        self.status == CampaignStatus::Active
+            && self.starts_at <= date
+            && date < self.ends_at
+            && purchase_amount >= self.min_purchase_amount
+            && matches_kind
+    }
+
+    /// Bonus on top of `base_points`, before the budget is applied.
+    pub fn bonus_points(&self, base_points: i64) -> i64 {
+        match self.kind {
+            CampaignKind::FirstPurchase => self.bonus_points.unwrap_or(0),
+            CampaignKind::Multiplier | CampaignKind::Category => {
+                let extra_bps = self.multiplier_bps.map(i64::from).unwrap_or(BASIS_POINTS) - BASIS_POINTS;
+                let points = base_points as i128 * extra_bps as i128 / BASIS_POINTS as i128;
+
+                i64::try_from(points).unwrap_or(i64::MAX)
+            }
+        }
+    }
+}
+
+/// Bonus points a campaign added to one earning.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct CampaignAward {
+    pub id: uuid::Uuid,
+    pub campaign_id: uuid::Uuid,
+    pub earning_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub points: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Points a campaign issued so far. `issued_points` counts every earning that
+/// received a bonus, `settled_points` only those whose points were minted or
+/// credited.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct CampaignReport {
+    pub campaign_id: uuid::Uuid,
+    pub name: String,
+    pub kind: CampaignKind,
+    pub status: CampaignStatus,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub budget_points: i64,
+    pub issued_points: i64,
+    pub settled_points: i64,
+    pub awards: i64,
+    pub users: i64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateCampaignRequest {
+    pub name: String,
+    pub kind: CampaignKind,
+    pub multiplier_bps: Option<i32>,
+    pub bonus_points: Option<i64>,
+    pub category: Option<String>,
+    pub min_purchase_amount: Option<i64>,
+    pub budget_points: i64,
+    /// Defaults to now.
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn campaign(kind: CampaignKind, multiplier_bps: Option<i32>, bonus_points: Option<i64>) -> Campaign {
+        let now = Utc::now();
+
+        Campaign {
+            id: uuid::Uuid::new_v4(),
+            program_id: uuid::Uuid::new_v4(),
+            name: "Campaign".to_string(),
+            kind,
+            multiplier_bps,
+            bonus_points,
+            category: Some("Shoes".to_string()),
+            min_purchase_amount: 100,
+            budget_points: 1_000,
+            issued_points: 0,
+            status: CampaignStatus::Active,
+            starts_at: now - Duration::days(1),
+            ends_at: now + Duration::days(1),
+            exhausted_at: None,
+            created_at: now,
+            updated_at: now
+        }
+    }
+
+    #[test]
+    fn bonus_points_adds_the_extra_of_the_multiplier() {
+        let campaign = campaign(CampaignKind::Multiplier, Some(15_000), None);
+
+        assert_eq!(campaign.bonus_points(100), 50);
+        assert_eq!(campaign.bonus_points(3), 1);
+        assert_eq!(campaign.bonus_points(1), 0);
+        assert_eq!(campaign.bonus_points(0), 0);
+    }
+
+    #[test]
+    fn bonus_points_of_first_purchase_ignores_base_points() {
+        let campaign = campaign(CampaignKind::FirstPurchase, None, Some(25));
+
+        assert_eq!(campaign.bonus_points(0), 25);
+        assert_eq!(campaign.bonus_points(1_000), 25);
+    }
+
+    #[test]
+    fn bonus_points_saturates_on_overflow() {
+        let campaign = campaign(CampaignKind::Category, Some(i32::MAX), None);
+
+        assert_eq!(campaign.bonus_points(i64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn applies_to_checks_kind_amount_and_window() {
+        let now = Utc::now();
+        let first_purchase = campaign(CampaignKind::FirstPurchase, None, Some(25));
+        let category = campaign(CampaignKind::Category, Some(20_000), None);
+
+        assert!(first_purchase.applies_to(100, None, true, now));
+        assert!(!first_purchase.applies_to(100, None, false, now));
+        assert!(!first_purchase.applies_to(99, None, true, now));
+        assert!(!first_purchase.applies_to(100, None, true, first_purchase.ends_at));
+
+        assert!(category.applies_to(100, Some("shoes"), false, now));
+        assert!(!category.applies_to(100, Some("hats"), false, now));
+        assert!(!category.applies_to(100, None, false, now));
+    }
+}
@@ -0,0 +1,139 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+/// `source_points` points of the source program convert into `target_points`
+/// points of the target program.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ExchangeRate {
+    pub id: uuid::Uuid,
+    pub source_program_id: uuid::Uuid,
+    pub target_program_id: uuid::Uuid,
+    pub source_points: i64,
+    pub target_points: i64,
+    /// Smallest amount of source points a conversion accepts.
+    pub min_source_points: i64,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A version of a rate, recorded every time it changes.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ExchangeRateSnapshot {
+    pub id: uuid::Uuid,
+    pub rate_id: uuid::Uuid,
+    pub source_points: i64,
+    pub target_points: i64,
+    pub min_source_points: i64,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ExchangeRateSnapshot {
+    /// Target points `source_points` convert into, rounded down.
+    pub fn convert(&self, source_points: i64) -> Option<i64> {
+        let target_points = source_points as i128 * self.target_points as i128 / self.source_points as i128;
+
+        i64::try_from(target_points).ok()
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum PointConversionStatus {
+    /// Source lots consumed, transaction not sent yet.
+    Reserved,
+    /// Burn and mint signed and sent in one transaction; its outcome decides the conversion.
+    Submitted,
+    Completed,
+    /// The transaction failed or expired. Source lots were released and no points moved.
+    Failed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct PointConversion {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub source_program_id: uuid::Uuid,
+    pub target_program_id: uuid::Uuid,
+    /// Version of the rate the conversion was priced with.
+    pub rate_snapshot_id: uuid::Uuid,
+    pub source_points: i64,
+    pub target_points: i64,
+    /// Wallet the source points are burned from and the target points minted to.
+    pub wallet_pubkey: String,
+    pub status: PointConversionStatus,
+    pub signature: Option<String>,
+    #[serde(skip_serializing)]
+    pub recent_blockhash: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PointConversionResponse {
+    #[serde(flatten)]
+    pub conversion: PointConversion,
+    pub rate: ExchangeRateSnapshot,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpsertExchangeRateRequest {
+    pub source_points: i64,
+    pub target_points: i64,
+    pub min_source_points: Option<i64>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConvertPointsRequest {
+    pub source_program_id: uuid::Uuid,
+    pub target_program_id: uuid::Uuid,
+    pub source_points: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(source_points: i64, target_points: i64) -> ExchangeRateSnapshot {
+        ExchangeRateSnapshot {
+            id: uuid::Uuid::new_v4(),
+            rate_id: uuid::Uuid::new_v4(),
+            source_points,
+            target_points,
+            min_source_points: 1,
+            enabled: true,
+            created_at: Utc::now()
+        }
+    }
+
+    #[test]
+    fn convert_rounds_down() {
+        let rate = snapshot(3, 2);
+
+        assert_eq!(rate.convert(9), Some(6));
+        assert_eq!(rate.convert(10), Some(6));
+        assert_eq!(rate.convert(11), Some(7));
+    }
+
+    #[test]
+    fn convert_returns_zero_below_one_target_point() {
+        let rate = snapshot(100, 1);
+
+        assert_eq!(rate.convert(99), Some(0));
+        assert_eq!(rate.convert(100), Some(1));
+    }
+
+    #[test]
+    fn convert_rejects_results_beyond_i64() {
+        assert_eq!(snapshot(1, 2).convert(i64::MAX), None);
+
+        // Intermediate products beyond i64 are fine when the result fits.
+        assert_eq!(snapshot(4, 2).convert(i64::MAX), Some(i64::MAX / 2));
+    }
+}
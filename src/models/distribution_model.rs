@@ -0,0 +1,46 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum DistributionLeafStatus {
+    Unclaimed,
+    /// Reserved by a claim whose mint has not been confirmed yet. Settling the
+    /// claim moves it to `Claimed`, or back to `Unclaimed` when the mint never landed.
+    Claiming,
+    Claimed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct Distribution {
+    pub id: uuid::Uuid,
+    pub mint_pubkey: String,
+    pub merkle_root: String,
+    pub leaf_count: i32,
+    pub total_amount: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct DistributionLeaf {
+    pub distribution_id: uuid::Uuid,
+    pub leaf_index: i32,
+    pub wallet_pubkey: String,
+    pub amount: i64,
+    pub proof: Vec<String>,
+    pub status: DistributionLeafStatus,
+    pub signature: Option<String>,
+    #[serde(skip_serializing)]
+    pub recent_blockhash: Option<String>,
+    pub claimed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ClaimRequest {
+    pub wallet_pubkey: String,
+    pub amount: u64,
+    pub proof: Vec<String>,
+}
@@ -0,0 +1,70 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Points earned in one purchase, or converted from another program.
+/// Redemptions and expirations consume lots oldest expiry first; points minted
+/// outside of earnings and conversions have no lot and never expire.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct PointLot {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub earning_id: Option<uuid::Uuid>,
+    pub conversion_id: Option<uuid::Uuid>,
+    pub points: i64,
+    pub remaining: i64,
+    /// Wallet the points were minted to. Missing on lots recorded before it was
+    /// tracked, whose points expire from the user's primary wallet.
+    pub wallet_pubkey: Option<String>,
+    pub earned_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum PointExpirationStatus {
+    /// Lots consumed, burn not sent yet.
+    Pending,
+    /// Burn signed and sent; its outcome decides the expiration.
+    Burning,
+    Completed,
+    /// Burn failed. The expiration can be retried.
+    Failed,
+    /// The points are in a wallet the service cannot sign for, so they are only
+    /// expired off chain.
+    Skipped,
+}
+
+/// Expired points of a user in a program held in one wallet, burned in one
+/// transaction.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct PointExpiration {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub points: i64,
+    pub status: PointExpirationStatus,
+    /// Wallet the points are burned from: the one their lots were minted to, or
+    /// the primary wallet when the burn is sent for lots without one.
+    pub wallet_pubkey: Option<String>,
+    pub signature: Option<String>,
+    #[serde(skip_serializing)]
+    pub recent_blockhash: Option<String>,
+    pub error: Option<String>,
+    pub notified_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Points of a program that expire on the same day.
+#[derive(Serialize, FromRow, Debug)]
+pub struct ExpiringPoints {
+    pub program_id: uuid::Uuid,
+    pub program_name: String,
+    pub points: i64,
+    pub expires_on: NaiveDate,
+}
@@ -0,0 +1,156 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum LedgerAccount {
+    /// Points a user holds in a program. The only account with a user.
+    UserBalance,
+    /// Counterpart of points the program awarded.
+    Issued,
+    /// Counterpart of points spent on rewards.
+    Redeemed,
+    /// Counterpart of points that expired.
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum LedgerTransactionKind {
+    Earning,
+    Redemption,
+    Refund,
+    Expiration,
+}
+
+/// A balanced set of entries. Posting one kind twice for a reference is a no-op.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct LedgerTransaction {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub kind: LedgerTransactionKind,
+    pub reference: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct LedgerEntry {
+    pub id: uuid::Uuid,
+    pub transaction_id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub account: LedgerAccount,
+    pub user_id: Option<uuid::Uuid>,
+    /// Positive for credits, negative for debits.
+    pub amount: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Ledger balance of a user in a deferred program.
+#[derive(Serialize, FromRow, Debug)]
+pub struct LedgerBalance {
+    pub program_id: uuid::Uuid,
+    pub program_name: String,
+    pub points: i64,
+}
+
+/// Where a user of a deferred program stands between the ledger and the chain.
+#[derive(FromRow, Debug)]
+pub struct SettlementPosition {
+    pub user_id: uuid::Uuid,
+    /// The user's primary wallet, if any.
+    pub wallet_pubkey: Option<String>,
+    pub ledger_points: i64,
+    /// Points confirmed on chain by settlements.
+    pub settled_points: i64,
+    /// Settled points plus the ones still in flight.
+    pub committed_points: i64,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum SettlementStatus {
+    /// Some items are not final yet. A program has one processing settlement at most.
+    Processing,
+    Completed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Settlement {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub status: SettlementStatus,
+    /// Batch minting the positive items.
+    pub mint_batch_id: Option<uuid::Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum SettlementItemStatus {
+    /// Not sent yet.
+    Pending,
+    /// Part of the settlement's mint batch, not confirmed yet.
+    Batched,
+    /// Burn signed and sent; its outcome decides the item.
+    Burning,
+    Completed,
+    /// Never landed. The next settlement nets the amount again.
+    Failed,
+    /// The points are in a wallet the service cannot burn from.
+    Skipped,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct SettlementItem {
+    pub id: uuid::Uuid,
+    pub settlement_id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub wallet_pubkey: String,
+    /// Points minted when positive, burned when negative.
+    pub amount: i64,
+    pub status: SettlementItemStatus,
+    pub mint_batch_position: Option<i32>,
+    pub signature: Option<String>,
+    #[serde(skip_serializing)]
+    pub recent_blockhash: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SettlementReport {
+    #[serde(flatten)]
+    pub settlement: Settlement,
+    pub items: Vec<SettlementItem>,
+}
+
+/// A user whose on-chain balance differs from the points settlements confirmed.
+#[derive(Serialize, Debug)]
+pub struct BalanceDivergence {
+    pub user_id: uuid::Uuid,
+    pub wallet_pubkey: Option<String>,
+    pub ledger_points: i64,
+    pub settled_points: i64,
+    /// Expected and actual balances, in base units of the mint.
+    pub expected_amount: u64,
+    pub on_chain_amount: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReconciliationReport {
+    pub program_id: uuid::Uuid,
+    pub checked_at: DateTime<Utc>,
+    pub checked: usize,
+    /// Ledger points not settled on chain yet, across users.
+    pub unsettled_points: i64,
+    pub divergences: Vec<BalanceDivergence>,
+}
@@ -0,0 +1,85 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum MintBatchStatus {
+    Processing,
+    Completed,
+    PartiallyFailed,
+    /// Given up by its owner after failing: unconfirmed items are never sent again.
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum MintBatchItemStatus {
+    /// Not sent yet, or safe to send again.
+    Pending,
+    /// Signed and handed to the cluster; outcome still unknown.
+    Submitted,
+    Confirmed,
+    Failed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct MintBatch {
+    pub id: uuid::Uuid,
+    pub mint_pubkey: String,
+    pub status: MintBatchStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct MintBatchItem {
+    pub batch_id: uuid::Uuid,
+    pub position: i32,
+    pub receiver_pubkey: String,
+    pub amount: i64,
+    pub status: MintBatchItemStatus,
+    pub signature: Option<String>,
+    pub recent_blockhash: Option<String>,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MintBatchReport {
+    pub id: uuid::Uuid,
+    pub mint_pubkey: String,
+    pub status: MintBatchStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub total: usize,
+    pub confirmed: usize,
+    pub failed: usize,
+    pub pending: usize,
+    pub items: Vec<MintBatchItem>,
+}
+
+impl MintBatchReport {
+    pub fn new(batch: MintBatch, items: Vec<MintBatchItem>) -> Self {
+        let count = |status: MintBatchItemStatus| items
+            .iter()
+            .filter(|item| item.status == status)
+            .count();
+
+        Self {
+            id: batch.id,
+            mint_pubkey: batch.mint_pubkey,
+            status: batch.status,
+            created_at: batch.created_at,
+            updated_at: batch.updated_at,
+            total: items.len(),
+            confirmed: count(MintBatchItemStatus::Confirmed),
+            failed: count(MintBatchItemStatus::Failed),
+            pending: count(MintBatchItemStatus::Pending) + count(MintBatchItemStatus::Submitted),
+            items,
+        }
+    }
+}
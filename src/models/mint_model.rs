@@ -0,0 +1,26 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+/// A mint whose mint authority is the service keypair.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Mint {
+    pub pubkey: String,
+    pub decimals: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Mint {
+    /// Token base units of `points` whole tokens, or `None` on overflow.
+    pub fn base_units(&self, points: i64) -> Option<u64> {
+        u64::try_from(points)
+            .ok()?
+            .checked_mul(10u64.checked_pow(self.decimals as u32)?)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RegisterMintRequest {
+    pub pubkey: String,
+}
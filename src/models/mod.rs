@@ -1 +1,19 @@
-pub mod user_model;
\ No newline at end of file
+pub mod user_model;
+pub mod mint_batch_model;
+pub mod airdrop_model;
+pub mod distribution_model;
+pub mod wallet_model;
+pub mod api_key_model;
+pub mod auth_model;
+pub mod role_model;
+pub mod rate_limit_model;
+pub mod mint_model;
+pub mod program_model;
+pub mod reward_model;
+pub mod expiration_model;
+pub mod ledger_model;
+pub mod reconciliation_model;
+pub mod referral_model;
+pub mod tier_model;
+pub mod conversion_model;
+pub mod campaign_model;
\ No newline at end of file
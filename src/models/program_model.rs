@@ -0,0 +1,321 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Duration, Utc};
+
+use super::tier_model::TierBasis;
+
+/// Multipliers are expressed in basis points of the base points.
+pub const BASIS_POINTS: i64 = 10_000;
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Merchant {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A loyalty program of a merchant. Its points are tokens of `mint_pubkey`,
+/// one point being one whole token.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Program {
+    pub id: uuid::Uuid,
+    pub merchant_id: uuid::Uuid,
+    pub name: String,
+    pub mint_pubkey: String,
+    /// Currency purchases are expressed in, in its smallest unit.
+    pub currency: String,
+    /// Most points one purchase earns, campaign bonuses included.
+    pub max_points_per_purchase: Option<i64>,
+    /// Days earned points stay valid. They never expire when missing.
+    pub points_ttl_days: Option<i32>,
+    pub settlement_mode: SettlementMode,
+    pub tier_basis: TierBasis,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Program {
+    pub fn points_expire_at(&self, earned_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.points_ttl_days.map(|days| earned_at + Duration::days(days.into()))
+    }
+
+    /// Base rules add up, the highest multiplier applies on top of their sum, and
+    /// the program cap applies last.
+    pub fn compute_points(&self, rules: &[EarningRule], purchase_amount: i64, date: DateTime<Utc>) -> i64 {
+        let applicable = rules
+            .iter()
+            .filter(|rule| rule.applies_to(purchase_amount, date));
+
+        let base_points: i128 = applicable
+            .clone()
+            .filter(|rule| rule.kind == EarningRuleKind::Base)
+            .map(|rule| rule.base_points(purchase_amount))
+            .fold(0, i128::saturating_add);
+
+        let multiplier_bps = applicable
+            .filter(|rule| rule.kind == EarningRuleKind::Multiplier)
+            .filter_map(|rule| rule.multiplier_bps)
+            .map(i64::from)
+            .max()
+            .unwrap_or(BASIS_POINTS);
+
+        let points = base_points.saturating_mul(multiplier_bps as i128) / BASIS_POINTS as i128;
+        let points = i64::try_from(points).unwrap_or(i64::MAX);
+
+        match self.max_points_per_purchase {
+            Some(max_points) => points.min(max_points),
+            None => points
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum SettlementMode {
+    /// Points are minted and burned on chain as they are earned and spent.
+    #[default]
+    Immediate,
+    /// Points move in the off-chain ledger and the net difference is settled on
+    /// chain in batches.
+    Deferred,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum EarningRuleKind {
+    /// `points_per_unit` points for every full `unit_amount` spent.
+    Base,
+    /// Scales the base points by `multiplier_bps`, e.g. 20000 for double points.
+    Multiplier,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct EarningRule {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub name: String,
+    pub kind: EarningRuleKind,
+    pub points_per_unit: Option<i64>,
+    pub unit_amount: Option<i64>,
+    pub multiplier_bps: Option<i32>,
+    /// Purchases below this amount do not trigger the rule.
+    pub min_purchase_amount: i64,
+    /// Caps the points a base rule awards per purchase.
+    pub max_points: Option<i64>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+impl EarningRule {
+    pub fn applies_to(&self, purchase_amount: i64, date: DateTime<Utc>) -> bool {
+        self.archived_at.is_none()
+            && self.starts_at <= date
+            && self.ends_at.is_none_or(|ends_at| date < ends_at)
+            && purchase_amount >= self.min_purchase_amount
+    }
+
+    fn base_points(&self, purchase_amount: i64) -> i128 {
+        let (Some(points_per_unit), Some(unit_amount)) = (self.points_per_unit, self.unit_amount) else {
+            return 0;
+        };
+
+        let points = (purchase_amount / unit_amount) as i128 * points_per_unit as i128;
+
+        match self.max_points {
+            Some(max_points) => points.min(max_points as i128),
+            None => points
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum PointEarningStatus {
+    /// Recorded, points not minted yet.
+    Pending,
+    /// The mint was signed and sent; its signature decides the outcome before
+    /// anything is sent again.
+    Submitted,
+    Completed,
+    /// Credited in the ledger of a deferred program; minted at the next settlement.
+    Credited,
+    /// Minting failed and no mint can land anymore; sending the same reference
+    /// again retries it.
+    Failed,
+}
+
+/// Points awarded to a user for one purchase.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct PointEarning {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// The merchant's purchase id. Earning twice with one reference is a no-op.
+    pub reference: Option<String>,
+    pub purchase_amount: i64,
+    pub points: i64,
+    pub receiver_pubkey: String,
+    pub status: PointEarningStatus,
+    pub signature: Option<String>,
+    pub recent_blockhash: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProgramResponse {
+    #[serde(flatten)]
+    pub program: Program,
+    pub rules: Vec<EarningRule>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateMerchantRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateProgramRequest {
+    pub merchant_id: uuid::Uuid,
+    pub name: String,
+    pub mint_pubkey: String,
+    pub currency: String,
+    pub max_points_per_purchase: Option<i64>,
+    pub points_ttl_days: Option<i32>,
+    #[serde(default)]
+    pub settlement_mode: SettlementMode,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateEarningRuleRequest {
+    pub name: String,
+    pub kind: EarningRuleKind,
+    pub points_per_unit: Option<i64>,
+    pub unit_amount: Option<i64>,
+    pub multiplier_bps: Option<i32>,
+    pub min_purchase_amount: Option<i64>,
+    pub max_points: Option<i64>,
+    /// Defaults to now.
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// Exactly one of `user_id` or `email` is expected.
+#[derive(Deserialize, Debug)]
+pub struct EarnPointsRequest {
+    pub user_id: Option<uuid::Uuid>,
+    pub email: Option<String>,
+    /// In the smallest unit of the program currency.
+    pub purchase_amount: i64,
+    pub reference: Option<String>,
+    /// Category of the purchase, matched by category campaigns.
+    pub category: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(max_points_per_purchase: Option<i64>) -> Program {
+        Program {
+            id: uuid::Uuid::new_v4(),
+            merchant_id: uuid::Uuid::new_v4(),
+            name: "Program".to_string(),
+            mint_pubkey: "mint".to_string(),
+            currency: "EUR".to_string(),
+            max_points_per_purchase,
+            points_ttl_days: None,
+            settlement_mode: SettlementMode::Immediate,
+            tier_basis: TierBasis::LifetimeEarned,
+            created_at: Utc::now() - Duration::days(1)
+        }
+    }
+
+    fn rule(kind: EarningRuleKind, points_per_unit: Option<i64>, unit_amount: Option<i64>, multiplier_bps: Option<i32>) -> EarningRule {
+        let now = Utc::now();
+
+        EarningRule {
+            id: uuid::Uuid::new_v4(),
+            program_id: uuid::Uuid::new_v4(),
+            name: "Rule".to_string(),
+            kind,
+            points_per_unit,
+            unit_amount,
+            multiplier_bps,
+            min_purchase_amount: 0,
+            max_points: None,
+            starts_at: now - Duration::days(1),
+            ends_at: None,
+            created_at: now,
+            archived_at: None
+        }
+    }
+
+    fn base(points_per_unit: i64, unit_amount: i64) -> EarningRule {
+        rule(EarningRuleKind::Base, Some(points_per_unit), Some(unit_amount), None)
+    }
+
+    fn multiplier(multiplier_bps: i32) -> EarningRule {
+        rule(EarningRuleKind::Multiplier, None, None, Some(multiplier_bps))
+    }
+
+    #[test]
+    fn compute_points_counts_full_units_only() {
+        let rules = [base(1, 100)];
+
+        assert_eq!(program(None).compute_points(&rules, 0, Utc::now()), 0);
+        assert_eq!(program(None).compute_points(&rules, 99, Utc::now()), 0);
+        assert_eq!(program(None).compute_points(&rules, 250, Utc::now()), 2);
+    }
+
+    #[test]
+    fn compute_points_adds_base_rules_and_applies_the_highest_multiplier() {
+        let rules = [base(1, 100), base(2, 100), multiplier(15_000), multiplier(20_000)];
+
+        assert_eq!(program(None).compute_points(&rules, 100, Utc::now()), 6);
+    }
+
+    #[test]
+    fn compute_points_rounds_multiplied_points_down() {
+        let rules = [base(1, 100), multiplier(15_000)];
+
+        assert_eq!(program(None).compute_points(&rules, 300, Utc::now()), 4);
+    }
+
+    #[test]
+    fn compute_points_applies_rule_and_program_caps() {
+        let mut capped = base(10, 1);
+        capped.max_points = Some(50);
+        let rules = [capped, base(1, 1)];
+
+        assert_eq!(program(None).compute_points(&rules, 100, Utc::now()), 150);
+        assert_eq!(program(Some(120)).compute_points(&rules, 100, Utc::now()), 120);
+    }
+
+    #[test]
+    fn compute_points_skips_inactive_rules() {
+        let now = Utc::now();
+        let mut archived = base(5, 1);
+        archived.archived_at = Some(now);
+        let mut minimum = base(5, 1);
+        minimum.min_purchase_amount = 1_000;
+        let mut ended = base(5, 1);
+        ended.ends_at = Some(now);
+
+        assert_eq!(program(None).compute_points(&[archived, minimum, ended, base(1, 1)], 10, now), 10);
+    }
+
+    #[test]
+    fn compute_points_saturates_on_overflow() {
+        let rules = [base(i64::MAX, 1), base(i64::MAX, 1), base(i64::MAX, 1), multiplier(i32::MAX)];
+
+        assert_eq!(program(None).compute_points(&rules, i64::MAX, Utc::now()), i64::MAX);
+        assert_eq!(program(Some(1_000)).compute_points(&rules, i64::MAX, Utc::now()), 1_000);
+    }
+}
@@ -0,0 +1,172 @@
+
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+/// A token bucket: `capacity` requests in a burst, refilled continuously at
+/// `refill_per_minute`.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_per_minute: u32,
+}
+
+impl RateLimitPolicy {
+    pub fn per_minute(requests: u32) -> Self {
+        Self { capacity: requests, refill_per_minute: requests }
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.refill_per_minute as f64 / 60.0
+    }
+
+    /// Refills `bucket` up to `date` and takes one token from it when available.
+    /// A missing bucket starts full.
+    pub fn take(&self, bucket: Option<TokenBucket>, date: DateTime<Utc>) -> (TokenBucket, RateLimitDecision) {
+        let capacity = self.capacity as f64;
+
+        let tokens = match bucket {
+            Some(bucket) => {
+                let elapsed = (date - bucket.updated_at).num_milliseconds().max(0) as f64 / 1000.0;
+                (bucket.tokens + elapsed * self.refill_per_second()).min(capacity)
+            },
+            None => capacity
+        };
+
+        let allowed = tokens >= 1.0;
+        let tokens = if allowed { tokens - 1.0 } else { tokens };
+
+        let retry_after_seconds = match allowed || self.refill_per_minute == 0 {
+            true => None,
+            false => Some(((1.0 - tokens) / self.refill_per_second()).ceil() as u64)
+        };
+
+        let decision = RateLimitDecision {
+            allowed,
+            limit: self.capacity,
+            remaining: tokens.floor() as u32,
+            retry_after_seconds
+        };
+
+        (TokenBucket { tokens, updated_at: date }, decision)
+    }
+}
+
+#[derive(FromRow, Debug, Clone, Copy)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// Missing while requests are allowed, or when the bucket never refills.
+    pub retry_after_seconds: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn take_starts_with_a_full_bucket() {
+        let now = Utc::now();
+        let (bucket, decision) = RateLimitPolicy::per_minute(3).take(None, now);
+
+        assert!(decision.allowed);
+        assert_eq!(decision.limit, 3);
+        assert_eq!(decision.remaining, 2);
+        assert_eq!(decision.retry_after_seconds, None);
+        assert_eq!(bucket.tokens, 2.0);
+        assert_eq!(bucket.updated_at, now);
+    }
+
+    #[test]
+    fn take_rejects_empty_bucket_with_retry_after() {
+        let now = Utc::now();
+        let policy = RateLimitPolicy::per_minute(2);
+        let bucket = TokenBucket { tokens: 0.0, updated_at: now };
+
+        let (bucket, decision) = policy.take(Some(bucket), now);
+
+        assert!(!decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(decision.retry_after_seconds, Some(30));
+        assert_eq!(bucket.tokens, 0.0);
+    }
+
+    #[test]
+    fn take_refills_across_time() {
+        let now = Utc::now();
+        let policy = RateLimitPolicy::per_minute(60);
+        let bucket = TokenBucket { tokens: 0.0, updated_at: now };
+
+        let (_, decision) = policy.take(Some(bucket), now + Duration::milliseconds(500));
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_seconds, Some(1));
+
+        let (bucket, decision) = policy.take(Some(bucket), now + Duration::seconds(10));
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 9);
+        assert_eq!(bucket.tokens, 9.0);
+    }
+
+    #[test]
+    fn take_refills_up_to_capacity() {
+        let now = Utc::now();
+        let policy = RateLimitPolicy::per_minute(5);
+        let bucket = TokenBucket { tokens: 0.0, updated_at: now - Duration::days(365) };
+
+        let (bucket, decision) = policy.take(Some(bucket), now);
+
+        assert_eq!(decision.remaining, 4);
+        assert_eq!(bucket.tokens, 4.0);
+    }
+
+    #[test]
+    fn take_ignores_buckets_from_the_future() {
+        let now = Utc::now();
+        let policy = RateLimitPolicy::per_minute(5);
+        let bucket = TokenBucket { tokens: 1.0, updated_at: now + Duration::minutes(1) };
+
+        let (bucket, decision) = policy.take(Some(bucket), now);
+
+        assert!(decision.allowed);
+        assert_eq!(bucket.tokens, 0.0);
+    }
+
+    #[test]
+    fn take_never_refills_without_rate() {
+        let now = Utc::now();
+        let policy = RateLimitPolicy { capacity: 1, refill_per_minute: 0 };
+
+        let (bucket, decision) = policy.take(None, now);
+        assert!(decision.allowed);
+
+        let (_, decision) = policy.take(Some(bucket), now + Duration::days(1));
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after_seconds, None);
+    }
+
+    #[test]
+    fn take_rejects_everything_with_zero_capacity() {
+        let (_, decision) = RateLimitPolicy::per_minute(0).take(None, Utc::now());
+
+        assert!(!decision.allowed);
+        assert_eq!(decision.limit, 0);
+        assert_eq!(decision.remaining, 0);
+    }
+
+    #[test]
+    fn take_handles_large_capacity() {
+        let policy = RateLimitPolicy::per_minute(u32::MAX);
+        let (_, decision) = policy.take(None, Utc::now());
+
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, u32::MAX - 1);
+    }
+}
@@ -0,0 +1,81 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum ReconciliationStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum DiscrepancyKind {
+    /// A registered mint could not be read from the chain.
+    MintMissing,
+    /// The service is no longer the mint authority.
+    MintAuthorityChanged,
+    /// Supply moved by more or less than the mints and burns recorded since the last report.
+    UnexpectedSupplyChange,
+    /// A recorded signature is unknown to the cluster or failed.
+    MissingTransaction,
+    /// A token account owner that is neither a wallet nor a recorded receiver.
+    UnknownHolder,
+    /// A deferred program's settled balance differs from the user's token balance.
+    LedgerDivergence,
+    /// A deferred program's ledger could not be compared with the chain.
+    LedgerUnchecked,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ChainReconciliation {
+    pub id: uuid::Uuid,
+    pub status: ReconciliationStatus,
+    pub discrepancy_count: i32,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct MintSnapshot {
+    pub report_id: uuid::Uuid,
+    pub mint_pubkey: String,
+    pub supply: i64,
+    /// Net supply change recorded in the database since the mint was registered.
+    pub recorded_supply: i64,
+    pub holders: i32,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Discrepancy {
+    pub id: uuid::Uuid,
+    pub report_id: uuid::Uuid,
+    pub kind: DiscrepancyKind,
+    pub mint_pubkey: String,
+    pub subject: Option<String>,
+    pub detail: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A mint, burn or claim the database believes landed on chain.
+#[derive(FromRow, Debug, Clone)]
+pub struct RecordedTransaction {
+    pub source: String,
+    pub reference: String,
+    pub mint_pubkey: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChainReconciliationResponse {
+    #[serde(flatten)]
+    pub report: ChainReconciliation,
+    pub snapshots: Vec<MintSnapshot>,
+    pub discrepancies: Vec<Discrepancy>,
+}
@@ -0,0 +1,81 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+/// The code a user shares so others can sign up as their referees.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ReferralCode {
+    pub user_id: uuid::Uuid,
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Referral {
+    pub id: uuid::Uuid,
+    pub referrer_id: uuid::Uuid,
+    pub referee_id: uuid::Uuid,
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a program pays for referrals and how often a referrer can be paid.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ReferralRule {
+    pub program_id: uuid::Uuid,
+    pub referrer_points: i64,
+    pub referee_points: i64,
+    /// The referee's first earning at or above this purchase amount qualifies.
+    pub min_purchase_amount: i64,
+    /// Lifetime rewards a referrer can get in the program. Unlimited when missing.
+    pub max_rewards_per_referrer: Option<i32>,
+    /// Rewards a referrer can get in the program over the last 24 hours.
+    pub max_daily_rewards_per_referrer: Option<i32>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum ReferralRewardStatus {
+    /// Qualified, points not issued yet.
+    Pending,
+    Completed,
+    /// Issuing points failed; retrying the reward issues what is missing.
+    Failed,
+    /// The referrer hit a limit of the program's rule. Never paid.
+    Rejected,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ReferralReward {
+    pub id: uuid::Uuid,
+    pub referral_id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub referrer_id: uuid::Uuid,
+    pub referee_id: uuid::Uuid,
+    /// The referee's earning that met the rule.
+    pub qualifying_earning_id: uuid::Uuid,
+    pub referrer_points: i64,
+    pub referee_points: i64,
+    pub referrer_earning_id: Option<uuid::Uuid>,
+    pub referee_earning_id: Option<uuid::Uuid>,
+    pub status: ReferralRewardStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpsertReferralRuleRequest {
+    pub referrer_points: i64,
+    pub referee_points: i64,
+    pub min_purchase_amount: Option<i64>,
+    pub max_rewards_per_referrer: Option<i32>,
+    pub max_daily_rewards_per_referrer: Option<i32>,
+    /// Defaults to true.
+    pub enabled: Option<bool>,
+}
@@ -0,0 +1,113 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+/// A catalog item users redeem program points for.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct RewardItem {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub point_price: i64,
+    /// Unlimited when missing.
+    pub stock: Option<i32>,
+    pub valid_from: DateTime<Utc>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum RedemptionOrderStatus {
+    /// Stock reserved, burn not sent yet.
+    Reserved,
+    /// Burn signed and sent; its outcome decides the order.
+    Burning,
+    /// Burn confirmed and code issued.
+    Fulfilled,
+    /// Burn failed or expired. Stock was released and no points were taken.
+    Failed,
+    /// Refund requested. Once its mint is signed and sent, the mint's outcome
+    /// decides the refund.
+    Refunding,
+    /// Points minted back, code voided and stock released.
+    Refunded,
+    /// Minting the points back failed and no refund mint can land anymore; the
+    /// refund can be retried.
+    RefundFailed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct RedemptionOrder {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub item_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// Wallet the points are burned from, and minted back to on refunds.
+    pub wallet_pubkey: String,
+    pub points: i64,
+    pub status: RedemptionOrderStatus,
+    pub code: Option<String>,
+    pub burn_signature: Option<String>,
+    #[serde(skip_serializing)]
+    pub burn_recent_blockhash: Option<String>,
+    pub refund_signature: Option<String>,
+    #[serde(skip_serializing)]
+    pub refund_recent_blockhash: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum RedemptionStep {
+    Reserved,
+    BurnSubmitted,
+    BurnConfirmed,
+    BurnFailed,
+    /// Points of a deferred program were debited in the ledger instead of burned.
+    LedgerDebited,
+    RefundRequested,
+    RefundSubmitted,
+    RefundMinted,
+    RefundCredited,
+    RefundFailed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct RedemptionOrderEvent {
+    pub id: uuid::Uuid,
+    pub order_id: uuid::Uuid,
+    pub step: RedemptionStep,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RedemptionOrderResponse {
+    #[serde(flatten)]
+    pub order: RedemptionOrder,
+    pub events: Vec<RedemptionOrderEvent>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRewardItemRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub point_price: i64,
+    pub stock: Option<i32>,
+    /// Defaults to now.
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PlaceRedemptionOrderRequest {
+    pub item_id: uuid::Uuid,
+}
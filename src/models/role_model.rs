@@ -0,0 +1,94 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+use super::{api_key_model::ApiKey, auth_model::AuthenticatedUser};
+
+/// Mirrors the `permissions` table. Routes require one permission each.
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "text")]
+pub enum Permission {
+    #[serde(rename = "users:read")]
+    #[sqlx(rename = "users:read")]
+    UsersRead,
+    #[serde(rename = "users:write")]
+    #[sqlx(rename = "users:write")]
+    UsersWrite,
+    #[serde(rename = "tokens:read")]
+    #[sqlx(rename = "tokens:read")]
+    TokensRead,
+    /// Minting to wallets and users, including batches, airdrops and distributions.
+    #[serde(rename = "tokens:mint")]
+    #[sqlx(rename = "tokens:mint")]
+    TokensMint,
+    /// Building and submitting transactions whose fees the service pays.
+    #[serde(rename = "tokens:sponsor")]
+    #[sqlx(rename = "tokens:sponsor")]
+    TokensSponsor,
+    #[serde(rename = "tokens:create_mint")]
+    #[sqlx(rename = "tokens:create_mint")]
+    TokensCreateMint,
+    #[serde(rename = "tokens:freeze")]
+    #[sqlx(rename = "tokens:freeze")]
+    TokensFreeze,
+    /// Managing merchants, loyalty programs and their earning rules.
+    #[serde(rename = "programs:manage")]
+    #[sqlx(rename = "programs:manage")]
+    ProgramsManage,
+    /// Awarding program points for purchases.
+    #[serde(rename = "programs:earn")]
+    #[sqlx(rename = "programs:earn")]
+    ProgramsEarn,
+    /// Grants every other permission, plus API key and role management.
+    #[serde(rename = "admin")]
+    #[sqlx(rename = "admin")]
+    Admin,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct Role {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Role {
+    pub fn grants(permissions: &[Permission], permission: Permission) -> bool {
+        permissions.contains(&permission) || permissions.contains(&Permission::Admin)
+    }
+}
+
+/// Gives a role to exactly one principal: an API key or an operator account.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct RoleAssignment {
+    pub id: uuid::Uuid,
+    pub role_id: uuid::Uuid,
+    pub api_key_id: Option<uuid::Uuid>,
+    pub user_id: Option<uuid::Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Whoever is behind a request. Operators are users with roles assigned, logged
+/// in through a magic link. Users without roles only reach their own routes.
+#[derive(Debug, Clone)]
+pub enum Principal {
+    ApiKey(ApiKey),
+    User(AuthenticatedUser),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<Permission>,
+}
+
+/// Exactly one of the fields is expected.
+#[derive(Deserialize, Debug)]
+pub struct AssignRoleRequest {
+    pub api_key_id: Option<uuid::Uuid>,
+    pub user_id: Option<uuid::Uuid>,
+}
@@ -0,0 +1,119 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum TierBasis {
+    /// Every point the user earned in the program, spent or not.
+    #[default]
+    LifetimeEarned,
+    /// Points the user holds now: the ledger balance of deferred programs, the
+    /// primary wallet's token balance otherwise.
+    CurrentBalance,
+}
+
+/// A tier users reach once their points meet `threshold`.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct ProgramTier {
+    pub id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub name: String,
+    pub threshold: i64,
+    pub badge_mint_pubkey: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+/// The tier a user was last computed to be in. `tier_id` is missing below the
+/// lowest threshold.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct UserTier {
+    pub user_id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub tier_id: Option<uuid::Uuid>,
+    pub points: i64,
+    pub computed_at: DateTime<Utc>,
+    /// When the user last moved to another tier.
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct UserTierSummary {
+    #[serde(skip)]
+    pub user_id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub program_name: String,
+    pub tier: String,
+    pub points: i64,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// A user who earned in a program, with what their tier can be computed from.
+#[derive(FromRow, Debug, Clone)]
+pub struct TierMember {
+    pub user_id: uuid::Uuid,
+    pub wallet_pubkey: Option<String>,
+    pub earned_points: i64,
+    pub ledger_points: i64,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum TierBadgeStatus {
+    /// Recorded, not minted and frozen yet.
+    Issuing,
+    /// Minted and frozen in the wallet.
+    Issued,
+    /// Issuing failed; the next refresh resumes it.
+    Failed,
+    /// Burned, or thawed when its mint holds the points of a program.
+    Revoked,
+    /// Tier lost, but the token stays frozen in a wallet the service does not
+    /// hold, so it could not be burned.
+    Stranded,
+}
+
+/// One badge token of a tier, frozen in the user's wallet so it cannot be transferred.
+#[derive(Serialize, FromRow, Deserialize, Debug, Clone)]
+pub struct TierBadge {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub program_id: uuid::Uuid,
+    pub tier_id: uuid::Uuid,
+    pub mint_pubkey: String,
+    pub wallet_pubkey: String,
+    pub status: TierBadgeStatus,
+    pub mint_signature: Option<String>,
+    pub freeze_signature: Option<String>,
+    pub revoke_signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TiersResponse {
+    pub program_id: uuid::Uuid,
+    pub basis: TierBasis,
+    pub tiers: Vec<ProgramTier>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TierDefinition {
+    pub name: String,
+    pub threshold: i64,
+    /// Registered mint whose tokens are issued as the tier's badge.
+    pub badge_mint_pubkey: Option<String>,
+}
+
+/// Replaces every tier of the program.
+#[derive(Deserialize, Debug)]
+pub struct SetTiersRequest {
+    #[serde(default)]
+    pub basis: TierBasis,
+    pub tiers: Vec<TierDefinition>,
+}
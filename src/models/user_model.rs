@@ -3,13 +3,22 @@ use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use chrono::{DateTime, Utc};
 
+use super::tier_model::UserTierSummary;
+
 #[derive(Serialize, FromRow, Deserialize, Debug)]
 pub struct User {
     pub id: uuid::Uuid,
     pub email: String,
     pub created_at: DateTime<Utc>,
+    /// Pubkey of the user's primary wallet.
     pub public_key: String,
-    pub secret_key: String,
+}
+
+impl User {
+    /// Emails are stored trimmed and lowercase, and unique in that form.
+    pub fn normalize_email(email: &str) -> String {
+        email.trim().to_lowercase()
+    }
 }
 
 #[derive(Serialize, FromRow, Deserialize, Debug)]
@@ -17,7 +26,11 @@ pub struct UserForResponse {
     pub id: uuid::Uuid,
     pub email: String,
     pub created_at: DateTime<Utc>,
-    pub public_key: String
+    pub public_key: String,
+    /// Programs the user has reached a tier in.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub tiers: Vec<UserTierSummary>
 }
 
 impl From<User> for UserForResponse {
@@ -27,6 +40,7 @@ impl From<User> for UserForResponse {
             email: value.email,
             created_at: value.created_at,
             public_key: value.public_key,
+            tiers: Vec::new(),
         }
     }
 }
@@ -34,5 +48,17 @@ impl From<User> for UserForResponse {
 #[derive(Deserialize, Debug)]
 pub struct CreateUserRequest {
     pub email: String,
+    /// Code of the user who referred this one.
+    pub referral_code: Option<String>,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_email_trims_and_lowercases() {
+        assert_eq!(User::normalize_email("  Ana.Perez@Example.COM \n"), "ana.perez@example.com");
+        assert_eq!(User::normalize_email("ana@example.com"), "ana@example.com");
+    }
+}
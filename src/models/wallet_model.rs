@@ -0,0 +1,196 @@
+
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use chrono::{DateTime, Utc};
+
+use crate::clients::solana_rpc_client::SweptBalance;
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct WalletChallenge {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub pubkey: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WalletChallengeResponse {
+    pub challenge_id: uuid::Uuid,
+    pub message: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<WalletChallenge> for WalletChallengeResponse {
+    fn from(value: WalletChallenge) -> Self {
+        Self {
+            challenge_id: value.id,
+            message: value.message,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum WalletKind {
+    /// Random keypair stored by the service, from before derivation.
+    Custodial,
+    /// Held by the user, who proved control of it by signing a challenge.
+    External,
+    /// Derived by the service from its master seed.
+    Derived,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct Wallet {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub pubkey: String,
+    pub kind: WalletKind,
+    pub label: Option<String>,
+    pub is_primary: bool,
+    #[serde(skip_serializing)]
+    pub derivation_index: Option<i32>,
+    #[serde(skip_serializing)]
+    pub secret_key: Option<String>,
+    /// Set once the secret key of a custodial or derived wallet has been exported.
+    pub exported_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+impl Wallet {
+    /// A new wallet under the service's master seed.
+    pub fn derived(
+        user_id: uuid::Uuid,
+        pubkey: String,
+        derivation_index: i32,
+        label: Option<String>,
+        is_primary: bool
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            pubkey,
+            kind: WalletKind::Derived,
+            label,
+            is_primary,
+            derivation_index: Some(derivation_index),
+            secret_key: None,
+            exported_at: None,
+            created_at: Utc::now(),
+            archived_at: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateWalletRequest {
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateWalletChallengeRequest {
+    pub pubkey: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyWalletChallengeRequest {
+    pub challenge_id: uuid::Uuid,
+    pub signature: String,
+    pub label: Option<String>,
+}
+
+/// A pending export, confirmed with a one-time code sent to the user's email.
+#[derive(FromRow, Debug)]
+pub struct WalletExport {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub pubkey: String,
+    pub code_hash: String,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WalletExportResponse {
+    pub export_id: uuid::Uuid,
+    pub pubkey: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<WalletExport> for WalletExportResponse {
+    fn from(value: WalletExport) -> Self {
+        Self {
+            export_id: value.id,
+            pubkey: value.pubkey,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
+/// How the exported secret key is encoded. Custodial keys are generated from
+/// random bytes rather than from a seed phrase, so there is no BIP39 mnemonic to
+/// export; wallets such as Phantom or Solflare import either format.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletExportFormat {
+    /// The 64-byte JSON array written by `solana-keygen`.
+    SolanaCliJson,
+    Base58,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConfirmWalletExportRequest {
+    pub code: String,
+    pub format: WalletExportFormat,
+    /// Moves every balance to a new derived wallet once the key is handed out, and
+    /// archives the exported one.
+    #[serde(default)]
+    pub sweep: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExportedWalletResponse {
+    pub pubkey: String,
+    pub secret_key: String,
+    pub exported_at: DateTime<Utc>,
+    pub sweep: Option<WalletSweepResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WalletSweepResponse {
+    /// Missing when the new wallet could not be created.
+    pub new_pubkey: Option<String>,
+    pub balances: Vec<SweptBalance>,
+    /// Set when the sweep failed. Some balances may have moved before it stopped.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum WalletExportEvent {
+    Requested,
+    VerificationFailed,
+    Exported,
+    Swept,
+    SweepFailed,
+}
+
+#[derive(Serialize, FromRow, Deserialize, Debug)]
+pub struct WalletExportAudit {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub export_id: Option<uuid::Uuid>,
+    pub pubkey: String,
+    pub event: WalletExportEvent,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,125 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::airdrop_model::{AirdropUpload, AirdropUploadRow, AirdropUploadStatus};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct AirdropRepository {
+    pool: PgPool
+}
+
+impl AirdropRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_upload(
+        &self,
+        id: &Uuid,
+        date: &DateTime<Utc>,
+        mint_pubkey: &str,
+        file_name: Option<&str>,
+        rows: &[AirdropUploadRow]
+    ) -> Result<AirdropUpload, AirdropRepositoryError> {
+        let row_numbers: Vec<i32> = rows.iter().map(|row| row.row_number).collect();
+        let recipients: Vec<String> = rows.iter().map(|row| row.recipient.clone()).collect();
+        let receiver_pubkeys: Vec<Option<String>> = rows.iter().map(|row| row.receiver_pubkey.clone()).collect();
+        let amounts: Vec<Option<i64>> = rows.iter().map(|row| row.amount).collect();
+        let errors: Vec<Option<String>> = rows.iter().map(|row| row.error.clone()).collect();
+
+        let mut transaction = self.pool.begin().await?;
+
+        let upload = sqlx::query_as::<_, AirdropUpload>("INSERT INTO airdrop_uploads (id, mint_pubkey, file_name, status, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *")
+            .bind(id)
+            .bind(mint_pubkey)
+            .bind(file_name)
+            .bind(AirdropUploadStatus::Previewed)
+            .bind(date)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        sqlx::query("INSERT INTO airdrop_upload_rows (upload_id, row_number, recipient, receiver_pubkey, amount, error) SELECT $1, * FROM UNNEST($2::INTEGER[], $3::TEXT[], $4::TEXT[], $5::BIGINT[], $6::TEXT[])")
+            .bind(id)
+            .bind(&row_numbers)
+            .bind(&recipients)
+            .bind(&receiver_pubkeys)
+            .bind(&amounts)
+            .bind(&errors)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(upload)
+    }
+
+    pub async fn fetch_upload(&self, id: &Uuid) -> Result<AirdropUpload, AirdropRepositoryError> {
+        match sqlx::query_as::<_, AirdropUpload>("SELECT * FROM airdrop_uploads WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(upload) => Ok(upload),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(AirdropRepositoryError::RowNotFound),
+                e => Err(AirdropRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_upload_rows(&self, upload_id: &Uuid) -> Result<Vec<AirdropUploadRow>, AirdropRepositoryError> {
+        let rows = sqlx::query_as::<_, AirdropUploadRow>("SELECT * FROM airdrop_upload_rows WHERE upload_id = $1 ORDER BY row_number")
+            .bind(upload_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows)
+    }
+
+    /// Moves a previewed upload to `confirmed`. Returns `None` if it was already confirmed.
+    pub async fn confirm_upload(
+        &self,
+        id: &Uuid,
+        date: &DateTime<Utc>
+    ) -> Result<Option<AirdropUpload>, AirdropRepositoryError> {
+        let upload = sqlx::query_as::<_, AirdropUpload>("UPDATE airdrop_uploads SET status = $2, confirmed_at = $3 WHERE id = $1 AND status = $4 RETURNING *")
+            .bind(id)
+            .bind(AirdropUploadStatus::Confirmed)
+            .bind(date)
+            .bind(AirdropUploadStatus::Previewed)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(upload)
+    }
+
+    pub async fn revert_confirmation(&self, id: &Uuid) -> Result<(), AirdropRepositoryError> {
+        sqlx::query("UPDATE airdrop_uploads SET status = $2, confirmed_at = NULL WHERE id = $1 AND batch_id IS NULL")
+            .bind(id)
+            .bind(AirdropUploadStatus::Previewed)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_upload_batch(&self, id: &Uuid, batch_id: &Uuid) -> Result<(), AirdropRepositoryError> {
+        sqlx::query("UPDATE airdrop_uploads SET batch_id = $2 WHERE id = $1")
+            .bind(id)
+            .bind(batch_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AirdropRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Airdrop upload was not found")]
+    RowNotFound
+}
@@ -0,0 +1,109 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::api_key_model::ApiKey;
+use chrono::{DateTime, Duration, Utc};
+
+/// How stale `last_used_at` may get before a request updates it again.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+#[derive(Clone)]
+pub struct ApiKeyRepository {
+    pool: PgPool
+}
+
+impl ApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Inserts the key and assigns it `role_ids`, unless a key with the same hash
+    /// exists, which keeps bootstrapping from secrets idempotent across restarts.
+    pub async fn create_api_key(&self, api_key: &ApiKey, role_ids: &[Uuid]) -> Result<Option<ApiKey>, ApiKeyRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        let created = sqlx::query_as::<_, ApiKey>("INSERT INTO api_keys (id, name, prefix, key_hash, created_at) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (key_hash) DO NOTHING RETURNING *")
+            .bind(api_key.id)
+            .bind(&api_key.name)
+            .bind(&api_key.prefix)
+            .bind(&api_key.key_hash)
+            .bind(api_key.created_at)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        if created.is_some() {
+            for role_id in role_ids {
+                sqlx::query("INSERT INTO role_assignments (id, role_id, api_key_id, created_at) VALUES ($1, $2, $3, $4)")
+                    .bind(Uuid::new_v4())
+                    .bind(role_id)
+                    .bind(api_key.id)
+                    .bind(api_key.created_at)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    pub async fn fetch_api_keys(&self) -> Result<Vec<ApiKey>, ApiKeyRepositoryError> {
+        let api_keys = sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(api_keys)
+    }
+
+    pub async fn fetch_active_api_key(&self, key_hash: &str) -> Result<ApiKey, ApiKeyRepositoryError> {
+        match sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL")
+            .bind(key_hash)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(api_key) => Ok(api_key),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ApiKeyRepositoryError::RowNotFound),
+                e => Err(ApiKeyRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn revoke_api_key(&self, id: &Uuid, date: &DateTime<Utc>) -> Result<ApiKey, ApiKeyRepositoryError> {
+        match sqlx::query_as::<_, ApiKey>("UPDATE api_keys SET revoked_at = COALESCE(revoked_at, $2) WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(date)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(api_key) => Ok(api_key),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ApiKeyRepositoryError::RowNotFound),
+                e => Err(ApiKeyRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Records a use of the key, at most once per `LAST_USED_RESOLUTION_SECONDS`
+    /// so busy keys do not write on every request.
+    pub async fn touch_api_key(&self, id: &Uuid, date: &DateTime<Utc>) -> Result<(), ApiKeyRepositoryError> {
+        sqlx::query("UPDATE api_keys SET last_used_at = $2 WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < $3)")
+            .bind(id)
+            .bind(date)
+            .bind(*date - Duration::seconds(LAST_USED_RESOLUTION_SECONDS))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ApiKeyRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("API key was not found")]
+    RowNotFound
+}
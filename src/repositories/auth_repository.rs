@@ -0,0 +1,122 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::auth_model::{MagicLink, RefreshToken};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct AuthRepository {
+    pool: PgPool
+}
+
+impl AuthRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_magic_link(&self, magic_link: &MagicLink) -> Result<(), AuthRepositoryError> {
+        sqlx::query("INSERT INTO magic_links (id, user_id, token_hash, created_at, expires_at) VALUES ($1, $2, $3, $4, $5)")
+            .bind(magic_link.id)
+            .bind(magic_link.user_id)
+            .bind(&magic_link.token_hash)
+            .bind(magic_link.created_at)
+            .bind(magic_link.expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks the link as used, failing when it is unknown, used or expired.
+    pub async fn consume_magic_link(
+        &self,
+        token_hash: &str,
+        date: &DateTime<Utc>
+    ) -> Result<MagicLink, AuthRepositoryError> {
+        sqlx::query_as::<_, MagicLink>("UPDATE magic_links SET used_at = $2 WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2 RETURNING *")
+            .bind(token_hash)
+            .bind(date)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AuthRepositoryError::TokenUnavailable)
+    }
+
+    pub async fn create_refresh_token(&self, refresh_token: &RefreshToken) -> Result<(), AuthRepositoryError> {
+        sqlx::query("INSERT INTO refresh_tokens (id, user_id, token_hash, created_at, expires_at) VALUES ($1, $2, $3, $4, $5)")
+            .bind(refresh_token.id)
+            .bind(refresh_token.user_id)
+            .bind(&refresh_token.token_hash)
+            .bind(refresh_token.created_at)
+            .bind(refresh_token.expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revokes the presented token and stores its replacement in one transaction.
+    /// Presenting an already revoked token means it leaked, so every session of
+    /// its user is revoked too.
+    pub async fn rotate_refresh_token(
+        &self,
+        token_hash: &str,
+        replacement_hash: &str,
+        replacement_expires_at: &DateTime<Utc>,
+        date: &DateTime<Utc>
+    ) -> Result<RefreshToken, AuthRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let rotated = sqlx::query_as::<_, RefreshToken>("UPDATE refresh_tokens SET revoked_at = $2 WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > $2 RETURNING *")
+            .bind(token_hash)
+            .bind(date)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        let rotated = match rotated {
+            Some(rotated) => rotated,
+            None => {
+                transaction.rollback().await?;
+
+                sqlx::query("UPDATE refresh_tokens SET revoked_at = $2 WHERE revoked_at IS NULL AND user_id = (SELECT user_id FROM refresh_tokens WHERE token_hash = $1 AND revoked_at IS NOT NULL)")
+                    .bind(token_hash)
+                    .bind(date)
+                    .execute(&self.pool)
+                    .await?;
+
+                return Err(AuthRepositoryError::TokenUnavailable);
+            }
+        };
+
+        let replacement = sqlx::query_as::<_, RefreshToken>("INSERT INTO refresh_tokens (id, user_id, token_hash, created_at, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING *")
+            .bind(Uuid::new_v4())
+            .bind(rotated.user_id)
+            .bind(replacement_hash)
+            .bind(date)
+            .bind(replacement_expires_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(replacement)
+    }
+
+    pub async fn revoke_refresh_token(&self, token_hash: &str, date: &DateTime<Utc>) -> Result<(), AuthRepositoryError> {
+        sqlx::query("UPDATE refresh_tokens SET revoked_at = $2 WHERE token_hash = $1 AND revoked_at IS NULL")
+            .bind(token_hash)
+            .bind(date)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AuthRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Token is invalid, used or expired")]
+    TokenUnavailable
+}
@@ -0,0 +1,115 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::{
+    campaign_model::{Campaign, CampaignReport, CampaignStatus},
+    program_model::PointEarningStatus
+};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct CampaignRepository {
+    pool: PgPool
+}
+
+impl CampaignRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_campaign(&self, campaign: &Campaign) -> Result<Campaign, CampaignRepositoryError> {
+        let campaign = sqlx::query_as::<_, Campaign>("INSERT INTO campaigns (id, program_id, name, kind, multiplier_bps, bonus_points, category, min_purchase_amount, budget_points, status, starts_at, ends_at, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $13) RETURNING *")
+            .bind(campaign.id)
+            .bind(campaign.program_id)
+            .bind(&campaign.name)
+            .bind(campaign.kind)
+            .bind(campaign.multiplier_bps)
+            .bind(campaign.bonus_points)
+            .bind(&campaign.category)
+            .bind(campaign.min_purchase_amount)
+            .bind(campaign.budget_points)
+            .bind(campaign.status)
+            .bind(campaign.starts_at)
+            .bind(campaign.ends_at)
+            .bind(campaign.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(campaign)
+    }
+
+    pub async fn fetch_campaign(&self, id: &Uuid) -> Result<Campaign, CampaignRepositoryError> {
+        match sqlx::query_as::<_, Campaign>("SELECT * FROM campaigns WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(campaign) => Ok(campaign),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(CampaignRepositoryError::RowNotFound),
+                e => Err(CampaignRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_campaigns(&self, program_id: &Uuid) -> Result<Vec<Campaign>, CampaignRepositoryError> {
+        let campaigns = sqlx::query_as::<_, Campaign>("SELECT * FROM campaigns WHERE program_id = $1 ORDER BY starts_at DESC")
+            .bind(program_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(campaigns)
+    }
+
+    /// Active campaigns of the program running at `date`.
+    pub async fn fetch_running_campaigns(&self, program_id: &Uuid, date: &DateTime<Utc>) -> Result<Vec<Campaign>, CampaignRepositoryError> {
+        let campaigns = sqlx::query_as::<_, Campaign>("SELECT * FROM campaigns WHERE program_id = $1 AND status = $2 AND starts_at <= $3 AND ends_at > $3 ORDER BY starts_at")
+            .bind(program_id)
+            .bind(CampaignStatus::Active)
+            .bind(date)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(campaigns)
+    }
+
+    /// Moves the campaign to `to` only when its status is `from`. Fails with
+    /// `InvalidStatus` otherwise.
+    pub async fn set_status(
+        &self,
+        id: &Uuid,
+        from: CampaignStatus,
+        to: CampaignStatus,
+        date: &DateTime<Utc>
+    ) -> Result<Campaign, CampaignRepositoryError> {
+        sqlx::query_as::<_, Campaign>("UPDATE campaigns SET status = $2, updated_at = $3 WHERE id = $1 AND status = $4 RETURNING *")
+            .bind(id)
+            .bind(to)
+            .bind(date)
+            .bind(from)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(CampaignRepositoryError::InvalidStatus)
+    }
+
+    pub async fn fetch_reports(&self, program_id: &Uuid) -> Result<Vec<CampaignReport>, CampaignRepositoryError> {
+        let reports = sqlx::query_as::<_, CampaignReport>("SELECT c.id AS campaign_id, c.name, c.kind, c.status, c.starts_at, c.ends_at, c.budget_points, c.issued_points, COALESCE(SUM(a.points) FILTER (WHERE e.status = ANY($2)), 0)::BIGINT AS settled_points, COUNT(a.id) AS awards, COUNT(DISTINCT a.user_id) AS users FROM campaigns c LEFT JOIN campaign_awards a ON a.campaign_id = c.id LEFT JOIN point_earnings e ON e.id = a.earning_id WHERE c.program_id = $1 GROUP BY c.id ORDER BY c.starts_at DESC")
+            .bind(program_id)
+            .bind([PointEarningStatus::Completed, PointEarningStatus::Credited])
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(reports)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CampaignRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Campaign is not in a status that allows this")]
+    InvalidStatus
+}
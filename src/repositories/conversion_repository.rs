@@ -0,0 +1,282 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::{
+    models::conversion_model::{ExchangeRate, ExchangeRateSnapshot, PointConversion, PointConversionStatus},
+    repositories::point_lot_repository::{consume_lots, release_lots, LotConsumer}
+};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct ConversionRepository {
+    pool: PgPool
+}
+
+impl ConversionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates or updates the rate of the program pair and records the new
+    /// version as a snapshot in the same transaction.
+    pub async fn upsert_rate(&self, rate: &ExchangeRate) -> Result<(ExchangeRate, ExchangeRateSnapshot), ConversionRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let rate = sqlx::query_as::<_, ExchangeRate>("INSERT INTO exchange_rates (id, source_program_id, target_program_id, source_points, target_points, min_source_points, enabled, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8) ON CONFLICT (source_program_id, target_program_id) DO UPDATE SET source_points = EXCLUDED.source_points, target_points = EXCLUDED.target_points, min_source_points = EXCLUDED.min_source_points, enabled = EXCLUDED.enabled, updated_at = EXCLUDED.updated_at RETURNING *")
+            .bind(rate.id)
+            .bind(rate.source_program_id)
+            .bind(rate.target_program_id)
+            .bind(rate.source_points)
+            .bind(rate.target_points)
+            .bind(rate.min_source_points)
+            .bind(rate.enabled)
+            .bind(rate.updated_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        let snapshot = sqlx::query_as::<_, ExchangeRateSnapshot>("INSERT INTO exchange_rate_snapshots (id, rate_id, source_points, target_points, min_source_points, enabled, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *")
+            .bind(Uuid::new_v4())
+            .bind(rate.id)
+            .bind(rate.source_points)
+            .bind(rate.target_points)
+            .bind(rate.min_source_points)
+            .bind(rate.enabled)
+            .bind(rate.updated_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok((rate, snapshot))
+    }
+
+    /// Rates converting points of the program into other programs.
+    pub async fn fetch_rates(&self, source_program_id: &Uuid) -> Result<Vec<ExchangeRate>, ConversionRepositoryError> {
+        let rates = sqlx::query_as::<_, ExchangeRate>("SELECT * FROM exchange_rates WHERE source_program_id = $1 ORDER BY created_at")
+            .bind(source_program_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rates)
+    }
+
+    /// The current version of the rate of the program pair.
+    pub async fn fetch_current_snapshot(&self, source_program_id: &Uuid, target_program_id: &Uuid) -> Result<ExchangeRateSnapshot, ConversionRepositoryError> {
+        match sqlx::query_as::<_, ExchangeRateSnapshot>("SELECT s.* FROM exchange_rate_snapshots s JOIN exchange_rates r ON r.id = s.rate_id WHERE r.source_program_id = $1 AND r.target_program_id = $2 ORDER BY s.created_at DESC, s.id LIMIT 1")
+            .bind(source_program_id)
+            .bind(target_program_id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(snapshot) => Ok(snapshot),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ConversionRepositoryError::RowNotFound),
+                e => Err(ConversionRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_snapshot(&self, id: &Uuid) -> Result<ExchangeRateSnapshot, ConversionRepositoryError> {
+        match sqlx::query_as::<_, ExchangeRateSnapshot>("SELECT * FROM exchange_rate_snapshots WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(snapshot) => Ok(snapshot),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ConversionRepositoryError::RowNotFound),
+                e => Err(ConversionRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_snapshots(&self, rate_id: &Uuid) -> Result<Vec<ExchangeRateSnapshot>, ConversionRepositoryError> {
+        let snapshots = sqlx::query_as::<_, ExchangeRateSnapshot>("SELECT * FROM exchange_rate_snapshots WHERE rate_id = $1 ORDER BY created_at DESC")
+            .bind(rate_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(snapshots)
+    }
+
+    /// Records the conversion and consumes the user's source lots oldest expiry
+    /// first in one transaction.
+    pub async fn reserve_conversion(&self, conversion: &PointConversion) -> Result<PointConversion, ConversionRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let conversion = sqlx::query_as::<_, PointConversion>("INSERT INTO point_conversions (id, user_id, source_program_id, target_program_id, rate_snapshot_id, source_points, target_points, wallet_pubkey, status, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10) RETURNING *")
+            .bind(conversion.id)
+            .bind(conversion.user_id)
+            .bind(conversion.source_program_id)
+            .bind(conversion.target_program_id)
+            .bind(conversion.rate_snapshot_id)
+            .bind(conversion.source_points)
+            .bind(conversion.target_points)
+            .bind(&conversion.wallet_pubkey)
+            .bind(PointConversionStatus::Reserved)
+            .bind(conversion.created_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        consume_lots(
+            &mut transaction,
+            LotConsumer::Conversion(conversion.id),
+            &conversion.user_id,
+            &conversion.source_program_id,
+            conversion.source_points,
+            &conversion.created_at
+        ).await?;
+
+        transaction.commit().await?;
+
+        Ok(conversion)
+    }
+
+    /// Records the signed transaction before it is sent, so its outcome can
+    /// always be looked up afterwards.
+    pub async fn submit_conversion(
+        &self,
+        id: &Uuid,
+        signature: &str,
+        recent_blockhash: &str,
+        date: &DateTime<Utc>
+    ) -> Result<PointConversion, ConversionRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let conversion = Self::transition(
+            &mut transaction,
+            id,
+            PointConversionStatus::Reserved,
+            PointConversionStatus::Submitted,
+            Some(signature),
+            Some(recent_blockhash),
+            None,
+            date
+        ).await?;
+
+        transaction.commit().await?;
+
+        Ok(conversion)
+    }
+
+    /// Completes a submitted conversion and records its target points as a lot,
+    /// expiring at `expires_at`.
+    pub async fn complete_conversion(
+        &self,
+        id: &Uuid,
+        expires_at: Option<DateTime<Utc>>,
+        date: &DateTime<Utc>
+    ) -> Result<PointConversion, ConversionRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let conversion = Self::transition(
+            &mut transaction,
+            id,
+            PointConversionStatus::Submitted,
+            PointConversionStatus::Completed,
+            None,
+            None,
+            None,
+            date
+        ).await?;
+
+        sqlx::query("INSERT INTO point_lots (id, program_id, user_id, conversion_id, points, remaining, wallet_pubkey, earned_at, expires_at, created_at) VALUES ($1, $2, $3, $4, $5, $5, $6, $7, $8, $7) ON CONFLICT (conversion_id) DO NOTHING")
+            .bind(Uuid::new_v4())
+            .bind(conversion.target_program_id)
+            .bind(conversion.user_id)
+            .bind(conversion.id)
+            .bind(conversion.target_points)
+            .bind(&conversion.wallet_pubkey)
+            .bind(date)
+            .bind(expires_at)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(conversion)
+    }
+
+    /// Fails a conversion whose transaction never landed and gives the consumed
+    /// source lots back.
+    pub async fn fail_conversion(&self, id: &Uuid, from: PointConversionStatus, error: &str, date: &DateTime<Utc>) -> Result<PointConversion, ConversionRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let conversion = Self::transition(
+            &mut transaction,
+            id,
+            from,
+            PointConversionStatus::Failed,
+            None,
+            None,
+            Some(error),
+            date
+        ).await?;
+
+        release_lots(&mut transaction, LotConsumer::Conversion(conversion.id)).await?;
+
+        transaction.commit().await?;
+
+        Ok(conversion)
+    }
+
+    pub async fn fetch_conversion(&self, id: &Uuid) -> Result<PointConversion, ConversionRepositoryError> {
+        match sqlx::query_as::<_, PointConversion>("SELECT * FROM point_conversions WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(conversion) => Ok(conversion),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ConversionRepositoryError::RowNotFound),
+                e => Err(ConversionRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_user_conversions(&self, user_id: &Uuid) -> Result<Vec<PointConversion>, ConversionRepositoryError> {
+        let conversions = sqlx::query_as::<_, PointConversion>("SELECT * FROM point_conversions WHERE user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(conversions)
+    }
+
+    /// Updates the conversion only when its status is `from`. Fails with
+    /// `InvalidStatus` otherwise.
+    #[allow(clippy::too_many_arguments)]
+    async fn transition(
+        transaction: &mut Transaction<'_, Postgres>,
+        id: &Uuid,
+        from: PointConversionStatus,
+        to: PointConversionStatus,
+        signature: Option<&str>,
+        recent_blockhash: Option<&str>,
+        error: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<PointConversion, ConversionRepositoryError> {
+        sqlx::query_as::<_, PointConversion>("UPDATE point_conversions SET status = $2, signature = COALESCE($3, signature), recent_blockhash = COALESCE($4, recent_blockhash), error = $5, updated_at = $6 WHERE id = $1 AND status = $7 RETURNING *")
+            .bind(id)
+            .bind(to)
+            .bind(signature)
+            .bind(recent_blockhash)
+            .bind(error)
+            .bind(date)
+            .bind(from)
+            .fetch_optional(&mut **transaction)
+            .await?
+            .ok_or(ConversionRepositoryError::InvalidStatus)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConversionRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Conversion is not in a status that allows this")]
+    InvalidStatus
+}
@@ -0,0 +1,179 @@
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::distribution_model::{Distribution, DistributionLeaf, DistributionLeafStatus};
+use chrono::{DateTime, Utc};
+
+/// Keeps each insert well below the Postgres limit of 65535 bind parameters.
+const LEAF_INSERT_CHUNK: usize = 1_000;
+
+#[derive(Clone)]
+pub struct DistributionRepository {
+    pool: PgPool
+}
+
+impl DistributionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_distribution(
+        &self,
+        distribution: &Distribution,
+        leaves: &[DistributionLeaf]
+    ) -> Result<Distribution, DistributionRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let created = sqlx::query_as::<_, Distribution>("INSERT INTO distributions (id, mint_pubkey, merkle_root, leaf_count, total_amount, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *")
+            .bind(distribution.id)
+            .bind(&distribution.mint_pubkey)
+            .bind(&distribution.merkle_root)
+            .bind(distribution.leaf_count)
+            .bind(distribution.total_amount)
+            .bind(distribution.created_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        for chunk in leaves.chunks(LEAF_INSERT_CHUNK) {
+            let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(
+                "INSERT INTO distribution_leaves (distribution_id, leaf_index, wallet_pubkey, amount, proof, status) "
+            );
+
+            builder.push_values(chunk, |mut row, leaf| {
+                row.push_bind(leaf.distribution_id)
+                    .push_bind(leaf.leaf_index)
+                    .push_bind(&leaf.wallet_pubkey)
+                    .push_bind(leaf.amount)
+                    .push_bind(&leaf.proof)
+                    .push_bind(leaf.status);
+            });
+
+            builder.build().execute(&mut *transaction).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(created)
+    }
+
+    pub async fn fetch_distribution(&self, id: &Uuid) -> Result<Distribution, DistributionRepositoryError> {
+        match sqlx::query_as::<_, Distribution>("SELECT * FROM distributions WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(distribution) => Ok(distribution),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(DistributionRepositoryError::RowNotFound),
+                e => Err(DistributionRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_leaf(
+        &self,
+        distribution_id: &Uuid,
+        wallet_pubkey: &str
+    ) -> Result<DistributionLeaf, DistributionRepositoryError> {
+        match sqlx::query_as::<_, DistributionLeaf>("SELECT * FROM distribution_leaves WHERE distribution_id = $1 AND wallet_pubkey = $2")
+            .bind(distribution_id)
+            .bind(wallet_pubkey)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(leaf) => Ok(leaf),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(DistributionRepositoryError::RowNotFound),
+                e => Err(DistributionRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Reserves an unclaimed leaf. Returns `None` when it is already being claimed or
+    /// was claimed, which is what prevents double claims.
+    pub async fn start_claim(
+        &self,
+        distribution_id: &Uuid,
+        wallet_pubkey: &str,
+        amount: i64,
+        date: &DateTime<Utc>
+    ) -> Result<Option<DistributionLeaf>, DistributionRepositoryError> {
+        let leaf = sqlx::query_as::<_, DistributionLeaf>("UPDATE distribution_leaves SET status = $4, claimed_at = $5 WHERE distribution_id = $1 AND wallet_pubkey = $2 AND amount = $3 AND status = $6 RETURNING *")
+            .bind(distribution_id)
+            .bind(wallet_pubkey)
+            .bind(amount)
+            .bind(DistributionLeafStatus::Claiming)
+            .bind(date)
+            .bind(DistributionLeafStatus::Unclaimed)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(leaf)
+    }
+
+    /// Records the mint of a reserved leaf before it is sent, so the claim can be
+    /// settled from it. Returns `None` when another claim recorded its mint first.
+    pub async fn submit_claim(
+        &self,
+        distribution_id: &Uuid,
+        wallet_pubkey: &str,
+        signature: &str,
+        recent_blockhash: &str
+    ) -> Result<Option<DistributionLeaf>, DistributionRepositoryError> {
+        let leaf = sqlx::query_as::<_, DistributionLeaf>("UPDATE distribution_leaves SET signature = $3, recent_blockhash = $4 WHERE distribution_id = $1 AND wallet_pubkey = $2 AND status = $5 AND signature IS NULL RETURNING *")
+            .bind(distribution_id)
+            .bind(wallet_pubkey)
+            .bind(signature)
+            .bind(recent_blockhash)
+            .bind(DistributionLeafStatus::Claiming)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(leaf)
+    }
+
+    pub async fn finish_claim(
+        &self,
+        distribution_id: &Uuid,
+        wallet_pubkey: &str
+    ) -> Result<Option<DistributionLeaf>, DistributionRepositoryError> {
+        let leaf = sqlx::query_as::<_, DistributionLeaf>("UPDATE distribution_leaves SET status = $3 WHERE distribution_id = $1 AND wallet_pubkey = $2 AND status = $4 RETURNING *")
+            .bind(distribution_id)
+            .bind(wallet_pubkey)
+            .bind(DistributionLeafStatus::Claimed)
+            .bind(DistributionLeafStatus::Claiming)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(leaf)
+    }
+
+    /// Makes a reserved leaf claimable again. Only releases the claim whose mint is
+    /// `signature`, so a claim that recorded another mint meanwhile is left alone.
+    pub async fn release_claim(
+        &self,
+        distribution_id: &Uuid,
+        wallet_pubkey: &str,
+        signature: Option<&str>
+    ) -> Result<Option<DistributionLeaf>, DistributionRepositoryError> {
+        let leaf = sqlx::query_as::<_, DistributionLeaf>("UPDATE distribution_leaves SET status = $3, signature = NULL, recent_blockhash = NULL, claimed_at = NULL WHERE distribution_id = $1 AND wallet_pubkey = $2 AND status = $4 AND signature IS NOT DISTINCT FROM $5 RETURNING *")
+            .bind(distribution_id)
+            .bind(wallet_pubkey)
+            .bind(DistributionLeafStatus::Unclaimed)
+            .bind(DistributionLeafStatus::Claiming)
+            .bind(signature)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(leaf)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DistributionRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Distribution was not found")]
+    RowNotFound
+}
@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::expiration_model::{ExpiringPoints, PointExpiration, PointExpirationStatus, PointLot};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct ExpirationRepository {
+    pool: PgPool
+}
+
+impl ExpirationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Consumes every lot expired at `date` and groups their points into one
+    /// pending expiration per user, program and wallet the lots were minted to. Lots locked by a concurrent run
+    /// or redemption are left for the next run.
+    pub async fn claim_expired_lots(&self, date: &DateTime<Utc>) -> Result<Vec<PointExpiration>, ExpirationRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let lots = sqlx::query_as::<_, PointLot>("SELECT * FROM point_lots WHERE remaining > 0 AND expires_at <= $1 ORDER BY user_id, program_id, wallet_pubkey FOR UPDATE SKIP LOCKED")
+            .bind(date)
+            .fetch_all(&mut *transaction)
+            .await?;
+
+        let mut lots_by_owner: BTreeMap<(Uuid, Uuid, Option<String>), Vec<PointLot>> = BTreeMap::new();
+
+        for lot in lots {
+            lots_by_owner.entry((lot.user_id, lot.program_id, lot.wallet_pubkey.clone())).or_default().push(lot);
+        }
+
+        let mut expirations = Vec::with_capacity(lots_by_owner.len());
+
+        for ((user_id, program_id, wallet_pubkey), lots) in lots_by_owner {
+            let expiration = sqlx::query_as::<_, PointExpiration>("INSERT INTO point_expirations (id, program_id, user_id, points, status, wallet_pubkey, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $7) RETURNING *")
+                .bind(Uuid::new_v4())
+                .bind(program_id)
+                .bind(user_id)
+                .bind(lots.iter().map(|lot| lot.remaining).sum::<i64>())
+                .bind(PointExpirationStatus::Pending)
+                .bind(wallet_pubkey)
+                .bind(date)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+            for lot in lots {
+                sqlx::query("UPDATE point_lots SET remaining = 0 WHERE id = $1")
+                    .bind(lot.id)
+                    .execute(&mut *transaction)
+                    .await?;
+
+                sqlx::query("INSERT INTO point_lot_consumptions (id, lot_id, expiration_id, points, created_at) VALUES ($1, $2, $3, $4, $5)")
+                    .bind(Uuid::new_v4())
+                    .bind(lot.id)
+                    .bind(expiration.id)
+                    .bind(lot.remaining)
+                    .bind(date)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+
+            expirations.push(expiration);
+        }
+
+        transaction.commit().await?;
+
+        Ok(expirations)
+    }
+
+    /// Expirations whose burn was not sent or not settled yet, and settled ones
+    /// whose user was not notified.
+    pub async fn fetch_unsettled_expirations(&self) -> Result<Vec<PointExpiration>, ExpirationRepositoryError> {
+        let expirations = sqlx::query_as::<_, PointExpiration>("SELECT * FROM point_expirations WHERE status = ANY($1) OR (status = ANY($2) AND notified_at IS NULL) ORDER BY created_at")
+            .bind([PointExpirationStatus::Pending, PointExpirationStatus::Burning])
+            .bind([PointExpirationStatus::Completed, PointExpirationStatus::Skipped])
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(expirations)
+    }
+
+    pub async fn fetch_expirations(&self, status: Option<PointExpirationStatus>) -> Result<Vec<PointExpiration>, ExpirationRepositoryError> {
+        let expirations = sqlx::query_as::<_, PointExpiration>("SELECT * FROM point_expirations WHERE $1::TEXT IS NULL OR status = $1 ORDER BY created_at DESC LIMIT 100")
+            .bind(status)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(expirations)
+    }
+
+    pub async fn fetch_expiration(&self, id: &Uuid) -> Result<PointExpiration, ExpirationRepositoryError> {
+        match sqlx::query_as::<_, PointExpiration>("SELECT * FROM point_expirations WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(expiration) => Ok(expiration),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ExpirationRepositoryError::RowNotFound),
+                e => Err(ExpirationRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Records the signed burn before it is sent, so its outcome can always be
+    /// looked up afterwards.
+    pub async fn submit_burn(
+        &self,
+        id: &Uuid,
+        wallet_pubkey: &str,
+        signature: &str,
+        recent_blockhash: &str,
+        date: &DateTime<Utc>
+    ) -> Result<PointExpiration, ExpirationRepositoryError> {
+        let expiration = sqlx::query_as::<_, PointExpiration>("UPDATE point_expirations SET status = $2, wallet_pubkey = $3, signature = $4, recent_blockhash = $5, error = NULL, updated_at = $6 WHERE id = $1 AND status = $7 RETURNING *")
+            .bind(id)
+            .bind(PointExpirationStatus::Burning)
+            .bind(wallet_pubkey)
+            .bind(signature)
+            .bind(recent_blockhash)
+            .bind(date)
+            .bind(PointExpirationStatus::Pending)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        expiration.ok_or(ExpirationRepositoryError::InvalidStatus)
+    }
+
+    /// Settles a pending or burning expiration.
+    pub async fn finish_expiration(
+        &self,
+        id: &Uuid,
+        status: PointExpirationStatus,
+        error: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<PointExpiration, ExpirationRepositoryError> {
+        let expiration = sqlx::query_as::<_, PointExpiration>("UPDATE point_expirations SET status = $2, error = $3, updated_at = $4 WHERE id = $1 AND status = ANY($5) RETURNING *")
+            .bind(id)
+            .bind(status)
+            .bind(error)
+            .bind(date)
+            .bind([PointExpirationStatus::Pending, PointExpirationStatus::Burning])
+            .fetch_optional(&self.pool)
+            .await?;
+
+        expiration.ok_or(ExpirationRepositoryError::InvalidStatus)
+    }
+
+    /// Puts a failed expiration back in the queue with a fresh burn.
+    pub async fn retry_expiration(&self, id: &Uuid, date: &DateTime<Utc>) -> Result<PointExpiration, ExpirationRepositoryError> {
+        let expiration = sqlx::query_as::<_, PointExpiration>("UPDATE point_expirations SET status = $2, signature = NULL, recent_blockhash = NULL, updated_at = $3 WHERE id = $1 AND status = $4 RETURNING *")
+            .bind(id)
+            .bind(PointExpirationStatus::Pending)
+            .bind(date)
+            .bind(PointExpirationStatus::Failed)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        expiration.ok_or(ExpirationRepositoryError::InvalidStatus)
+    }
+
+    pub async fn mark_notified(&self, id: &Uuid, date: &DateTime<Utc>) -> Result<(), ExpirationRepositoryError> {
+        sqlx::query("UPDATE point_expirations SET notified_at = $2 WHERE id = $1")
+            .bind(id)
+            .bind(date)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Points of the user expiring after `date` and up to `until`, per program and day.
+    pub async fn fetch_expiring_points(
+        &self,
+        user_id: &Uuid,
+        date: &DateTime<Utc>,
+        until: &DateTime<Utc>
+    ) -> Result<Vec<ExpiringPoints>, ExpirationRepositoryError> {
+        let expiring = sqlx::query_as::<_, ExpiringPoints>("SELECT l.program_id, p.name AS program_name, SUM(l.remaining)::BIGINT AS points, (l.expires_at AT TIME ZONE 'UTC')::DATE AS expires_on FROM point_lots l JOIN programs p ON p.id = l.program_id WHERE l.user_id = $1 AND l.remaining > 0 AND l.expires_at > $2 AND l.expires_at <= $3 GROUP BY l.program_id, p.name, expires_on ORDER BY expires_on, p.name")
+            .bind(user_id)
+            .bind(date)
+            .bind(until)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(expiring)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ExpirationRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Expiration is not in a status that allows this")]
+    InvalidStatus
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::repositories::fixtures::{insert_lot, insert_program, insert_user};
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn claim_expired_lots_groups_expired_points_by_wallet_once(pool: PgPool) {
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "new-wallet").await;
+        let repository = ExpirationRepository::new(pool.clone());
+        let now = Utc::now();
+
+        insert_lot(&pool, program_id, user_id, 10, "old-wallet", Some(now - Duration::days(2))).await;
+        insert_lot(&pool, program_id, user_id, 20, "old-wallet", Some(now - Duration::days(1))).await;
+        insert_lot(&pool, program_id, user_id, 5, "new-wallet", Some(now - Duration::days(1))).await;
+        insert_lot(&pool, program_id, user_id, 40, "new-wallet", Some(now + Duration::days(1))).await;
+
+        let mut expirations = repository.claim_expired_lots(&now).await.unwrap();
+        expirations.sort_by_key(|expiration| expiration.points);
+
+        let claimed: Vec<(Option<&str>, i64)> = expirations
+            .iter()
+            .map(|expiration| (expiration.wallet_pubkey.as_deref(), expiration.points))
+            .collect();
+
+        assert_eq!(claimed, [(Some("new-wallet"), 5), (Some("old-wallet"), 30)]);
+        assert!(expirations.iter().all(|expiration| expiration.status == PointExpirationStatus::Pending));
+
+        // Consumed lots are not expired again; the lot not expired yet keeps its points.
+        assert!(repository.claim_expired_lots(&now).await.unwrap().is_empty());
+
+        let remaining = sqlx::query_scalar::<_, i64>("SELECT SUM(remaining)::BIGINT FROM point_lots WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(remaining, 40);
+    }
+}
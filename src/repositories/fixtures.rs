@@ -0,0 +1,76 @@
+//! Rows the repository tests build on.
+
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// Inserts a merchant, the mint `mint_pubkey` and a program on it.
+pub async fn insert_program(pool: &PgPool, mint_pubkey: &str) -> Uuid {
+    let (merchant_id, program_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    sqlx::query("INSERT INTO merchants (id, name, created_at) VALUES ($1, 'Merchant', NOW())")
+        .bind(merchant_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO mints (pubkey, decimals, created_at) VALUES ($1, 6, NOW()) ON CONFLICT DO NOTHING")
+        .bind(mint_pubkey)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO programs (id, merchant_id, name, mint_pubkey, currency, created_at) VALUES ($1, $2, 'Program', $3, 'EUR', NOW())")
+        .bind(program_id)
+        .bind(merchant_id)
+        .bind(mint_pubkey)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    program_id
+}
+
+/// Inserts a user whose primary wallet is the external `wallet_pubkey`.
+pub async fn insert_user(pool: &PgPool, email: &str, wallet_pubkey: &str) -> Uuid {
+    let user_id = Uuid::new_v4();
+
+    sqlx::query("INSERT INTO users (id, email, created_at) VALUES ($1, $2, NOW())")
+        .bind(user_id)
+        .bind(email)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO wallets (id, user_id, pubkey, kind, is_primary, created_at) VALUES ($1, $2, $3, 'external', TRUE, NOW())")
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(wallet_pubkey)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    user_id
+}
+
+/// Inserts a lot of `points` minted to `wallet_pubkey`.
+pub async fn insert_lot(
+    pool: &PgPool,
+    program_id: Uuid,
+    user_id: Uuid,
+    points: i64,
+    wallet_pubkey: &str,
+    expires_at: Option<DateTime<Utc>>
+) -> Uuid {
+    let lot_id = Uuid::new_v4();
+
+    sqlx::query("INSERT INTO point_lots (id, program_id, user_id, points, remaining, wallet_pubkey, earned_at, expires_at, created_at) VALUES ($1, $2, $3, $4, $4, $5, NOW(), $6, NOW())")
+        .bind(lot_id)
+        .bind(program_id)
+        .bind(user_id)
+        .bind(points)
+        .bind(wallet_pubkey)
+        .bind(expires_at)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    lot_id
+}
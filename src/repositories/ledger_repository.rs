@@ -0,0 +1,524 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::ledger_model::{
+    LedgerAccount,
+    LedgerBalance,
+    LedgerEntry,
+    LedgerTransaction,
+    LedgerTransactionKind,
+    Settlement,
+    SettlementItem,
+    SettlementItemStatus,
+    SettlementPosition,
+    SettlementStatus
+};
+use chrono::{DateTime, Utc};
+
+/// Items whose points are on chain or on their way there.
+const COMMITTED_ITEM_STATUSES: [SettlementItemStatus; 5] = [
+    SettlementItemStatus::Pending,
+    SettlementItemStatus::Batched,
+    SettlementItemStatus::Burning,
+    SettlementItemStatus::Completed,
+    SettlementItemStatus::Skipped
+];
+
+#[derive(Clone)]
+pub struct LedgerRepository {
+    pool: PgPool
+}
+
+impl LedgerRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Posts `amount` to the user's balance against `counter_account`: a credit
+    /// when positive, a debit when negative. Debits never take the balance below
+    /// zero. Posting a kind again for the same reference returns the first
+    /// transaction.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn post_user_transaction(
+        &self,
+        program_id: &Uuid,
+        user_id: &Uuid,
+        kind: LedgerTransactionKind,
+        counter_account: LedgerAccount,
+        amount: i64,
+        reference: &str,
+        date: &DateTime<Utc>
+    ) -> Result<LedgerTransaction, LedgerRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Serializes postings per user and program, so concurrent debits cannot
+        // both pass the balance check.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::TEXT || $2::TEXT, 0))")
+            .bind(program_id)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let existing = sqlx::query_as::<_, LedgerTransaction>("SELECT * FROM ledger_transactions WHERE kind = $1 AND reference = $2")
+            .bind(kind)
+            .bind(reference)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        if let Some(existing) = existing {
+            transaction.rollback().await?;
+            return Ok(existing);
+        }
+
+        if amount < 0 {
+            let balance = sqlx::query_scalar::<_, i64>("SELECT COALESCE(SUM(amount), 0)::BIGINT FROM ledger_entries WHERE program_id = $1 AND user_id = $2")
+                .bind(program_id)
+                .bind(user_id)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+            if balance + amount < 0 {
+                transaction.rollback().await?;
+                return Err(LedgerRepositoryError::InsufficientBalance);
+            }
+        }
+
+        let ledger_transaction = sqlx::query_as::<_, LedgerTransaction>("INSERT INTO ledger_transactions (id, program_id, kind, reference, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *")
+            .bind(Uuid::new_v4())
+            .bind(program_id)
+            .bind(kind)
+            .bind(reference)
+            .bind(date)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        sqlx::query("INSERT INTO ledger_entries (id, transaction_id, program_id, account, user_id, amount, created_at) SELECT entry.id, $1, $2, entry.account, entry.user_id, entry.amount, $3 FROM UNNEST($4::UUID[], $5::TEXT[], $6::UUID[], $7::BIGINT[]) AS entry(id, account, user_id, amount)")
+            .bind(ledger_transaction.id)
+            .bind(program_id)
+            .bind(date)
+            .bind([Uuid::new_v4(), Uuid::new_v4()])
+            .bind([LedgerAccount::UserBalance, counter_account])
+            .bind([Some(*user_id), None])
+            .bind([amount, -amount])
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(ledger_transaction)
+    }
+
+    pub async fn fetch_user_balances(&self, user_id: &Uuid) -> Result<Vec<LedgerBalance>, LedgerRepositoryError> {
+        let balances = sqlx::query_as::<_, LedgerBalance>("SELECT e.program_id, p.name AS program_name, SUM(e.amount)::BIGINT AS points FROM ledger_entries e JOIN programs p ON p.id = e.program_id WHERE e.user_id = $1 GROUP BY e.program_id, p.name ORDER BY p.name")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(balances)
+    }
+
+    pub async fn fetch_user_entries(&self, user_id: &Uuid) -> Result<Vec<LedgerEntry>, LedgerRepositoryError> {
+        let entries = sqlx::query_as::<_, LedgerEntry>("SELECT * FROM ledger_entries WHERE user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    /// Ledger, settled and committed points of every user of the program.
+    pub async fn fetch_settlement_positions(&self, program_id: &Uuid) -> Result<Vec<SettlementPosition>, LedgerRepositoryError> {
+        let positions = sqlx::query_as::<_, SettlementPosition>("WITH ledger AS (SELECT user_id, SUM(amount)::BIGINT AS points FROM ledger_entries WHERE program_id = $1 AND account = $2 GROUP BY user_id), settled AS (SELECT user_id, COALESCE(SUM(amount) FILTER (WHERE status = $3), 0)::BIGINT AS settled, COALESCE(SUM(amount) FILTER (WHERE status = ANY($4)), 0)::BIGINT AS committed FROM settlement_items WHERE program_id = $1 GROUP BY user_id) SELECT COALESCE(l.user_id, s.user_id) AS user_id, w.pubkey AS wallet_pubkey, COALESCE(l.points, 0) AS ledger_points, COALESCE(s.settled, 0) AS settled_points, COALESCE(s.committed, 0) AS committed_points FROM ledger l FULL JOIN settled s ON s.user_id = l.user_id LEFT JOIN wallets w ON w.user_id = COALESCE(l.user_id, s.user_id) AND w.is_primary ORDER BY user_id")
+            .bind(program_id)
+            .bind(LedgerAccount::UserBalance)
+            .bind(SettlementItemStatus::Completed)
+            .bind(COMMITTED_ITEM_STATUSES)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(positions)
+    }
+
+    /// Records a settlement with one item per user whose ledger balance differs
+    /// from the points committed on chain. Returns `None` when there is nothing
+    /// to settle, and `SettlementInProgress` while another one is processing.
+    pub async fn create_settlement(
+        &self,
+        program_id: &Uuid,
+        positions: &[SettlementPosition],
+        date: &DateTime<Utc>
+    ) -> Result<Option<Settlement>, LedgerRepositoryError> {
+        let items: Vec<(&SettlementPosition, &String)> = positions
+            .iter()
+            .filter(|position| position.ledger_points != position.committed_points)
+            .filter_map(|position| position.wallet_pubkey.as_ref().map(|wallet_pubkey| (position, wallet_pubkey)))
+            .collect();
+
+        if items.is_empty() {
+            return Ok(None);
+        }
+
+        let mut transaction = self.pool.begin().await?;
+
+        let settlement = match sqlx::query_as::<_, Settlement>("INSERT INTO settlements (id, program_id, status, created_at, updated_at) VALUES ($1, $2, $3, $4, $4) RETURNING *")
+            .bind(Uuid::new_v4())
+            .bind(program_id)
+            .bind(SettlementStatus::Processing)
+            .bind(date)
+            .fetch_one(&mut *transaction)
+            .await
+        {
+            Ok(settlement) => settlement,
+            Err(SqlxError::Database(db_error)) if db_error.is_unique_violation() => {
+                return Err(LedgerRepositoryError::SettlementInProgress);
+            },
+            Err(e) => return Err(LedgerRepositoryError::DatabaseError(e))
+        };
+
+        for (position, wallet_pubkey) in items {
+            sqlx::query("INSERT INTO settlement_items (id, settlement_id, program_id, user_id, wallet_pubkey, amount, status, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)")
+                .bind(Uuid::new_v4())
+                .bind(settlement.id)
+                .bind(program_id)
+                .bind(position.user_id)
+                .bind(wallet_pubkey)
+                .bind(position.ledger_points - position.committed_points)
+                .bind(SettlementItemStatus::Pending)
+                .bind(date)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(Some(settlement))
+    }
+
+    pub async fn fetch_processing_settlement(&self, program_id: &Uuid) -> Result<Option<Settlement>, LedgerRepositoryError> {
+        let settlement = sqlx::query_as::<_, Settlement>("SELECT * FROM settlements WHERE program_id = $1 AND status = $2")
+            .bind(program_id)
+            .bind(SettlementStatus::Processing)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(settlement)
+    }
+
+    pub async fn fetch_settlements(&self, program_id: &Uuid) -> Result<Vec<Settlement>, LedgerRepositoryError> {
+        let settlements = sqlx::query_as::<_, Settlement>("SELECT * FROM settlements WHERE program_id = $1 ORDER BY created_at DESC")
+            .bind(program_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(settlements)
+    }
+
+    pub async fn fetch_settlement(&self, id: &Uuid) -> Result<Settlement, LedgerRepositoryError> {
+        match sqlx::query_as::<_, Settlement>("SELECT * FROM settlements WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(settlement) => Ok(settlement),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(LedgerRepositoryError::RowNotFound),
+                e => Err(LedgerRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_settlement_items(&self, settlement_id: &Uuid) -> Result<Vec<SettlementItem>, LedgerRepositoryError> {
+        let items = sqlx::query_as::<_, SettlementItem>("SELECT * FROM settlement_items WHERE settlement_id = $1 ORDER BY amount DESC, user_id")
+            .bind(settlement_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(items)
+    }
+
+    /// Links the settlement to the batch minting its positive items, each item
+    /// at the given batch position. Returns `false` when the settlement already
+    /// has a batch.
+    pub async fn attach_mint_batch(
+        &self,
+        settlement_id: &Uuid,
+        mint_batch_id: &Uuid,
+        positions: &[(Uuid, i32)],
+        date: &DateTime<Utc>
+    ) -> Result<bool, LedgerRepositoryError> {
+        let item_ids: Vec<Uuid> = positions.iter().map(|(item_id, _)| *item_id).collect();
+        let batch_positions: Vec<i32> = positions.iter().map(|(_, position)| *position).collect();
+
+        let mut transaction = self.pool.begin().await?;
+
+        let attached = sqlx::query("UPDATE settlements SET mint_batch_id = $2, updated_at = $3 WHERE id = $1 AND mint_batch_id IS NULL")
+            .bind(settlement_id)
+            .bind(mint_batch_id)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?;
+
+        if attached.rows_affected() == 0 {
+            transaction.rollback().await?;
+            return Ok(false);
+        }
+
+        sqlx::query("UPDATE settlement_items SET status = $2, mint_batch_position = item.position, updated_at = $3 FROM UNNEST($4::UUID[], $5::INTEGER[]) AS item(id, position) WHERE settlement_items.id = item.id AND settlement_items.settlement_id = $1")
+            .bind(settlement_id)
+            .bind(SettlementItemStatus::Batched)
+            .bind(date)
+            .bind(&item_ids)
+            .bind(&batch_positions)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Completes the batched items whose batch position was confirmed.
+    pub async fn confirm_mint_items(
+        &self,
+        settlement_id: &Uuid,
+        positions: &[i32],
+        date: &DateTime<Utc>
+    ) -> Result<(), LedgerRepositoryError> {
+        sqlx::query("UPDATE settlement_items SET status = $2, updated_at = $3 WHERE settlement_id = $1 AND status = $4 AND mint_batch_position = ANY($5)")
+            .bind(settlement_id)
+            .bind(SettlementItemStatus::Completed)
+            .bind(date)
+            .bind(SettlementItemStatus::Batched)
+            .bind(positions)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Fails the items still batched once their batch is closed, so the next
+    /// settlement nets their amounts again.
+    pub async fn fail_batched_items(
+        &self,
+        settlement_id: &Uuid,
+        error: &str,
+        date: &DateTime<Utc>
+    ) -> Result<(), LedgerRepositoryError> {
+        sqlx::query("UPDATE settlement_items SET status = $2, error = $3, updated_at = $4 WHERE settlement_id = $1 AND status = $5")
+            .bind(settlement_id)
+            .bind(SettlementItemStatus::Failed)
+            .bind(error)
+            .bind(date)
+            .bind(SettlementItemStatus::Batched)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Records the signed burn of an item before it is sent.
+    pub async fn submit_burn_item(
+        &self,
+        id: &Uuid,
+        signature: &str,
+        recent_blockhash: &str,
+        date: &DateTime<Utc>
+    ) -> Result<SettlementItem, LedgerRepositoryError> {
+        let item = sqlx::query_as::<_, SettlementItem>("UPDATE settlement_items SET status = $2, signature = $3, recent_blockhash = $4, updated_at = $5 WHERE id = $1 AND status = $6 RETURNING *")
+            .bind(id)
+            .bind(SettlementItemStatus::Burning)
+            .bind(signature)
+            .bind(recent_blockhash)
+            .bind(date)
+            .bind(SettlementItemStatus::Pending)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        item.ok_or(LedgerRepositoryError::InvalidStatus)
+    }
+
+    /// Settles an item that is not final yet.
+    pub async fn finish_item(
+        &self,
+        id: &Uuid,
+        status: SettlementItemStatus,
+        error: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<SettlementItem, LedgerRepositoryError> {
+        let item = sqlx::query_as::<_, SettlementItem>("UPDATE settlement_items SET status = $2, error = $3, updated_at = $4 WHERE id = $1 AND status = ANY($5) RETURNING *")
+            .bind(id)
+            .bind(status)
+            .bind(error)
+            .bind(date)
+            .bind([SettlementItemStatus::Pending, SettlementItemStatus::Batched, SettlementItemStatus::Burning])
+            .fetch_optional(&self.pool)
+            .await?;
+
+        item.ok_or(LedgerRepositoryError::InvalidStatus)
+    }
+
+    /// Completes the settlement once none of its items is in flight.
+    pub async fn complete_settlement(&self, id: &Uuid, date: &DateTime<Utc>) -> Result<Settlement, LedgerRepositoryError> {
+        let settlement = sqlx::query_as::<_, Settlement>("UPDATE settlements SET status = $2, updated_at = $3 WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM settlement_items WHERE settlement_id = $1 AND status = ANY($4)) RETURNING *")
+            .bind(id)
+            .bind(SettlementStatus::Completed)
+            .bind(date)
+            .bind([SettlementItemStatus::Pending, SettlementItemStatus::Batched, SettlementItemStatus::Burning])
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match settlement {
+            Some(settlement) => Ok(settlement),
+            None => self.fetch_settlement(id).await
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LedgerRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Not enough points")]
+    InsufficientBalance,
+    #[error("A settlement of this program is already processing")]
+    SettlementInProgress,
+    #[error("Settlement item is not in a status that allows this")]
+    InvalidStatus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::mint_batch_model::{MintBatchItemStatus, MintBatchStatus};
+    use crate::repositories::fixtures::{insert_program, insert_user};
+    use crate::repositories::solana_repository::SolanaRepository;
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn settlement_completes_once_its_batch_is_closed_and_nets_failed_items_again(pool: PgPool) {
+        let repository = LedgerRepository::new(pool.clone());
+        let solana_repository = SolanaRepository::new(pool.clone());
+        let now = Utc::now();
+        let program_id = insert_program(&pool, "mint").await;
+        let minted_id = insert_user(&pool, "minted@example.com", "minted-wallet").await;
+        let failing_id = insert_user(&pool, "failing@example.com", "failing-wallet").await;
+
+        for (user_id, amount) in [(minted_id, 100), (failing_id, 40)] {
+            repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Earning, LedgerAccount::Issued, amount, &user_id.to_string(), &now).await.unwrap();
+        }
+
+        let positions = repository.fetch_settlement_positions(&program_id).await.unwrap();
+        let settlement = repository.create_settlement(&program_id, &positions, &now).await.unwrap().unwrap();
+        let items = repository.fetch_settlement_items(&settlement.id).await.unwrap();
+        let recipients: Vec<(String, i64)> = items.iter().map(|item| (item.wallet_pubkey.clone(), item.amount)).collect();
+        let batch_positions: Vec<(Uuid, i32)> = items.iter().enumerate().map(|(position, item)| (item.id, position as i32)).collect();
+        let failing_position = items.iter().position(|item| item.user_id == failing_id).unwrap() as i32;
+
+        let batch_id = Uuid::new_v4();
+        solana_repository.create_mint_batch(&batch_id, &now, "mint", &recipients).await.unwrap();
+        assert!(repository.attach_mint_batch(&settlement.id, &batch_id, &batch_positions, &now).await.unwrap());
+
+        // The batch mints one recipient and keeps failing the other.
+        repository.confirm_mint_items(&settlement.id, &[1 - failing_position], &now).await.unwrap();
+        solana_repository.update_mint_batch_items(&batch_id, &[failing_position], MintBatchItemStatus::Failed, None, None, Some("mint failed")).await.unwrap();
+        solana_repository.update_mint_batch_status(&batch_id, MintBatchStatus::PartiallyFailed).await.unwrap();
+        assert_eq!(repository.complete_settlement(&settlement.id, &now).await.unwrap().status, SettlementStatus::Processing);
+
+        assert!(solana_repository.close_mint_batch(&batch_id).await.unwrap().is_some());
+        assert!(solana_repository.claim_mint_batch(&batch_id).await.unwrap().is_none());
+        repository.fail_batched_items(&settlement.id, "Mint batch failed", &now).await.unwrap();
+        assert_eq!(repository.complete_settlement(&settlement.id, &now).await.unwrap().status, SettlementStatus::Completed);
+
+        let positions = repository.fetch_settlement_positions(&program_id).await.unwrap();
+        let next = repository.create_settlement(&program_id, &positions, &now).await.unwrap().unwrap();
+        let items = repository.fetch_settlement_items(&next.id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!((items[0].user_id, items[0].amount), (failing_id, 40));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn close_mint_batch_keeps_batches_with_submitted_items(pool: PgPool) {
+        let solana_repository = SolanaRepository::new(pool.clone());
+        let now = Utc::now();
+        insert_program(&pool, "mint").await;
+
+        let batch_id = Uuid::new_v4();
+        solana_repository.create_mint_batch(&batch_id, &now, "mint", &[("a".to_string(), 1), ("b".to_string(), 2)]).await.unwrap();
+        solana_repository.update_mint_batch_items(&batch_id, &[0], MintBatchItemStatus::Submitted, Some("signature"), Some("blockhash"), None).await.unwrap();
+        solana_repository.update_mint_batch_items(&batch_id, &[1], MintBatchItemStatus::Failed, None, None, Some("mint failed")).await.unwrap();
+        solana_repository.update_mint_batch_status(&batch_id, MintBatchStatus::PartiallyFailed).await.unwrap();
+
+        // The submitted mint may still land, so the batch must stay resumable.
+        assert!(solana_repository.close_mint_batch(&batch_id).await.unwrap().is_none());
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn post_user_transaction_is_idempotent_and_never_overdraws(pool: PgPool) {
+        let repository = LedgerRepository::new(pool.clone());
+        let now = Utc::now();
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+
+        let earned = repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Earning, LedgerAccount::Issued, 50, "earning", &now).await.unwrap();
+        let again = repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Earning, LedgerAccount::Issued, 50, "earning", &now).await.unwrap();
+
+        assert_eq!(earned.id, again.id);
+
+        assert!(matches!(
+            repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Redemption, LedgerAccount::Redeemed, -60, "order", &now).await,
+            Err(LedgerRepositoryError::InsufficientBalance)
+        ));
+        repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Redemption, LedgerAccount::Redeemed, -30, "order", &now).await.unwrap();
+
+        let balances = repository.fetch_user_balances(&user_id).await.unwrap();
+        assert_eq!(balances.iter().map(|balance| balance.points).collect::<Vec<_>>(), [20]);
+
+        // Every transaction balances its user entry with the counter account.
+        let total = sqlx::query_scalar::<_, i64>("SELECT SUM(amount)::BIGINT FROM ledger_entries WHERE program_id = $1")
+            .bind(program_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(total, 0);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn create_settlement_nets_uncommitted_points_one_settlement_at_a_time(pool: PgPool) {
+        let repository = LedgerRepository::new(pool.clone());
+        let now = Utc::now();
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+
+        assert!(repository.create_settlement(&program_id, &[], &now).await.unwrap().is_none());
+
+        repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Earning, LedgerAccount::Issued, 50, "earning", &now).await.unwrap();
+
+        let positions = repository.fetch_settlement_positions(&program_id).await.unwrap();
+        let settlement = repository.create_settlement(&program_id, &positions, &now).await.unwrap().unwrap();
+        let items = repository.fetch_settlement_items(&settlement.id).await.unwrap();
+
+        assert_eq!(items.iter().map(|item| (item.user_id, item.amount)).collect::<Vec<_>>(), [(user_id, 50)]);
+        assert!(matches!(
+            repository.create_settlement(&program_id, &positions, &now).await,
+            Err(LedgerRepositoryError::SettlementInProgress)
+        ));
+
+        // Completed items count as committed, so only what was spent since is netted.
+        repository.finish_item(&items[0].id, SettlementItemStatus::Completed, None, &now).await.unwrap();
+        repository.complete_settlement(&settlement.id, &now).await.unwrap();
+        repository.post_user_transaction(&program_id, &user_id, LedgerTransactionKind::Redemption, LedgerAccount::Redeemed, -20, "order", &now).await.unwrap();
+
+        let positions = repository.fetch_settlement_positions(&program_id).await.unwrap();
+        let next = repository.create_settlement(&program_id, &positions, &now).await.unwrap().unwrap();
+        let items = repository.fetch_settlement_items(&next.id).await.unwrap();
+
+        assert_eq!(items.iter().map(|item| item.amount).collect::<Vec<_>>(), [-20]);
+    }
+}
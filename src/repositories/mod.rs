@@ -1,2 +1,21 @@
 pub mod user_repository;
-pub mod solana_repository;
\ No newline at end of file
+pub mod solana_repository;
+pub mod airdrop_repository;
+pub mod distribution_repository;
+pub mod wallet_repository;
+pub mod api_key_repository;
+pub mod auth_repository;
+pub mod role_repository;
+pub mod rate_limit_repository;
+pub mod program_repository;
+pub mod reward_repository;
+pub mod expiration_repository;
+pub mod ledger_repository;
+pub mod reconciliation_repository;
+pub mod referral_repository;
+pub mod tier_repository;
+pub mod conversion_repository;
+pub mod campaign_repository;
+pub mod point_lot_repository;
+#[cfg(test)]
+pub mod fixtures;
\ No newline at end of file
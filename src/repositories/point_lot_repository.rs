@@ -0,0 +1,104 @@
+use sqlx::{Postgres, Transaction};
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::expiration_model::PointLot;
+use chrono::{DateTime, Utc};
+
+/// What takes points from lots before they are burned: a redemption order or a
+/// conversion. Their consumptions are released when the points are given back.
+#[derive(Debug, Clone, Copy)]
+pub enum LotConsumer {
+    Order(Uuid),
+    Conversion(Uuid)
+}
+
+impl LotConsumer {
+    fn id(&self) -> Uuid {
+        match self {
+            LotConsumer::Order(id) | LotConsumer::Conversion(id) => *id
+        }
+    }
+
+    fn insert_consumption_sql(&self) -> &'static str {
+        match self {
+            LotConsumer::Order(_) => "INSERT INTO point_lot_consumptions (id, lot_id, order_id, points, created_at) VALUES ($1, $2, $3, $4, $5)",
+            LotConsumer::Conversion(_) => "INSERT INTO point_lot_consumptions (id, lot_id, conversion_id, points, created_at) VALUES ($1, $2, $3, $4, $5)"
+        }
+    }
+
+    fn release_sql(&self) -> &'static str {
+        match self {
+            LotConsumer::Order(_) => "UPDATE point_lots SET remaining = point_lots.remaining + c.points FROM point_lot_consumptions c WHERE c.lot_id = point_lots.id AND c.order_id = $1",
+            LotConsumer::Conversion(_) => "UPDATE point_lots SET remaining = point_lots.remaining + c.points FROM point_lot_consumptions c WHERE c.lot_id = point_lots.id AND c.conversion_id = $1"
+        }
+    }
+
+    fn delete_consumptions_sql(&self) -> &'static str {
+        match self {
+            LotConsumer::Order(_) => "DELETE FROM point_lot_consumptions WHERE order_id = $1",
+            LotConsumer::Conversion(_) => "DELETE FROM point_lot_consumptions WHERE conversion_id = $1"
+        }
+    }
+}
+
+/// Takes `points` from the user's lots in the program that are not expired at
+/// `date`, oldest expiry first. Points beyond the tracked lots were not earned
+/// through the program and are taken from the untracked balance.
+pub async fn consume_lots(
+    transaction: &mut Transaction<'_, Postgres>,
+    consumer: LotConsumer,
+    user_id: &Uuid,
+    program_id: &Uuid,
+    points: i64,
+    date: &DateTime<Utc>
+) -> Result<(), SqlxError> {
+    let lots = sqlx::query_as::<_, PointLot>("SELECT * FROM point_lots WHERE user_id = $1 AND program_id = $2 AND remaining > 0 AND (expires_at IS NULL OR expires_at > $3) ORDER BY expires_at ASC NULLS LAST, earned_at FOR UPDATE")
+        .bind(user_id)
+        .bind(program_id)
+        .bind(date)
+        .fetch_all(&mut **transaction)
+        .await?;
+
+    let mut points_left = points;
+
+    for lot in lots {
+        if points_left == 0 {
+            break;
+        }
+
+        let points = lot.remaining.min(points_left);
+        points_left -= points;
+
+        sqlx::query("UPDATE point_lots SET remaining = remaining - $2 WHERE id = $1")
+            .bind(lot.id)
+            .bind(points)
+            .execute(&mut **transaction)
+            .await?;
+
+        sqlx::query(consumer.insert_consumption_sql())
+            .bind(Uuid::new_v4())
+            .bind(lot.id)
+            .bind(consumer.id())
+            .bind(points)
+            .bind(date)
+            .execute(&mut **transaction)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Gives the points the consumer took back to their lots.
+pub async fn release_lots(transaction: &mut Transaction<'_, Postgres>, consumer: LotConsumer) -> Result<(), SqlxError> {
+    sqlx::query(consumer.release_sql())
+        .bind(consumer.id())
+        .execute(&mut **transaction)
+        .await?;
+
+    sqlx::query(consumer.delete_consumptions_sql())
+        .bind(consumer.id())
+        .execute(&mut **transaction)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,519 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::{
+    campaign_model::{CampaignAward, CampaignKind, CampaignStatus},
+    program_model::{EarningRule, Merchant, PointEarning, PointEarningStatus, Program}
+};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct ProgramRepository {
+    pool: PgPool
+}
+
+impl ProgramRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_merchant(&self, merchant: &Merchant) -> Result<Merchant, ProgramRepositoryError> {
+        let merchant = sqlx::query_as::<_, Merchant>("INSERT INTO merchants (id, name, created_at) VALUES ($1, $2, $3) RETURNING *")
+            .bind(merchant.id)
+            .bind(&merchant.name)
+            .bind(merchant.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(merchant)
+    }
+
+    pub async fn fetch_merchants(&self) -> Result<Vec<Merchant>, ProgramRepositoryError> {
+        let merchants = sqlx::query_as::<_, Merchant>("SELECT * FROM merchants ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(merchants)
+    }
+
+    pub async fn fetch_merchant(&self, id: &Uuid) -> Result<Merchant, ProgramRepositoryError> {
+        match sqlx::query_as::<_, Merchant>("SELECT * FROM merchants WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(merchant) => Ok(merchant),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ProgramRepositoryError::RowNotFound),
+                e => Err(ProgramRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn create_program(&self, program: &Program) -> Result<Program, ProgramRepositoryError> {
+        let program = sqlx::query_as::<_, Program>("INSERT INTO programs (id, merchant_id, name, mint_pubkey, currency, max_points_per_purchase, points_ttl_days, settlement_mode, tier_basis, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *")
+            .bind(program.id)
+            .bind(program.merchant_id)
+            .bind(&program.name)
+            .bind(&program.mint_pubkey)
+            .bind(&program.currency)
+            .bind(program.max_points_per_purchase)
+            .bind(program.points_ttl_days)
+            .bind(program.settlement_mode)
+            .bind(program.tier_basis)
+            .bind(program.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(program)
+    }
+
+    pub async fn fetch_programs(&self) -> Result<Vec<Program>, ProgramRepositoryError> {
+        let programs = sqlx::query_as::<_, Program>("SELECT * FROM programs ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(programs)
+    }
+
+    pub async fn fetch_program(&self, id: &Uuid) -> Result<Program, ProgramRepositoryError> {
+        match sqlx::query_as::<_, Program>("SELECT * FROM programs WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(program) => Ok(program),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ProgramRepositoryError::RowNotFound),
+                e => Err(ProgramRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Whether the mint holds the points of any program.
+    pub async fn is_program_mint(&self, mint_pubkey: &str) -> Result<bool, ProgramRepositoryError> {
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM programs WHERE mint_pubkey = $1)")
+            .bind(mint_pubkey)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(exists)
+    }
+
+    /// Whether any tier, archived ones included, uses the mint for its badge.
+    pub async fn is_badge_mint(&self, mint_pubkey: &str) -> Result<bool, ProgramRepositoryError> {
+        let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM program_tiers WHERE badge_mint_pubkey = $1)")
+            .bind(mint_pubkey)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(exists)
+    }
+
+    pub async fn create_earning_rule(&self, rule: &EarningRule) -> Result<EarningRule, ProgramRepositoryError> {
+        let rule = sqlx::query_as::<_, EarningRule>("INSERT INTO earning_rules (id, program_id, name, kind, points_per_unit, unit_amount, multiplier_bps, min_purchase_amount, max_points, starts_at, ends_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING *")
+            .bind(rule.id)
+            .bind(rule.program_id)
+            .bind(&rule.name)
+            .bind(rule.kind)
+            .bind(rule.points_per_unit)
+            .bind(rule.unit_amount)
+            .bind(rule.multiplier_bps)
+            .bind(rule.min_purchase_amount)
+            .bind(rule.max_points)
+            .bind(rule.starts_at)
+            .bind(rule.ends_at)
+            .bind(rule.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(rule)
+    }
+
+    /// Rules that are not archived, including future and expired ones.
+    pub async fn fetch_earning_rules(&self, program_id: &Uuid) -> Result<Vec<EarningRule>, ProgramRepositoryError> {
+        let rules = sqlx::query_as::<_, EarningRule>("SELECT * FROM earning_rules WHERE program_id = $1 AND archived_at IS NULL ORDER BY created_at")
+            .bind(program_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rules)
+    }
+
+    pub async fn archive_earning_rule(
+        &self,
+        program_id: &Uuid,
+        rule_id: &Uuid,
+        date: &DateTime<Utc>
+    ) -> Result<EarningRule, ProgramRepositoryError> {
+        match sqlx::query_as::<_, EarningRule>("UPDATE earning_rules SET archived_at = COALESCE(archived_at, $3) WHERE id = $2 AND program_id = $1 RETURNING *")
+            .bind(program_id)
+            .bind(rule_id)
+            .bind(date)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(rule) => Ok(rule),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ProgramRepositoryError::RowNotFound),
+                e => Err(ProgramRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Records the earning unless the program already has one with its reference,
+    /// in which case `None` is returned and no campaign budget is spent.
+    ///
+    /// `awards` are the campaign bonuses the earning qualifies for, first purchase
+    /// bonuses included. Those are dropped unless the user has no other purchase
+    /// earning in the program, which is decided under a lock per user so concurrent
+    /// purchases cannot both get them. Failed earnings keep their awards, and the
+    /// budget they took, for their retry, so they count as purchases as long as
+    /// they have awards. Each remaining award is cut to what is left
+    /// of its campaign's budget and of `max_points` for the whole earning, added to
+    /// the earning's points and recorded in the same transaction; campaigns whose
+    /// budget runs out are marked exhausted.
+    pub async fn create_earning(
+        &self,
+        earning: &PointEarning,
+        awards: &[CampaignAward],
+        max_points: Option<i64>
+    ) -> Result<Option<PointEarning>, ProgramRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+        let mut granted = Vec::with_capacity(awards.len());
+
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::TEXT || $2::TEXT, 3))")
+            .bind(earning.program_id)
+            .bind(earning.user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let has_purchase = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM point_earnings WHERE program_id = $1 AND user_id = $2 AND purchase_amount > 0 AND (status <> $3 OR EXISTS (SELECT 1 FROM campaign_awards WHERE campaign_awards.earning_id = point_earnings.id)))")
+            .bind(earning.program_id)
+            .bind(earning.user_id)
+            .bind(PointEarningStatus::Failed)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        let mut awards: Vec<&CampaignAward> = awards.iter().collect();
+        awards.sort_by_key(|award| award.campaign_id);
+
+        let mut points = earning.points;
+
+        for award in awards {
+            let campaign = sqlx::query_as::<_, (i64, CampaignKind)>("SELECT budget_points - issued_points, kind FROM campaigns WHERE id = $1 AND status = $2 FOR UPDATE")
+                .bind(award.campaign_id)
+                .bind(CampaignStatus::Active)
+                .fetch_optional(&mut *transaction)
+                .await?;
+
+            let Some((remaining, kind)) = campaign else {
+                continue;
+            };
+
+            if kind == CampaignKind::FirstPurchase && has_purchase {
+                continue;
+            }
+
+            let allowed = max_points.map_or(i64::MAX, |max_points| max_points.saturating_sub(points));
+            let award_points = award.points.min(remaining).min(allowed);
+
+            if award_points <= 0 {
+                continue;
+            }
+
+            sqlx::query("UPDATE campaigns SET issued_points = issued_points + $2, status = CASE WHEN issued_points + $2 >= budget_points THEN $3 ELSE status END, exhausted_at = CASE WHEN issued_points + $2 >= budget_points THEN $4 ELSE exhausted_at END, updated_at = $4 WHERE id = $1")
+                .bind(award.campaign_id)
+                .bind(award_points)
+                .bind(CampaignStatus::Exhausted)
+                .bind(earning.created_at)
+                .execute(&mut *transaction)
+                .await?;
+
+            points = points.saturating_add(award_points);
+            granted.push((award, award_points));
+        }
+
+        // Nothing to mint when every budget ran out and the rules awarded nothing.
+        let status = if points > 0 { earning.status } else { PointEarningStatus::Completed };
+
+        let created = sqlx::query_as::<_, PointEarning>("INSERT INTO point_earnings (id, program_id, user_id, reference, purchase_amount, points, receiver_pubkey, status, signature, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10) ON CONFLICT (program_id, reference) DO NOTHING RETURNING *")
+            .bind(earning.id)
+            .bind(earning.program_id)
+            .bind(earning.user_id)
+            .bind(&earning.reference)
+            .bind(earning.purchase_amount)
+            .bind(points)
+            .bind(&earning.receiver_pubkey)
+            .bind(status)
+            .bind(&earning.signature)
+            .bind(earning.created_at)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        let Some(created) = created else {
+            transaction.rollback().await?;
+            return Ok(None);
+        };
+
+        for (award, points) in granted {
+            sqlx::query("INSERT INTO campaign_awards (id, campaign_id, earning_id, user_id, points, created_at) VALUES ($1, $2, $3, $4, $5, $6)")
+                .bind(award.id)
+                .bind(award.campaign_id)
+                .bind(created.id)
+                .bind(created.user_id)
+                .bind(points)
+                .bind(award.created_at)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(Some(created))
+    }
+
+    pub async fn fetch_earning_by_reference(&self, program_id: &Uuid, reference: &str) -> Result<PointEarning, ProgramRepositoryError> {
+        match sqlx::query_as::<_, PointEarning>("SELECT * FROM point_earnings WHERE program_id = $1 AND reference = $2")
+            .bind(program_id)
+            .bind(reference)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(earning) => Ok(earning),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ProgramRepositoryError::RowNotFound),
+                e => Err(ProgramRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_earning(&self, id: &Uuid) -> Result<PointEarning, ProgramRepositoryError> {
+        match sqlx::query_as::<_, PointEarning>("SELECT * FROM point_earnings WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(earning) => Ok(earning),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ProgramRepositoryError::RowNotFound),
+                e => Err(ProgramRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Moves a failed earning, or one left pending since before `pending_before`,
+    /// back to pending so exactly one caller retries it.
+    pub async fn claim_earning(
+        &self,
+        id: &Uuid,
+        pending_before: &DateTime<Utc>,
+        date: &DateTime<Utc>
+    ) -> Result<Option<PointEarning>, ProgramRepositoryError> {
+        let earning = sqlx::query_as::<_, PointEarning>("UPDATE point_earnings SET status = $2, error = NULL, updated_at = $3 WHERE id = $1 AND (status = $4 OR (status = $2 AND updated_at < $5)) RETURNING *")
+            .bind(id)
+            .bind(PointEarningStatus::Pending)
+            .bind(date)
+            .bind(PointEarningStatus::Failed)
+            .bind(pending_before)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(earning)
+    }
+
+    /// Records the mint of a pending earning before it is sent, so a retry can look
+    /// it up instead of minting again.
+    pub async fn submit_earning(
+        &self,
+        id: &Uuid,
+        signature: &str,
+        recent_blockhash: &str,
+        date: &DateTime<Utc>
+    ) -> Result<Option<PointEarning>, ProgramRepositoryError> {
+        let earning = sqlx::query_as::<_, PointEarning>("UPDATE point_earnings SET status = $2, signature = $3, recent_blockhash = $4, updated_at = $5 WHERE id = $1 AND status = $6 RETURNING *")
+            .bind(id)
+            .bind(PointEarningStatus::Submitted)
+            .bind(signature)
+            .bind(recent_blockhash)
+            .bind(date)
+            .bind(PointEarningStatus::Pending)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(earning)
+    }
+
+    /// Settles a pending or submitted earning. Completed and credited earnings
+    /// also record their points as a lot, expiring at `expires_at`. A recorded
+    /// signature is kept unless another is given.
+    pub async fn finish_earning(
+        &self,
+        id: &Uuid,
+        status: PointEarningStatus,
+        signature: Option<&str>,
+        error: Option<&str>,
+        expires_at: Option<DateTime<Utc>>,
+        date: &DateTime<Utc>
+    ) -> Result<PointEarning, ProgramRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let earning = sqlx::query_as::<_, PointEarning>("UPDATE point_earnings SET status = $2, signature = COALESCE($3, signature), error = $4, updated_at = $5 WHERE id = $1 AND status = ANY($6) RETURNING *")
+            .bind(id)
+            .bind(status)
+            .bind(signature)
+            .bind(error)
+            .bind(date)
+            .bind([PointEarningStatus::Pending, PointEarningStatus::Submitted])
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or(ProgramRepositoryError::InvalidStatus)?;
+
+        if matches!(earning.status, PointEarningStatus::Completed | PointEarningStatus::Credited) && earning.points > 0 {
+            sqlx::query("INSERT INTO point_lots (id, program_id, user_id, earning_id, points, remaining, wallet_pubkey, earned_at, expires_at, created_at) VALUES ($1, $2, $3, $4, $5, $5, $6, $7, $8, $7) ON CONFLICT (earning_id) DO NOTHING")
+                .bind(Uuid::new_v4())
+                .bind(earning.program_id)
+                .bind(earning.user_id)
+                .bind(earning.id)
+                .bind(earning.points)
+                .bind(&earning.receiver_pubkey)
+                .bind(date)
+                .bind(expires_at)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(earning)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProgramRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Earning is not in a status that allows this")]
+    InvalidStatus
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::repositories::fixtures::{insert_program, insert_user};
+
+    async fn insert_campaign(pool: &PgPool, program_id: Uuid, kind: &str, multiplier_bps: Option<i32>, bonus_points: Option<i64>) -> Uuid {
+        let id = Uuid::new_v4();
+
+        sqlx::query("INSERT INTO campaigns (id, program_id, name, kind, multiplier_bps, bonus_points, budget_points, status, starts_at, ends_at, created_at, updated_at) VALUES ($1, $2, 'Campaign', $3, $4, $5, 1000, 'active', NOW() - INTERVAL '1 day', NOW() + INTERVAL '1 day', NOW(), NOW())")
+            .bind(id)
+            .bind(program_id)
+            .bind(kind)
+            .bind(multiplier_bps)
+            .bind(bonus_points)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        id
+    }
+
+    fn earning(program_id: Uuid, user_id: Uuid, points: i64) -> PointEarning {
+        let now = Utc::now();
+
+        PointEarning {
+            id: Uuid::new_v4(),
+            program_id,
+            user_id,
+            reference: Some(Uuid::new_v4().to_string()),
+            purchase_amount: 100,
+            points,
+            receiver_pubkey: "receiver".to_string(),
+            status: PointEarningStatus::Pending,
+            signature: None,
+            recent_blockhash: None,
+            error: None,
+            created_at: now,
+            updated_at: now
+        }
+    }
+
+    fn award(campaign_id: Uuid, earning: &PointEarning, points: i64) -> CampaignAward {
+        CampaignAward {
+            id: Uuid::new_v4(),
+            campaign_id,
+            earning_id: earning.id,
+            user_id: earning.user_id,
+            points,
+            created_at: earning.created_at - Duration::seconds(1)
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn create_earning_grants_first_purchase_bonus_once_and_caps_the_total(pool: PgPool) {
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+
+        let first_purchase = insert_campaign(&pool, program_id, "first_purchase", None, Some(50)).await;
+        let multiplier = insert_campaign(&pool, program_id, "multiplier", Some(20_000), None).await;
+        let repository = ProgramRepository::new(pool.clone());
+
+        // Base 40 and bonuses 50 + 40 are capped at 100 for the whole earning.
+        let first = earning(program_id, user_id, 40);
+        let awards = [award(first_purchase, &first, 50), award(multiplier, &first, 40)];
+        let created = repository.create_earning(&first, &awards, Some(100)).await.unwrap().unwrap();
+
+        assert_eq!(created.points, 100);
+
+        // The failed earning keeps its bonuses for its retry.
+        repository.finish_earning(&created.id, PointEarningStatus::Failed, None, Some("mint failed"), None, &Utc::now()).await.unwrap();
+
+        let issued = sqlx::query_scalar::<_, i64>("SELECT SUM(issued_points)::BIGINT FROM campaigns WHERE program_id = $1")
+            .bind(program_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert_eq!(issued, 60);
+
+        // The second purchase gets no first purchase bonus.
+        let second = earning(program_id, user_id, 40);
+        let awards = [award(first_purchase, &second, 50), award(multiplier, &second, 40)];
+        let created = repository.create_earning(&second, &awards, None).await.unwrap().unwrap();
+
+        assert_eq!(created.points, 80);
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn claim_earning_claims_failed_and_stale_pending_earnings_once(pool: PgPool) {
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+        let repository = ProgramRepository::new(pool.clone());
+        let now = Utc::now();
+        let earning = repository.create_earning(&earning(program_id, user_id, 40), &[], None).await.unwrap().unwrap();
+
+        // A pending earning is being minted until it goes stale.
+        assert!(repository.claim_earning(&earning.id, &(now - Duration::minutes(5)), &now).await.unwrap().is_none());
+
+        let later = now + Duration::minutes(10);
+        assert!(repository.claim_earning(&earning.id, &(later - Duration::minutes(5)), &later).await.unwrap().is_some());
+        assert!(repository.claim_earning(&earning.id, &(later - Duration::minutes(5)), &later).await.unwrap().is_none());
+
+        repository.finish_earning(&earning.id, PointEarningStatus::Failed, None, Some("mint failed"), None, &later).await.unwrap();
+        assert!(matches!(
+            repository.finish_earning(&earning.id, PointEarningStatus::Completed, None, None, None, &later).await,
+            Err(ProgramRepositoryError::InvalidStatus)
+        ));
+
+        let claimed = repository.claim_earning(&earning.id, &(later - Duration::minutes(5)), &later).await.unwrap().unwrap();
+
+        assert_eq!((claimed.status, claimed.error), (PointEarningStatus::Pending, None));
+    }
+}
@@ -0,0 +1,63 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use crate::models::rate_limit_model::{RateLimitDecision, RateLimitPolicy, TokenBucket};
+use chrono::{DateTime, Utc};
+
+/// Buckets shared by every instance of the service.
+#[derive(Clone)]
+pub struct RateLimitRepository {
+    pool: PgPool
+}
+
+impl RateLimitRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Takes a token from the bucket of `key`. The row lock serializes concurrent
+    /// requests of the same key.
+    pub async fn take_token(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+        date: &DateTime<Utc>
+    ) -> Result<RateLimitDecision, RateLimitRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let bucket = sqlx::query_as::<_, TokenBucket>("SELECT tokens, updated_at FROM rate_limit_buckets WHERE key = $1 FOR UPDATE")
+            .bind(key)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        let (bucket, decision) = policy.take(bucket, *date);
+
+        sqlx::query("INSERT INTO rate_limit_buckets (key, tokens, updated_at) VALUES ($1, $2, $3) ON CONFLICT (key) DO UPDATE SET tokens = EXCLUDED.tokens, updated_at = EXCLUDED.updated_at")
+            .bind(key)
+            .bind(bucket.tokens)
+            .bind(bucket.updated_at)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(decision)
+    }
+
+    /// Deletes the buckets last used before `idle_before`.
+    pub async fn delete_idle_buckets(&self, idle_before: &DateTime<Utc>) -> Result<u64, RateLimitRepositoryError> {
+        let deleted = sqlx::query("DELETE FROM rate_limit_buckets WHERE updated_at < $1")
+            .bind(idle_before)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(deleted)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RateLimitRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError)
+}
@@ -0,0 +1,300 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::{
+    conversion_model::PointConversionStatus,
+    distribution_model::DistributionLeafStatus,
+    expiration_model::PointExpirationStatus,
+    ledger_model::SettlementItemStatus,
+    mint_batch_model::MintBatchItemStatus,
+    program_model::PointEarningStatus,
+    reconciliation_model::{ChainReconciliation, Discrepancy, MintSnapshot, RecordedTransaction, ReconciliationStatus},
+    reward_model::RedemptionOrderStatus
+};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct ReconciliationRepository {
+    pool: PgPool
+}
+
+impl ReconciliationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_report(&self, report: &ChainReconciliation) -> Result<ChainReconciliation, ReconciliationRepositoryError> {
+        let report = sqlx::query_as::<_, ChainReconciliation>("INSERT INTO reconciliation_reports (id, status, started_at) VALUES ($1, $2, $3) RETURNING *")
+            .bind(report.id)
+            .bind(report.status)
+            .bind(report.started_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(report)
+    }
+
+    /// Stores what the report found and completes it.
+    pub async fn finish_report(
+        &self,
+        id: &Uuid,
+        snapshots: &[MintSnapshot],
+        discrepancies: &[Discrepancy],
+        date: &DateTime<Utc>
+    ) -> Result<ChainReconciliation, ReconciliationRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        for snapshot in snapshots {
+            sqlx::query("INSERT INTO reconciliation_mint_snapshots (report_id, mint_pubkey, supply, recorded_supply, holders) VALUES ($1, $2, $3, $4, $5)")
+                .bind(id)
+                .bind(&snapshot.mint_pubkey)
+                .bind(snapshot.supply)
+                .bind(snapshot.recorded_supply)
+                .bind(snapshot.holders)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        for discrepancy in discrepancies {
+            sqlx::query("INSERT INTO reconciliation_discrepancies (id, report_id, kind, mint_pubkey, subject, detail, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+                .bind(discrepancy.id)
+                .bind(id)
+                .bind(discrepancy.kind)
+                .bind(&discrepancy.mint_pubkey)
+                .bind(&discrepancy.subject)
+                .bind(&discrepancy.detail)
+                .bind(discrepancy.created_at)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        let report = sqlx::query_as::<_, ChainReconciliation>("UPDATE reconciliation_reports SET status = $2, discrepancy_count = $3, finished_at = $4 WHERE id = $1 AND status = $5 RETURNING *")
+            .bind(id)
+            .bind(ReconciliationStatus::Completed)
+            .bind(i32::try_from(discrepancies.len()).unwrap_or(i32::MAX))
+            .bind(date)
+            .bind(ReconciliationStatus::Running)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or(ReconciliationRepositoryError::InvalidStatus)?;
+
+        transaction.commit().await?;
+
+        Ok(report)
+    }
+
+    pub async fn fail_report(&self, id: &Uuid, error: &str, date: &DateTime<Utc>) -> Result<ChainReconciliation, ReconciliationRepositoryError> {
+        let report = sqlx::query_as::<_, ChainReconciliation>("UPDATE reconciliation_reports SET status = $2, error = $3, finished_at = $4 WHERE id = $1 AND status = $5 RETURNING *")
+            .bind(id)
+            .bind(ReconciliationStatus::Failed)
+            .bind(error)
+            .bind(date)
+            .bind(ReconciliationStatus::Running)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(ReconciliationRepositoryError::InvalidStatus)?;
+
+        Ok(report)
+    }
+
+    pub async fn fetch_reports(&self) -> Result<Vec<ChainReconciliation>, ReconciliationRepositoryError> {
+        let reports = sqlx::query_as::<_, ChainReconciliation>("SELECT * FROM reconciliation_reports ORDER BY started_at DESC LIMIT 100")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(reports)
+    }
+
+    pub async fn fetch_report(&self, id: &Uuid) -> Result<ChainReconciliation, ReconciliationRepositoryError> {
+        match sqlx::query_as::<_, ChainReconciliation>("SELECT * FROM reconciliation_reports WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(report) => Ok(report),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ReconciliationRepositoryError::RowNotFound),
+                e => Err(ReconciliationRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_latest_report(&self) -> Result<ChainReconciliation, ReconciliationRepositoryError> {
+        match sqlx::query_as::<_, ChainReconciliation>("SELECT * FROM reconciliation_reports WHERE status = $1 ORDER BY started_at DESC LIMIT 1")
+            .bind(ReconciliationStatus::Completed)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(report) => Ok(report),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ReconciliationRepositoryError::RowNotFound),
+                e => Err(ReconciliationRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_snapshots(&self, report_id: &Uuid) -> Result<Vec<MintSnapshot>, ReconciliationRepositoryError> {
+        let snapshots = sqlx::query_as::<_, MintSnapshot>("SELECT * FROM reconciliation_mint_snapshots WHERE report_id = $1 ORDER BY mint_pubkey")
+            .bind(report_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(snapshots)
+    }
+
+    /// The mint's snapshot in the most recent completed report that has one.
+    pub async fn fetch_latest_snapshot(&self, mint_pubkey: &str) -> Result<Option<MintSnapshot>, ReconciliationRepositoryError> {
+        let snapshot = sqlx::query_as::<_, MintSnapshot>("SELECT s.* FROM reconciliation_mint_snapshots s JOIN reconciliation_reports r ON r.id = s.report_id WHERE s.mint_pubkey = $1 AND r.status = $2 ORDER BY r.started_at DESC LIMIT 1")
+            .bind(mint_pubkey)
+            .bind(ReconciliationStatus::Completed)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(snapshot)
+    }
+
+    pub async fn fetch_discrepancies(&self, report_id: &Uuid) -> Result<Vec<Discrepancy>, ReconciliationRepositoryError> {
+        let discrepancies = sqlx::query_as::<_, Discrepancy>("SELECT * FROM reconciliation_discrepancies WHERE report_id = $1 ORDER BY mint_pubkey, kind, created_at")
+            .bind(report_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(discrepancies)
+    }
+
+    /// Net supply change, in base units, of every mint and burn the database
+    /// records as landed for the mint: confirmed batch mints and distribution
+    /// claims, completed earnings and refunds, minus confirmed redemption burns,
+    /// expiration burns and settlement burns.
+    pub async fn fetch_recorded_supply(&self, mint_pubkey: &str, decimals: i16) -> Result<i64, ReconciliationRepositoryError> {
+        let recorded_supply = sqlx::query_scalar::<_, i64>(
+            "SELECT (
+                COALESCE((SELECT SUM(i.amount) FROM mint_batch_items i JOIN mint_batches b ON b.id = i.batch_id WHERE b.mint_pubkey = $1 AND i.status = $3), 0)
+                + COALESCE((SELECT SUM(l.amount) FROM distribution_leaves l JOIN distributions d ON d.id = l.distribution_id WHERE d.mint_pubkey = $1 AND l.status = $4), 0)
+                + (10::NUMERIC ^ $2) * (
+                    COALESCE((SELECT SUM(e.points) FROM point_earnings e JOIN programs p ON p.id = e.program_id WHERE p.mint_pubkey = $1 AND e.status = $5 AND e.signature IS NOT NULL), 0)
+                    + COALESCE((SELECT SUM(o.points) FROM redemption_orders o JOIN programs p ON p.id = o.program_id WHERE p.mint_pubkey = $1 AND o.status = $6 AND o.refund_signature IS NOT NULL), 0)
+                    - COALESCE((SELECT SUM(o.points) FROM redemption_orders o JOIN programs p ON p.id = o.program_id WHERE p.mint_pubkey = $1 AND o.code IS NOT NULL AND o.burn_signature IS NOT NULL), 0)
+                    - COALESCE((SELECT SUM(x.points) FROM point_expirations x JOIN programs p ON p.id = x.program_id WHERE p.mint_pubkey = $1 AND x.status = $7 AND x.signature IS NOT NULL), 0)
+                    + COALESCE((SELECT SUM(s.amount) FROM settlement_items s JOIN programs p ON p.id = s.program_id WHERE p.mint_pubkey = $1 AND s.status = $8 AND s.amount < 0), 0)
+                    + COALESCE((SELECT SUM(c.target_points) FROM point_conversions c JOIN programs p ON p.id = c.target_program_id WHERE p.mint_pubkey = $1 AND c.status = $9), 0)
+                    - COALESCE((SELECT SUM(c.source_points) FROM point_conversions c JOIN programs p ON p.id = c.source_program_id WHERE p.mint_pubkey = $1 AND c.status = $9), 0)
+                    + (SELECT COUNT(*) FROM tier_badges t WHERE t.mint_pubkey = $1 AND t.mint_signature IS NOT NULL)
+                    - (SELECT COUNT(*) FROM tier_badges t WHERE t.mint_pubkey = $1 AND t.revoke_signature IS NOT NULL)
+                )
+            )::BIGINT"
+        )
+            .bind(mint_pubkey)
+            .bind(i32::from(decimals))
+            .bind(MintBatchItemStatus::Confirmed)
+            .bind(DistributionLeafStatus::Claimed)
+            .bind(PointEarningStatus::Completed)
+            .bind(RedemptionOrderStatus::Refunded)
+            .bind(PointExpirationStatus::Completed)
+            .bind(SettlementItemStatus::Completed)
+            .bind(PointConversionStatus::Completed)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(recorded_supply)
+    }
+
+    /// Signatures the database records as landed, updated at `since` or later, or
+    /// all of them without a `since`.
+    pub async fn fetch_recorded_transactions(&self, since: Option<&DateTime<Utc>>) -> Result<Vec<RecordedTransaction>, ReconciliationRepositoryError> {
+        let transactions = sqlx::query_as::<_, RecordedTransaction>(
+            "SELECT 'mint_batch_item' AS source, i.batch_id::TEXT || ':' || i.position AS reference, b.mint_pubkey, i.signature FROM mint_batch_items i JOIN mint_batches b ON b.id = i.batch_id WHERE i.status = $2 AND i.signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR i.updated_at >= $1)
+            UNION ALL
+            SELECT 'distribution_claim', l.distribution_id::TEXT || ':' || l.leaf_index, d.mint_pubkey, l.signature FROM distribution_leaves l JOIN distributions d ON d.id = l.distribution_id WHERE l.status = $3 AND l.signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR l.claimed_at >= $1)
+            UNION ALL
+            SELECT 'point_earning', e.id::TEXT, p.mint_pubkey, e.signature FROM point_earnings e JOIN programs p ON p.id = e.program_id WHERE e.status = $4 AND e.signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR e.updated_at >= $1)
+            UNION ALL
+            SELECT 'redemption_burn', o.id::TEXT, p.mint_pubkey, o.burn_signature FROM redemption_orders o JOIN programs p ON p.id = o.program_id WHERE o.code IS NOT NULL AND o.burn_signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR o.updated_at >= $1)
+            UNION ALL
+            SELECT 'redemption_refund', o.id::TEXT, p.mint_pubkey, o.refund_signature FROM redemption_orders o JOIN programs p ON p.id = o.program_id WHERE o.status = $5 AND o.refund_signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR o.updated_at >= $1)
+            UNION ALL
+            SELECT 'point_expiration', x.id::TEXT, p.mint_pubkey, x.signature FROM point_expirations x JOIN programs p ON p.id = x.program_id WHERE x.status = $6 AND x.signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR x.updated_at >= $1)
+            UNION ALL
+            SELECT 'settlement_burn', s.id::TEXT, p.mint_pubkey, s.signature FROM settlement_items s JOIN programs p ON p.id = s.program_id WHERE s.status = $7 AND s.amount < 0 AND s.signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR s.updated_at >= $1)
+            UNION ALL
+            SELECT 'conversion', c.id::TEXT, p.mint_pubkey, c.signature FROM point_conversions c JOIN programs p ON p.id = c.source_program_id WHERE c.status = $8 AND ($1::TIMESTAMPTZ IS NULL OR c.updated_at >= $1)
+            UNION ALL
+            SELECT 'tier_badge_mint', t.id::TEXT, t.mint_pubkey, t.mint_signature FROM tier_badges t WHERE t.mint_signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR t.updated_at >= $1)
+            UNION ALL
+            SELECT 'tier_badge_revoke', t.id::TEXT, t.mint_pubkey, t.revoke_signature FROM tier_badges t WHERE t.revoke_signature IS NOT NULL AND ($1::TIMESTAMPTZ IS NULL OR t.updated_at >= $1)"
+        )
+            .bind(since)
+            .bind(MintBatchItemStatus::Confirmed)
+            .bind(DistributionLeafStatus::Claimed)
+            .bind(PointEarningStatus::Completed)
+            .bind(RedemptionOrderStatus::Refunded)
+            .bind(PointExpirationStatus::Completed)
+            .bind(SettlementItemStatus::Completed)
+            .bind(PointConversionStatus::Completed)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(transactions)
+    }
+
+    /// The `owners` that are neither a wallet, a user's legacy key, nor a recorded
+    /// receiver of the mint.
+    pub async fn fetch_unknown_owners(&self, mint_pubkey: &str, owners: &[String]) -> Result<Vec<String>, ReconciliationRepositoryError> {
+        let unknown = sqlx::query_scalar::<_, String>(
+            "SELECT owner FROM UNNEST($2::TEXT[]) AS owner
+            WHERE NOT EXISTS (SELECT 1 FROM wallets w WHERE w.pubkey = owner)
+            AND NOT EXISTS (SELECT 1 FROM mint_batch_items i JOIN mint_batches b ON b.id = i.batch_id WHERE b.mint_pubkey = $1 AND i.receiver_pubkey = owner)
+            AND NOT EXISTS (SELECT 1 FROM distribution_leaves l JOIN distributions d ON d.id = l.distribution_id WHERE d.mint_pubkey = $1 AND l.wallet_pubkey = owner)
+            AND NOT EXISTS (SELECT 1 FROM point_earnings e JOIN programs p ON p.id = e.program_id WHERE p.mint_pubkey = $1 AND e.receiver_pubkey = owner)
+            ORDER BY owner"
+        )
+            .bind(mint_pubkey)
+            .bind(owners)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(unknown)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReconciliationRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Report is not in a status that allows this")]
+    InvalidStatus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn fetch_unknown_owners_skips_known_wallets(pool: PgPool) {
+        let user_id = Uuid::new_v4();
+
+        sqlx::query("INSERT INTO users (id, email, created_at) VALUES ($1, 'owner@example.com', NOW())")
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO wallets (id, user_id, pubkey, kind, is_primary, created_at) VALUES ($1, $2, 'known', 'external', TRUE, NOW())")
+            .bind(Uuid::new_v4())
+            .bind(user_id)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let owners = vec!["unknown".to_string(), "known".to_string()];
+        let unknown = ReconciliationRepository::new(pool)
+            .fetch_unknown_owners("mint", &owners)
+            .await
+            .unwrap();
+
+        assert_eq!(unknown, vec!["unknown".to_string()]);
+    }
+}
@@ -0,0 +1,366 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::referral_model::{Referral, ReferralCode, ReferralReward, ReferralRewardStatus, ReferralRule};
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Clone)]
+pub struct ReferralRepository {
+    pool: PgPool
+}
+
+impl ReferralRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores `code` for the user unless they already have one, and returns the
+    /// user's code either way.
+    pub async fn create_code(&self, code: &ReferralCode) -> Result<ReferralCode, ReferralRepositoryError> {
+        sqlx::query("INSERT INTO referral_codes (user_id, code, created_at) VALUES ($1, $2, $3) ON CONFLICT (user_id) DO NOTHING")
+            .bind(code.user_id)
+            .bind(&code.code)
+            .bind(code.created_at)
+            .execute(&self.pool)
+            .await?;
+
+        let code = sqlx::query_as::<_, ReferralCode>("SELECT * FROM referral_codes WHERE user_id = $1")
+            .bind(code.user_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(code)
+    }
+
+    pub async fn fetch_code(&self, code: &str) -> Result<ReferralCode, ReferralRepositoryError> {
+        match sqlx::query_as::<_, ReferralCode>("SELECT * FROM referral_codes WHERE code = UPPER($1)")
+            .bind(code)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(code) => Ok(code),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ReferralRepositoryError::RowNotFound),
+                e => Err(ReferralRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_referrals(&self, referrer_id: &Uuid) -> Result<Vec<Referral>, ReferralRepositoryError> {
+        let referrals = sqlx::query_as::<_, Referral>("SELECT * FROM referrals WHERE referrer_id = $1 ORDER BY created_at DESC")
+            .bind(referrer_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(referrals)
+    }
+
+    pub async fn fetch_referral_by_referee(&self, referee_id: &Uuid) -> Result<Option<Referral>, ReferralRepositoryError> {
+        let referral = sqlx::query_as::<_, Referral>("SELECT * FROM referrals WHERE referee_id = $1")
+            .bind(referee_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(referral)
+    }
+
+    pub async fn upsert_rule(&self, rule: &ReferralRule) -> Result<ReferralRule, ReferralRepositoryError> {
+        let rule = sqlx::query_as::<_, ReferralRule>("INSERT INTO referral_rules (program_id, referrer_points, referee_points, min_purchase_amount, max_rewards_per_referrer, max_daily_rewards_per_referrer, enabled, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8) ON CONFLICT (program_id) DO UPDATE SET referrer_points = EXCLUDED.referrer_points, referee_points = EXCLUDED.referee_points, min_purchase_amount = EXCLUDED.min_purchase_amount, max_rewards_per_referrer = EXCLUDED.max_rewards_per_referrer, max_daily_rewards_per_referrer = EXCLUDED.max_daily_rewards_per_referrer, enabled = EXCLUDED.enabled, updated_at = EXCLUDED.updated_at RETURNING *")
+            .bind(rule.program_id)
+            .bind(rule.referrer_points)
+            .bind(rule.referee_points)
+            .bind(rule.min_purchase_amount)
+            .bind(rule.max_rewards_per_referrer)
+            .bind(rule.max_daily_rewards_per_referrer)
+            .bind(rule.enabled)
+            .bind(rule.updated_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(rule)
+    }
+
+    pub async fn fetch_rule(&self, program_id: &Uuid) -> Result<Option<ReferralRule>, ReferralRepositoryError> {
+        let rule = sqlx::query_as::<_, ReferralRule>("SELECT * FROM referral_rules WHERE program_id = $1")
+            .bind(program_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(rule)
+    }
+
+    /// Records the reward of a qualified referral, rejected when the referrer is
+    /// over one of the rule's limits. Returns `None` when the referral already has
+    /// a reward in the program.
+    pub async fn claim_reward(&self, reward: &ReferralReward, rule: &ReferralRule) -> Result<Option<ReferralReward>, ReferralRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Serializes claims per referrer and program, so concurrent claims cannot
+        // both pass the limits.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::TEXT || $2::TEXT, 1))")
+            .bind(reward.program_id)
+            .bind(reward.referrer_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let (total, daily) = sqlx::query_as::<_, (i64, i64)>("SELECT COUNT(*), COUNT(*) FILTER (WHERE created_at > $3) FROM referral_rewards WHERE program_id = $1 AND referrer_id = $2 AND status <> $4")
+            .bind(reward.program_id)
+            .bind(reward.referrer_id)
+            .bind(reward.created_at - Duration::days(1))
+            .bind(ReferralRewardStatus::Rejected)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        let error = if rule.max_rewards_per_referrer.is_some_and(|max| total >= i64::from(max)) {
+            Some("Referrer reached the program's referral limit")
+        } else if rule.max_daily_rewards_per_referrer.is_some_and(|max| daily >= i64::from(max)) {
+            Some("Referrer reached the program's daily referral limit")
+        } else {
+            None
+        };
+
+        let status = match error {
+            Some(_) => ReferralRewardStatus::Rejected,
+            None => ReferralRewardStatus::Pending
+        };
+
+        let reward = sqlx::query_as::<_, ReferralReward>("INSERT INTO referral_rewards (id, referral_id, program_id, referrer_id, referee_id, qualifying_earning_id, referrer_points, referee_points, status, error, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11) ON CONFLICT (referral_id, program_id) DO NOTHING RETURNING *")
+            .bind(reward.id)
+            .bind(reward.referral_id)
+            .bind(reward.program_id)
+            .bind(reward.referrer_id)
+            .bind(reward.referee_id)
+            .bind(reward.qualifying_earning_id)
+            .bind(reward.referrer_points)
+            .bind(reward.referee_points)
+            .bind(status)
+            .bind(error)
+            .bind(reward.created_at)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(reward)
+    }
+
+    /// Moves a failed reward, or one left pending since before `pending_before`,
+    /// back to pending so it can be issued again.
+    pub async fn retry_reward(
+        &self,
+        id: &Uuid,
+        pending_before: &DateTime<Utc>,
+        date: &DateTime<Utc>
+    ) -> Result<ReferralReward, ReferralRepositoryError> {
+        let reward = sqlx::query_as::<_, ReferralReward>("UPDATE referral_rewards SET status = $2, error = NULL, updated_at = $3 WHERE id = $1 AND (status = $4 OR (status = $2 AND updated_at < $5)) RETURNING *")
+            .bind(id)
+            .bind(ReferralRewardStatus::Pending)
+            .bind(date)
+            .bind(ReferralRewardStatus::Failed)
+            .bind(pending_before)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(ReferralRepositoryError::InvalidStatus)?;
+
+        Ok(reward)
+    }
+
+    pub async fn finish_reward(
+        &self,
+        id: &Uuid,
+        status: ReferralRewardStatus,
+        referrer_earning_id: Option<&Uuid>,
+        referee_earning_id: Option<&Uuid>,
+        error: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<ReferralReward, ReferralRepositoryError> {
+        let reward = sqlx::query_as::<_, ReferralReward>("UPDATE referral_rewards SET status = $2, referrer_earning_id = COALESCE($3, referrer_earning_id), referee_earning_id = COALESCE($4, referee_earning_id), error = $5, updated_at = $6 WHERE id = $1 AND status = $7 RETURNING *")
+            .bind(id)
+            .bind(status)
+            .bind(referrer_earning_id)
+            .bind(referee_earning_id)
+            .bind(error)
+            .bind(date)
+            .bind(ReferralRewardStatus::Pending)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(ReferralRepositoryError::InvalidStatus)?;
+
+        Ok(reward)
+    }
+
+    pub async fn fetch_reward(&self, id: &Uuid) -> Result<ReferralReward, ReferralRepositoryError> {
+        match sqlx::query_as::<_, ReferralReward>("SELECT * FROM referral_rewards WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(reward) => Ok(reward),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(ReferralRepositoryError::RowNotFound),
+                e => Err(ReferralRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_program_rewards(
+        &self,
+        program_id: &Uuid,
+        status: Option<ReferralRewardStatus>
+    ) -> Result<Vec<ReferralReward>, ReferralRepositoryError> {
+        let rewards = sqlx::query_as::<_, ReferralReward>("SELECT * FROM referral_rewards WHERE program_id = $1 AND ($2::TEXT IS NULL OR status = $2) ORDER BY created_at DESC LIMIT 100")
+            .bind(program_id)
+            .bind(status)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rewards)
+    }
+
+    /// Rewards the user got as referrer or as referee.
+    pub async fn fetch_user_rewards(&self, user_id: &Uuid) -> Result<Vec<ReferralReward>, ReferralRepositoryError> {
+        let rewards = sqlx::query_as::<_, ReferralReward>("SELECT * FROM referral_rewards WHERE referrer_id = $1 OR referee_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rewards)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ReferralRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Reward is not in a status that allows this")]
+    InvalidStatus
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::fixtures::{insert_program, insert_user};
+
+    fn rule(program_id: Uuid, max_rewards: Option<i32>, max_daily_rewards: Option<i32>) -> ReferralRule {
+        let now = Utc::now();
+
+        ReferralRule {
+            program_id,
+            referrer_points: 50,
+            referee_points: 25,
+            min_purchase_amount: 0,
+            max_rewards_per_referrer: max_rewards,
+            max_daily_rewards_per_referrer: max_daily_rewards,
+            enabled: true,
+            created_at: now,
+            updated_at: now
+        }
+    }
+
+    /// Signs a new referee up with the referrer's code and records their
+    /// qualifying earning.
+    async fn referred_reward(pool: &PgPool, program_id: Uuid, referrer_id: Uuid, date: DateTime<Utc>) -> ReferralReward {
+        let (referral_id, earning_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let referee_id = insert_user(pool, &format!("{}@example.com", Uuid::new_v4()), &Uuid::new_v4().to_string()).await;
+
+        sqlx::query("INSERT INTO referrals (id, referrer_id, referee_id, code, created_at) VALUES ($1, $2, $3, 'CODE', $4)")
+            .bind(referral_id)
+            .bind(referrer_id)
+            .bind(referee_id)
+            .bind(date)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO point_earnings (id, program_id, user_id, purchase_amount, points, receiver_pubkey, status, created_at, updated_at) VALUES ($1, $2, $3, 100, 10, 'receiver', 'completed', $4, $4)")
+            .bind(earning_id)
+            .bind(program_id)
+            .bind(referee_id)
+            .bind(date)
+            .execute(pool)
+            .await
+            .unwrap();
+
+        ReferralReward {
+            id: Uuid::new_v4(),
+            referral_id,
+            program_id,
+            referrer_id,
+            referee_id,
+            qualifying_earning_id: earning_id,
+            referrer_points: 50,
+            referee_points: 25,
+            referrer_earning_id: None,
+            referee_earning_id: None,
+            status: ReferralRewardStatus::Pending,
+            error: None,
+            created_at: date,
+            updated_at: date
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn claim_reward_rejects_rewards_over_the_referrer_limits(pool: PgPool) {
+        let repository = ReferralRepository::new(pool.clone());
+        let program_id = insert_program(&pool, "mint").await;
+        let referrer_id = insert_user(&pool, "referrer@example.com", "referrer").await;
+        let rule = rule(program_id, Some(2), Some(1));
+        let now = Utc::now();
+
+        let first = referred_reward(&pool, program_id, referrer_id, now).await;
+        let claimed = repository.claim_reward(&first, &rule).await.unwrap().unwrap();
+        assert_eq!(claimed.status, ReferralRewardStatus::Pending);
+        assert!(repository.claim_reward(&first, &rule).await.unwrap().is_none());
+
+        let same_day = referred_reward(&pool, program_id, referrer_id, now + Duration::hours(1)).await;
+        let rejected = repository.claim_reward(&same_day, &rule).await.unwrap().unwrap();
+        assert_eq!(rejected.status, ReferralRewardStatus::Rejected);
+        assert_eq!(rejected.error.as_deref(), Some("Referrer reached the program's daily referral limit"));
+
+        // Rejected rewards do not count towards the limits.
+        let next_day = referred_reward(&pool, program_id, referrer_id, now + Duration::days(2)).await;
+        let claimed = repository.claim_reward(&next_day, &rule).await.unwrap().unwrap();
+        assert_eq!(claimed.status, ReferralRewardStatus::Pending);
+
+        let later = referred_reward(&pool, program_id, referrer_id, now + Duration::days(4)).await;
+        let rejected = repository.claim_reward(&later, &rule).await.unwrap().unwrap();
+        assert_eq!(rejected.status, ReferralRewardStatus::Rejected);
+        assert_eq!(rejected.error.as_deref(), Some("Referrer reached the program's referral limit"));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn retry_reward_claims_failed_and_stale_pending_rewards_once(pool: PgPool) {
+        let repository = ReferralRepository::new(pool.clone());
+        let program_id = insert_program(&pool, "mint").await;
+        let referrer_id = insert_user(&pool, "referrer@example.com", "referrer").await;
+        let rule = rule(program_id, None, None);
+        let now = Utc::now();
+
+        let reward = referred_reward(&pool, program_id, referrer_id, now).await;
+        let reward = repository.claim_reward(&reward, &rule).await.unwrap().unwrap();
+
+        // A reward pending since after `pending_before` may still be in flight.
+        assert!(matches!(
+            repository.retry_reward(&reward.id, &(now - Duration::minutes(5)), &now).await,
+            Err(ReferralRepositoryError::InvalidStatus)
+        ));
+
+        let later = now + Duration::minutes(10);
+        let retried = repository.retry_reward(&reward.id, &(later - Duration::minutes(5)), &later).await.unwrap();
+        assert_eq!(retried.status, ReferralRewardStatus::Pending);
+        assert!(matches!(
+            repository.retry_reward(&reward.id, &(later - Duration::minutes(5)), &later).await,
+            Err(ReferralRepositoryError::InvalidStatus)
+        ));
+
+        let failed = repository.finish_reward(&reward.id, ReferralRewardStatus::Failed, None, None, Some("Mint failed"), &later).await.unwrap();
+        assert_eq!(failed.status, ReferralRewardStatus::Failed);
+        let retried = repository.retry_reward(&reward.id, &now, &later).await.unwrap();
+        assert_eq!(retried.status, ReferralRewardStatus::Pending);
+        assert_eq!(retried.error, None);
+    }
+}
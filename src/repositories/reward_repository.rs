@@ -0,0 +1,570 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::{
+    models::reward_model::{
+        RedemptionOrder,
+        RedemptionOrderEvent,
+        RedemptionOrderStatus,
+        RedemptionStep,
+        RewardItem
+    },
+    repositories::point_lot_repository::{consume_lots, release_lots, LotConsumer}
+};
+use chrono::{DateTime, Utc};
+
+/// Columns an order transition may set. Missing ones keep their value, except
+/// `error`, which is cleared.
+#[derive(Default)]
+struct OrderChanges<'a> {
+    code: Option<&'a str>,
+    burn_signature: Option<&'a str>,
+    burn_recent_blockhash: Option<&'a str>,
+    refund_signature: Option<&'a str>,
+    refund_recent_blockhash: Option<&'a str>,
+    error: Option<&'a str>,
+    /// Only applies the transition while the order was last updated at this date,
+    /// that is while it is still held by the caller that claimed it.
+    claimed_at: Option<&'a DateTime<Utc>>,
+    /// Gives the stock unit and the consumed point lots back, for orders whose
+    /// points were not taken or were minted back.
+    released: bool
+}
+
+#[derive(Clone)]
+pub struct RewardRepository {
+    pool: PgPool
+}
+
+impl RewardRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_item(&self, item: &RewardItem) -> Result<RewardItem, RewardRepositoryError> {
+        let item = sqlx::query_as::<_, RewardItem>("INSERT INTO reward_items (id, program_id, name, description, point_price, stock, valid_from, valid_until, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *")
+            .bind(item.id)
+            .bind(item.program_id)
+            .bind(&item.name)
+            .bind(&item.description)
+            .bind(item.point_price)
+            .bind(item.stock)
+            .bind(item.valid_from)
+            .bind(item.valid_until)
+            .bind(item.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(item)
+    }
+
+    /// Items of a program that can be redeemed at `date`.
+    pub async fn fetch_available_items(&self, program_id: &Uuid, date: &DateTime<Utc>) -> Result<Vec<RewardItem>, RewardRepositoryError> {
+        let items = sqlx::query_as::<_, RewardItem>("SELECT * FROM reward_items WHERE program_id = $1 AND archived_at IS NULL AND valid_from <= $2 AND (valid_until IS NULL OR valid_until > $2) AND (stock IS NULL OR stock > 0) ORDER BY point_price, name")
+            .bind(program_id)
+            .bind(date)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(items)
+    }
+
+    pub async fn fetch_item(&self, id: &Uuid) -> Result<RewardItem, RewardRepositoryError> {
+        match sqlx::query_as::<_, RewardItem>("SELECT * FROM reward_items WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(item) => Ok(item),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(RewardRepositoryError::RowNotFound),
+                e => Err(RewardRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn archive_item(&self, program_id: &Uuid, id: &Uuid, date: &DateTime<Utc>) -> Result<RewardItem, RewardRepositoryError> {
+        match sqlx::query_as::<_, RewardItem>("UPDATE reward_items SET archived_at = COALESCE(archived_at, $3) WHERE id = $2 AND program_id = $1 RETURNING *")
+            .bind(program_id)
+            .bind(id)
+            .bind(date)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(item) => Ok(item),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(RewardRepositoryError::RowNotFound),
+                e => Err(RewardRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Takes one unit of the item, consumes the user's point lots oldest expiry
+    /// first and records the order in one transaction. Fails with `ItemUnavailable`
+    /// when the item is out of stock, archived or not valid at the order date.
+    pub async fn reserve_order(&self, order: &RedemptionOrder) -> Result<RedemptionOrder, RewardRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let reserved = sqlx::query("UPDATE reward_items SET stock = stock - 1 WHERE id = $1 AND program_id = $2 AND archived_at IS NULL AND valid_from <= $3 AND (valid_until IS NULL OR valid_until > $3) AND (stock IS NULL OR stock > 0)")
+            .bind(order.item_id)
+            .bind(order.program_id)
+            .bind(order.created_at)
+            .execute(&mut *transaction)
+            .await?;
+
+        if reserved.rows_affected() == 0 {
+            transaction.rollback().await?;
+            return Err(RewardRepositoryError::ItemUnavailable);
+        }
+
+        let order = sqlx::query_as::<_, RedemptionOrder>("INSERT INTO redemption_orders (id, program_id, item_id, user_id, wallet_pubkey, points, status, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8) RETURNING *")
+            .bind(order.id)
+            .bind(order.program_id)
+            .bind(order.item_id)
+            .bind(order.user_id)
+            .bind(&order.wallet_pubkey)
+            .bind(order.points)
+            .bind(RedemptionOrderStatus::Reserved)
+            .bind(order.created_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        consume_lots(
+            &mut transaction,
+            LotConsumer::Order(order.id),
+            &order.user_id,
+            &order.program_id,
+            order.points,
+            &order.created_at
+        ).await?;
+
+        Self::insert_event(&mut transaction, &order.id, RedemptionStep::Reserved, None, &order.created_at).await?;
+
+        transaction.commit().await?;
+
+        Ok(order)
+    }
+
+    /// Records the signed burn before it is sent, so its outcome can always be
+    /// looked up afterwards.
+    pub async fn submit_burn(
+        &self,
+        id: &Uuid,
+        signature: &str,
+        recent_blockhash: &str,
+        date: &DateTime<Utc>
+    ) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Reserved],
+            RedemptionOrderStatus::Burning,
+            OrderChanges { burn_signature: Some(signature), burn_recent_blockhash: Some(recent_blockhash), ..Default::default() },
+            RedemptionStep::BurnSubmitted,
+            Some(signature),
+            date
+        ).await
+    }
+
+    /// Only orders whose burn is in flight can be fulfilled.
+    pub async fn fulfill_order(&self, id: &Uuid, code: &str, date: &DateTime<Utc>) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Burning],
+            RedemptionOrderStatus::Fulfilled,
+            OrderChanges { code: Some(code), ..Default::default() },
+            RedemptionStep::BurnConfirmed,
+            None,
+            date
+        ).await
+    }
+
+    /// Fulfills an order of a deferred program, whose points were debited in the
+    /// ledger instead of burned.
+    pub async fn fulfill_debited_order(
+        &self,
+        id: &Uuid,
+        code: &str,
+        ledger_transaction_id: &Uuid,
+        date: &DateTime<Utc>
+    ) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Reserved],
+            RedemptionOrderStatus::Fulfilled,
+            OrderChanges { code: Some(code), ..Default::default() },
+            RedemptionStep::LedgerDebited,
+            Some(&ledger_transaction_id.to_string()),
+            date
+        ).await
+    }
+
+    pub async fn fail_order(&self, id: &Uuid, error: &str, date: &DateTime<Utc>) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Reserved, RedemptionOrderStatus::Burning],
+            RedemptionOrderStatus::Failed,
+            OrderChanges { error: Some(error), released: true, ..Default::default() },
+            RedemptionStep::BurnFailed,
+            Some(error),
+            date
+        ).await
+    }
+
+    /// Moves the order to `Refunding`, so only one caller mints the points back.
+    pub async fn claim_refund(&self, id: &Uuid, date: &DateTime<Utc>) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Fulfilled, RedemptionOrderStatus::RefundFailed],
+            RedemptionOrderStatus::Refunding,
+            OrderChanges::default(),
+            RedemptionStep::RefundRequested,
+            None,
+            date
+        ).await
+    }
+
+    /// Records the signed refund mint before it is sent, so its outcome can always
+    /// be looked up before another one is built. Fails with `InvalidStatus` once
+    /// the refund claimed at `claimed_at` was given up or claimed again.
+    pub async fn submit_refund(
+        &self,
+        id: &Uuid,
+        signature: &str,
+        recent_blockhash: &str,
+        claimed_at: &DateTime<Utc>,
+        date: &DateTime<Utc>
+    ) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Refunding],
+            RedemptionOrderStatus::Refunding,
+            OrderChanges {
+                refund_signature: Some(signature),
+                refund_recent_blockhash: Some(recent_blockhash),
+                claimed_at: Some(claimed_at),
+                ..Default::default()
+            },
+            RedemptionStep::RefundSubmitted,
+            Some(signature),
+            date
+        ).await
+    }
+
+    /// Refunds are minted on chain, with a signature, or credited in the ledger
+    /// of deferred programs.
+    pub async fn complete_refund(&self, id: &Uuid, signature: Option<&str>, date: &DateTime<Utc>) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Refunding],
+            RedemptionOrderStatus::Refunded,
+            OrderChanges { refund_signature: signature, released: true, ..Default::default() },
+            if signature.is_some() { RedemptionStep::RefundMinted } else { RedemptionStep::RefundCredited },
+            signature,
+            date
+        ).await
+    }
+
+    pub async fn fail_refund(&self, id: &Uuid, error: &str, date: &DateTime<Utc>) -> Result<RedemptionOrder, RewardRepositoryError> {
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Refunding],
+            RedemptionOrderStatus::RefundFailed,
+            OrderChanges { error: Some(error), ..Default::default() },
+            RedemptionStep::RefundFailed,
+            Some(error),
+            date
+        ).await
+    }
+
+    /// Fails a refund claimed at `claimed_at` that never recorded its mint, so it
+    /// can be claimed again. Fails with `InvalidStatus` when the refund moved on.
+    pub async fn time_out_refund(&self, id: &Uuid, claimed_at: &DateTime<Utc>, date: &DateTime<Utc>) -> Result<RedemptionOrder, RewardRepositoryError> {
+        let error = "Refund timed out before its mint was sent";
+
+        self.transition(
+            id,
+            &[RedemptionOrderStatus::Refunding],
+            RedemptionOrderStatus::RefundFailed,
+            OrderChanges { error: Some(error), claimed_at: Some(claimed_at), ..Default::default() },
+            RedemptionStep::RefundFailed,
+            Some(error),
+            date
+        ).await
+    }
+
+    pub async fn fetch_order(&self, id: &Uuid) -> Result<RedemptionOrder, RewardRepositoryError> {
+        match sqlx::query_as::<_, RedemptionOrder>("SELECT * FROM redemption_orders WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(order) => Ok(order),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(RewardRepositoryError::RowNotFound),
+                e => Err(RewardRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_user_orders(&self, user_id: &Uuid) -> Result<Vec<RedemptionOrder>, RewardRepositoryError> {
+        let orders = sqlx::query_as::<_, RedemptionOrder>("SELECT * FROM redemption_orders WHERE user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(orders)
+    }
+
+    pub async fn fetch_order_events(&self, order_id: &Uuid) -> Result<Vec<RedemptionOrderEvent>, RewardRepositoryError> {
+        let events = sqlx::query_as::<_, RedemptionOrderEvent>("SELECT * FROM redemption_order_events WHERE order_id = $1 ORDER BY created_at, step")
+            .bind(order_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(events)
+    }
+
+    /// Updates the order only when its status is one of `from`, and records the
+    /// step in the same transaction. Fails with `InvalidStatus` otherwise.
+    #[allow(clippy::too_many_arguments)]
+    async fn transition(
+        &self,
+        id: &Uuid,
+        from: &[RedemptionOrderStatus],
+        to: RedemptionOrderStatus,
+        changes: OrderChanges<'_>,
+        step: RedemptionStep,
+        detail: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<RedemptionOrder, RewardRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let order = sqlx::query_as::<_, RedemptionOrder>("UPDATE redemption_orders SET status = $2, code = COALESCE($3, code), burn_signature = COALESCE($4, burn_signature), burn_recent_blockhash = COALESCE($5, burn_recent_blockhash), refund_signature = COALESCE($6, refund_signature), refund_recent_blockhash = COALESCE($7, refund_recent_blockhash), error = $8, updated_at = $9 WHERE id = $1 AND status = ANY($10) AND ($11::TIMESTAMPTZ IS NULL OR updated_at = $11) RETURNING *")
+            .bind(id)
+            .bind(to)
+            .bind(changes.code)
+            .bind(changes.burn_signature)
+            .bind(changes.burn_recent_blockhash)
+            .bind(changes.refund_signature)
+            .bind(changes.refund_recent_blockhash)
+            .bind(changes.error)
+            .bind(date)
+            .bind(from)
+            .bind(changes.claimed_at)
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+        let Some(order) = order else {
+            transaction.rollback().await?;
+            return Err(RewardRepositoryError::InvalidStatus);
+        };
+
+        if changes.released {
+            sqlx::query("UPDATE reward_items SET stock = stock + 1 WHERE id = $1 AND stock IS NOT NULL")
+                .bind(order.item_id)
+                .execute(&mut *transaction)
+                .await?;
+
+            release_lots(&mut transaction, LotConsumer::Order(order.id)).await?;
+        }
+
+        Self::insert_event(&mut transaction, id, step, detail, date).await?;
+
+        transaction.commit().await?;
+
+        Ok(order)
+    }
+
+    async fn insert_event(
+        transaction: &mut Transaction<'_, Postgres>,
+        order_id: &Uuid,
+        step: RedemptionStep,
+        detail: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<(), RewardRepositoryError> {
+        sqlx::query("INSERT INTO redemption_order_events (id, order_id, step, detail, created_at) VALUES ($1, $2, $3, $4, $5)")
+            .bind(Uuid::new_v4())
+            .bind(order_id)
+            .bind(step)
+            .bind(detail)
+            .bind(date)
+            .execute(&mut **transaction)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RewardRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Reward is out of stock or not available")]
+    ItemUnavailable,
+    #[error("Order is not in a status that allows this")]
+    InvalidStatus
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::repositories::fixtures::{insert_lot, insert_program, insert_user};
+
+    async fn create_item(repository: &RewardRepository, program_id: Uuid, stock: i32) -> RewardItem {
+        let now = Utc::now();
+
+        repository.create_item(&RewardItem {
+            id: Uuid::new_v4(),
+            program_id,
+            name: "Coffee".to_string(),
+            description: None,
+            point_price: 15,
+            stock: Some(stock),
+            valid_from: now - Duration::days(1),
+            valid_until: None,
+            created_at: now,
+            archived_at: None
+        }).await.unwrap()
+    }
+
+    fn order(item: &RewardItem, user_id: Uuid) -> RedemptionOrder {
+        let now = Utc::now();
+
+        RedemptionOrder {
+            id: Uuid::new_v4(),
+            program_id: item.program_id,
+            item_id: item.id,
+            user_id,
+            wallet_pubkey: "ana-wallet".to_string(),
+            points: item.point_price,
+            status: RedemptionOrderStatus::Reserved,
+            code: None,
+            burn_signature: None,
+            burn_recent_blockhash: None,
+            refund_signature: None,
+            refund_recent_blockhash: None,
+            error: None,
+            created_at: now,
+            updated_at: now
+        }
+    }
+
+    async fn remaining(pool: &PgPool, lot_ids: &[Uuid]) -> Vec<i64> {
+        let mut remaining = Vec::with_capacity(lot_ids.len());
+
+        for lot_id in lot_ids {
+            remaining.push(
+                sqlx::query_scalar::<_, i64>("SELECT remaining FROM point_lots WHERE id = $1")
+                    .bind(lot_id)
+                    .fetch_one(pool)
+                    .await
+                    .unwrap()
+            );
+        }
+
+        remaining
+    }
+
+    async fn stock(repository: &RewardRepository, item_id: &Uuid) -> Option<i32> {
+        repository.fetch_item(item_id).await.unwrap().stock
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn reserve_order_consumes_lots_oldest_expiry_first_and_failing_gives_them_back(pool: PgPool) {
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+        let repository = RewardRepository::new(pool.clone());
+        let now = Utc::now();
+
+        let lots = [
+            insert_lot(&pool, program_id, user_id, 10, "ana-wallet", None).await,
+            insert_lot(&pool, program_id, user_id, 10, "ana-wallet", Some(now + Duration::days(2))).await,
+            insert_lot(&pool, program_id, user_id, 10, "ana-wallet", Some(now + Duration::days(1))).await,
+            insert_lot(&pool, program_id, user_id, 10, "ana-wallet", Some(now - Duration::days(1))).await
+        ];
+        let item = create_item(&repository, program_id, 1).await;
+
+        let reserved = repository.reserve_order(&order(&item, user_id)).await.unwrap();
+
+        // Expired lots are skipped and lots without expiry are taken last.
+        assert_eq!(remaining(&pool, &lots).await, [10, 5, 0, 10]);
+        assert_eq!(stock(&repository, &item.id).await, Some(0));
+        assert!(matches!(
+            repository.reserve_order(&order(&item, user_id)).await,
+            Err(RewardRepositoryError::ItemUnavailable)
+        ));
+
+        repository.fail_order(&reserved.id, "burn failed", &now).await.unwrap();
+
+        assert_eq!(remaining(&pool, &lots).await, [10, 10, 10, 10]);
+        assert_eq!(stock(&repository, &item.id).await, Some(1));
+        assert!(matches!(
+            repository.fail_order(&reserved.id, "burn failed", &now).await,
+            Err(RewardRepositoryError::InvalidStatus)
+        ));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn completed_refund_gives_stock_and_lots_back(pool: PgPool) {
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+        let repository = RewardRepository::new(pool.clone());
+        let now = Utc::now();
+
+        let lots = [insert_lot(&pool, program_id, user_id, 20, "ana-wallet", Some(now + Duration::days(1))).await];
+        let item = create_item(&repository, program_id, 1).await;
+
+        let reserved = repository.reserve_order(&order(&item, user_id)).await.unwrap();
+        repository.submit_burn(&reserved.id, "burn", "blockhash", &now).await.unwrap();
+        repository.fulfill_order(&reserved.id, "CODE", &now).await.unwrap();
+
+        assert_eq!(remaining(&pool, &lots).await, [5]);
+
+        let claimed = repository.claim_refund(&reserved.id, &now).await.unwrap();
+        repository.submit_refund(&reserved.id, "refund", "blockhash", &claimed.updated_at, &now).await.unwrap();
+        let refunded = repository.complete_refund(&reserved.id, Some("refund"), &now).await.unwrap();
+
+        assert_eq!(refunded.status, RedemptionOrderStatus::Refunded);
+        assert_eq!(remaining(&pool, &lots).await, [20]);
+        assert_eq!(stock(&repository, &item.id).await, Some(1));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn timed_out_refund_cannot_be_submitted_by_its_former_claimer(pool: PgPool) {
+        let program_id = insert_program(&pool, "mint").await;
+        let user_id = insert_user(&pool, "ana@example.com", "ana-wallet").await;
+        let repository = RewardRepository::new(pool.clone());
+        let now = Utc::now();
+        let later = now + Duration::minutes(10);
+        let item = create_item(&repository, program_id, 1).await;
+
+        let reserved = repository.reserve_order(&order(&item, user_id)).await.unwrap();
+        repository.submit_burn(&reserved.id, "burn", "blockhash", &now).await.unwrap();
+        repository.fulfill_order(&reserved.id, "CODE", &now).await.unwrap();
+
+        let stale = repository.claim_refund(&reserved.id, &now).await.unwrap();
+        let failed = repository.time_out_refund(&reserved.id, &stale.updated_at, &later).await.unwrap();
+        assert_eq!(failed.status, RedemptionOrderStatus::RefundFailed);
+
+        let claimed = repository.claim_refund(&reserved.id, &(later + Duration::seconds(1))).await.unwrap();
+
+        assert!(matches!(
+            repository.submit_refund(&reserved.id, "stale", "blockhash", &stale.updated_at, &later).await,
+            Err(RewardRepositoryError::InvalidStatus)
+        ));
+        assert!(matches!(
+            repository.time_out_refund(&reserved.id, &stale.updated_at, &later).await,
+            Err(RewardRepositoryError::InvalidStatus)
+        ));
+
+        repository.submit_refund(&reserved.id, "refund", "blockhash", &claimed.updated_at, &later).await.unwrap();
+    }
+}
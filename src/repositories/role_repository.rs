@@ -0,0 +1,133 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::role_model::{Permission, Role, RoleAssignment};
+
+/// Roles are read together with their permissions.
+const SELECT_ROLES: &str = "SELECT roles.*, ARRAY(SELECT permission FROM role_permissions WHERE role_permissions.role_id = roles.id ORDER BY permission) AS permissions FROM roles";
+
+#[derive(Clone)]
+pub struct RoleRepository {
+    pool: PgPool
+}
+
+impl RoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn fetch_roles(&self) -> Result<Vec<Role>, RoleRepositoryError> {
+        let roles = sqlx::query_as::<_, Role>(&format!("{} ORDER BY roles.created_at, roles.name", SELECT_ROLES))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn fetch_roles_by_name(&self, names: &[String]) -> Result<Vec<Role>, RoleRepositoryError> {
+        let roles = sqlx::query_as::<_, Role>(&format!("{} WHERE roles.name = ANY($1) ORDER BY roles.name", SELECT_ROLES))
+            .bind(names)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn create_role(&self, role: &Role) -> Result<(), RoleRepositoryError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("INSERT INTO roles (id, name, description, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(role.id)
+            .bind(&role.name)
+            .bind(&role.description)
+            .bind(role.created_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| match e {
+                SqlxError::Database(db_error) if db_error.is_unique_violation() => RoleRepositoryError::RoleExists,
+                e => RoleRepositoryError::DatabaseError(e)
+            })?;
+
+        sqlx::query("INSERT INTO role_permissions (role_id, permission) SELECT $1, UNNEST($2::TEXT[]) ON CONFLICT DO NOTHING")
+            .bind(role.id)
+            .bind(&role.permissions)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_assignments(&self) -> Result<Vec<RoleAssignment>, RoleRepositoryError> {
+        let assignments = sqlx::query_as::<_, RoleAssignment>("SELECT * FROM role_assignments ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(assignments)
+    }
+
+    pub async fn assign_role(&self, assignment: &RoleAssignment) -> Result<RoleAssignment, RoleRepositoryError> {
+        match sqlx::query_as::<_, RoleAssignment>("INSERT INTO role_assignments (id, role_id, api_key_id, user_id, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *")
+            .bind(assignment.id)
+            .bind(assignment.role_id)
+            .bind(assignment.api_key_id)
+            .bind(assignment.user_id)
+            .bind(assignment.created_at)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(assignment) => Ok(assignment),
+            Err(e) => match e {
+                SqlxError::Database(db_error) if db_error.is_unique_violation() => Err(RoleRepositoryError::AlreadyAssigned),
+                SqlxError::Database(db_error) if db_error.is_foreign_key_violation() => Err(RoleRepositoryError::RowNotFound),
+                e => Err(RoleRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn revoke_assignment(&self, id: &Uuid) -> Result<RoleAssignment, RoleRepositoryError> {
+        match sqlx::query_as::<_, RoleAssignment>("DELETE FROM role_assignments WHERE id = $1 RETURNING *")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(assignment) => Ok(assignment),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(RoleRepositoryError::RowNotFound),
+                e => Err(RoleRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_api_key_permissions(&self, api_key_id: &Uuid) -> Result<Vec<Permission>, RoleRepositoryError> {
+        let permissions = sqlx::query_scalar::<_, Permission>("SELECT DISTINCT role_permissions.permission FROM role_assignments JOIN role_permissions ON role_permissions.role_id = role_assignments.role_id WHERE role_assignments.api_key_id = $1")
+            .bind(api_key_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(permissions)
+    }
+
+    pub async fn fetch_user_permissions(&self, user_id: &Uuid) -> Result<Vec<Permission>, RoleRepositoryError> {
+        let permissions = sqlx::query_scalar::<_, Permission>("SELECT DISTINCT role_permissions.permission FROM role_assignments JOIN role_permissions ON role_permissions.role_id = role_assignments.role_id WHERE role_assignments.user_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(permissions)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RoleRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Role, API key or user was not found")]
+    RowNotFound,
+    #[error("A role with this name already exists")]
+    RoleExists,
+    #[error("Role is already assigned")]
+    AlreadyAssigned
+}
@@ -0,0 +1,289 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::{
+    mint_batch_model::{MintBatch, MintBatchItem, MintBatchItemStatus, MintBatchStatus},
+    mint_model::Mint
+};
+use chrono::{DateTime, Utc};
+
+/// How long a batch may stay `processing` without progress before it can be resumed.
+const STALE_BATCH_MINUTES: i32 = 10;
+
+#[derive(Clone)]
+pub struct SolanaRepository {
+    pool: PgPool
+}
+
+impl SolanaRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_mint_batch(
+        &self,
+        id: &Uuid,
+        date: &DateTime<Utc>,
+        mint_pubkey: &str,
+        recipients: &[(String, i64)],
+    ) -> Result<MintBatch, SolanaRepositoryError> {
+        let positions: Vec<i32> = (0..recipients.len() as i32).collect();
+        let receiver_pubkeys: Vec<String> = recipients.iter().map(|(pubkey, _)| pubkey.clone()).collect();
+        let amounts: Vec<i64> = recipients.iter().map(|(_, amount)| *amount).collect();
+
+        let mut transaction = self.pool.begin().await?;
+
+        let batch = sqlx::query_as::<_, MintBatch>("INSERT INTO mint_batches (id, mint_pubkey, status, created_at, updated_at) VALUES ($1, $2, $3, $4, $4) RETURNING *")
+            .bind(id)
+            .bind(mint_pubkey)
+            .bind(MintBatchStatus::Processing)
+            .bind(date)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        sqlx::query("INSERT INTO mint_batch_items (batch_id, position, receiver_pubkey, amount, status, updated_at) SELECT $1, item.position, item.receiver_pubkey, item.amount, $2, $3 FROM UNNEST($4::INTEGER[], $5::TEXT[], $6::BIGINT[]) AS item(position, receiver_pubkey, amount)")
+            .bind(id)
+            .bind(MintBatchItemStatus::Pending)
+            .bind(date)
+            .bind(&positions)
+            .bind(&receiver_pubkeys)
+            .bind(&amounts)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(batch)
+    }
+
+    pub async fn fetch_mint_batch(&self, id: &Uuid) -> Result<MintBatch, SolanaRepositoryError> {
+        match sqlx::query_as::<_, MintBatch>("SELECT * FROM mint_batches WHERE id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(batch) => Ok(batch),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(SolanaRepositoryError::RowNotFound),
+                e => Err(SolanaRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    pub async fn fetch_mint_batch_items(&self, batch_id: &Uuid) -> Result<Vec<MintBatchItem>, SolanaRepositoryError> {
+        let items = sqlx::query_as::<_, MintBatchItem>("SELECT * FROM mint_batch_items WHERE batch_id = $1 ORDER BY position")
+            .bind(batch_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(items)
+    }
+
+    /// Marks a batch as `processing` unless another worker is already processing it.
+    pub async fn claim_mint_batch(&self, id: &Uuid) -> Result<Option<MintBatch>, SolanaRepositoryError> {
+        let batch = sqlx::query_as::<_, MintBatch>("UPDATE mint_batches SET status = $2, updated_at = NOW() WHERE id = $1 AND status <> $4 AND (status <> $2 OR updated_at < NOW() - make_interval(mins => $3)) RETURNING *")
+            .bind(id)
+            .bind(MintBatchStatus::Processing)
+            .bind(STALE_BATCH_MINUTES)
+            .bind(MintBatchStatus::Closed)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(batch)
+    }
+
+    /// Closes a partially failed batch none of whose items may still land, so it
+    /// is never resumed. Returns `None` when the batch is processing, has a
+    /// submitted item, or completed.
+    pub async fn close_mint_batch(&self, id: &Uuid) -> Result<Option<MintBatch>, SolanaRepositoryError> {
+        let batch = sqlx::query_as::<_, MintBatch>("UPDATE mint_batches SET status = $2, updated_at = NOW() WHERE id = $1 AND status = $3 AND NOT EXISTS (SELECT 1 FROM mint_batch_items WHERE batch_id = $1 AND status = $4) RETURNING *")
+            .bind(id)
+            .bind(MintBatchStatus::Closed)
+            .bind(MintBatchStatus::PartiallyFailed)
+            .bind(MintBatchItemStatus::Submitted)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(batch)
+    }
+
+    pub async fn update_mint_batch_status(
+        &self,
+        id: &Uuid,
+        status: MintBatchStatus
+    ) -> Result<MintBatch, SolanaRepositoryError> {
+        let batch = sqlx::query_as::<_, MintBatch>("UPDATE mint_batches SET status = $2, updated_at = NOW() WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(status)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(batch)
+    }
+
+    /// Moves the given items to `status`. Signature and blockhash are only overwritten
+    /// when provided, so failed items keep a trace of their last attempt.
+    pub async fn update_mint_batch_items(
+        &self,
+        batch_id: &Uuid,
+        positions: &[i32],
+        status: MintBatchItemStatus,
+        signature: Option<&str>,
+        recent_blockhash: Option<&str>,
+        error: Option<&str>
+    ) -> Result<(), SolanaRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query("UPDATE mint_batch_items SET status = $3, signature = COALESCE($4, signature), recent_blockhash = COALESCE($5, recent_blockhash), error = $6, updated_at = NOW() WHERE batch_id = $1 AND position = ANY($2)")
+            .bind(batch_id)
+            .bind(positions)
+            .bind(status)
+            .bind(signature)
+            .bind(recent_blockhash)
+            .bind(error)
+            .execute(&mut *transaction)
+            .await?;
+
+        sqlx::query("UPDATE mint_batches SET updated_at = NOW() WHERE id = $1")
+            .bind(batch_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn create_mint(&self, mint: &Mint) -> Result<(), SolanaRepositoryError> {
+        sqlx::query("INSERT INTO mints (pubkey, decimals, created_at) VALUES ($1, $2, $3) ON CONFLICT (pubkey) DO NOTHING")
+            .bind(&mint.pubkey)
+            .bind(mint.decimals)
+            .bind(mint.created_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_mints(&self) -> Result<Vec<Mint>, SolanaRepositoryError> {
+        let mints = sqlx::query_as::<_, Mint>("SELECT * FROM mints ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(mints)
+    }
+
+    pub async fn fetch_mint(&self, pubkey: &str) -> Result<Mint, SolanaRepositoryError> {
+        match sqlx::query_as::<_, Mint>("SELECT * FROM mints WHERE pubkey = $1")
+            .bind(pubkey)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(mint) => Ok(mint),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(SolanaRepositoryError::RowNotFound),
+                e => Err(SolanaRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Fails with `SponsoredTokenAccountLimit` when recording `account_pubkeys`
+    /// would put the owner over `max_accounts`, without recording anything.
+    pub async fn check_sponsored_token_accounts(
+        &self,
+        owner_pubkey: &str,
+        account_pubkeys: &[String],
+        max_accounts: i64
+    ) -> Result<(), SolanaRepositoryError> {
+        let (recorded, known) = sqlx::query_as::<_, (i64, i64)>("SELECT COUNT(*) FILTER (WHERE owner_pubkey = $1), COUNT(*) FILTER (WHERE account_pubkey = ANY($2)) FROM sponsored_token_accounts")
+            .bind(owner_pubkey)
+            .bind(account_pubkeys)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let new = account_pubkeys.len() as i64 - known;
+
+        if new > 0 && recorded + new > max_accounts {
+            return Err(SolanaRepositoryError::SponsoredTokenAccountLimit);
+        }
+
+        Ok(())
+    }
+
+    /// Records the token accounts a sponsored transaction signed by `owner_pubkey`
+    /// creates. Fails with `SponsoredTokenAccountLimit`, recording nothing, when the
+    /// owner would go over `max_accounts`. Accounts recorded before are not counted twice.
+    pub async fn record_sponsored_token_accounts(
+        &self,
+        owner_pubkey: &str,
+        account_pubkeys: &[String],
+        max_accounts: i64,
+        date: &DateTime<Utc>
+    ) -> Result<(), SolanaRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Serializes recordings per owner, so concurrent submissions cannot both
+        // pass the limit.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::TEXT, 2))")
+            .bind(owner_pubkey)
+            .execute(&mut *transaction)
+            .await?;
+
+        let recorded = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sponsored_token_accounts WHERE owner_pubkey = $1")
+            .bind(owner_pubkey)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        let inserted = sqlx::query("INSERT INTO sponsored_token_accounts (account_pubkey, owner_pubkey, created_at) SELECT UNNEST($1::TEXT[]), $2, $3 ON CONFLICT (account_pubkey) DO NOTHING")
+            .bind(account_pubkeys)
+            .bind(owner_pubkey)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+
+        if inserted > 0 && recorded + inserted as i64 > max_accounts {
+            return Err(SolanaRepositoryError::SponsoredTokenAccountLimit);
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SolanaRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound,
+    #[error("Sponsored token account limit reached")]
+    SponsoredTokenAccountLimit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn record_sponsored_token_accounts_enforces_the_limit(pool: PgPool) {
+        let repository = SolanaRepository::new(pool);
+        let now = Utc::now();
+        let accounts = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        repository.record_sponsored_token_accounts("owner", &accounts(&["a", "b"]), 3, &now).await.unwrap();
+
+        // Accounts recorded before are not counted twice.
+        repository.record_sponsored_token_accounts("owner", &accounts(&["b", "c"]), 3, &now).await.unwrap();
+
+        assert!(matches!(
+            repository.record_sponsored_token_accounts("owner", &accounts(&["d"]), 3, &now).await,
+            Err(SolanaRepositoryError::SponsoredTokenAccountLimit)
+        ));
+
+        repository.record_sponsored_token_accounts("other", &accounts(&["d"]), 3, &now).await.unwrap();
+    }
+}
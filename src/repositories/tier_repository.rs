@@ -0,0 +1,196 @@
+use sqlx::PgPool;
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::{
+    ledger_model::LedgerAccount,
+    program_model::PointEarningStatus,
+    tier_model::{ProgramTier, TierBadge, TierBadgeStatus, TierBasis, TierMember, UserTier, UserTierSummary}
+};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct TierRepository {
+    pool: PgPool
+}
+
+impl TierRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Archives the program's tiers and creates `tiers` in their place.
+    pub async fn replace_tiers(
+        &self,
+        program_id: &Uuid,
+        basis: TierBasis,
+        tiers: &[ProgramTier],
+        date: &DateTime<Utc>
+    ) -> Result<Vec<ProgramTier>, TierRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query("UPDATE programs SET tier_basis = $2 WHERE id = $1")
+            .bind(program_id)
+            .bind(basis)
+            .execute(&mut *transaction)
+            .await?;
+
+        sqlx::query("UPDATE program_tiers SET archived_at = $2 WHERE program_id = $1 AND archived_at IS NULL")
+            .bind(program_id)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?;
+
+        let mut created = Vec::with_capacity(tiers.len());
+
+        for tier in tiers {
+            let tier = sqlx::query_as::<_, ProgramTier>("INSERT INTO program_tiers (id, program_id, name, threshold, badge_mint_pubkey, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *")
+                .bind(tier.id)
+                .bind(program_id)
+                .bind(&tier.name)
+                .bind(tier.threshold)
+                .bind(&tier.badge_mint_pubkey)
+                .bind(tier.created_at)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+            created.push(tier);
+        }
+
+        transaction.commit().await?;
+
+        Ok(created)
+    }
+
+    pub async fn fetch_tiers(&self, program_id: &Uuid) -> Result<Vec<ProgramTier>, TierRepositoryError> {
+        let tiers = sqlx::query_as::<_, ProgramTier>("SELECT * FROM program_tiers WHERE program_id = $1 AND archived_at IS NULL ORDER BY threshold")
+            .bind(program_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(tiers)
+    }
+
+    /// Programs with tiers, and programs whose users still hold a tier that was
+    /// archived since.
+    pub async fn fetch_tiered_program_ids(&self) -> Result<Vec<Uuid>, TierRepositoryError> {
+        let program_ids = sqlx::query_scalar::<_, Uuid>("SELECT program_id FROM program_tiers WHERE archived_at IS NULL UNION SELECT program_id FROM user_tiers WHERE tier_id IS NOT NULL")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(program_ids)
+    }
+
+    /// Users who earned in the program, or only `user_id` when given.
+    pub async fn fetch_members(&self, program_id: &Uuid, user_id: Option<&Uuid>) -> Result<Vec<TierMember>, TierRepositoryError> {
+        let members = sqlx::query_as::<_, TierMember>("SELECT e.user_id, w.pubkey AS wallet_pubkey, COALESCE(SUM(e.points) FILTER (WHERE e.status = ANY($3)), 0)::BIGINT AS earned_points, COALESCE((SELECT SUM(l.amount) FROM ledger_entries l WHERE l.program_id = $1 AND l.user_id = e.user_id AND l.account = $4), 0)::BIGINT AS ledger_points FROM point_earnings e LEFT JOIN wallets w ON w.user_id = e.user_id AND w.is_primary WHERE e.program_id = $1 AND ($2::UUID IS NULL OR e.user_id = $2) GROUP BY e.user_id, w.pubkey")
+            .bind(program_id)
+            .bind(user_id)
+            .bind([PointEarningStatus::Completed, PointEarningStatus::Credited])
+            .bind(LedgerAccount::UserBalance)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(members)
+    }
+
+    /// Stores the computed tier, moving `changed_at` only when the tier differs.
+    pub async fn save_user_tier(&self, user_tier: &UserTier) -> Result<UserTier, TierRepositoryError> {
+        let user_tier = sqlx::query_as::<_, UserTier>("INSERT INTO user_tiers (user_id, program_id, tier_id, points, computed_at, changed_at) VALUES ($1, $2, $3, $4, $5, $5) ON CONFLICT (user_id, program_id) DO UPDATE SET points = EXCLUDED.points, computed_at = EXCLUDED.computed_at, changed_at = CASE WHEN user_tiers.tier_id IS DISTINCT FROM EXCLUDED.tier_id THEN EXCLUDED.computed_at ELSE user_tiers.changed_at END, tier_id = EXCLUDED.tier_id RETURNING *")
+            .bind(user_tier.user_id)
+            .bind(user_tier.program_id)
+            .bind(user_tier.tier_id)
+            .bind(user_tier.points)
+            .bind(user_tier.computed_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(user_tier)
+    }
+
+    /// Programs the users of `user_ids` have reached a tier in.
+    pub async fn fetch_tier_summaries(&self, user_ids: &[Uuid]) -> Result<Vec<UserTierSummary>, TierRepositoryError> {
+        let summaries = sqlx::query_as::<_, UserTierSummary>("SELECT ut.user_id, ut.program_id, p.name AS program_name, t.name AS tier, ut.points, ut.changed_at FROM user_tiers ut JOIN program_tiers t ON t.id = ut.tier_id JOIN programs p ON p.id = ut.program_id WHERE ut.user_id = ANY($1) ORDER BY p.name")
+            .bind(user_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(summaries)
+    }
+
+    pub async fn create_badge(&self, badge: &TierBadge) -> Result<TierBadge, TierRepositoryError> {
+        let badge = sqlx::query_as::<_, TierBadge>("INSERT INTO tier_badges (id, user_id, program_id, tier_id, mint_pubkey, wallet_pubkey, status, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8) RETURNING *")
+            .bind(badge.id)
+            .bind(badge.user_id)
+            .bind(badge.program_id)
+            .bind(badge.tier_id)
+            .bind(&badge.mint_pubkey)
+            .bind(&badge.wallet_pubkey)
+            .bind(badge.status)
+            .bind(badge.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(badge)
+    }
+
+    /// Badges of the user in the program that are not revoked.
+    pub async fn fetch_active_badges(&self, user_id: &Uuid, program_id: &Uuid) -> Result<Vec<TierBadge>, TierRepositoryError> {
+        let badges = sqlx::query_as::<_, TierBadge>("SELECT * FROM tier_badges WHERE user_id = $1 AND program_id = $2 AND status <> $3 ORDER BY created_at")
+            .bind(user_id)
+            .bind(program_id)
+            .bind(TierBadgeStatus::Revoked)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(badges)
+    }
+
+    pub async fn fetch_user_badges(&self, user_id: &Uuid) -> Result<Vec<TierBadge>, TierRepositoryError> {
+        let badges = sqlx::query_as::<_, TierBadge>("SELECT * FROM tier_badges WHERE user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(badges)
+    }
+
+    /// Signatures already recorded are kept when none is given.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_badge(
+        &self,
+        id: &Uuid,
+        status: TierBadgeStatus,
+        mint_signature: Option<&str>,
+        freeze_signature: Option<&str>,
+        revoke_signature: Option<&str>,
+        error: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<TierBadge, TierRepositoryError> {
+        match sqlx::query_as::<_, TierBadge>("UPDATE tier_badges SET status = $2, mint_signature = COALESCE($3, mint_signature), freeze_signature = COALESCE($4, freeze_signature), revoke_signature = COALESCE($5, revoke_signature), error = $6, updated_at = $7 WHERE id = $1 RETURNING *")
+            .bind(id)
+            .bind(status)
+            .bind(mint_signature)
+            .bind(freeze_signature)
+            .bind(revoke_signature)
+            .bind(error)
+            .bind(date)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(badge) => Ok(badge),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(TierRepositoryError::RowNotFound),
+                e => Err(TierRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum TierRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Record was not found")]
+    RowNotFound
+}
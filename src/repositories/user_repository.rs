@@ -2,7 +2,7 @@ use sqlx::PgPool;
 use thiserror::Error;
 use sqlx::Error as SqlxError;
 use uuid::Uuid;
-use crate::models::user_model::User;
+use crate::models::{referral_model::Referral, user_model::User, wallet_model::Wallet};
 use chrono::{DateTime, Utc};
 
 #[derive(Clone)]
@@ -15,34 +15,84 @@ impl UserRepository {
         Self { pool }
     }
 
+    /// Creates the user together with its primary wallet, and the referral when
+    /// the user signed up with a referral code. `user_email` must be normalized
+    /// with `User::normalize_email`, as emails are unique regardless of case.
     pub async fn create_user(&self, 
         id: &Uuid,
         date: &DateTime<Utc>,
         user_email: &str,
-        public_key: &str,
-        secret_key: &str,
+        wallet: &Wallet,
+        referral: Option<&Referral>,
     ) -> Result<User, UserRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
 
-        match sqlx::query_as::<_, User>("INSERT INTO users (id, email, created_at, public_key, secret_key) VALUES ($1, $2, $3, $4, $5) RETURNING id, email, created_at, public_key, secret_key")
+        if let Err(e) = sqlx::query("INSERT INTO users (id, email, created_at) VALUES ($1, $2, $3)")
             .bind(id)
             .bind(user_email.to_string())
             .bind(date)
-            .bind(public_key)
-            .bind(secret_key)
+            .execute(&mut *transaction)
+            .await
+        {
+            println!("error: {}", e);
+            return Err(match e {
+                SqlxError::Database(db_error) if db_error.is_unique_violation() => UserRepositoryError::EmailTaken,
+                e => UserRepositoryError::DatabaseError(e)
+            });
+        }
+
+        sqlx::query("INSERT INTO wallets (id, user_id, pubkey, kind, label, is_primary, derivation_index, secret_key, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)")
+            .bind(wallet.id)
+            .bind(id)
+            .bind(&wallet.pubkey)
+            .bind(wallet.kind)
+            .bind(&wallet.label)
+            .bind(wallet.is_primary)
+            .bind(wallet.derivation_index)
+            .bind(&wallet.secret_key)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?;
+
+        if let Some(referral) = referral {
+            sqlx::query("INSERT INTO referrals (id, referrer_id, referee_id, code, created_at) VALUES ($1, $2, $3, $4, $5)")
+                .bind(referral.id)
+                .bind(referral.referrer_id)
+                .bind(id)
+                .bind(&referral.code)
+                .bind(date)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(User {
+            id: *id,
+            email: user_email.to_string(),
+            created_at: *date,
+            public_key: wallet.pubkey.clone()
+        })
+    }
+
+    pub async fn fetch_user(&self, id: &Uuid) -> Result<User, UserRepositoryError> {
+        match sqlx::query_as::<_, User>("SELECT users.*, wallets.pubkey AS public_key FROM users JOIN wallets ON wallets.user_id = users.id AND wallets.is_primary WHERE users.id = $1")
+            .bind(id)
             .fetch_one(&self.pool)
             .await
         {
             Ok(user) => Ok(user),
-            Err(e) => {
-                println!("error: {}", e);
-                Err(UserRepositoryError::DatabaseError(e))
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(UserRepositoryError::RowNotFound),
+                e => Err(UserRepositoryError::DatabaseError(e))
             }
         }
     }
 
-    pub async fn fetch_user(&self, id: &Uuid) -> Result<User, UserRepositoryError> {
-        match sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-            .bind(id)
+    /// Stored emails are normalized, so `email` matches regardless of case.
+    pub async fn fetch_user_by_email(&self, email: &str) -> Result<User, UserRepositoryError> {
+        match sqlx::query_as::<_, User>("SELECT users.*, wallets.pubkey AS public_key FROM users JOIN wallets ON wallets.user_id = users.id AND wallets.is_primary WHERE users.email = $1")
+            .bind(User::normalize_email(email))
             .fetch_one(&self.pool)
             .await
         {
@@ -54,8 +104,20 @@ impl UserRepository {
         }
     }
 
+    /// `emails` must be normalized with `User::normalize_email`, as stored emails are.
+    pub async fn fetch_users_by_emails(&self, emails: &[String]) -> Result<Vec<User>, UserRepositoryError> {
+        match sqlx::query_as::<_, User>("SELECT users.*, wallets.pubkey AS public_key FROM users JOIN wallets ON wallets.user_id = users.id AND wallets.is_primary WHERE users.email = ANY($1)")
+            .bind(emails)
+            .fetch_all(&self.pool)
+            .await
+        {
+            Ok(users) => Ok(users),
+            Err(e) => Err(UserRepositoryError::DatabaseError(e))
+        }
+    }
+
     pub async fn fetch_all_users(&self) -> Result<Vec<User>, UserRepositoryError> {
-        match sqlx::query_as::<_, User>("SELECT * FROM users")
+        match sqlx::query_as::<_, User>("SELECT users.*, wallets.pubkey AS public_key FROM users JOIN wallets ON wallets.user_id = users.id AND wallets.is_primary")
             .fetch_all(&self.pool)
             .await
         {
@@ -73,5 +135,33 @@ pub enum UserRepositoryError {
     #[error("Database error: {0}")]
     DatabaseError(#[from] SqlxError),
     #[error("User was not found")]
-    RowNotFound
+    RowNotFound,
+    #[error("Email is already registered")]
+    EmailTaken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "./migrations")]
+    #[ignore = "requires DATABASE_URL pointing at a Postgres server"]
+    async fn create_user_rejects_emails_differing_only_in_case(pool: PgPool) {
+        let repository = UserRepository::new(pool);
+        let now = Utc::now();
+
+        for (index, email) in ["ana@example.com", "ANA@example.com"].into_iter().enumerate() {
+            let id = Uuid::new_v4();
+            let wallet = Wallet::derived(id, format!("wallet-{}", index), index as i32, None, true);
+            let result = repository.create_user(&id, &now, email, &wallet, None).await;
+
+            match index {
+                0 => assert!(result.is_ok()),
+                _ => assert!(matches!(result, Err(UserRepositoryError::EmailTaken)))
+            }
+        }
+
+        let user = repository.fetch_user_by_email("Ana@Example.com").await.unwrap();
+        assert_eq!(user.email, "ana@example.com");
+    }
 }
@@ -0,0 +1,406 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use thiserror::Error;
+use sqlx::Error as SqlxError;
+use uuid::Uuid;
+use crate::models::wallet_model::{
+    Wallet, 
+    WalletChallenge, 
+    WalletExport, 
+    WalletExportAudit, 
+    WalletExportEvent
+};
+use chrono::{DateTime, Utc};
+
+#[derive(Clone)]
+pub struct WalletRepository {
+    pool: PgPool
+}
+
+impl WalletRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_challenge(
+        &self,
+        challenge: &WalletChallenge
+    ) -> Result<WalletChallenge, WalletRepositoryError> {
+        let challenge = sqlx::query_as::<_, WalletChallenge>("INSERT INTO wallet_challenges (id, user_id, pubkey, message, created_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *")
+            .bind(challenge.id)
+            .bind(challenge.user_id)
+            .bind(&challenge.pubkey)
+            .bind(&challenge.message)
+            .bind(challenge.created_at)
+            .bind(challenge.expires_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(challenge)
+    }
+
+    pub async fn fetch_challenge(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid
+    ) -> Result<WalletChallenge, WalletRepositoryError> {
+        match sqlx::query_as::<_, WalletChallenge>("SELECT * FROM wallet_challenges WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(challenge) => Ok(challenge),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(WalletRepositoryError::RowNotFound),
+                e => Err(WalletRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Consumes the challenge and links its wallet in one transaction, so a signed
+    /// challenge can only ever be used once.
+    pub async fn link_wallet(
+        &self,
+        challenge: &WalletChallenge,
+        label: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<Wallet, WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let consumed = sqlx::query("UPDATE wallet_challenges SET used_at = $2 WHERE id = $1 AND used_at IS NULL AND expires_at > $2")
+            .bind(challenge.id)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?;
+
+        if consumed.rows_affected() == 0 {
+            return Err(WalletRepositoryError::ChallengeUnavailable);
+        }
+
+        let wallet = sqlx::query_as::<_, Wallet>("INSERT INTO wallets (id, user_id, pubkey, kind, label, created_at) VALUES ($1, $2, $3, 'external', $4, $5) RETURNING *")
+            .bind(Uuid::new_v4())
+            .bind(challenge.user_id)
+            .bind(&challenge.pubkey)
+            .bind(label)
+            .bind(date)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|e| match e {
+                SqlxError::Database(db_error) if db_error.is_unique_violation() => WalletRepositoryError::AlreadyLinked,
+                e => WalletRepositoryError::DatabaseError(e)
+            })?;
+
+        transaction.commit().await?;
+
+        Ok(wallet)
+    }
+
+    /// Reserves the next index under the master seed. Indexes are never reused,
+    /// even when the insert that needed one fails.
+    pub async fn next_derivation_index(&self) -> Result<i32, WalletRepositoryError> {
+        let index = sqlx::query_scalar::<_, i32>("SELECT nextval('user_derivation_index_seq')::INTEGER")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(index)
+    }
+
+    pub async fn create_wallet(&self, wallet: &Wallet) -> Result<Wallet, WalletRepositoryError> {
+        let wallet = sqlx::query_as::<_, Wallet>("INSERT INTO wallets (id, user_id, pubkey, kind, label, is_primary, derivation_index, secret_key, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *")
+            .bind(wallet.id)
+            .bind(wallet.user_id)
+            .bind(&wallet.pubkey)
+            .bind(wallet.kind)
+            .bind(&wallet.label)
+            .bind(wallet.is_primary)
+            .bind(wallet.derivation_index)
+            .bind(&wallet.secret_key)
+            .bind(wallet.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(wallet)
+    }
+
+    pub async fn fetch_wallets(&self, user_id: &Uuid) -> Result<Vec<Wallet>, WalletRepositoryError> {
+        let wallets = sqlx::query_as::<_, Wallet>("SELECT * FROM wallets WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(wallets)
+    }
+
+    pub async fn fetch_wallet(
+        &self,
+        user_id: &Uuid,
+        pubkey: &str
+    ) -> Result<Wallet, WalletRepositoryError> {
+        match sqlx::query_as::<_, Wallet>("SELECT * FROM wallets WHERE user_id = $1 AND pubkey = $2")
+            .bind(user_id)
+            .bind(pubkey)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(wallet) => Ok(wallet),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(WalletRepositoryError::WalletNotFound),
+                e => Err(WalletRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Makes an active wallet the user's primary one, demoting the current primary.
+    pub async fn set_primary_wallet(
+        &self,
+        user_id: &Uuid,
+        pubkey: &str
+    ) -> Result<Wallet, WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query("UPDATE wallets SET is_primary = FALSE WHERE user_id = $1 AND is_primary")
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let wallet = sqlx::query_as::<_, Wallet>("UPDATE wallets SET is_primary = TRUE WHERE user_id = $1 AND pubkey = $2 AND archived_at IS NULL RETURNING *")
+            .bind(user_id)
+            .bind(pubkey)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or(WalletRepositoryError::WalletUnavailable)?;
+
+        transaction.commit().await?;
+
+        Ok(wallet)
+    }
+
+    /// Archives a wallet that is not the user's primary one.
+    pub async fn archive_wallet(
+        &self,
+        user_id: &Uuid,
+        pubkey: &str,
+        date: &DateTime<Utc>
+    ) -> Result<Wallet, WalletRepositoryError> {
+        sqlx::query_as::<_, Wallet>("UPDATE wallets SET archived_at = $3 WHERE user_id = $1 AND pubkey = $2 AND archived_at IS NULL AND NOT is_primary RETURNING *")
+            .bind(user_id)
+            .bind(pubkey)
+            .bind(date)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(WalletRepositoryError::WalletUnavailable)
+    }
+
+    /// Archives `old` and inserts `new` in its place, keeping it primary if `old` was.
+    pub async fn replace_wallet(
+        &self,
+        old: &Wallet,
+        new: &Wallet,
+        date: &DateTime<Utc>
+    ) -> Result<Wallet, WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query("UPDATE wallets SET is_primary = FALSE, archived_at = $2 WHERE id = $1")
+            .bind(old.id)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?;
+
+        let wallet = sqlx::query_as::<_, Wallet>("INSERT INTO wallets (id, user_id, pubkey, kind, label, is_primary, derivation_index, secret_key, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *")
+            .bind(new.id)
+            .bind(new.user_id)
+            .bind(&new.pubkey)
+            .bind(new.kind)
+            .bind(&new.label)
+            .bind(old.is_primary)
+            .bind(new.derivation_index)
+            .bind(&new.secret_key)
+            .bind(new.created_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(wallet)
+    }
+
+    /// Stores a pending export along with its `Requested` audit entry.
+    pub async fn create_export(&self, export: &WalletExport) -> Result<WalletExport, WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let created = sqlx::query_as::<_, WalletExport>("INSERT INTO wallet_exports (id, user_id, pubkey, code_hash, created_at, expires_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *")
+            .bind(export.id)
+            .bind(export.user_id)
+            .bind(&export.pubkey)
+            .bind(&export.code_hash)
+            .bind(export.created_at)
+            .bind(export.expires_at)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        Self::insert_audit(&mut transaction, &created, WalletExportEvent::Requested, None, &created.created_at).await?;
+
+        transaction.commit().await?;
+
+        Ok(created)
+    }
+
+    pub async fn fetch_export(
+        &self,
+        id: &Uuid,
+        user_id: &Uuid
+    ) -> Result<WalletExport, WalletRepositoryError> {
+        match sqlx::query_as::<_, WalletExport>("SELECT * FROM wallet_exports WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(export) => Ok(export),
+            Err(e) => match e {
+                SqlxError::RowNotFound => Err(WalletRepositoryError::ExportNotFound),
+                e => Err(WalletRepositoryError::DatabaseError(e))
+            }
+        }
+    }
+
+    /// Counts a verification attempt and returns the attempts made so far. The
+    /// check and the increment are one statement, so concurrent attempts cannot
+    /// exceed `max_attempts`. Fails when the export was used, expired or ran out of
+    /// attempts.
+    pub async fn claim_export_attempt(
+        &self,
+        id: &Uuid,
+        max_attempts: i32,
+        date: &DateTime<Utc>
+    ) -> Result<i32, WalletRepositoryError> {
+        sqlx::query_scalar::<_, i32>("UPDATE wallet_exports SET attempts = attempts + 1 WHERE id = $1 AND attempts < $2 AND confirmed_at IS NULL AND expires_at > $3 RETURNING attempts")
+            .bind(id)
+            .bind(max_attempts)
+            .bind(date)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(WalletRepositoryError::ExportUnavailable)
+    }
+
+    /// Audits a wrong code. The attempt itself was counted by `claim_export_attempt`.
+    pub async fn record_failed_export_attempt(
+        &self,
+        export: &WalletExport,
+        date: &DateTime<Utc>
+    ) -> Result<(), WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        Self::insert_audit(&mut transaction, export, WalletExportEvent::VerificationFailed, None, date).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Consumes the export, flags the wallet as exported and audits it in one
+    /// transaction, so no secret key leaves the service without an audit entry. Fails
+    /// when the export was used, expired, went over `max_attempts`, or the wallet
+    /// was archived since it was requested.
+    pub async fn complete_export(
+        &self,
+        export: &WalletExport,
+        max_attempts: i32,
+        date: &DateTime<Utc>
+    ) -> Result<(), WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let consumed = sqlx::query("UPDATE wallet_exports SET confirmed_at = $2 WHERE id = $1 AND confirmed_at IS NULL AND expires_at > $2 AND attempts <= $3")
+            .bind(export.id)
+            .bind(date)
+            .bind(max_attempts)
+            .execute(&mut *transaction)
+            .await?;
+
+        if consumed.rows_affected() == 0 {
+            return Err(WalletRepositoryError::ExportUnavailable);
+        }
+
+        let flagged = sqlx::query("UPDATE wallets SET exported_at = $3 WHERE user_id = $1 AND pubkey = $2 AND archived_at IS NULL")
+            .bind(export.user_id)
+            .bind(&export.pubkey)
+            .bind(date)
+            .execute(&mut *transaction)
+            .await?;
+
+        if flagged.rows_affected() == 0 {
+            return Err(WalletRepositoryError::ExportUnavailable);
+        }
+
+        Self::insert_audit(&mut transaction, export, WalletExportEvent::Exported, None, date).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn audit_export(
+        &self,
+        export: &WalletExport,
+        event: WalletExportEvent,
+        detail: Option<&str>
+    ) -> Result<(), WalletRepositoryError> {
+        let mut transaction = self.pool.begin().await?;
+
+        Self::insert_audit(&mut transaction, export, event, detail, &Utc::now()).await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_export_audit(&self, user_id: &Uuid) -> Result<Vec<WalletExportAudit>, WalletRepositoryError> {
+        let entries = sqlx::query_as::<_, WalletExportAudit>("SELECT * FROM wallet_export_audit WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
+    async fn insert_audit(
+        transaction: &mut Transaction<'_, Postgres>,
+        export: &WalletExport,
+        event: WalletExportEvent,
+        detail: Option<&str>,
+        date: &DateTime<Utc>
+    ) -> Result<(), WalletRepositoryError> {
+        sqlx::query("INSERT INTO wallet_export_audit (id, user_id, export_id, pubkey, event, detail, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+            .bind(Uuid::new_v4())
+            .bind(export.user_id)
+            .bind(export.id)
+            .bind(&export.pubkey)
+            .bind(event)
+            .bind(detail)
+            .bind(date)
+            .execute(&mut **transaction)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WalletRepositoryError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] SqlxError),
+    #[error("Challenge was not found")]
+    RowNotFound,
+    #[error("Challenge was already used or has expired")]
+    ChallengeUnavailable,
+    #[error("Wallet is already linked")]
+    AlreadyLinked,
+    #[error("Export was not found")]
+    ExportNotFound,
+    #[error("Export was already used, has expired, or the wallet was archived")]
+    ExportUnavailable,
+    #[error("Wallet was not found")]
+    WalletNotFound,
+    #[error("Wallet is archived or primary")]
+    WalletUnavailable
+}
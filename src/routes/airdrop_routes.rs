@@ -0,0 +1,100 @@
+use axum::{
+    extract::{DefaultBodyLimit, Multipart, Path, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{airdrop_controller::AirdropController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{permission_middleware, RequiredPermission},
+    models::{
+        airdrop_model::AirdropPreview,
+        role_model::Permission,
+        mint_batch_model::MintBatchReport
+    }
+};
+
+const MAX_CSV_BYTES: usize = 10 * 1024 * 1024;
+
+pub fn airdrop_routes(airdrop_controller: AirdropController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/airdrops/:id", get(fetch_airdrop))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensRead),
+            permission_middleware
+        ));
+
+    let mint_routes = Router::new()
+        .route("/airdrops", post(upload_airdrop))
+        .route("/airdrops/:id/confirm", post(confirm_airdrop))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensMint),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(mint_routes)
+        .layer(DefaultBodyLimit::max(MAX_CSV_BYTES))
+        .with_state(airdrop_controller)
+}
+
+/// Expects a `mint_pubkey` text field and a `file` field holding the CSV.
+async fn upload_airdrop(
+    State(airdrop_controller): State<AirdropController>,
+    mut multipart: Multipart
+) -> Result<Json<AirdropPreview>, ApiError> {
+    let mut mint_pubkey: Option<String> = None;
+    let mut file: Option<(Option<String>, Vec<u8>)> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+    {
+        match field.name() {
+            Some("mint_pubkey") => {
+                let value = field.text().await.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                mint_pubkey = Some(value);
+            },
+            Some("file") => {
+                let file_name = field.file_name().map(|name| name.to_string());
+                let bytes = field.bytes().await.map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                file = Some((file_name, bytes.to_vec()));
+            },
+            _ => {}
+        }
+    }
+
+    let mint_pubkey = mint_pubkey.ok_or((StatusCode::BAD_REQUEST, "Missing mint_pubkey field".to_string()))?;
+    let (file_name, bytes) = file.ok_or((StatusCode::BAD_REQUEST, "Missing file field".to_string()))?;
+
+    let preview = airdrop_controller.create_upload(
+        &mint_pubkey,
+        file_name.as_deref(),
+        &bytes
+    ).await?;
+
+    Ok(Json(preview))
+}
+
+async fn fetch_airdrop(
+    State(airdrop_controller): State<AirdropController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<AirdropPreview>, ApiError> {
+    let preview = airdrop_controller.fetch_preview(id).await?;
+
+    Ok(Json(preview))
+}
+
+async fn confirm_airdrop(
+    State(airdrop_controller): State<AirdropController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<MintBatchReport>, ApiError> {
+    let report = airdrop_controller.confirm_upload(id).await?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,54 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::post,
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{api_key_controller::ApiKeyController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{permission_middleware, RequiredPermission},
+    models::{
+        api_key_model::{ApiKey, CreateApiKeyRequest, CreatedApiKeyResponse},
+        role_model::Permission
+    }
+};
+
+pub fn api_key_routes(api_key_controller: ApiKeyController, role_controller: RoleController) -> Router {
+    Router::new()
+        .route("/api-keys", post(create_api_key).get(fetch_api_keys))
+        .route("/api-keys/:id/revoke", post(revoke_api_key))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::Admin),
+            permission_middleware
+        ))
+        .with_state(api_key_controller)
+}
+
+async fn create_api_key(
+    State(api_key_controller): State<ApiKeyController>,
+    Json(body): Json<CreateApiKeyRequest>
+) -> Result<Json<CreatedApiKeyResponse>, ApiError> {
+    let api_key = api_key_controller.create_api_key(body).await?;
+
+    Ok(Json(api_key))
+}
+
+async fn fetch_api_keys(
+    State(api_key_controller): State<ApiKeyController>
+) -> Result<Json<Vec<ApiKey>>, ApiError> {
+    let api_keys = api_key_controller.fetch_api_keys().await?;
+
+    Ok(Json(api_keys))
+}
+
+async fn revoke_api_key(
+    State(api_key_controller): State<ApiKeyController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<ApiKey>, ApiError> {
+    let api_key = api_key_controller.revoke_api_key(id).await?;
+
+    Ok(Json(api_key))
+}
@@ -0,0 +1,63 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::post,
+    Json,
+    Router
+};
+
+use crate::{
+    controllers::{auth_controller::AuthController, ApiError},
+    models::auth_model::{
+        MagicLinkRequest,
+        RefreshTokenRequest,
+        SessionResponse,
+        VerifyMagicLinkRequest
+    }
+};
+
+/// Login routes are public: they are how users get their access tokens.
+pub fn auth_routes(auth_controller: AuthController) -> Router {
+    Router::new()
+        .route("/auth/magic-link", post(request_magic_link))
+        .route("/auth/magic-link/verify", post(verify_magic_link))
+        .route("/auth/refresh", post(refresh_session))
+        .route("/auth/logout", post(logout))
+        .with_state(auth_controller)
+}
+
+async fn request_magic_link(
+    State(auth_controller): State<AuthController>,
+    Json(body): Json<MagicLinkRequest>
+) -> Result<StatusCode, ApiError> {
+    auth_controller.request_magic_link(&body.email).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn verify_magic_link(
+    State(auth_controller): State<AuthController>,
+    Json(body): Json<VerifyMagicLinkRequest>
+) -> Result<Json<SessionResponse>, ApiError> {
+    let session = auth_controller.verify_magic_link(&body.token).await?;
+
+    Ok(Json(session))
+}
+
+async fn refresh_session(
+    State(auth_controller): State<AuthController>,
+    Json(body): Json<RefreshTokenRequest>
+) -> Result<Json<SessionResponse>, ApiError> {
+    let session = auth_controller.refresh_session(&body.refresh_token).await?;
+
+    Ok(Json(session))
+}
+
+async fn logout(
+    State(auth_controller): State<AuthController>,
+    Json(body): Json<RefreshTokenRequest>
+) -> Result<StatusCode, ApiError> {
+    auth_controller.logout(&body.refresh_token).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{campaign_controller::CampaignController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{permission_middleware, RequiredPermission},
+    models::{
+        campaign_model::{Campaign, CampaignReport, CreateCampaignRequest},
+        role_model::Permission
+    }
+};
+
+pub fn campaign_routes(campaign_controller: CampaignController, role_controller: RoleController) -> Router {
+    Router::new()
+        .route("/programs/:id/campaigns", get(fetch_campaigns).post(create_campaign))
+        .route("/programs/:id/campaigns/report", get(fetch_reports))
+        .route("/campaigns/:id/pause", post(pause_campaign))
+        .route("/campaigns/:id/resume", post(resume_campaign))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ))
+        .with_state(campaign_controller)
+}
+
+async fn create_campaign(
+    State(campaign_controller): State<CampaignController>,
+    Path(program_id): Path<Uuid>,
+    Json(body): Json<CreateCampaignRequest>
+) -> Result<Json<Campaign>, ApiError> {
+    let campaign = campaign_controller.create_campaign(program_id, body).await?;
+
+    Ok(Json(campaign))
+}
+
+async fn fetch_campaigns(
+    State(campaign_controller): State<CampaignController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Vec<Campaign>>, ApiError> {
+    let campaigns = campaign_controller.fetch_campaigns(program_id).await?;
+
+    Ok(Json(campaigns))
+}
+
+/// Points issued per campaign of the program.
+async fn fetch_reports(
+    State(campaign_controller): State<CampaignController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Vec<CampaignReport>>, ApiError> {
+    let reports = campaign_controller.fetch_reports(program_id).await?;
+
+    Ok(Json(reports))
+}
+
+async fn pause_campaign(
+    State(campaign_controller): State<CampaignController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<Campaign>, ApiError> {
+    let campaign = campaign_controller.pause_campaign(id).await?;
+
+    Ok(Json(campaign))
+}
+
+async fn resume_campaign(
+    State(campaign_controller): State<CampaignController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<Campaign>, ApiError> {
+    let campaign = campaign_controller.resume_campaign(id).await?;
+
+    Ok(Json(campaign))
+}
@@ -0,0 +1,132 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post, put},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{conversion_controller::ConversionController, role_controller::RoleController, ApiError},
+    middlewares::{
+        permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission},
+        rate_limit_middleware::{rate_limit_middleware, RateLimit}
+    },
+    models::{
+        conversion_model::{
+            ConvertPointsRequest,
+            ExchangeRate,
+            ExchangeRateSnapshot,
+            PointConversion,
+            PointConversionResponse,
+            UpsertExchangeRateRequest
+        },
+        role_model::Permission
+    }
+};
+
+/// Program managers set the rates between partner programs; users convert and
+/// list their own conversions with their session. Converting moves points on
+/// chain, so it is rate limited like minting.
+pub fn conversion_routes(
+    conversion_controller: ConversionController, 
+    role_controller: RoleController, 
+    mint_rate_limit: RateLimit
+) -> Router {
+    let manage_routes = Router::new()
+        .route("/programs/:id/exchange-rates", get(fetch_rates))
+        .route("/programs/:id/exchange-rates/:target_id", put(upsert_rate))
+        .route("/exchange-rates/:id/snapshots", get(fetch_rate_snapshots))
+        .route("/conversions/:id", get(fetch_conversion))
+        .route("/conversions/:id/resume", post(resume_conversion))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    let read_routes = Router::new()
+        .route("/users/:id/conversions", get(fetch_user_conversions))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let write_routes = Router::new()
+        .route("/users/:id/conversions", post(convert))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit, rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersWrite),
+            own_user_middleware
+        ));
+
+    manage_routes
+        .merge(read_routes)
+        .merge(write_routes)
+        .with_state(conversion_controller)
+}
+
+async fn fetch_rates(
+    State(conversion_controller): State<ConversionController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Vec<ExchangeRate>>, ApiError> {
+    let rates = conversion_controller.fetch_rates(program_id).await?;
+
+    Ok(Json(rates))
+}
+
+async fn upsert_rate(
+    State(conversion_controller): State<ConversionController>,
+    Path((program_id, target_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<UpsertExchangeRateRequest>
+) -> Result<Json<ExchangeRate>, ApiError> {
+    let rate = conversion_controller.upsert_rate(program_id, target_id, body).await?;
+
+    Ok(Json(rate))
+}
+
+async fn fetch_rate_snapshots(
+    State(conversion_controller): State<ConversionController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<Vec<ExchangeRateSnapshot>>, ApiError> {
+    let snapshots = conversion_controller.fetch_rate_snapshots(id).await?;
+
+    Ok(Json(snapshots))
+}
+
+async fn fetch_conversion(
+    State(conversion_controller): State<ConversionController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<PointConversionResponse>, ApiError> {
+    let conversion = conversion_controller.fetch_conversion(id).await?;
+
+    Ok(Json(conversion))
+}
+
+async fn resume_conversion(
+    State(conversion_controller): State<ConversionController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<PointConversion>, ApiError> {
+    let conversion = conversion_controller.resume_conversion(id).await?;
+
+    Ok(Json(conversion))
+}
+
+async fn fetch_user_conversions(
+    State(conversion_controller): State<ConversionController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<PointConversion>>, ApiError> {
+    let conversions = conversion_controller.fetch_user_conversions(user_id).await?;
+
+    Ok(Json(conversions))
+}
+
+async fn convert(
+    State(conversion_controller): State<ConversionController>,
+    Path(user_id): Path<Uuid>,
+    Json(body): Json<ConvertPointsRequest>
+) -> Result<Json<PointConversion>, ApiError> {
+    let conversion = conversion_controller.convert(user_id, body).await?;
+
+    Ok(Json(conversion))
+}
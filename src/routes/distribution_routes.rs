@@ -0,0 +1,98 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    clients::solana_rpc_client::MintRecipient,
+    controllers::{role_controller::RoleController, distribution_controller::DistributionController, ApiError},
+    middlewares::permission_middleware::{permission_middleware, RequiredPermission},
+    models::{
+        role_model::Permission,
+        distribution_model::{ClaimRequest, Distribution, DistributionLeaf}
+    }
+};
+
+/// Fetching proofs, claiming and settling claims stay public: a claim can only
+/// ever mint the amount in its leaf to the wallet in its leaf.
+pub fn distribution_routes(
+    distribution_controller: DistributionController,
+    role_controller: RoleController
+) -> Router {
+    let public_routes = Router::new()
+        .route("/distributions/:id", get(fetch_distribution))
+        .route("/distributions/:id/proofs/:wallet_pubkey", get(fetch_proof))
+        .route("/distributions/:id/claim", post(claim))
+        .route("/distributions/:id/claims/:wallet_pubkey/settle", post(settle_claim));
+
+    let mint_routes = Router::new()
+        .route("/distributions", post(create_distribution))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensMint),
+            permission_middleware
+        ));
+
+    public_routes
+        .merge(mint_routes)
+        .with_state(distribution_controller)
+}
+
+#[derive(Deserialize)]
+struct CreateDistributionRequest {
+    mint_pubkey: String,
+    recipients: Vec<MintRecipient>
+}
+
+async fn create_distribution(
+    State(distribution_controller): State<DistributionController>,
+    Json(payload): Json<CreateDistributionRequest>
+) -> Result<Json<Distribution>, ApiError> {
+    let distribution = distribution_controller.create_distribution(
+        &payload.mint_pubkey,
+        payload.recipients
+    ).await?;
+
+    Ok(Json(distribution))
+}
+
+async fn fetch_distribution(
+    State(distribution_controller): State<DistributionController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<Distribution>, ApiError> {
+    let distribution = distribution_controller.fetch_distribution(id).await?;
+
+    Ok(Json(distribution))
+}
+
+async fn fetch_proof(
+    State(distribution_controller): State<DistributionController>,
+    Path((id, wallet_pubkey)): Path<(Uuid, String)>
+) -> Result<Json<DistributionLeaf>, ApiError> {
+    let leaf = distribution_controller.fetch_leaf(id, &wallet_pubkey).await?;
+
+    Ok(Json(leaf))
+}
+
+async fn claim(
+    State(distribution_controller): State<DistributionController>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ClaimRequest>
+) -> Result<Json<DistributionLeaf>, ApiError> {
+    let leaf = distribution_controller.claim(id, payload).await?;
+
+    Ok(Json(leaf))
+}
+
+async fn settle_claim(
+    State(distribution_controller): State<DistributionController>,
+    Path((id, wallet_pubkey)): Path<(Uuid, String)>
+) -> Result<Json<DistributionLeaf>, ApiError> {
+    let leaf = distribution_controller.settle_claim(id, &wallet_pubkey).await?;
+
+    Ok(Json(leaf))
+}
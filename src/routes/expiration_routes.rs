@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    controllers::{expiration_controller::ExpirationController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission},
+    models::{
+        expiration_model::{ExpiringPoints, PointExpiration, PointExpirationStatus},
+        role_model::Permission
+    }
+};
+
+pub fn expiration_routes(expiration_controller: ExpirationController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/users/:id/points/expiring", get(fetch_expiring_points))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let manage_routes = Router::new()
+        .route("/expirations", get(fetch_expirations))
+        .route("/expirations/run", post(run_expirations))
+        .route("/expirations/:id", get(fetch_expiration))
+        .route("/expirations/:id/retry", post(retry_expiration))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(manage_routes)
+        .with_state(expiration_controller)
+}
+
+#[derive(Deserialize)]
+struct ExpiringPointsQuery {
+    /// How many days ahead to look, 30 by default.
+    days: Option<i64>
+}
+
+async fn fetch_expiring_points(
+    State(expiration_controller): State<ExpirationController>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<ExpiringPointsQuery>
+) -> Result<Json<Vec<ExpiringPoints>>, ApiError> {
+    let expiring = expiration_controller.fetch_expiring_points(user_id, query.days).await?;
+
+    Ok(Json(expiring))
+}
+
+#[derive(Deserialize)]
+struct ExpirationsQuery {
+    status: Option<PointExpirationStatus>
+}
+
+async fn fetch_expirations(
+    State(expiration_controller): State<ExpirationController>,
+    Query(query): Query<ExpirationsQuery>
+) -> Result<Json<Vec<PointExpiration>>, ApiError> {
+    let expirations = expiration_controller.fetch_expirations(query.status).await?;
+
+    Ok(Json(expirations))
+}
+
+async fn run_expirations(
+    State(expiration_controller): State<ExpirationController>
+) -> Result<Json<Vec<PointExpiration>>, ApiError> {
+    let expirations = expiration_controller.run_expirations().await?;
+
+    Ok(Json(expirations))
+}
+
+async fn fetch_expiration(
+    State(expiration_controller): State<ExpirationController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<PointExpiration>, ApiError> {
+    let expiration = expiration_controller.fetch_expiration(id).await?;
+
+    Ok(Json(expiration))
+}
+
+async fn retry_expiration(
+    State(expiration_controller): State<ExpirationController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<PointExpiration>, ApiError> {
+    let expiration = expiration_controller.retry_expiration(id).await?;
+
+    Ok(Json(expiration))
+}
@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{ledger_controller::LedgerController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission},
+    models::{
+        ledger_model::{LedgerBalance, LedgerEntry, ReconciliationReport, Settlement, SettlementReport},
+        role_model::Permission
+    }
+};
+
+pub fn ledger_routes(ledger_controller: LedgerController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/users/:id/points", get(fetch_user_balances))
+        .route("/users/:id/points/ledger", get(fetch_user_entries))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let manage_routes = Router::new()
+        .route("/programs/:id/settlements", post(settle_program).get(fetch_settlements))
+        .route("/programs/:id/reconciliation", get(reconcile))
+        .route("/settlements/:id", get(fetch_settlement))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(manage_routes)
+        .with_state(ledger_controller)
+}
+
+async fn fetch_user_balances(
+    State(ledger_controller): State<LedgerController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<LedgerBalance>>, ApiError> {
+    let balances = ledger_controller.fetch_user_balances(user_id).await?;
+
+    Ok(Json(balances))
+}
+
+async fn fetch_user_entries(
+    State(ledger_controller): State<LedgerController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<LedgerEntry>>, ApiError> {
+    let entries = ledger_controller.fetch_user_entries(user_id).await?;
+
+    Ok(Json(entries))
+}
+
+/// Responds with `null` when every balance is already settled.
+async fn settle_program(
+    State(ledger_controller): State<LedgerController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Option<SettlementReport>>, ApiError> {
+    let report = ledger_controller.settle_program(program_id).await?;
+
+    Ok(Json(report))
+}
+
+async fn fetch_settlements(
+    State(ledger_controller): State<LedgerController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Vec<Settlement>>, ApiError> {
+    let settlements = ledger_controller.fetch_settlements(program_id).await?;
+
+    Ok(Json(settlements))
+}
+
+async fn fetch_settlement(
+    State(ledger_controller): State<LedgerController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<SettlementReport>, ApiError> {
+    let report = ledger_controller.fetch_settlement(id).await?;
+
+    Ok(Json(report))
+}
+
+async fn reconcile(
+    State(ledger_controller): State<LedgerController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let report = ledger_controller.reconcile(program_id).await?;
+
+    Ok(Json(report))
+}
@@ -1,2 +1,17 @@
 pub mod user_routes;
-pub mod token_routes;
\ No newline at end of file
+pub mod token_routes;
+pub mod airdrop_routes;
+pub mod distribution_routes;
+pub mod wallet_routes;
+pub mod api_key_routes;
+pub mod auth_routes;
+pub mod role_routes;
+pub mod program_routes;
+pub mod reward_routes;
+pub mod expiration_routes;
+pub mod ledger_routes;
+pub mod reconciliation_routes;
+pub mod referral_routes;
+pub mod tier_routes;
+pub mod conversion_routes;
+pub mod campaign_routes;
\ No newline at end of file
@@ -0,0 +1,142 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{program_controller::ProgramController, role_controller::RoleController, ApiError},
+    middlewares::{
+        permission_middleware::{permission_middleware, RequiredPermission},
+        rate_limit_middleware::{rate_limit_middleware, RateLimit}
+    },
+    models::{
+        program_model::{
+            CreateEarningRuleRequest,
+            CreateMerchantRequest,
+            CreateProgramRequest,
+            EarnPointsRequest,
+            EarningRule,
+            Merchant,
+            PointEarning,
+            Program,
+            ProgramResponse
+        },
+        role_model::Permission
+    }
+};
+
+/// Earning mints points on chain, so it is rate limited like minting.
+pub fn program_routes(
+    program_controller: ProgramController, 
+    role_controller: RoleController, 
+    mint_rate_limit: RateLimit
+) -> Router {
+    let manage_routes = Router::new()
+        .route("/merchants", post(create_merchant).get(fetch_merchants))
+        .route("/programs", post(create_program).get(fetch_programs))
+        .route("/programs/:id", get(fetch_program))
+        .route("/programs/:id/rules", post(create_earning_rule))
+        .route("/programs/:id/rules/:rule_id/archive", post(archive_earning_rule))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    let earn_routes = Router::new()
+        .route("/programs/:id/earn", post(earn))
+        .route("/earnings/:id/resume", post(resume_earning))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit, rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsEarn),
+            permission_middleware
+        ));
+
+    manage_routes
+        .merge(earn_routes)
+        .with_state(program_controller)
+}
+
+async fn create_merchant(
+    State(program_controller): State<ProgramController>,
+    Json(body): Json<CreateMerchantRequest>
+) -> Result<Json<Merchant>, ApiError> {
+    let merchant = program_controller.create_merchant(body).await?;
+
+    Ok(Json(merchant))
+}
+
+async fn fetch_merchants(
+    State(program_controller): State<ProgramController>
+) -> Result<Json<Vec<Merchant>>, ApiError> {
+    let merchants = program_controller.fetch_merchants().await?;
+
+    Ok(Json(merchants))
+}
+
+async fn create_program(
+    State(program_controller): State<ProgramController>,
+    Json(body): Json<CreateProgramRequest>
+) -> Result<Json<Program>, ApiError> {
+    let program = program_controller.create_program(body).await?;
+
+    Ok(Json(program))
+}
+
+async fn fetch_programs(
+    State(program_controller): State<ProgramController>
+) -> Result<Json<Vec<Program>>, ApiError> {
+    let programs = program_controller.fetch_programs().await?;
+
+    Ok(Json(programs))
+}
+
+async fn fetch_program(
+    State(program_controller): State<ProgramController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<ProgramResponse>, ApiError> {
+    let program = program_controller.fetch_program(id).await?;
+
+    Ok(Json(program))
+}
+
+async fn create_earning_rule(
+    State(program_controller): State<ProgramController>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<CreateEarningRuleRequest>
+) -> Result<Json<EarningRule>, ApiError> {
+    let rule = program_controller.create_earning_rule(id, body).await?;
+
+    Ok(Json(rule))
+}
+
+async fn archive_earning_rule(
+    State(program_controller): State<ProgramController>,
+    Path((id, rule_id)): Path<(Uuid, Uuid)>
+) -> Result<Json<EarningRule>, ApiError> {
+    let rule = program_controller.archive_earning_rule(id, rule_id).await?;
+
+    Ok(Json(rule))
+}
+
+async fn earn(
+    State(program_controller): State<ProgramController>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<EarnPointsRequest>
+) -> Result<Json<PointEarning>, ApiError> {
+    let earning = program_controller.earn(id, body).await?;
+
+    Ok(Json(earning))
+}
+
+async fn resume_earning(
+    State(program_controller): State<ProgramController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<PointEarning>, ApiError> {
+    let earning = program_controller.resume_earning(id).await?;
+
+    Ok(Json(earning))
+}
@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::get,
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{reconciliation_controller::ReconciliationController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{permission_middleware, RequiredPermission},
+    models::{
+        reconciliation_model::{ChainReconciliation, ChainReconciliationResponse},
+        role_model::Permission
+    }
+};
+
+pub fn reconciliation_routes(reconciliation_controller: ReconciliationController, role_controller: RoleController) -> Router {
+    Router::new()
+        .route("/reconciliation/reports", get(fetch_reports).post(run_reconciliation))
+        .route("/reconciliation/reports/latest", get(fetch_latest_report))
+        .route("/reconciliation/reports/:id", get(fetch_report))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::Admin),
+            permission_middleware
+        ))
+        .with_state(reconciliation_controller)
+}
+
+async fn fetch_reports(
+    State(reconciliation_controller): State<ReconciliationController>
+) -> Result<Json<Vec<ChainReconciliation>>, ApiError> {
+    let reports = reconciliation_controller.fetch_reports().await?;
+
+    Ok(Json(reports))
+}
+
+async fn run_reconciliation(
+    State(reconciliation_controller): State<ReconciliationController>
+) -> Result<Json<ChainReconciliationResponse>, ApiError> {
+    let report = reconciliation_controller.run_reconciliation().await?;
+
+    Ok(Json(report))
+}
+
+async fn fetch_latest_report(
+    State(reconciliation_controller): State<ReconciliationController>
+) -> Result<Json<ChainReconciliationResponse>, ApiError> {
+    let report = reconciliation_controller.fetch_latest_report().await?;
+
+    Ok(Json(report))
+}
+
+async fn fetch_report(
+    State(reconciliation_controller): State<ReconciliationController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<ChainReconciliationResponse>, ApiError> {
+    let report = reconciliation_controller.fetch_report(id).await?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,120 @@
+use axum::{
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    controllers::{referral_controller::ReferralController, role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission},
+    models::{
+        referral_model::{
+            Referral,
+            ReferralCode,
+            ReferralReward,
+            ReferralRewardStatus,
+            ReferralRule,
+            UpsertReferralRuleRequest
+        },
+        role_model::Permission
+    }
+};
+
+pub fn referral_routes(referral_controller: ReferralController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/users/:id/referral-code", get(fetch_referral_code))
+        .route("/users/:id/referrals", get(fetch_referrals))
+        .route("/users/:id/referral-rewards", get(fetch_user_rewards))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let manage_routes = Router::new()
+        .route("/programs/:id/referral-rule", get(fetch_rule).put(upsert_rule))
+        .route("/programs/:id/referral-rewards", get(fetch_program_rewards))
+        .route("/referral-rewards/:id/retry", post(retry_reward))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(manage_routes)
+        .with_state(referral_controller)
+}
+
+/// Creates the user's code the first time it is fetched.
+async fn fetch_referral_code(
+    State(referral_controller): State<ReferralController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<ReferralCode>, ApiError> {
+    let code = referral_controller.fetch_referral_code(user_id).await?;
+
+    Ok(Json(code))
+}
+
+async fn fetch_referrals(
+    State(referral_controller): State<ReferralController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<Referral>>, ApiError> {
+    let referrals = referral_controller.fetch_referrals(user_id).await?;
+
+    Ok(Json(referrals))
+}
+
+async fn fetch_user_rewards(
+    State(referral_controller): State<ReferralController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<ReferralReward>>, ApiError> {
+    let rewards = referral_controller.fetch_user_rewards(user_id).await?;
+
+    Ok(Json(rewards))
+}
+
+async fn upsert_rule(
+    State(referral_controller): State<ReferralController>,
+    Path(program_id): Path<Uuid>,
+    Json(body): Json<UpsertReferralRuleRequest>
+) -> Result<Json<ReferralRule>, ApiError> {
+    let rule = referral_controller.upsert_rule(program_id, body).await?;
+
+    Ok(Json(rule))
+}
+
+async fn fetch_rule(
+    State(referral_controller): State<ReferralController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<ReferralRule>, ApiError> {
+    let rule = referral_controller.fetch_rule(program_id).await?;
+
+    Ok(Json(rule))
+}
+
+#[derive(Deserialize)]
+struct ReferralRewardsQuery {
+    status: Option<ReferralRewardStatus>
+}
+
+async fn fetch_program_rewards(
+    State(referral_controller): State<ReferralController>,
+    Path(program_id): Path<Uuid>,
+    Query(query): Query<ReferralRewardsQuery>
+) -> Result<Json<Vec<ReferralReward>>, ApiError> {
+    let rewards = referral_controller.fetch_program_rewards(program_id, query.status).await?;
+
+    Ok(Json(rewards))
+}
+
+async fn retry_reward(
+    State(referral_controller): State<ReferralController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<ReferralReward>, ApiError> {
+    let reward = referral_controller.retry_reward(id).await?;
+
+    Ok(Json(reward))
+}
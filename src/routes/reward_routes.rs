@@ -0,0 +1,144 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{reward_controller::RewardController, role_controller::RoleController, ApiError},
+    middlewares::{
+        permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission},
+        rate_limit_middleware::{rate_limit_middleware, RateLimit}
+    },
+    models::{
+        reward_model::{
+            CreateRewardItemRequest,
+            PlaceRedemptionOrderRequest,
+            RedemptionOrder,
+            RedemptionOrderResponse,
+            RewardItem
+        },
+        role_model::Permission
+    }
+};
+
+/// The catalog is public. Users place and list their own orders with their
+/// session, rate limited like minting since orders move points on chain;
+/// everything else is for program managers.
+pub fn reward_routes(
+    reward_controller: RewardController, 
+    role_controller: RoleController, 
+    mint_rate_limit: RateLimit
+) -> Router {
+    let catalog_routes = Router::new()
+        .route("/programs/:id/rewards", get(fetch_available_items));
+
+    let manage_routes = Router::new()
+        .route("/programs/:id/rewards", post(create_reward_item))
+        .route("/programs/:id/rewards/:item_id/archive", post(archive_reward_item))
+        .route("/redemptions/:id", get(fetch_order))
+        .route("/redemptions/:id/resume", post(resume_order))
+        .route("/redemptions/:id/refund", post(refund_order))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    let read_routes = Router::new()
+        .route("/users/:id/redemptions", get(fetch_user_orders))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let write_routes = Router::new()
+        .route("/users/:id/redemptions", post(place_order))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit, rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersWrite),
+            own_user_middleware
+        ));
+
+    catalog_routes
+        .merge(manage_routes)
+        .merge(read_routes)
+        .merge(write_routes)
+        .with_state(reward_controller)
+}
+
+async fn fetch_available_items(
+    State(reward_controller): State<RewardController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Vec<RewardItem>>, ApiError> {
+    let items = reward_controller.fetch_available_items(program_id).await?;
+
+    Ok(Json(items))
+}
+
+async fn create_reward_item(
+    State(reward_controller): State<RewardController>,
+    Path(program_id): Path<Uuid>,
+    Json(body): Json<CreateRewardItemRequest>
+) -> Result<Json<RewardItem>, ApiError> {
+    let item = reward_controller.create_reward_item(program_id, body).await?;
+
+    Ok(Json(item))
+}
+
+async fn archive_reward_item(
+    State(reward_controller): State<RewardController>,
+    Path((program_id, item_id)): Path<(Uuid, Uuid)>
+) -> Result<Json<RewardItem>, ApiError> {
+    let item = reward_controller.archive_reward_item(program_id, item_id).await?;
+
+    Ok(Json(item))
+}
+
+async fn place_order(
+    State(reward_controller): State<RewardController>,
+    Path(user_id): Path<Uuid>,
+    Json(body): Json<PlaceRedemptionOrderRequest>
+) -> Result<Json<RedemptionOrder>, ApiError> {
+    let order = reward_controller.place_order(user_id, body.item_id).await?;
+
+    Ok(Json(order))
+}
+
+async fn fetch_user_orders(
+    State(reward_controller): State<RewardController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<RedemptionOrder>>, ApiError> {
+    let orders = reward_controller.fetch_user_orders(user_id).await?;
+
+    Ok(Json(orders))
+}
+
+async fn fetch_order(
+    State(reward_controller): State<RewardController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<RedemptionOrderResponse>, ApiError> {
+    let order = reward_controller.fetch_order(id).await?;
+
+    Ok(Json(order))
+}
+
+async fn resume_order(
+    State(reward_controller): State<RewardController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<RedemptionOrder>, ApiError> {
+    let order = reward_controller.resume_order(id).await?;
+
+    Ok(Json(order))
+}
+
+async fn refund_order(
+    State(reward_controller): State<RewardController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<RedemptionOrder>, ApiError> {
+    let order = reward_controller.refund_order(id).await?;
+
+    Ok(Json(order))
+}
@@ -0,0 +1,71 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{role_controller::RoleController, ApiError},
+    middlewares::permission_middleware::{permission_middleware, RequiredPermission},
+    models::role_model::{AssignRoleRequest, CreateRoleRequest, Permission, Role, RoleAssignment}
+};
+
+pub fn role_routes(role_controller: RoleController) -> Router {
+    Router::new()
+        .route("/roles", post(create_role).get(fetch_roles))
+        .route("/roles/:id/assignments", post(assign_role))
+        .route("/role-assignments", get(fetch_assignments))
+        .route("/role-assignments/:id/revoke", post(revoke_assignment))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::Admin),
+            permission_middleware
+        ))
+        .with_state(role_controller)
+}
+
+async fn create_role(
+    State(role_controller): State<RoleController>,
+    Json(body): Json<CreateRoleRequest>
+) -> Result<Json<Role>, ApiError> {
+    let role = role_controller.create_role(body).await?;
+
+    Ok(Json(role))
+}
+
+async fn fetch_roles(
+    State(role_controller): State<RoleController>
+) -> Result<Json<Vec<Role>>, ApiError> {
+    let roles = role_controller.fetch_roles().await?;
+
+    Ok(Json(roles))
+}
+
+async fn assign_role(
+    State(role_controller): State<RoleController>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<AssignRoleRequest>
+) -> Result<Json<RoleAssignment>, ApiError> {
+    let assignment = role_controller.assign_role(id, body).await?;
+
+    Ok(Json(assignment))
+}
+
+async fn fetch_assignments(
+    State(role_controller): State<RoleController>
+) -> Result<Json<Vec<RoleAssignment>>, ApiError> {
+    let assignments = role_controller.fetch_assignments().await?;
+
+    Ok(Json(assignments))
+}
+
+async fn revoke_assignment(
+    State(role_controller): State<RoleController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<RoleAssignment>, ApiError> {
+    let assignment = role_controller.revoke_assignment(id).await?;
+
+    Ok(Json(assignment))
+}
@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{role_controller::RoleController, tier_controller::TierController, ApiError},
+    middlewares::permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission},
+    models::{
+        role_model::Permission,
+        tier_model::{SetTiersRequest, TierBadge, TiersResponse, UserTier, UserTierSummary}
+    }
+};
+
+pub fn tier_routes(tier_controller: TierController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/users/:id/tiers", get(fetch_user_tiers))
+        .route("/users/:id/badges", get(fetch_user_badges))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let manage_routes = Router::new()
+        .route("/programs/:id/tiers", get(fetch_tiers).put(set_tiers))
+        .route("/programs/:id/tiers/refresh", post(refresh_program))
+        .route("/users/:id/tiers/refresh", post(refresh_user))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::ProgramsManage),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(manage_routes)
+        .with_state(tier_controller)
+}
+
+async fn fetch_user_tiers(
+    State(tier_controller): State<TierController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<UserTierSummary>>, ApiError> {
+    let tiers = tier_controller.fetch_user_tiers(user_id).await?;
+
+    Ok(Json(tiers))
+}
+
+async fn fetch_user_badges(
+    State(tier_controller): State<TierController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<TierBadge>>, ApiError> {
+    let badges = tier_controller.fetch_user_badges(user_id).await?;
+
+    Ok(Json(badges))
+}
+
+async fn fetch_tiers(
+    State(tier_controller): State<TierController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<TiersResponse>, ApiError> {
+    let tiers = tier_controller.fetch_tiers(program_id).await?;
+
+    Ok(Json(tiers))
+}
+
+/// Replaces the program's tiers. Users are moved at the next refresh.
+async fn set_tiers(
+    State(tier_controller): State<TierController>,
+    Path(program_id): Path<Uuid>,
+    Json(body): Json<SetTiersRequest>
+) -> Result<Json<TiersResponse>, ApiError> {
+    let tiers = tier_controller.set_tiers(program_id, body).await?;
+
+    Ok(Json(tiers))
+}
+
+async fn refresh_program(
+    State(tier_controller): State<TierController>,
+    Path(program_id): Path<Uuid>
+) -> Result<Json<Vec<UserTier>>, ApiError> {
+    let user_tiers = tier_controller.refresh_program(program_id).await?;
+
+    Ok(Json(user_tiers))
+}
+
+async fn refresh_user(
+    State(tier_controller): State<TierController>,
+    Path(user_id): Path<Uuid>
+) -> Result<Json<Vec<UserTier>>, ApiError> {
+    let user_tiers = tier_controller.refresh_user(user_id).await?;
+
+    Ok(Json(user_tiers))
+}
@@ -1,12 +1,82 @@
-use axum::{extract::{Path, State}, routing::{get, post}, Json, Router};
+use axum::{extract::{Path, Query, State}, http::StatusCode, middleware, routing::{get, post}, Json, Router};
 use serde::Deserialize;
-use crate::{clients::solana_rpc_client::{CreateMintResponse, MintResponse, MintToResponse}, controllers::{token_controller::TokenController, ApiError}};
+use uuid::Uuid;
+use crate::{clients::solana_rpc_client::{CreateMintResponse, FeeEstimateResponse, FreezeAccountResponse, MintRecipient, MintResponse, MintToResponse, PackedSimulation, PlannedOperation, SponsoredTransactionResponse, SubmitSponsoredResponse, TransactionOutcome}, controllers::{role_controller::RoleController, token_controller::{MintReceiver, TokenController}, ApiError}, middlewares::{permission_middleware::{permission_middleware, RequiredPermission}, rate_limit_middleware::{rate_limit_middleware, RateLimit}}, models::{role_model::Permission, mint_batch_model::MintBatchReport, mint_model::{Mint, RegisterMintRequest}}};
 
-pub fn token_routes(token_controller: TokenController) -> Router {
-    Router::new()
-        .route("/mint", post(create_mint))
-        .route("/mint/mint_to", post(mint_to))
+/// Creating mints, minting and sponsoring spend treasury SOL on every call, so
+/// on top of their permissions they are rate limited per API key or user.
+pub fn token_routes(
+    token_controller: TokenController, 
+    role_controller: RoleController, 
+    mint_rate_limit: RateLimit
+) -> Router {
+    let read_routes = Router::new()
+        .route("/mint/mint_to/batch/:id", get(fetch_mint_batch))
         .route("/mint/:pubkey", get(get_mint_account))
+        .route("/estimate", post(estimate))
+        .route("/mints", get(fetch_mints))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensRead),
+            permission_middleware
+        ));
+
+    let mint_to_routes = Router::new()
+        .route("/mint/mint_to", post(mint_to))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit.clone(), rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensMint),
+            permission_middleware
+        ));
+
+    let mint_routes = Router::new()
+        .route("/mint/mint_to/batch", post(create_mint_batch))
+        .route("/mint/mint_to/batch/:id/resume", post(resume_mint_batch))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit.clone(), rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensMint),
+            permission_middleware
+        ));
+
+    let sponsor_routes = Router::new()
+        .route("/sponsored/transfer", post(build_sponsored_transfer))
+        .route("/sponsored/burn", post(build_sponsored_burn))
+        .route("/sponsored/submit", post(submit_sponsored_transaction))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit.clone(), rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensSponsor),
+            permission_middleware
+        ));
+
+    let create_mint_routes = Router::new()
+        .route("/mint", post(create_mint))
+        .route_layer(middleware::from_fn_with_state(mint_rate_limit, rate_limit_middleware))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensCreateMint),
+            permission_middleware
+        ));
+
+    let mint_registry_routes = Router::new()
+        .route("/mints", post(register_mint))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensCreateMint),
+            permission_middleware
+        ));
+
+    let freeze_routes = Router::new()
+        .route("/token-accounts/freeze", post(freeze_token_account))
+        .route("/token-accounts/thaw", post(thaw_token_account))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::TokensFreeze),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(mint_to_routes)
+        .merge(mint_routes)
+        .merge(sponsor_routes)
+        .merge(create_mint_routes)
+        .merge(mint_registry_routes)
+        .merge(freeze_routes)
         .with_state(token_controller)
 }
 
@@ -19,34 +89,210 @@ async fn get_mint_account(
     Ok(Json(mint))
 }
 
+/// Query options shared by every route that sends a transaction.
+#[derive(Deserialize)]
+struct TransactionQuery {
+    #[serde(default)]
+    dry_run: bool
+}
+
 async fn create_mint(
+    State(token_controller): State<TokenController>,
+    Query(query): Query<TransactionQuery>
+) -> Result<Json<TransactionOutcome<CreateMintResponse>>, ApiError> {
+    let mint = token_controller.create_mint(query.dry_run).await?;
+
+    Ok(Json(mint))
+}
+
+async fn fetch_mints(
     State(token_controller): State<TokenController>
-) -> Result<Json<CreateMintResponse>, ApiError> {
-    let mint = token_controller.create_mint().await?;
+) -> Result<Json<Vec<Mint>>, ApiError> {
+    let mints = token_controller.fetch_mints().await?;
+
+    Ok(Json(mints))
+}
+
+async fn register_mint(
+    State(token_controller): State<TokenController>,
+    Json(payload): Json<RegisterMintRequest>
+) -> Result<Json<Mint>, ApiError> {
+    let mint = token_controller.register_mint(&payload.pubkey).await?;
 
     Ok(Json(mint))
 }
 
+/// Exactly one receiver field is expected. Users receive tokens in their primary wallet.
 #[derive(Deserialize)]
 struct MintToRequest {
     mint_pubkey: String,
-    receiver_pubkey: String,
+    receiver_pubkey: Option<String>,
+    user_id: Option<Uuid>,
+    email: Option<String>,
     amount: u64
 }
 
 async fn mint_to(
     State(token_controller): State<TokenController>,
+    Query(query): Query<TransactionQuery>,
     Json(payload): Json<MintToRequest>
-) -> Result<Json<MintToResponse>, ApiError> {
+) -> Result<Json<TransactionOutcome<MintToResponse>>, ApiError> {
     let mint_pubkey_str = payload.mint_pubkey;
-    let receiver_pubkey_str = payload.receiver_pubkey;
     let amount = payload.amount;
 
-    let signature = token_controller.mint_to(
+    let receiver = match (payload.receiver_pubkey, payload.user_id, payload.email) {
+        (Some(receiver_pubkey_str), None, None) => MintReceiver::Pubkey(receiver_pubkey_str),
+        (None, Some(user_id), None) => MintReceiver::UserId(user_id),
+        (None, None, Some(email)) => MintReceiver::Email(email),
+        _ => return Err((StatusCode::BAD_REQUEST, "Exactly one of receiver_pubkey, user_id or email is required".to_string()))
+    };
+
+    let signature = token_controller.mint_to_receiver(
         &mint_pubkey_str, 
-        &receiver_pubkey_str, 
-        amount
+        receiver, 
+        amount,
+        query.dry_run
+    ).await?;
+
+    Ok(Json(signature))
+}
+
+async fn estimate(
+    State(token_controller): State<TokenController>,
+    Json(operation): Json<PlannedOperation>
+) -> Result<Json<FeeEstimateResponse>, ApiError> {
+    let estimate = token_controller.estimate(operation).await?;
+
+    Ok(Json(estimate))
+}
+
+#[derive(Deserialize)]
+struct TokenAccountRequest {
+    mint_pubkey: String,
+    owner_pubkey: String
+}
+
+async fn freeze_token_account(
+    State(token_controller): State<TokenController>,
+    Query(query): Query<TransactionQuery>,
+    Json(payload): Json<TokenAccountRequest>
+) -> Result<Json<TransactionOutcome<FreezeAccountResponse>>, ApiError> {
+    let outcome = token_controller.set_account_frozen(
+        &payload.mint_pubkey, 
+        &payload.owner_pubkey, 
+        true,
+        query.dry_run
     ).await?;
 
+    Ok(Json(outcome))
+}
+
+async fn thaw_token_account(
+    State(token_controller): State<TokenController>,
+    Query(query): Query<TransactionQuery>,
+    Json(payload): Json<TokenAccountRequest>
+) -> Result<Json<TransactionOutcome<FreezeAccountResponse>>, ApiError> {
+    let outcome = token_controller.set_account_frozen(
+        &payload.mint_pubkey, 
+        &payload.owner_pubkey, 
+        false,
+        query.dry_run
+    ).await?;
+
+    Ok(Json(outcome))
+}
+
+#[derive(Deserialize)]
+struct MintBatchRequest {
+    mint_pubkey: String,
+    recipients: Vec<MintRecipient>
+}
+
+async fn create_mint_batch(
+    State(token_controller): State<TokenController>,
+    Query(query): Query<TransactionQuery>,
+    Json(payload): Json<MintBatchRequest>
+) -> Result<Json<TransactionOutcome<MintBatchReport, Vec<PackedSimulation>>>, ApiError> {
+    let report = token_controller.create_mint_batch(
+        &payload.mint_pubkey, 
+        payload.recipients,
+        query.dry_run
+    ).await?;
+
+    Ok(Json(report))
+}
+
+async fn fetch_mint_batch(
+    State(token_controller): State<TokenController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<MintBatchReport>, ApiError> {
+    let report = token_controller.fetch_mint_batch(id).await?;
+
+    Ok(Json(report))
+}
+
+async fn resume_mint_batch(
+    State(token_controller): State<TokenController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<MintBatchReport>, ApiError> {
+    let report = token_controller.resume_mint_batch(id).await?;
+
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+struct SponsoredTransferRequest {
+    mint_pubkey: String,
+    owner_pubkey: String,
+    destination_pubkey: String,
+    amount: u64
+}
+
+async fn build_sponsored_transfer(
+    State(token_controller): State<TokenController>,
+    Json(payload): Json<SponsoredTransferRequest>
+) -> Result<Json<SponsoredTransactionResponse>, ApiError> {
+    let transaction = token_controller.build_sponsored_transfer(
+        &payload.mint_pubkey, 
+        &payload.owner_pubkey, 
+        &payload.destination_pubkey, 
+        payload.amount
+    ).await?;
+
+    Ok(Json(transaction))
+}
+
+#[derive(Deserialize)]
+struct SponsoredBurnRequest {
+    mint_pubkey: String,
+    owner_pubkey: String,
+    amount: u64
+}
+
+async fn build_sponsored_burn(
+    State(token_controller): State<TokenController>,
+    Json(payload): Json<SponsoredBurnRequest>
+) -> Result<Json<SponsoredTransactionResponse>, ApiError> {
+    let transaction = token_controller.build_sponsored_burn(
+        &payload.mint_pubkey, 
+        &payload.owner_pubkey, 
+        payload.amount
+    ).await?;
+
+    Ok(Json(transaction))
+}
+
+#[derive(Deserialize)]
+struct SubmitSponsoredRequest {
+    transaction: String
+}
+
+async fn submit_sponsored_transaction(
+    State(token_controller): State<TokenController>,
+    Query(query): Query<TransactionQuery>,
+    Json(payload): Json<SubmitSponsoredRequest>
+) -> Result<Json<TransactionOutcome<SubmitSponsoredResponse>>, ApiError> {
+    let signature = token_controller.submit_sponsored_transaction(&payload.transaction, query.dry_run).await?;
+
     Ok(Json(signature))
 }
\ No newline at end of file
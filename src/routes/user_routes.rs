@@ -1,5 +1,6 @@
 use axum::{
     extract::{Path, State}, 
+    middleware, 
     routing::{get, post}, 
     Json, 
     Router
@@ -7,18 +8,43 @@ use axum::{
 use uuid::Uuid;
 
 use crate::{
-    controllers::{user_controller::UserController, ApiError}, 
-    models::user_model::{
-        CreateUserRequest, 
-        UserForResponse
+    controllers::{role_controller::RoleController, user_controller::UserController, ApiError}, 
+    middlewares::permission_middleware::{own_user_middleware, permission_middleware, RequiredPermission}, 
+    models::{
+        role_model::Permission, 
+        user_model::{
+            CreateUserRequest, 
+            UserForResponse
+        }
     }
 };
 
 
-pub fn user_routes(user_controller: UserController) -> Router {
-    Router::new()
-        .route("/users", post(create_user).get(fetch_all_users))
+pub fn user_routes(user_controller: UserController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/users", get(fetch_all_users))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            permission_middleware
+        ));
+
+    let own_user_routes = Router::new()
         .route("/users/:id", get(fetch_user))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let write_routes = Router::new()
+        .route("/users", post(create_user))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersWrite),
+            permission_middleware
+        ));
+
+    read_routes
+        .merge(own_user_routes)
+        .merge(write_routes)
         .with_state(user_controller)
 }
 
@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Path, State},
+    middleware,
+    routing::{get, post},
+    Json,
+    Router
+};
+use uuid::Uuid;
+
+use crate::{
+    controllers::{role_controller::RoleController, wallet_controller::WalletController, ApiError},
+    middlewares::permission_middleware::{own_user_middleware, RequiredPermission},
+    models::{
+        role_model::Permission,
+        wallet_model::{
+            ConfirmWalletExportRequest,
+            CreateWalletChallengeRequest,
+            CreateWalletRequest,
+            ExportedWalletResponse,
+            VerifyWalletChallengeRequest,
+            Wallet,
+            WalletChallengeResponse,
+            WalletExportAudit,
+            WalletExportResponse
+        }
+    }
+};
+
+/// Every wallet route is scoped to a user, so that user's own access token opens
+/// it as well as any principal with the permission.
+pub fn wallet_routes(wallet_controller: WalletController, role_controller: RoleController) -> Router {
+    let read_routes = Router::new()
+        .route("/users/:id/wallets", get(fetch_wallets))
+        .route("/users/:id/wallets/exports", get(fetch_export_audit))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersRead),
+            own_user_middleware
+        ));
+
+    let write_routes = Router::new()
+        .route("/users/:id/wallets", post(create_wallet))
+        .route("/users/:id/wallets/challenge", post(create_challenge))
+        .route("/users/:id/wallets/verify", post(verify_challenge))
+        .route("/users/:id/wallets/exports/:export_id/confirm", post(confirm_export))
+        .route("/users/:id/wallets/:pubkey/primary", post(set_primary_wallet))
+        .route("/users/:id/wallets/:pubkey/archive", post(archive_wallet))
+        .route("/users/:id/wallets/:pubkey/export", post(request_export))
+        .route_layer(middleware::from_fn_with_state(
+            RequiredPermission::new(&role_controller, Permission::UsersWrite),
+            own_user_middleware
+        ));
+
+    read_routes
+        .merge(write_routes)
+        .with_state(wallet_controller)
+}
+
+async fn create_challenge(
+    State(wallet_controller): State<WalletController>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<CreateWalletChallengeRequest>
+) -> Result<Json<WalletChallengeResponse>, ApiError> {
+    let challenge = wallet_controller.create_challenge(id, &body.pubkey).await?;
+
+    Ok(Json(challenge))
+}
+
+async fn verify_challenge(
+    State(wallet_controller): State<WalletController>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<VerifyWalletChallengeRequest>
+) -> Result<Json<Wallet>, ApiError> {
+    let wallet = wallet_controller.verify_challenge(
+        id, 
+        body.challenge_id, 
+        &body.signature, 
+        body.label.as_deref()
+    ).await?;
+
+    Ok(Json(wallet))
+}
+
+async fn fetch_wallets(
+    State(wallet_controller): State<WalletController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<Vec<Wallet>>, ApiError> {
+    let wallets = wallet_controller.fetch_wallets(id).await?;
+
+    Ok(Json(wallets))
+}
+
+async fn create_wallet(
+    State(wallet_controller): State<WalletController>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<CreateWalletRequest>
+) -> Result<Json<Wallet>, ApiError> {
+    let wallet = wallet_controller.create_wallet(id, body.label).await?;
+
+    Ok(Json(wallet))
+}
+
+async fn set_primary_wallet(
+    State(wallet_controller): State<WalletController>,
+    Path((id, pubkey)): Path<(Uuid, String)>
+) -> Result<Json<Wallet>, ApiError> {
+    let wallet = wallet_controller.set_primary_wallet(id, &pubkey).await?;
+
+    Ok(Json(wallet))
+}
+
+async fn archive_wallet(
+    State(wallet_controller): State<WalletController>,
+    Path((id, pubkey)): Path<(Uuid, String)>
+) -> Result<Json<Wallet>, ApiError> {
+    let wallet = wallet_controller.archive_wallet(id, &pubkey).await?;
+
+    Ok(Json(wallet))
+}
+
+async fn request_export(
+    State(wallet_controller): State<WalletController>,
+    Path((id, pubkey)): Path<(Uuid, String)>
+) -> Result<Json<WalletExportResponse>, ApiError> {
+    let export = wallet_controller.request_export(id, &pubkey).await?;
+
+    Ok(Json(export))
+}
+
+async fn confirm_export(
+    State(wallet_controller): State<WalletController>,
+    Path((id, export_id)): Path<(Uuid, Uuid)>,
+    Json(body): Json<ConfirmWalletExportRequest>
+) -> Result<Json<ExportedWalletResponse>, ApiError> {
+    let exported = wallet_controller.confirm_export(id, export_id, body).await?;
+
+    Ok(Json(exported))
+}
+
+async fn fetch_export_audit(
+    State(wallet_controller): State<WalletController>,
+    Path(id): Path<Uuid>
+) -> Result<Json<Vec<WalletExportAudit>>, ApiError> {
+    let entries = wallet_controller.fetch_export_audit(id).await?;
+
+    Ok(Json(entries))
+}